@@ -0,0 +1,597 @@
+use std::collections::VecDeque;
+
+use bevy::{app::{Plugin, PostStartup, Update}, prelude::{default, Added, BuildChildren, ButtonBundle, Changed, Commands, Component, DetectChanges, Local, NodeBundle, Query, RemovedComponents, Res, ResMut, Resource, TextBundle, With, Without}, text::Text, ui::{BackgroundColor, Display, FlexDirection, Interaction, PositionType, RelativeCursorPosition, Style, UiRect, Val}};
+
+use crate::app::{build_graph::components::{Edge, Vertex}, ui::{button_style, text_style, MenuAppExt, BUTTON_COLOR, BUTTON_HOVERED_COLOR, HEADER_COLOR, PANEL_COLOR}};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Step, Trace, HEAT_LEVELS}};
+
+pub struct CentralityPlugin;
+impl Plugin for CentralityPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<CentralityTable>()
+        .add_systems(PostStartup, spawn_table)
+        .add_systems(Update, (
+            drag_damping_slider,
+            press_sort_buttons,
+            refresh_table,
+            update_table,
+        ))
+        .add_menu_button("Centrality", "Degree", run_degree)
+        .add_menu_button("Centrality", "Closeness", run_closeness)
+        .add_menu_button("Centrality", "Betweenness (Brandes)", run_betweenness)
+        .add_menu_button("Centrality", "Eigenvector", run_eigenvector)
+        .add_menu_button("Centrality", "PageRank", run_pagerank)
+        .add_menu_button("Centrality", "Toggle table", toggle_table)
+        ;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Metric {
+    Degree,
+    Closeness,
+    Betweenness,
+    Eigenvector,
+    PageRank,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 5] = [Metric::Degree, Metric::Closeness, Metric::Betweenness, Metric::Eigenvector, Metric::PageRank];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Degree => "Degree",
+            Metric::Closeness => "Closeness",
+            Metric::Betweenness => "Betweenness",
+            Metric::Eigenvector => "Eigenvector",
+            Metric::PageRank => "PageRank",
+        }
+    }
+}
+
+/**
+    # Centrality Table
+    Every metric of every vertex, sorted by one column. The table is filled when a metric is computed
+    and kept up to date while it is shown.
+*/
+#[derive(Resource)]
+pub struct CentralityTable {
+    pub damping: f32,
+    visible: bool,
+    names: Vec<String>,
+    // Vertex -> one value per metric in the order of Metric::ALL
+    values: Vec<[f32; 5]>,
+    // None sorts by name
+    sort_by: Option<usize>,
+    descending: bool,
+}
+
+impl Default for CentralityTable {
+    fn default() -> Self {
+        Self {
+            damping: 0.85,
+            visible: false,
+            names: vec![],
+            values: vec![],
+            sort_by: None,
+            descending: true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct TablePanel;
+
+#[derive(Component)]
+struct TableText;
+
+#[derive(Component)]
+struct DampingSlider;
+
+#[derive(Component)]
+struct DampingFill;
+
+#[derive(Component)]
+struct DampingText;
+
+// Sorts the table by a column, None is the name column
+#[derive(Component)]
+struct SortButton(Option<usize>);
+
+// Rows shown in the table
+const TABLE_ROWS: usize = 25;
+const PAGERANK_TOLERANCE: f32 = 1e-6;
+const MAX_ITERATIONS: usize = 100;
+// Smallest and biggest vertex scale of the heatmap
+const MIN_SCALE: f32 = 0.6;
+const MAX_SCALE: f32 = 1.4;
+const DAMPING_RANGE: (f32, f32) = (0.05, 0.99);
+
+fn run_degree(graph: GraphData, mut table: ResMut<CentralityTable>, mut run: ResMut<AlgorithmRun>) {
+    run_metric(Metric::Degree, &graph.snapshot(), &mut table, &mut run);
+}
+
+fn run_closeness(graph: GraphData, mut table: ResMut<CentralityTable>, mut run: ResMut<AlgorithmRun>) {
+    run_metric(Metric::Closeness, &graph.snapshot(), &mut table, &mut run);
+}
+
+fn run_betweenness(graph: GraphData, mut table: ResMut<CentralityTable>, mut run: ResMut<AlgorithmRun>) {
+    run_metric(Metric::Betweenness, &graph.snapshot(), &mut table, &mut run);
+}
+
+fn run_eigenvector(graph: GraphData, mut table: ResMut<CentralityTable>, mut run: ResMut<AlgorithmRun>) {
+    run_metric(Metric::Eigenvector, &graph.snapshot(), &mut table, &mut run);
+}
+
+fn run_pagerank(graph: GraphData, mut table: ResMut<CentralityTable>, mut run: ResMut<AlgorithmRun>) {
+    run_metric(Metric::PageRank, &graph.snapshot(), &mut table, &mut run);
+}
+
+fn run_metric(metric: Metric, graph: &GraphSnapshot, table: &mut CentralityTable, run: &mut AlgorithmRun) {
+    let mut values = vec![];
+    if !graph.is_empty() {
+        fill_table(graph, table);
+        let column = Metric::ALL.iter().position(|m| *m == metric).expect("every metric has a column");
+        table.sort_by = Some(column);
+        table.descending = true;
+        values = table.values.iter().map(|row| row[column]).collect();
+    }
+    run.start(metric.name(), metric_trace(metric, graph, table.damping, &values));
+}
+
+// The values are the ones of the metric, PageRank shows its iterations instead
+pub fn metric_trace(metric: Metric, graph: &GraphSnapshot, damping: f32, values: &[f32]) -> Trace {
+    let mut trace = Trace::new();
+    if graph.is_empty() {
+        trace.step("The graph has no vertices");
+        return trace;
+    }
+    match metric {
+        Metric::PageRank => {
+            pagerank(graph, damping, Some(&mut trace));
+        },
+        _ => {
+            let step = trace.step(explanation(metric));
+            heat_step(graph, step, values);
+            let best = (0..graph.len()).max_by(|a, b| values[*a].total_cmp(&values[*b])).expect("the graph has vertices");
+            step.message.push_str(&format!("\nThe most central vertex is {} with {:.3}", graph.name(best), values[best]));
+        },
+    }
+    trace
+}
+
+pub fn metric_values(metric: Metric, graph: &GraphSnapshot, damping: f32) -> Vec<f32> {
+    match metric {
+        Metric::Degree => degree(graph),
+        Metric::Closeness => closeness(graph),
+        Metric::Betweenness => betweenness(graph),
+        Metric::Eigenvector => eigenvector(graph),
+        Metric::PageRank => pagerank(graph, damping, None),
+    }
+}
+
+fn explanation(metric: Metric) -> &'static str {
+    match metric {
+        Metric::Degree => "Degree centrality counts the edges of a vertex, divided by n - 1",
+        Metric::Closeness => "Closeness centrality is the inverse average distance to the reachable vertices, scaled down by the share of vertices that can be reached",
+        Metric::Betweenness => "Betweenness centrality with Brandes' algorithm counts the shortest paths that pass through a vertex, normalized by the number of vertex pairs",
+        Metric::Eigenvector => "Eigenvector centrality makes a vertex important when its neighbours are, found by power iteration",
+        Metric::PageRank => "PageRank is the chance that a random surfer who follows links and jumps with probability 1 - damping is at a vertex",
+    }
+}
+
+// Colors and scales every vertex by its value relative to the biggest one
+fn heat_step(graph: &GraphSnapshot, step: &mut Step, values: &[f32]) {
+    let max = values.iter().copied().fold(0., f32::max);
+    for (v, value) in values.iter().enumerate() {
+        let t = if max > 0. { value / max } else { 0. };
+        let level = (t * (HEAT_LEVELS - 1) as f32).round() as usize;
+        step.vertex(graph.vertices[v], Mark::Heat(level))
+            .scale(graph.vertices[v], MIN_SCALE + (MAX_SCALE - MIN_SCALE) * t)
+            .vertex_label(graph.vertices[v], format!("{value:.3}"));
+    }
+}
+
+fn fill_table(graph: &GraphSnapshot, table: &mut CentralityTable) {
+    let columns = Metric::ALL.map(|metric| metric_values(metric, graph, table.damping));
+    table.names = graph.names.clone();
+    table.values = (0..graph.len())
+        .map(|v| [columns[0][v], columns[1][v], columns[2][v], columns[3][v], columns[4][v]])
+        .collect();
+}
+
+pub fn degree(graph: &GraphSnapshot) -> Vec<f32> {
+    let n = graph.len();
+    let scale = if n > 1 { 1. / (n - 1) as f32 } else { 1. };
+    (0..n).map(|v| graph.neighbours(v).count() as f32 * scale).collect()
+}
+
+// Distances along the edges that can be walked from the source, None for unreachable vertices
+fn bfs(graph: &GraphSnapshot, source: usize) -> Vec<Option<usize>> {
+    let mut distance = vec![None; graph.len()];
+    distance[source] = Some(0);
+    let mut queue = VecDeque::from([source]);
+    while let Some(v) = queue.pop_front() {
+        for (w, _) in graph.out[v].iter() {
+            if distance[*w].is_none() {
+                distance[*w] = distance[v].map(|d| d + 1);
+                queue.push_back(*w);
+            }
+        }
+    }
+    distance
+}
+
+// Wasserman and Faust closeness so vertices that reach only a few others don't look central
+pub fn closeness(graph: &GraphSnapshot) -> Vec<f32> {
+    let n = graph.len();
+    (0..n)
+        .map(|v| {
+            let distance = bfs(graph, v);
+            let reached = distance.iter().flatten().filter(|d| **d > 0).count();
+            let total: usize = distance.iter().flatten().sum();
+            if total == 0 || n < 2 {
+                return 0.;
+            }
+            (reached as f32 / total as f32) * (reached as f32 / (n - 1) as f32)
+        })
+        .collect()
+}
+
+/**
+    # Brandes
+    One BFS per source counts the shortest paths sigma, then the dependencies are accumulated from the farthest
+    vertex back to the source. Undirected edges count every path from both ends, so the sum is halved before
+    it is normalized.
+*/
+pub fn betweenness(graph: &GraphSnapshot) -> Vec<f32> {
+    let n = graph.len();
+    let mut centrality = vec![0f32; n];
+    for source in 0..n {
+        let mut order = vec![];
+        let mut predecessors: Vec<Vec<usize>> = vec![vec![]; n];
+        let mut sigma = vec![0f32; n];
+        let mut distance: Vec<Option<usize>> = vec![None; n];
+        sigma[source] = 1.;
+        distance[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(v) = queue.pop_front() {
+            order.push(v);
+            for (w, _) in graph.out[v].iter().copied() {
+                if distance[w].is_none() {
+                    distance[w] = distance[v].map(|d| d + 1);
+                    queue.push_back(w);
+                }
+                if distance[w] == distance[v].map(|d| d + 1) {
+                    sigma[w] += sigma[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+        let mut dependency = vec![0f32; n];
+        for w in order.into_iter().rev() {
+            for v in predecessors[w].iter() {
+                dependency[*v] += sigma[*v] / sigma[w] * (1. + dependency[w]);
+            }
+            if w != source {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+    // Halving the undirected sum and dividing by the (n - 1)(n - 2) / 2 unordered pairs cancels out
+    let pairs = if n > 2 { ((n - 1) * (n - 2)) as f32 } else { 1. };
+    centrality.iter().map(|c| c / pairs).collect()
+}
+
+// Power iteration on A + I, the added identity keeps bipartite graphs from oscillating. Directions are ignored
+pub fn eigenvector(graph: &GraphSnapshot) -> Vec<f32> {
+    let n = graph.len();
+    let adjacency = graph.simple_adjacency();
+    let mut x = vec![1. / (n as f32).sqrt(); n];
+    for _ in 0..MAX_ITERATIONS {
+        let mut next: Vec<f32> = (0..n).map(|v| x[v] + adjacency[v].iter().map(|w| x[*w]).sum::<f32>()).collect();
+        let norm = next.iter().map(|value| value * value).sum::<f32>().sqrt();
+        if norm == 0. {
+            return vec![0.; n];
+        }
+        next.iter_mut().for_each(|value| *value /= norm);
+        let change: f32 = next.iter().zip(x.iter()).map(|(a, b)| (a - b).abs()).sum();
+        x = next;
+        if change < PAGERANK_TOLERANCE {
+            break;
+        }
+    }
+    x
+}
+
+/**
+    # PageRank
+    Power iteration of rank = (1 - d) / n + d * (rank flowing in over the edges). Vertices without outgoing edges
+    spread their rank over all vertices.
+*/
+pub fn pagerank(graph: &GraphSnapshot, damping: f32, mut trace: Option<&mut Trace>) -> Vec<f32> {
+    let n = graph.len();
+    let mut rank = vec![1. / n as f32; n];
+    if let Some(trace) = trace.as_deref_mut() {
+        let step = trace.step(format!("{}\nStart with rank 1/n everywhere, damping {:.2}", explanation(Metric::PageRank), damping));
+        heat_step(graph, step, &rank);
+    }
+    for iteration in 1..=MAX_ITERATIONS {
+        let dangling: f32 = (0..n).filter(|v| graph.out[*v].is_empty()).map(|v| rank[v]).sum();
+        let mut next = vec![(1. - damping) / n as f32 + damping * dangling / n as f32; n];
+        for (v, out) in graph.out.iter().enumerate() {
+            let share = rank[v] / out.len().max(1) as f32;
+            for (w, _) in out.iter() {
+                next[*w] += damping * share;
+            }
+        }
+        let change: f32 = next.iter().zip(rank.iter()).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if let Some(trace) = trace.as_deref_mut() {
+            let step = trace.step(format!("Iteration {iteration}, the ranks changed by {change:.6} in total"));
+            heat_step(graph, step, &rank);
+        }
+        if change < PAGERANK_TOLERANCE {
+            if let Some(trace) = trace {
+                let best = (0..n).max_by(|a, b| rank[*a].total_cmp(&rank[*b])).expect("the graph has vertices");
+                trace.step(format!("Converged after {iteration} iterations, {} has the highest rank {:.3}", graph.name(best), rank[best]));
+            }
+            break;
+        }
+    }
+    rank
+}
+
+fn toggle_table(
+    graph: GraphData,
+    mut table: ResMut<CentralityTable>,
+) {
+    table.visible = !table.visible;
+    if table.visible {
+        fill_table(&graph.snapshot(), &mut table);
+    }
+}
+
+// Keeps the values up to date while the table is shown and the graph or the damping changes
+fn refresh_table(
+    graph: GraphData,
+    mut table: ResMut<CentralityTable>,
+    q_added_vertex: Query<(), Added<Vertex>>,
+    q_added_edge: Query<(), Added<Edge>>,
+    mut removed_vertices: RemovedComponents<Vertex>,
+    mut removed_edges: RemovedComponents<Edge>,
+    mut damping: Local<f32>,
+) {
+    // Read even while hidden so that old removals don't refresh the table once it is shown
+    let removed = removed_vertices.read().count() + removed_edges.read().count() > 0;
+    if !table.visible {
+        return;
+    }
+    let damping_changed = *damping != table.damping;
+    if !damping_changed && !removed && q_added_vertex.is_empty() && q_added_edge.is_empty() {
+        return;
+    }
+    *damping = table.damping;
+    fill_table(&graph.snapshot(), &mut table);
+}
+
+fn spawn_table(
+    mut commands: Commands,
+    table: Res<CentralityTable>,
+) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.),
+            top: Val::Px(0.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        background_color: PANEL_COLOR.into(),
+        ..default()
+    }, Interaction::default(), TablePanel))
+    .with_children(|panel| {
+        panel.spawn((TextBundle::from_section(format!("PageRank damping {:.2}", table.damping), text_style()), DampingText));
+        panel.spawn((NodeBundle {
+            style: Style {
+                width: Val::Px(240.),
+                height: Val::Px(14.),
+                margin: UiRect::vertical(Val::Px(4.)),
+                ..default()
+            },
+            background_color: BUTTON_COLOR.into(),
+            ..default()
+        }, Interaction::default(), RelativeCursorPosition::default(), DampingSlider))
+        .with_children(|slider| {
+            slider.spawn((NodeBundle {
+                style: Style {
+                    width: Val::Percent(table.damping * 100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                background_color: BUTTON_HOVERED_COLOR.into(),
+                ..default()
+            }, DampingFill));
+        });
+
+        panel.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Row,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|row| {
+            let columns = std::iter::once((None, "Vertex")).chain(Metric::ALL.iter().enumerate().map(|(i, metric)| (Some(i), metric.name())));
+            for (column, label) in columns {
+                row.spawn((ButtonBundle {
+                    style: Style {
+                        margin: UiRect::right(Val::Px(2.)),
+                        ..button_style()
+                    },
+                    background_color: HEADER_COLOR.into(),
+                    ..default()
+                }, SortButton(column)))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(label, text_style()));
+                });
+            }
+        });
+        panel.spawn((TextBundle::from_section("", text_style()), TableText));
+    });
+}
+
+fn drag_damping_slider(
+    mut table: ResMut<CentralityTable>,
+    q_slider: Query<(&Interaction, &RelativeCursorPosition), With<DampingSlider>>,
+) {
+    for (interaction, cursor) in q_slider.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(position) = cursor.normalized else {continue};
+        let damping = position.x.clamp(DAMPING_RANGE.0, DAMPING_RANGE.1);
+        if (damping - table.damping).abs() > 0.005 {
+            table.damping = (damping * 100.).round() / 100.;
+        }
+    }
+}
+
+fn press_sort_buttons(
+    mut table: ResMut<CentralityTable>,
+    mut q_button: Query<(&Interaction, &SortButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in q_button.iter_mut() {
+        match interaction {
+            Interaction::Pressed => {
+                // The same column again flips the order
+                if table.sort_by == button.0 {
+                    table.descending = !table.descending;
+                } else {
+                    table.sort_by = button.0;
+                    table.descending = button.0.is_some();
+                }
+            },
+            Interaction::Hovered => *color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *color = HEADER_COLOR.into(),
+        }
+    }
+}
+
+// Sorts names by their text and then by the number they end with, so v2 comes before v10
+fn name_key(name: &str) -> (&str, Option<u64>, &str) {
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    (prefix, name[prefix.len()..].parse().ok(), name)
+}
+
+fn update_table(
+    table: Res<CentralityTable>,
+    mut q_panel: Query<&mut Style, With<TablePanel>>,
+    mut q_fill: Query<&mut Style, (With<DampingFill>, Without<TablePanel>)>,
+    mut q_text: Query<&mut Text, With<TableText>>,
+    mut q_damping_text: Query<&mut Text, (With<DampingText>, Without<TableText>)>,
+) {
+    if !table.is_changed() {
+        return;
+    }
+    if let Ok(mut style) = q_panel.get_single_mut() {
+        style.display = if table.visible { Display::Flex } else { Display::None };
+    }
+    if let Ok(mut style) = q_fill.get_single_mut() {
+        style.width = Val::Percent(table.damping * 100.);
+    }
+    if let Ok(mut text) = q_damping_text.get_single_mut() {
+        text.sections[0].value = format!("PageRank damping {:.2}", table.damping);
+    }
+    let Ok(mut text) = q_text.get_single_mut() else {return};
+
+    let mut rows: Vec<usize> = (0..table.names.len()).collect();
+    match table.sort_by {
+        None => rows.sort_by(|a, b| name_key(&table.names[*a]).cmp(&name_key(&table.names[*b]))),
+        Some(column) => rows.sort_by(|a, b| table.values[*a][column].total_cmp(&table.values[*b][column])),
+    }
+    if table.descending {
+        rows.reverse();
+    }
+
+    let arrow = if table.descending { "v" } else { "^" };
+    let mut lines = vec![format!(
+        "sorted by {} {arrow}",
+        table.sort_by.map_or("Vertex", |column| Metric::ALL[column].name())
+    )];
+    for row in rows.iter().take(TABLE_ROWS) {
+        let values: Vec<String> = table.values[*row].iter().map(|value| format!("{value:>8.3}")).collect();
+        lines.push(format!("{:<8}{}", table.names[*row], values.join("   ")));
+    }
+    if rows.len() > TABLE_ROWS {
+        lines.push(format!("... {} more", rows.len() - TABLE_ROWS));
+    }
+    text.sections[0].value = lines.join("\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{betweenness, closeness, degree, name_key, pagerank};
+
+    fn undirected(n: usize, edges: &[(usize, usize)]) -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = edges.iter().map(|(a, b)| (*a, *b, 1, false)).collect();
+        GraphSnapshot::test_graph(n, &edges)
+    }
+
+    fn assert_close(values: &[f32], expected: &[f32]) {
+        assert_eq!(values.len(), expected.len());
+        for (value, expected) in values.iter().zip(expected.iter()) {
+            assert!((value - expected).abs() < 1e-4, "{values:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn betweenness_of_a_path() {
+        let path = undirected(5, &[(0, 1), (1, 2), (2, 3), (3, 4)]);
+        assert_close(&betweenness(&path), &[0., 0.5, 2. / 3., 0.5, 0.]);
+    }
+
+    #[test]
+    fn betweenness_of_a_star() {
+        let star = undirected(5, &[(0, 1), (0, 2), (0, 3), (0, 4)]);
+        assert_close(&betweenness(&star), &[1., 0., 0., 0., 0.]);
+        assert_close(&degree(&star), &[1., 0.25, 0.25, 0.25, 0.25]);
+    }
+
+    #[test]
+    fn shortest_paths_are_shared() {
+        // A square, each vertex lies on one of the two shortest paths between its neighbours
+        let square = undirected(4, &[(0, 1), (1, 2), (2, 3), (3, 0)]);
+        assert_close(&betweenness(&square), &[1. / 6.; 4]);
+    }
+
+    #[test]
+    fn closeness_counts_unreachable_vertices() {
+        let path = undirected(3, &[(0, 1), (1, 2)]);
+        assert_close(&closeness(&path), &[2. / 3., 1., 2. / 3.]);
+        // The isolated vertex halves what the others can reach
+        let split = undirected(3, &[(0, 1)]);
+        assert_close(&closeness(&split), &[0.5, 0.5, 0.]);
+    }
+
+    #[test]
+    fn pagerank_sums_to_one() {
+        let edges = [(0, 1, 1, true), (1, 2, 1, true), (2, 0, 1, true), (3, 0, 1, true)];
+        let rank = pagerank(&GraphSnapshot::test_graph(5, &edges), 0.85, None);
+        assert!((rank.iter().sum::<f32>() - 1.).abs() < 1e-4, "{rank:?}");
+        assert!(rank[0] > rank[3] && rank[0] > rank[4]);
+    }
+
+    #[test]
+    fn names_sort_by_their_number() {
+        let mut names = vec!["v10", "b", "v2", "a7", "v", "v1", "a"];
+        names.sort_by_key(|name| name_key(name));
+        assert_eq!(names, vec!["a", "a7", "b", "v", "v1", "v2", "v10"]);
+    }
+}
@@ -0,0 +1,459 @@
+use bevy::{app::Plugin, prelude::ResMut};
+
+use crate::app::ui::MenuAppExt;
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Step, Trace}};
+
+pub struct CliquesPlugin;
+impl Plugin for CliquesPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_menu_button("Cliques and Covers", "Bron-Kerbosch", run_bron_kerbosch)
+        .add_menu_button("Cliques and Covers", "Maximum independent set", run_independent_set)
+        .add_menu_button("Cliques and Covers", "Minimum vertex cover", run_vertex_cover)
+        ;
+    }
+}
+
+// The search for maximal cliques stops after this many
+const CLIQUE_LIMIT: usize = 10_000;
+// Calls of the search that are recorded as steps
+const TRACE_LIMIT: usize = 500;
+// Cliques named in the final message
+const LISTED_CLIQUES: usize = 12;
+// Up to this many vertices the independent set and the vertex cover are exact, the vertex sets fit in a u64
+const EXACT_LIMIT: usize = 40;
+
+fn run_bron_kerbosch(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("Bron-Kerbosch", bron_kerbosch(&graph.snapshot()));
+}
+
+fn run_independent_set(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("Maximum independent set", independent_set(&graph.snapshot()));
+}
+
+fn run_vertex_cover(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("Minimum vertex cover", vertex_cover(&graph.snapshot()));
+}
+
+fn set_names(graph: &GraphSnapshot, set: &[usize]) -> String {
+    format!("{{{}}}", set.iter().map(|v| graph.name(*v)).collect::<Vec<_>>().join(", "))
+}
+
+// Marks the edges between the vertices of the set
+fn mark_edges_within(graph: &GraphSnapshot, step: &mut Step, set: &[usize], mark: Mark) {
+    for &u in set {
+        for (w, e) in graph.neighbours(u) {
+            if u < w && set.contains(&w) {
+                step.edge(graph.edge_entity(e), mark);
+            }
+        }
+    }
+}
+
+struct BronKerbosch<'a> {
+    graph: &'a GraphSnapshot,
+    adjacency: Vec<Vec<usize>>,
+    trace: &'a mut Trace,
+    cliques: Vec<Vec<usize>>,
+    calls: usize,
+    // Edges highlighted with the last clique found, cleared by the next step
+    highlighted: Vec<usize>,
+}
+
+impl<'a> BronKerbosch<'a> {
+    fn adjacent(&self, u: usize, v: usize) -> bool {
+        self.adjacency[u].binary_search(&v).is_ok()
+    }
+
+    fn record(&mut self, message: String, r: &[usize], p: &[usize], x: &[usize]) -> Option<&mut Step> {
+        if self.calls > TRACE_LIMIT {
+            return None;
+        }
+        let graph = self.graph;
+        let highlighted = std::mem::take(&mut self.highlighted);
+        let step = self.trace.step(message);
+        for e in highlighted {
+            step.clear(graph.edge_entity(e));
+        }
+        for v in 0..graph.len() {
+            if r.contains(&v) {
+                step.vertex(graph.vertices[v], Mark::Active);
+            } else if p.contains(&v) {
+                step.vertex(graph.vertices[v], Mark::Frontier);
+            } else if x.contains(&v) {
+                step.vertex(graph.vertices[v], Mark::Done);
+            } else {
+                step.clear(graph.vertices[v]);
+            }
+        }
+        Some(step)
+    }
+
+    /**
+        # Bron-Kerbosch
+        R is the clique grown so far, P holds the vertices that can still extend it and X the ones that were already tried.
+        R is maximal when P and X are empty. Every maximal clique containing R has a vertex outside the pivot's
+        neighbourhood, so only those vertices of P need a branch.
+    */
+    fn search(&mut self, r: &mut Vec<usize>, mut p: Vec<usize>, mut x: Vec<usize>) {
+        if self.cliques.len() >= CLIQUE_LIMIT {
+            return;
+        }
+        self.calls += 1;
+        let graph = self.graph;
+
+        if p.is_empty() && x.is_empty() {
+            let message = format!("P and X are empty, {} is a maximal clique", set_names(graph, r));
+            if self.record(message, r, &p, &x).is_some() {
+                let mut edges = vec![];
+                for &u in r.iter() {
+                    for (w, e) in graph.neighbours(u) {
+                        if u < w && r.contains(&w) {
+                            edges.push(e);
+                        }
+                    }
+                }
+                let step = self.trace.steps.last_mut().expect("the step was just recorded");
+                for &e in edges.iter() {
+                    step.edge(graph.edge_entity(e), Mark::Highlight);
+                }
+                for &v in r.iter() {
+                    step.vertex(graph.vertices[v], Mark::Highlight);
+                }
+                self.highlighted = edges;
+            }
+            self.cliques.push(r.clone());
+            return;
+        }
+        if p.is_empty() {
+            let message = format!("R = {} can't grow but X = {} could extend it, not maximal", set_names(graph, r), set_names(graph, &x));
+            self.record(message, r, &p, &x);
+            return;
+        }
+
+        let pivot = p.iter().chain(x.iter()).copied()
+            .max_by_key(|u| p.iter().filter(|v| self.adjacent(*u, **v)).count())
+            .expect("P is not empty");
+        let branches: Vec<usize> = p.iter().copied().filter(|v| !self.adjacent(pivot, *v)).collect();
+        let message = format!(
+            "R = {}, P = {}, X = {}\nPivot {}, branch on {}",
+            set_names(graph, r), set_names(graph, &p), set_names(graph, &x), graph.name(pivot), set_names(graph, &branches)
+        );
+        self.record(message, r, &p, &x);
+
+        for v in branches {
+            let next_p: Vec<usize> = p.iter().copied().filter(|w| self.adjacent(v, *w)).collect();
+            let next_x: Vec<usize> = x.iter().copied().filter(|w| self.adjacent(v, *w)).collect();
+            r.push(v);
+            self.search(r, next_p, next_x);
+            r.pop();
+            p.retain(|w| *w != v);
+            x.push(v);
+        }
+    }
+}
+
+pub fn bron_kerbosch(graph: &GraphSnapshot) -> Trace {
+    let mut trace = Trace::new();
+    if graph.is_empty() {
+        trace.step("The graph has no vertices");
+        return trace;
+    }
+    let mut search = BronKerbosch {
+        graph,
+        adjacency: graph.simple_adjacency(),
+        trace: &mut trace,
+        cliques: vec![],
+        calls: 0,
+        highlighted: vec![],
+    };
+    search.search(&mut vec![], (0..graph.len()).collect(), vec![]);
+    let calls = search.calls;
+    let mut cliques = std::mem::take(&mut search.cliques);
+    cliques.sort_by_key(|clique| std::cmp::Reverse(clique.len()));
+
+    let mut message = format!("{} maximal cliques", cliques.len());
+    if cliques.len() >= CLIQUE_LIMIT {
+        message.push_str(&format!(", the search stopped at {CLIQUE_LIMIT}"));
+    }
+    if calls > TRACE_LIMIT {
+        message.push_str(&format!(", only the first {TRACE_LIMIT} of {calls} calls were shown"));
+    }
+    let listed: Vec<String> = cliques.iter().take(LISTED_CLIQUES).map(|clique| set_names(graph, clique)).collect();
+    message.push_str(&format!(":\n{}", listed.join(" ")));
+    if cliques.len() > LISTED_CLIQUES {
+        message.push_str(" ...");
+    }
+    let maximum = &cliques[0];
+    message.push_str(&format!("\nThe maximum clique {} has {} vertices", set_names(graph, maximum), maximum.len()));
+
+    let step = trace.step(message);
+    for e in 0..graph.edges.len() {
+        step.clear(graph.edge_entity(e));
+    }
+    for v in 0..graph.len() {
+        step.clear(graph.vertices[v]);
+    }
+    for v in maximum.iter() {
+        step.vertex(graph.vertices[*v], Mark::Highlight);
+    }
+    mark_edges_within(graph, step, maximum, Mark::Matched);
+    trace
+}
+
+/**
+    # Maximum Clique
+    Branch and bound on bit sets, a branch is cut once R and P together can't beat the best clique.
+*/
+fn maximum_clique(adjacency: &[u64], r: u64, mut p: u64, best: &mut u64) {
+    if p == 0 {
+        if r.count_ones() > best.count_ones() {
+            *best = r;
+        }
+        return;
+    }
+    if r.count_ones() + p.count_ones() <= best.count_ones() {
+        return;
+    }
+    let mut candidates = p;
+    let mut pivot = 0;
+    let mut pivot_degree = 0;
+    while candidates != 0 {
+        let u = candidates.trailing_zeros() as usize;
+        candidates &= candidates - 1;
+        let degree = (p & adjacency[u]).count_ones();
+        if degree >= pivot_degree {
+            pivot = u;
+            pivot_degree = degree;
+        }
+    }
+    let mut branches = p & !adjacency[pivot];
+    while branches != 0 {
+        let v = branches.trailing_zeros() as usize;
+        branches &= branches - 1;
+        maximum_clique(adjacency, r | 1 << v, p & adjacency[v], best);
+        p &= !(1u64 << v);
+        if r.count_ones() + p.count_ones() <= best.count_ones() {
+            return;
+        }
+    }
+}
+
+// Exact for small graphs, otherwise repeatedly takes a vertex of smallest degree and drops its neighbours
+fn maximum_independent_set(graph: &GraphSnapshot) -> (Vec<usize>, bool) {
+    let n = graph.len();
+    let adjacency = graph.simple_adjacency();
+    if n <= EXACT_LIMIT {
+        let full: u64 = (1 << n) - 1;
+        // An independent set of the graph is a clique of the complement
+        let complement: Vec<u64> = (0..n)
+            .map(|v| {
+                let neighbours = adjacency[v].iter().fold(0u64, |set, w| set | 1 << w);
+                full & !neighbours & !(1u64 << v)
+            })
+            .collect();
+        let mut best = 0;
+        maximum_clique(&complement, 0, full, &mut best);
+        return ((0..n).filter(|v| best & (1u64 << v) != 0).collect(), true);
+    }
+
+    let mut removed = vec![false; n];
+    let mut set = vec![];
+    while let Some(v) = (0..n).filter(|v| !removed[*v]).min_by_key(|v| adjacency[*v].iter().filter(|w| !removed[**w]).count()) {
+        set.push(v);
+        removed[v] = true;
+        for w in adjacency[v].iter() {
+            removed[*w] = true;
+        }
+    }
+    (set, false)
+}
+
+pub fn independent_set(graph: &GraphSnapshot) -> Trace {
+    let mut trace = Trace::new();
+    if graph.is_empty() {
+        trace.step("The graph has no vertices");
+        return trace;
+    }
+    let (set, exact) = maximum_independent_set(graph);
+    let cover: Vec<usize> = (0..graph.len()).filter(|v| !set.contains(v)).collect();
+
+    let how = if exact {
+        "An independent set is a clique of the complement graph, branch and bound finds the largest one"
+    } else {
+        "The graph is too big for the exact search, greedily take vertices of smallest degree and drop their neighbours"
+    };
+    trace.step(how);
+
+    let step = trace.step(format!(
+        "{} independent set {} with {} vertices, no edge joins two of them",
+        if exact { "Maximum" } else { "Greedy" }, set_names(graph, &set), set.len()
+    ));
+    for v in set.iter() {
+        step.vertex(graph.vertices[*v], Mark::Group(0));
+    }
+
+    let complement = if exact {
+        "the complement of a maximum independent set is a minimum vertex cover"
+    } else {
+        "the complement of an independent set is a vertex cover"
+    };
+    let step = trace.step(format!(
+        "The other {} vertices {} touch every edge, {complement}",
+        cover.len(), set_names(graph, &cover)
+    ));
+    for v in cover.iter() {
+        step.vertex(graph.vertices[*v], Mark::Group(1));
+    }
+    for e in 0..graph.edges.len() {
+        step.edge(graph.edge_entity(e), Mark::Done);
+    }
+    trace
+}
+
+pub fn vertex_cover(graph: &GraphSnapshot) -> Trace {
+    let n = graph.len();
+    let mut trace = Trace::new();
+    if graph.is_empty() {
+        trace.step("The graph has no vertices");
+        return trace;
+    }
+
+    if n <= EXACT_LIMIT {
+        let (set, _) = maximum_independent_set(graph);
+        let cover: Vec<usize> = (0..n).filter(|v| !set.contains(v)).collect();
+        trace.step("A vertex cover is the complement of an independent set, find a maximum independent set first");
+        let step = trace.step(format!("Maximum independent set {}", set_names(graph, &set)));
+        for v in set.iter() {
+            step.vertex(graph.vertices[*v], Mark::Group(0));
+        }
+        let step = trace.step(format!("Minimum vertex cover {} with {} vertices", set_names(graph, &cover), cover.len()));
+        for v in cover.iter() {
+            step.vertex(graph.vertices[*v], Mark::Group(1));
+        }
+        for e in 0..graph.edges.len() {
+            step.edge(graph.edge_entity(e), Mark::Done);
+        }
+        return trace;
+    }
+
+    // Both endpoints of a maximal matching, the optimum needs one endpoint of every matched edge
+    trace.step("The graph is too big for the exact search, take both endpoints of every edge that isn't covered yet");
+    let mut covered = vec![false; n];
+    let mut matched = 0;
+    for (e, edge) in graph.edges.iter().enumerate() {
+        if covered[edge.from] || covered[edge.to] || edge.from == edge.to {
+            continue;
+        }
+        covered[edge.from] = true;
+        covered[edge.to] = true;
+        matched += 1;
+        trace.step(format!("{}-{} isn't covered, take both endpoints", graph.name(edge.from), graph.name(edge.to)))
+            .edge(graph.edge_entity(e), Mark::Matched)
+            .vertex(graph.vertices[edge.from], Mark::Group(1))
+            .vertex(graph.vertices[edge.to], Mark::Group(1));
+    }
+    let cover: Vec<usize> = (0..n).filter(|v| covered[*v]).collect();
+    trace.step(format!(
+        "Vertex cover with {} vertices. The {matched} matched edges share no endpoint so every cover needs at least {matched} vertices, this one is at most twice the minimum",
+        cover.len()
+    ));
+    trace
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::{Change, Mark, Step}};
+
+    use super::{bron_kerbosch, maximum_independent_set, vertex_cover};
+
+    fn cycle(n: usize) -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = (0..n).map(|v| (v, (v + 1) % n, 1, false)).collect();
+        GraphSnapshot::test_graph(n, &edges)
+    }
+
+    fn petersen() -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = (0..5)
+            .flat_map(|i| [(i, (i + 1) % 5, 1, false), (i, i + 5, 1, false), (i + 5, 5 + (i + 2) % 5, 1, false)])
+            .collect();
+        GraphSnapshot::test_graph(10, &edges)
+    }
+
+    // The vertices a step gives this mark, sorted
+    fn marked(graph: &GraphSnapshot, step: &Step, mark: Mark) -> Vec<usize> {
+        let mut vertices: Vec<usize> = step.changes.iter()
+            .filter_map(|change| match change {
+                Change::Vertex(entity, m) if *m == mark => graph.index.get(entity).copied(),
+                _ => None,
+            })
+            .collect();
+        vertices.sort();
+        vertices
+    }
+
+    fn assert_independent(graph: &GraphSnapshot, set: &[usize]) {
+        assert!(graph.edges.iter().all(|edge| !set.contains(&edge.from) || !set.contains(&edge.to)));
+    }
+
+    #[test]
+    fn maximal_cliques() {
+        // K4 without the edge 0-1
+        let graph = GraphSnapshot::test_graph(4, &[(0, 2, 1, false), (0, 3, 1, false), (1, 2, 1, false), (1, 3, 1, false), (2, 3, 1, false)]);
+        let trace = bron_kerbosch(&graph);
+        let last = trace.steps.last().unwrap();
+        assert!(last.message.starts_with("2 maximal cliques"), "{}", last.message);
+        assert!(last.message.ends_with("has 3 vertices"));
+        assert_eq!(marked(&graph, last, Mark::Highlight).len(), 3);
+    }
+
+    #[test]
+    fn moon_moser_graph() {
+        // The complete tripartite graph K(3, 3, 3) has the most maximal cliques for 9 vertices, 3^3
+        let edges: Vec<(usize, usize, i32, bool)> = (0..9)
+            .flat_map(|u| (u + 1..9).filter(move |v| u / 3 != v / 3).map(move |v| (u, v, 1, false)))
+            .collect();
+        let graph = GraphSnapshot::test_graph(9, &edges);
+        let trace = bron_kerbosch(&graph);
+        let message = &trace.steps.last().unwrap().message;
+        assert!(message.starts_with("27 maximal cliques"), "{message}");
+        assert!(message.ends_with("has 3 vertices"));
+    }
+
+    #[test]
+    fn maximum_independent_sets() {
+        for (graph, size) in [(cycle(5), 2), (petersen(), 4), (cycle(8), 4), (GraphSnapshot::test_graph(3, &[]), 3)] {
+            let (set, exact) = maximum_independent_set(&graph);
+            assert!(exact);
+            assert_eq!(set.len(), size);
+            assert_independent(&graph, &set);
+        }
+        // Too big for the exact search, the greedy set is still independent and maximal
+        let graph = cycle(50);
+        let (set, exact) = maximum_independent_set(&graph);
+        assert!(!exact);
+        assert_independent(&graph, &set);
+        assert!(set.len() >= 17);
+    }
+
+    #[test]
+    fn vertex_cover_is_the_complement_of_the_independent_set() {
+        for graph in [cycle(5), petersen()] {
+            let (set, _) = maximum_independent_set(&graph);
+            let trace = vertex_cover(&graph);
+            let cover = marked(&graph, trace.steps.last().unwrap(), Mark::Group(1));
+            assert_eq!(cover, (0..graph.len()).filter(|v| !set.contains(v)).collect::<Vec<_>>());
+            assert!(graph.edges.iter().all(|edge| cover.contains(&edge.from) || cover.contains(&edge.to)));
+        }
+        assert_eq!(marked(&petersen(), vertex_cover(&petersen()).steps.last().unwrap(), Mark::Group(1)).len(), 6);
+    }
+
+    #[test]
+    fn big_vertex_cover_covers_every_edge() {
+        let graph = cycle(50);
+        let trace = vertex_cover(&graph);
+        let cover: Vec<usize> = trace.steps.iter().flat_map(|step| marked(&graph, step, Mark::Group(1))).collect();
+        assert!(graph.edges.iter().all(|edge| cover.contains(&edge.from) || cover.contains(&edge.to)));
+        assert!(cover.len() <= 50);
+    }
+}
@@ -0,0 +1,525 @@
+use bevy::{app::{Plugin, Update}, asset::Handle, ecs::system::SystemParam, prelude::{Added, Commands, Component, DetectChanges, Entity, NextState, Query, Ref, Res, ResMut, Resource, State, With}, sprite::ColorMaterial};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::app::{build_graph::{components::{resting_material, BaseMaterial, Edge, EditorState, OwnMaterial, Vertex}, res::{GraphAssets, NearestPoints}}, input::NormalInput, ui::{InfoPanel, MenuAppExt}};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::{AlgorithmAssets, AlgorithmRun}, steps::{Mark, Step, Trace}};
+
+pub struct ColoringPlugin;
+impl Plugin for ColoringPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<ManualColoring>()
+        .add_systems(Update, (paint_vertex, show_manual_coloring))
+        .add_menu_button("Coloring", "Greedy in insertion order", run_greedy_insertion)
+        .add_menu_button("Coloring", "Greedy largest first", run_greedy_largest_first)
+        .add_menu_button("Coloring", "Greedy smallest last", run_greedy_smallest_last)
+        .add_menu_button("Coloring", "DSatur", run_dsatur)
+        .add_menu_button("Coloring", "Exact backtracking", run_exact)
+        .add_menu_button("Coloring", "Color by hand", toggle_manual_coloring)
+        ;
+    }
+}
+
+// The color a vertex was given by hand, an index into the palette
+#[derive(Component)]
+pub struct PaintedColor(pub usize);
+
+// While enabled a click on a vertex gives it the next color, the editor stays in edit mode meanwhile
+#[derive(Resource, Default)]
+pub struct ManualColoring {
+    previous: Option<EditorState>,
+}
+
+// Bigger graphs take too long to color exactly
+const EXACT_VERTEX_LIMIT: usize = 30;
+// The backtracking stops recording its search after this many steps
+const EXACT_TRACE_LIMIT: usize = 400;
+// The backtracking gives up after trying this many colors, some graphs below the vertex limit still take too long
+const EXACT_SEARCH_LIMIT: usize = 200_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Ordering {
+    Insertion,
+    LargestFirst,
+    SmallestLast,
+}
+
+fn run_greedy_insertion(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("Greedy coloring", greedy(&graph.snapshot(), Ordering::Insertion));
+}
+
+fn run_greedy_largest_first(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("Greedy coloring", greedy(&graph.snapshot(), Ordering::LargestFirst));
+}
+
+fn run_greedy_smallest_last(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("Greedy coloring", greedy(&graph.snapshot(), Ordering::SmallestLast));
+}
+
+fn run_dsatur(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("DSatur", dsatur(&graph.snapshot()));
+}
+
+fn run_exact(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    run.start("Exact coloring", exact(&graph.snapshot()));
+}
+
+// The smallest color none of the colored neighbours uses
+fn smallest_free_color(adjacent: &[usize], colors: &[Option<usize>]) -> usize {
+    let mut used: Vec<usize> = adjacent.iter().filter_map(|w| colors[*w]).collect();
+    used.sort();
+    used.dedup();
+    used.iter().enumerate().find(|(i, c)| *i != **c).map_or(used.len(), |(i, _)| i)
+}
+
+/**
+    # Clique Lower Bound
+    Every vertex of a clique needs its own color so the largest clique bounds the chromatic number from below.
+    Finding the largest clique is hard, so the clique is grown greedily from every vertex and the biggest one is kept.
+*/
+pub fn greedy_clique(graph: &GraphSnapshot) -> Vec<usize> {
+    let adjacency = graph.simple_adjacency();
+    let mut best: Vec<usize> = vec![];
+    for v in 0..graph.len() {
+        let mut candidates = adjacency[v].clone();
+        candidates.sort_by_key(|w| std::cmp::Reverse(adjacency[*w].len()));
+        let mut clique = vec![v];
+        for w in candidates {
+            if clique.iter().all(|u| adjacency[w].binary_search(u).is_ok()) {
+                clique.push(w);
+            }
+        }
+        if clique.len() > best.len() {
+            best = clique;
+        }
+    }
+    best
+}
+
+// Colors used, the clique lower bound and the clique itself highlighted
+fn summary(graph: &GraphSnapshot, trace: &mut Trace, algorithm: &str, colors: &[Option<usize>], proven: bool) {
+    let used = colors.iter().flatten().max().map_or(0, |c| c + 1);
+    let clique = greedy_clique(graph);
+    let bound = if proven {
+        format!("the chromatic number is {used}")
+    } else if clique.len() == used {
+        format!("a clique of size {} shows that {used} colors are optimal", clique.len())
+    } else {
+        format!("the chromatic number is between {} and {used}", clique.len())
+    };
+    let step = trace.step(format!("{algorithm} used {used} colors, {bound}"));
+    for v in 0..graph.len() {
+        step.vertex_label(graph.vertices[v], "");
+    }
+    for (a, &u) in clique.iter().enumerate() {
+        for &w in clique[a + 1..].iter() {
+            for (x, e) in graph.neighbours(u) {
+                if x == w {
+                    step.edge(graph.edge_entity(e), Mark::Highlight);
+                }
+            }
+        }
+    }
+}
+
+fn color_vertex(graph: &GraphSnapshot, trace: &mut Trace, v: usize, color: usize, reason: String) {
+    trace.step(reason)
+        .vertex(graph.vertices[v], Mark::Group(color))
+        .vertex_label(graph.vertices[v], format!("{}", color + 1));
+}
+
+fn used_colors_text(adjacent: &[usize], colors: &[Option<usize>]) -> String {
+    let mut used: Vec<usize> = adjacent.iter().filter_map(|w| colors[*w]).collect();
+    used.sort();
+    used.dedup();
+    if used.is_empty() {
+        return "no neighbour is colored".to_string();
+    }
+    let used: Vec<String> = used.iter().map(|c| format!("{}", c + 1)).collect();
+    format!("neighbours use {}", used.join(", "))
+}
+
+/**
+    # Greedy Coloring
+    Colors the vertices one after the other with the smallest color their colored neighbours don't use.
+    Largest first starts with the vertices of highest degree, smallest last repeatedly removes a vertex of smallest degree
+    and colors in reverse removal order, which uses at most degeneracy + 1 colors.
+*/
+pub fn greedy(graph: &GraphSnapshot, ordering: Ordering) -> Trace {
+    let n = graph.len();
+    let adjacency = graph.simple_adjacency();
+    let mut trace = Trace::new();
+
+    let order: Vec<usize> = match ordering {
+        Ordering::Insertion => (0..n).collect(),
+        Ordering::LargestFirst => {
+            let mut order: Vec<usize> = (0..n).collect();
+            order.sort_by_key(|v| std::cmp::Reverse(adjacency[*v].len()));
+            order
+        },
+        Ordering::SmallestLast => {
+            let mut degree: Vec<usize> = adjacency.iter().map(|adjacent| adjacent.len()).collect();
+            let mut removed = vec![false; n];
+            let mut order = vec![];
+            for _ in 0..n {
+                let v = (0..n).filter(|v| !removed[*v]).min_by_key(|v| degree[*v]).expect("a vertex is left");
+                removed[v] = true;
+                order.push(v);
+                for w in adjacency[v].iter() {
+                    degree[*w] = degree[*w].saturating_sub(1);
+                }
+            }
+            order.reverse();
+            order
+        },
+    };
+    let names: Vec<&str> = order.iter().map(|v| graph.name(*v)).collect();
+    trace.step(format!("Color in the order {}", names.join(", ")));
+
+    let mut colors: Vec<Option<usize>> = vec![None; n];
+    for v in order {
+        let color = smallest_free_color(&adjacency[v], &colors);
+        let reason = format!("{} gets color {}, {}", graph.name(v), color + 1, used_colors_text(&adjacency[v], &colors));
+        colors[v] = Some(color);
+        color_vertex(graph, &mut trace, v, color, reason);
+    }
+    let name = match ordering {
+        Ordering::Insertion => "Greedy in insertion order",
+        Ordering::LargestFirst => "Greedy largest first",
+        Ordering::SmallestLast => "Greedy smallest last",
+    };
+    summary(graph, &mut trace, name, &colors, false);
+    trace
+}
+
+/**
+    # DSatur
+    Always colors the vertex whose neighbours already use the most different colors, ties go to the higher degree.
+*/
+pub fn dsatur(graph: &GraphSnapshot) -> Trace {
+    let n = graph.len();
+    let adjacency = graph.simple_adjacency();
+    let mut trace = Trace::new();
+    let mut colors: Vec<Option<usize>> = vec![None; n];
+
+    let saturation = |colors: &[Option<usize>], v: usize| {
+        let mut used: Vec<usize> = adjacency[v].iter().filter_map(|w| colors[*w]).collect();
+        used.sort();
+        used.dedup();
+        used.len()
+    };
+
+    for _ in 0..n {
+        let v = (0..n)
+            .filter(|v| colors[*v].is_none())
+            .max_by_key(|v| (saturation(&colors, *v), adjacency[*v].len(), std::cmp::Reverse(*v)))
+            .expect("an uncolored vertex is left");
+        let color = smallest_free_color(&adjacency[v], &colors);
+        let reason = format!(
+            "{} has saturation {} and degree {}, it gets color {}, {}",
+            graph.name(v), saturation(&colors, v), adjacency[v].len(), color + 1, used_colors_text(&adjacency[v], &colors)
+        );
+        colors[v] = Some(color);
+        color_vertex(graph, &mut trace, v, color, reason);
+    }
+    summary(graph, &mut trace, "DSatur", &colors, false);
+    trace
+}
+
+/**
+    # Exact Coloring
+    Tries k colors for k from the clique lower bound upwards. The backtracking colors the vertices in DSatur order
+    and only opens a new color once per vertex, which removes the symmetric colorings.
+*/
+pub fn exact(graph: &GraphSnapshot) -> Trace {
+    exact_within(graph, EXACT_SEARCH_LIMIT)
+}
+
+// Exact coloring that gives up after trying `limit` colors for one k
+fn exact_within(graph: &GraphSnapshot, limit: usize) -> Trace {
+    let n = graph.len();
+    let mut trace = Trace::new();
+    if n > EXACT_VERTEX_LIMIT {
+        trace.step(format!("Exact coloring is limited to {EXACT_VERTEX_LIMIT} vertices, try DSatur instead"));
+        return trace;
+    }
+    if n == 0 {
+        trace.step("The graph has no vertices to color");
+        return trace;
+    }
+    let adjacency = graph.simple_adjacency();
+    let clique = greedy_clique(graph);
+    let mut k = clique.len();
+    trace.step(format!("A clique of size {k} needs {k} colors, start the search there"));
+    loop {
+        trace.step(format!("Try to color the graph with {k} colors"));
+        let mut colors: Vec<Option<usize>> = vec![None; n];
+        let mut search = ExactSearch { graph, adjacency: &adjacency, trace: &mut trace, k, recorded: 0, tried: 0, limit };
+        let found = search.color(&mut colors, 0);
+        if search.tried > limit {
+            trace.step(format!(
+                "The search gave up after trying {limit} colors, the graph needs at least {k} colors. Try DSatur instead"
+            ));
+            return trace;
+        }
+        if found {
+            let step = trace.step(format!("{k} colors are enough"));
+            for (v, color) in colors.iter().enumerate() {
+                let color = color.expect("every vertex is colored");
+                step.vertex(graph.vertices[v], Mark::Group(color))
+                    .vertex_label(graph.vertices[v], format!("{}", color + 1));
+            }
+            summary(graph, &mut trace, "Backtracking", &colors, true);
+            return trace;
+        }
+        let step = trace.step(format!("There is no coloring with {k} colors"));
+        for v in 0..n {
+            step.clear(graph.vertices[v]).vertex_label(graph.vertices[v], "");
+        }
+        k += 1;
+    }
+}
+
+struct ExactSearch<'a> {
+    graph: &'a GraphSnapshot,
+    adjacency: &'a [Vec<usize>],
+    trace: &'a mut Trace,
+    k: usize,
+    recorded: usize,
+    // Colors given so far, the search unwinds once this passes the limit
+    tried: usize,
+    limit: usize,
+}
+
+impl<'a> ExactSearch<'a> {
+    fn record(&mut self) -> Option<&mut Step> {
+        self.recorded += 1;
+        if self.recorded > EXACT_TRACE_LIMIT {
+            return None;
+        }
+        if self.recorded == EXACT_TRACE_LIMIT {
+            return Some(self.trace.step("The search is long, only its result is shown from here on"));
+        }
+        Some(self.trace.step(""))
+    }
+
+    fn color(&mut self, colors: &mut [Option<usize>], colored: usize) -> bool {
+        let n = colors.len();
+        if colored == n {
+            return true;
+        }
+        let saturation = |v: usize| {
+            let mut used: Vec<usize> = self.adjacency[v].iter().filter_map(|w| colors[*w]).collect();
+            used.sort();
+            used.dedup();
+            used.len()
+        };
+        let v = (0..n)
+            .filter(|v| colors[*v].is_none())
+            .max_by_key(|v| (saturation(*v), self.adjacency[*v].len(), std::cmp::Reverse(*v)))
+            .expect("an uncolored vertex is left");
+        let opened = colors.iter().flatten().max().map_or(0, |c| c + 1);
+
+        for color in 0..self.k.min(opened + 1) {
+            if self.adjacency[v].iter().any(|w| colors[*w] == Some(color)) {
+                continue;
+            }
+            self.tried += 1;
+            if self.tried > self.limit {
+                colors[v] = None;
+                return false;
+            }
+            colors[v] = Some(color);
+            let graph = self.graph;
+            if let Some(step) = self.record() {
+                if step.message.is_empty() {
+                    step.message = format!("Give {} color {}", graph.name(v), color + 1);
+                }
+                step.vertex(graph.vertices[v], Mark::Group(color))
+                    .vertex_label(graph.vertices[v], format!("{}", color + 1));
+            }
+            if self.color(colors, colored + 1) {
+                return true;
+            }
+            colors[v] = None;
+            if self.tried > self.limit {
+                return false;
+            }
+        }
+
+        let graph = self.graph;
+        if let Some(step) = self.record() {
+            if step.message.is_empty() {
+                step.message = format!("No color is left for {}, backtrack", graph.name(v));
+            }
+            step.vertex(graph.vertices[v], Mark::Cycle);
+            step.vertex_label(graph.vertices[v], "");
+        }
+        false
+    }
+}
+
+// The vertices painted by hand and what is needed to change their look
+#[derive(SystemParam)]
+struct Painting<'w, 's> {
+    commands: Commands<'w, 's>,
+    graph_assets: Res<'w, GraphAssets>,
+    q_painted: Query<'w, 's, (Entity, Ref<'static, PaintedColor>), With<Vertex>>,
+    q_own: Query<'w, 's, &'static OwnMaterial>,
+}
+
+impl<'w, 's> Painting<'w, 's> {
+    fn resting_material(&self, entity: Entity) -> Handle<ColorMaterial> {
+        resting_material(self.q_own.get(entity).ok(), &self.graph_assets)
+    }
+}
+
+fn toggle_manual_coloring(
+    mut manual: ResMut<ManualColoring>,
+    state: Res<State<EditorState>>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+    mut painting: Painting,
+    q_edge: Query<Entity, With<Edge>>,
+) {
+    if let Some(previous) = manual.previous.take() {
+        next_state.set(previous);
+        let painted = painting.q_painted.iter().map(|(entity, _)| entity);
+        let entities: Vec<Entity> = painted.chain(q_edge.iter()).collect();
+        for entity in entities {
+            let material = painting.resting_material(entity);
+            painting.commands.entity(entity).remove::<PaintedColor>().insert(BaseMaterial(material));
+        }
+        info.text.clear();
+        return;
+    }
+    manual.previous = Some(*state.get());
+    next_state.set(EditorState::Edit);
+}
+
+fn paint_vertex(
+    mut commands: Commands,
+    manual: Res<ManualColoring>,
+    nearest_points: Res<NearestPoints>,
+    q_painted: Query<&PaintedColor>,
+    q_my_action: Query<&ActionState<NormalInput>>,
+    graph_assets: Res<GraphAssets>,
+) {
+    if manual.previous.is_none() {
+        return;
+    }
+    let my_action = q_my_action.single();
+    if !my_action.just_pressed(&NormalInput::Select) {
+        return;
+    }
+    let Some(vertex) = nearest_points.hovered() else {return};
+    let color = q_painted.get(vertex).map_or(0, |painted| (painted.0 + 1) % graph_assets.palette.len());
+    commands.entity(vertex).insert((PaintedColor(color), BaseMaterial(graph_assets.palette[color].clone())));
+}
+
+// Highlights the edges between vertices of the same color and reports the progress
+fn show_manual_coloring(
+    manual: Res<ManualColoring>,
+    mut info: ResMut<InfoPanel>,
+    graph: GraphData,
+    assets: Res<AlgorithmAssets>,
+    mut painting: Painting,
+    q_added_edge: Query<(), Added<Edge>>,
+) {
+    if manual.previous.is_none() {
+        return;
+    }
+    let painted = painting.q_painted.iter().any(|(_, painted)| painted.is_changed());
+    if !manual.is_changed() && !painted && q_added_edge.is_empty() {
+        return;
+    }
+    let graph = graph.snapshot();
+    let colors: Vec<Option<usize>> = graph.vertices.iter()
+        .map(|v| painting.q_painted.get(*v).ok().map(|(_, painted)| painted.0))
+        .collect();
+
+    let mut conflicts = 0;
+    for (e, edge) in graph.edges.iter().enumerate() {
+        let conflict = colors[edge.from].is_some() && colors[edge.from] == colors[edge.to];
+        let material = if conflict { assets.material(Mark::Cycle) } else { painting.resting_material(graph.edge_entity(e)) };
+        conflicts += usize::from(conflict);
+        painting.commands.entity(graph.edge_entity(e)).insert(BaseMaterial(material));
+    }
+
+    let mut used: Vec<usize> = colors.iter().flatten().copied().collect();
+    used.sort();
+    used.dedup();
+    let uncolored = colors.iter().filter(|c| c.is_none()).count();
+    let bound = greedy_clique(&graph).len();
+    let verdict = if uncolored == 0 && conflicts == 0 {
+        "The coloring is proper!"
+    } else {
+        "Click a vertex to give it the next color"
+    };
+    info.text = format!(
+        "Coloring by hand\n{} colors used, {} conflicts, {} uncolored, a clique shows at least {} colors are needed\n{}",
+        used.len(), conflicts, uncolored, bound, verdict
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::Trace};
+
+    use super::{dsatur, exact, exact_within, greedy, Ordering};
+
+    fn undirected(n: usize, edges: &[(usize, usize)]) -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = edges.iter().map(|(a, b)| (*a, *b, 1, false)).collect();
+        GraphSnapshot::test_graph(n, &edges)
+    }
+
+    // The number of colors from the summary "... used k colors, ..."
+    fn used(trace: &Trace) -> usize {
+        let message = &trace.steps.last().unwrap().message;
+        let (before, _) = message.split_once(" colors,").expect("the summary is the last step");
+        before.rsplit(' ').next().unwrap().parse().unwrap()
+    }
+
+    // The Mycielski graph of a graph has the same largest clique and needs one more color
+    fn mycielski(n: usize, edges: &[(usize, usize)]) -> (usize, Vec<(usize, usize)>) {
+        let mut next = edges.to_vec();
+        for (a, b) in edges.iter() {
+            next.push((*a, b + n));
+            next.push((*b, a + n));
+        }
+        next.extend((0..n).map(|v| (v + n, 2 * n)));
+        (2 * n + 1, next)
+    }
+
+    #[test]
+    fn exact_colors() {
+        let cycle = undirected(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+        assert_eq!(used(&exact(&cycle)), 3);
+        let even = undirected(6, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 0)]);
+        assert_eq!(used(&exact(&even)), 2);
+        let complete: Vec<(usize, usize)> = (0..5).flat_map(|a| (a + 1..5).map(move |b| (a, b))).collect();
+        assert_eq!(used(&exact(&undirected(5, &complete))), 5);
+        // The Grötzsch graph has no triangle but needs 4 colors
+        let (n, edges) = mycielski(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+        assert_eq!(used(&exact(&undirected(n, &edges))), 4);
+    }
+
+    #[test]
+    fn heuristics_color_properly() {
+        // A crown graph, greedy in insertion order needs a color per pair while 2 are enough
+        let edges: Vec<(usize, usize)> = (0..4).flat_map(|a| (0..4).filter(move |b| *b != a).map(move |b| (2 * a, 2 * b + 1))).collect();
+        let graph = undirected(8, &edges);
+        assert_eq!(used(&greedy(&graph, Ordering::Insertion)), 4);
+        assert_eq!(used(&greedy(&graph, Ordering::SmallestLast)), 2);
+        assert_eq!(used(&dsatur(&graph)), 2);
+    }
+
+    #[test]
+    fn exact_search_gives_up() {
+        let (n, edges) = mycielski(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+        let message = exact_within(&undirected(n, &edges), 20).steps.last().unwrap().message.clone();
+        assert!(message.contains("gave up"), "{message}");
+        assert!(message.contains("at least 3 colors"), "{message}");
+    }
+}
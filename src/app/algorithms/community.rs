@@ -0,0 +1,478 @@
+use bevy::{app::{Plugin, Update}, asset::Assets, color::Color, math::{Vec2, Vec3}, prelude::{Added, BuildChildren, Circle, Commands, Component, DespawnRecursiveExt, DetectChanges, Entity, Mesh, Or, Query, RemovedComponents, Res, ResMut, Resource, Transform, Visibility, With}, sprite::{ColorMesh2dBundle, Mesh2dHandle}, utils::HashMap};
+
+use crate::app::{build_graph::{components::{edge_mesh, label_text, Edge, Vertex, EDGE_WIDTH}, RADIUS}, ui::{InfoPanel, MenuAppExt}};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::{AlgorithmAssets, AlgorithmRun}, steps::{Mark, Trace}};
+
+pub struct CommunityPlugin;
+impl Plugin for CommunityPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<Communities>()
+        .add_systems(Update, (expand_on_edit, update_super_vertices))
+        .add_menu_button("Communities", "Label propagation", run_label_propagation)
+        .add_menu_button("Communities", "Louvain", run_louvain)
+        .add_menu_button("Communities", "Collapse / expand", toggle_collapse)
+        ;
+    }
+}
+
+/**
+    # Communities
+    The communities found by the last run. While collapsed every community is drawn as one super-vertex
+    at the centroid of its members and the edges between two communities are bundled into one.
+*/
+#[derive(Resource, Default)]
+pub struct Communities {
+    // Vertex -> Community
+    pub assignment: HashMap<Entity, usize>,
+    pub collapsed: bool,
+}
+
+// A vertex or an edge of the collapsed view
+#[derive(Component)]
+struct SuperVertex;
+
+const MAX_SWEEPS: usize = 100;
+const SUPER_EDGE_WIDTH: f32 = 2. * EDGE_WIDTH;
+const SUPER_VERTEX_Z: f32 = 0.5;
+const SUPER_EDGE_Z: f32 = -0.8;
+const ANNOTATION_OFFSET: Vec3 = Vec3::new(0., 0., 1.);
+
+/**
+    # Weighted Graph
+    A symmetric adjacency matrix stored as lists. An entry on the diagonal holds the edges inside an aggregated vertex
+    counted from both ends, so the row sums stay the degrees. Directions, multi edges and weights of the drawn graph are ignored.
+*/
+struct WeightedGraph {
+    adjacency: Vec<Vec<(usize, f32)>>,
+}
+
+impl WeightedGraph {
+    fn new(graph: &GraphSnapshot) -> Self {
+        let adjacency = graph.simple_adjacency()
+            .into_iter()
+            .enumerate()
+            .map(|(v, adjacent)| adjacent.into_iter().filter(|w| *w != v).map(|w| (w, 1.)).collect())
+            .collect();
+        Self { adjacency }
+    }
+
+    fn len(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    fn degree(&self, v: usize) -> f32 {
+        self.adjacency[v].iter().map(|(_, weight)| weight).sum()
+    }
+
+    // 2m, every edge counted from both ends
+    fn total(&self) -> f32 {
+        (0..self.len()).map(|v| self.degree(v)).sum()
+    }
+
+    // Sum over the communities of (edges inside / m) - (degree sum / 2m)^2
+    fn modularity(&self, community: &[usize]) -> f32 {
+        let total = self.total();
+        if total == 0. {
+            return 0.;
+        }
+        let count = community.iter().max().map_or(0, |c| c + 1);
+        let mut inside = vec![0f32; count];
+        let mut degrees = vec![0f32; count];
+        for v in 0..self.len() {
+            degrees[community[v]] += self.degree(v);
+            for (w, weight) in self.adjacency[v].iter() {
+                if community[*w] == community[v] {
+                    inside[community[v]] += weight;
+                }
+            }
+        }
+        (0..count).map(|c| inside[c] / total - (degrees[c] / total).powi(2)).sum()
+    }
+
+    // Every community becomes one vertex, the edges between two communities add up
+    fn aggregate(&self, community: &[usize], count: usize) -> Self {
+        let mut weights: Vec<HashMap<usize, f32>> = vec![HashMap::new(); count];
+        for v in 0..self.len() {
+            for (w, weight) in self.adjacency[v].iter() {
+                *weights[community[v]].entry(community[*w]).or_insert(0.) += weight;
+            }
+        }
+        let adjacency = weights
+            .into_iter()
+            .map(|row| {
+                let mut row: Vec<(usize, f32)> = row.into_iter().collect();
+                row.sort_by_key(|(w, _)| *w);
+                row
+            })
+            .collect();
+        Self { adjacency }
+    }
+}
+
+// Numbers the labels 0.. in the order they first appear and returns their count
+fn renumber(labels: &mut [usize]) -> usize {
+    let mut numbers: HashMap<usize, usize> = HashMap::new();
+    for label in labels.iter_mut() {
+        let next = numbers.len();
+        *label = *numbers.entry(*label).or_insert(next);
+    }
+    numbers.len()
+}
+
+// Colors every vertex by its community, edges inside a community take its color
+fn community_step(graph: &GraphSnapshot, trace: &mut Trace, community: &[usize], message: String) {
+    let step = trace.step(message);
+    for (v, c) in community.iter().enumerate() {
+        step.vertex(graph.vertices[v], Mark::Group(*c));
+    }
+    for (e, edge) in graph.edges.iter().enumerate() {
+        if community[edge.from] == community[edge.to] {
+            step.edge(graph.edge_entity(e), Mark::Group(community[edge.from]));
+        } else {
+            step.clear(graph.edge_entity(e));
+        }
+    }
+}
+
+/**
+    # Label Propagation
+    Every vertex starts with a label of its own and takes the most common label of its neighbours, keeping its own
+    on ties if it is among the most common ones. The vertices are visited in a shuffled order every sweep, the shuffle
+    has a fixed seed so the result can be replayed.
+*/
+pub fn label_propagation(graph: &GraphSnapshot, mut trace: Option<&mut Trace>) -> Vec<usize> {
+    let weighted = WeightedGraph::new(graph);
+    let n = graph.len();
+    let mut labels: Vec<usize> = (0..n).collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+
+    if let Some(trace) = trace.as_deref_mut() {
+        community_step(graph, trace, &labels, format!("Every vertex starts in a community of its own, modularity {:.3}", weighted.modularity(&labels)));
+    }
+    for sweep in 1..=MAX_SWEEPS {
+        // Fisher-Yates with a linear congruential generator
+        for i in (1..n).rev() {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            order.swap(i, (seed >> 33) as usize % (i + 1));
+        }
+        let mut changed = 0;
+        for v in order.iter().copied() {
+            let mut counts: HashMap<usize, f32> = HashMap::new();
+            for (w, weight) in weighted.adjacency[v].iter() {
+                *counts.entry(labels[*w]).or_insert(0.) += weight;
+            }
+            let Some(best) = counts.values().copied().reduce(f32::max) else {continue};
+            if counts.get(&labels[v]) == Some(&best) {
+                continue;
+            }
+            let label = counts.iter().filter(|(_, count)| **count == best).map(|(label, _)| *label).min().expect("a label has the best count");
+            labels[v] = label;
+            changed += 1;
+        }
+        let mut shown = labels.clone();
+        let count = renumber(&mut shown);
+        if let Some(trace) = trace.as_deref_mut() {
+            community_step(graph, trace, &shown, format!(
+                "Sweep {sweep}: {changed} vertices took the label of most of their neighbours\n{count} communities, modularity {:.3}",
+                weighted.modularity(&shown)
+            ));
+        }
+        if changed == 0 {
+            if let Some(trace) = trace {
+                trace.step(format!("No label changed, the labels are stable after {sweep} sweeps with {count} communities"));
+            }
+            return shown;
+        }
+    }
+    renumber(&mut labels);
+    labels
+}
+
+/**
+    # Louvain
+    A pass moves single vertices to the neighbouring community with the best modularity gain
+    k_in(C) - tot(C) * k / 2m until no move helps, then merges every community into one vertex.
+    The passes repeat on the merged graph until a pass doesn't merge anything.
+*/
+pub fn louvain(graph: &GraphSnapshot, mut trace: Option<&mut Trace>) -> Vec<usize> {
+    let original = WeightedGraph::new(graph);
+    let mut current = WeightedGraph::new(graph);
+    // Original vertex -> vertex of the current graph
+    let mut membership: Vec<usize> = (0..graph.len()).collect();
+    let total = original.total();
+
+    if let Some(trace) = trace.as_deref_mut() {
+        community_step(graph, trace, &membership, format!("Every vertex starts in a community of its own, modularity {:.3}", original.modularity(&membership)));
+    }
+    if total == 0. {
+        return membership;
+    }
+
+    for pass in 1.. {
+        let n = current.len();
+        let mut community: Vec<usize> = (0..n).collect();
+        let degree: Vec<f32> = (0..n).map(|v| current.degree(v)).collect();
+        let mut tot = degree.clone();
+
+        for sweep in 1..=MAX_SWEEPS {
+            let mut moved = 0;
+            for v in 0..n {
+                let own = community[v];
+                tot[own] -= degree[v];
+                let mut links: HashMap<usize, f32> = HashMap::new();
+                links.insert(own, 0.);
+                for (w, weight) in current.adjacency[v].iter().filter(|(w, _)| *w != v) {
+                    *links.entry(community[*w]).or_insert(0.) += weight;
+                }
+                let gain = |c: usize, links: f32| links - tot[c] * degree[v] / total;
+                let mut best = (own, gain(own, links[&own]));
+                let mut candidates: Vec<(usize, f32)> = links.into_iter().collect();
+                candidates.sort_by_key(|(c, _)| *c);
+                for (c, links) in candidates {
+                    let gain = gain(c, links);
+                    if gain > best.1 + 1e-6 {
+                        best = (c, gain);
+                    }
+                }
+                tot[best.0] += degree[v];
+                if best.0 != own {
+                    community[v] = best.0;
+                    moved += 1;
+                }
+            }
+            if let Some(trace) = trace.as_deref_mut() {
+                let shown: Vec<usize> = membership.iter().map(|v| community[*v]).collect();
+                let mut numbered = shown.clone();
+                let count = renumber(&mut numbered);
+                community_step(graph, trace, &numbered, format!(
+                    "Pass {pass}, sweep {sweep}: {moved} moves improved the modularity\n{count} communities, modularity {:.3}",
+                    original.modularity(&numbered)
+                ));
+            }
+            if moved == 0 {
+                break;
+            }
+        }
+
+        let count = renumber(&mut community);
+        membership.iter_mut().for_each(|v| *v = community[*v]);
+        if count == n {
+            if let Some(trace) = trace {
+                trace.step(format!(
+                    "Pass {pass} merged nothing, Louvain stops with {count} communities and modularity {:.3}",
+                    original.modularity(&membership)
+                ));
+            }
+            break;
+        }
+        current = current.aggregate(&community, count);
+        if let Some(trace) = trace.as_deref_mut() {
+            community_step(graph, trace, &membership, format!(
+                "Pass {pass} merges {n} vertices into {count} super-vertices, modularity {:.3}",
+                original.modularity(&membership)
+            ));
+        }
+    }
+    membership
+}
+
+fn store(graph: &GraphSnapshot, labels: &[usize], communities: &mut Communities) {
+    communities.assignment = graph.vertices.iter().copied().zip(labels.iter().copied()).collect();
+}
+
+fn run_label_propagation(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+    mut communities: ResMut<Communities>,
+) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    let labels = label_propagation(&graph, Some(&mut trace));
+    store(&graph, &labels, &mut communities);
+    run.start("Label propagation", trace);
+}
+
+fn run_louvain(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+    mut communities: ResMut<Communities>,
+) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    let labels = louvain(&graph, Some(&mut trace));
+    store(&graph, &labels, &mut communities);
+    run.start("Louvain", trace);
+}
+
+// Without a previous run the communities come from Louvain
+fn toggle_collapse(
+    graph: GraphData,
+    mut communities: ResMut<Communities>,
+    mut info: ResMut<InfoPanel>,
+) {
+    if communities.collapsed {
+        communities.collapsed = false;
+        return;
+    }
+    if communities.assignment.is_empty() {
+        let graph = graph.snapshot();
+        if graph.is_empty() {
+            info.text = "There are no communities to collapse".to_string();
+            return;
+        }
+        let labels = louvain(&graph, None);
+        store(&graph, &labels, &mut communities);
+    }
+    communities.collapsed = true;
+}
+
+// The communities are stale once the graph changes
+fn expand_on_edit(
+    mut communities: ResMut<Communities>,
+    q_added: Query<(), Added<Vertex>>,
+    q_added_edge: Query<(), Added<Edge>>,
+    mut removed_vertices: RemovedComponents<Vertex>,
+    mut removed_edges: RemovedComponents<Edge>,
+) {
+    let removed = removed_vertices.read().count() + removed_edges.read().count() > 0;
+    if !removed && q_added.is_empty() && q_added_edge.is_empty() {
+        return;
+    }
+    if !communities.assignment.is_empty() {
+        communities.assignment.clear();
+        communities.collapsed = false;
+    }
+}
+
+type GraphElement = Or<(With<Vertex>, With<Edge>)>;
+
+#[allow(clippy::too_many_arguments)]
+fn update_super_vertices(
+    mut commands: Commands,
+    communities: Res<Communities>,
+    assets: Res<AlgorithmAssets>,
+    graph: GraphData,
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_super: Query<Entity, With<SuperVertex>>,
+    mut q_visibility: Query<&mut Visibility, GraphElement>,
+) {
+    if !communities.is_changed() {
+        return;
+    }
+    for entity in q_super.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let visibility = if communities.collapsed { Visibility::Hidden } else { Visibility::Inherited };
+    for mut current in q_visibility.iter_mut() {
+        if *current != visibility {
+            *current = visibility;
+        }
+    }
+    if !communities.collapsed {
+        return;
+    }
+
+    let graph = graph.snapshot();
+    let count = communities.assignment.values().max().map_or(0, |c| c + 1);
+    let mut members = vec![0usize; count];
+    let mut centroids = vec![Vec2::ZERO; count];
+    for (v, entity) in graph.vertices.iter().enumerate() {
+        let Some(c) = communities.assignment.get(entity).copied() else {continue};
+        members[c] += 1;
+        centroids[c] += graph.positions[v];
+    }
+    for c in 0..count {
+        centroids[c] /= members[c].max(1) as f32;
+    }
+
+    // Edges between two communities, counted once
+    let mut bundles: HashMap<(usize, usize), usize> = HashMap::new();
+    for edge in graph.edges.iter() {
+        let (Some(a), Some(b)) = (communities.assignment.get(&graph.vertices[edge.from]), communities.assignment.get(&graph.vertices[edge.to])) else {continue};
+        if a != b {
+            *bundles.entry((*a.min(b), *a.max(b))).or_insert(0) += 1;
+        }
+    }
+    for ((a, b), edges) in bundles {
+        let (start, end) = (centroids[a], centroids[b]);
+        let width = SUPER_EDGE_WIDTH * (edges as f32).sqrt();
+        commands.spawn((
+            ColorMesh2dBundle {
+                mesh: Mesh2dHandle::from(meshes.add(edge_mesh(start, end, false, width))),
+                material: assets.material(Mark::Done),
+                transform: Transform::from_translation(((start + end) / 2.).extend(SUPER_EDGE_Z)),
+                ..Default::default()
+            },
+            SuperVertex,
+        ))
+        .with_children(|parent| {
+            parent.spawn(label_text(edges.to_string(), Color::WHITE, ANNOTATION_OFFSET));
+        });
+    }
+    for c in (0..count).filter(|c| members[*c] > 0) {
+        let radius = RADIUS * (members[c] as f32).sqrt();
+        commands.spawn((
+            ColorMesh2dBundle {
+                mesh: Mesh2dHandle::from(meshes.add(Circle::new(radius))),
+                material: assets.material(Mark::Group(c)),
+                transform: Transform::from_translation(centroids[c].extend(SUPER_VERTEX_Z)),
+                ..Default::default()
+            },
+            SuperVertex,
+        ))
+        .with_children(|parent| {
+            parent.spawn(label_text(members[c].to_string(), Color::BLACK, ANNOTATION_OFFSET));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{label_propagation, louvain, WeightedGraph};
+
+    // Two cliques of four, joined by a single edge if bridged
+    fn two_cliques(bridged: bool) -> GraphSnapshot {
+        let mut edges = if bridged { vec![(3, 4, 1, false)] } else { vec![] };
+        for offset in [0, 4] {
+            for a in 0..4 {
+                for b in a + 1..4 {
+                    edges.push((a + offset, b + offset, 1, false));
+                }
+            }
+        }
+        GraphSnapshot::test_graph(8, &edges)
+    }
+
+    fn assert_two_cliques(labels: &[usize]) {
+        assert!(labels[..4].iter().all(|c| *c == labels[0]), "{labels:?}");
+        assert!(labels[4..].iter().all(|c| *c == labels[4]), "{labels:?}");
+        assert_ne!(labels[0], labels[4]);
+    }
+
+    #[test]
+    fn louvain_splits_two_cliques() {
+        let graph = two_cliques(true);
+        let labels = louvain(&graph, None);
+        assert_two_cliques(&labels);
+        // 13 edges, each side has 6 inside and degree sum 13
+        let expected = 2. * (6. / 13. - 0.25);
+        assert!((WeightedGraph::new(&graph).modularity(&labels) - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn label_propagation_keeps_cliques_apart() {
+        // With the bridge a label can flood both cliques, so only separate cliques are certain
+        assert_two_cliques(&label_propagation(&two_cliques(false), None));
+    }
+
+    #[test]
+    fn edgeless_graph_keeps_single_communities() {
+        let graph = GraphSnapshot::test_graph(3, &[]);
+        assert_eq!(louvain(&graph, None), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,377 @@
+use bevy::{app::{Plugin, Startup, Update}, asset::{Assets, Handle}, math::{Vec2, Vec3}, prelude::{Added, BuildChildren, Circle, Commands, Component, DespawnRecursiveExt, DetectChanges, Entity, IntoSystemConfigs, Mesh, Parent, Query, Ref, RemovedComponents, Res, ResMut, Resource, Transform, With, Without}, sprite::{ColorMesh2dBundle, Mesh2dHandle}};
+
+use crate::app::{build_graph::{components::{edge_mesh, Edge, Vertex}, RADIUS}, ui::MenuAppExt};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::{AlgorithmAssets, AlgorithmRun}, steps::{Mark, Trace}};
+
+pub struct ConnectivityPlugin;
+impl Plugin for ConnectivityPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_systems(Startup, init_overlay)
+        .add_systems(Update, (update_overlay, update_halo_meshes).chain())
+        .add_menu_button("Connectivity", "Articulation points and bridges", run_articulation_points)
+        .add_menu_button("Connectivity", "Toggle live overlay", toggle_overlay)
+        ;
+    }
+}
+
+/**
+    # Connectivity Overlay
+    Outlines the articulation points, the bridges and the biconnected components while the graph is edited.
+    The overlay is drawn as halos behind the vertices and edges so it doesn't fight with the colors of a running algorithm.
+*/
+#[derive(Resource)]
+pub struct ConnectivityOverlay {
+    pub enabled: bool,
+    halo_mesh: Handle<Mesh>,
+}
+
+// A mesh child of a vertex or an edge drawn by the overlay
+#[derive(Component)]
+struct Halo;
+
+const HALO_RADIUS: f32 = RADIUS + 12.;
+const HALO_EDGE_WIDTH: f32 = 22.;
+// Behind the vertex and the edge it belongs to, vertices sit at z 0 and edges at z -1
+const VERTEX_HALO_Z: f32 = -1.6;
+const EDGE_HALO_Z: f32 = -0.5;
+
+/**
+    # Biconnectivity
+    The result of Tarjan's DFS, edge directions are ignored.
+    Every edge belongs to exactly one component, a bridge is a component of its own.
+*/
+pub struct Biconnectivity {
+    pub articulation: Vec<bool>,
+    pub bridges: Vec<usize>,
+    pub components: Vec<Vec<usize>>,
+}
+
+fn init_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    commands.insert_resource(ConnectivityOverlay {
+        enabled: false,
+        halo_mesh: meshes.add(Circle::new(HALO_RADIUS)),
+    });
+}
+
+fn toggle_overlay(
+    mut overlay: ResMut<ConnectivityOverlay>,
+) {
+    overlay.enabled = !overlay.enabled;
+}
+
+fn run_articulation_points(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    biconnectivity(&graph, Some(&mut trace));
+    run.start("Articulation points and bridges", trace);
+}
+
+/**
+    # Biconnectivity
+    Iterative DFS so big graphs don't overflow the stack. low(v) is the smallest discovery time reachable
+    from the subtree of v with at most one back edge. A child w of u with low(w) >= disc(u) closes a biconnected
+    component and makes u an articulation point unless u is the root with a single child. If even low(w) > disc(u)
+    the tree edge is a bridge.
+*/
+pub fn biconnectivity(graph: &GraphSnapshot, mut trace: Option<&mut Trace>) -> Biconnectivity {
+    let n = graph.len();
+    let adjacency: Vec<Vec<(usize, usize)>> = (0..n).map(|v| graph.neighbours(v).collect()).collect();
+    let mut discovery: Vec<Option<usize>> = vec![None; n];
+    let mut low = vec![0usize; n];
+    let mut articulation = vec![false; n];
+    let mut bridges = vec![];
+    let mut components: Vec<Vec<usize>> = vec![];
+    let mut edge_stack: Vec<usize> = vec![];
+    let mut time = 0;
+
+    let label = |discovery: &[Option<usize>], low: &[usize], v: usize| {
+        format!("d{} l{}", discovery[v].unwrap_or_default(), low[v])
+    };
+
+    for root in 0..n {
+        if discovery[root].is_some() {
+            continue;
+        }
+        discovery[root] = Some(time);
+        low[root] = time;
+        time += 1;
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.step(format!("Start a DFS at {}", graph.name(root)))
+                .vertex(graph.vertices[root], Mark::Active)
+                .vertex_label(graph.vertices[root], label(&discovery, &low, root));
+        }
+        let mut root_children = 0;
+        // (Vertex, TreeEdge, NextNeighbour)
+        let mut stack: Vec<(usize, Option<usize>, usize)> = vec![(root, None, 0)];
+
+        while let Some(&mut (v, tree_edge, ref mut next)) = stack.last_mut() {
+            if let Some(&(w, e)) = adjacency[v].get(*next) {
+                *next += 1;
+                if Some(e) == tree_edge {
+                    continue;
+                }
+                let v_discovery = discovery[v].expect("vertices on the stack are discovered");
+                match discovery[w] {
+                    None => {
+                        edge_stack.push(e);
+                        discovery[w] = Some(time);
+                        low[w] = time;
+                        time += 1;
+                        if v == root {
+                            root_children += 1;
+                        }
+                        stack.push((w, Some(e), 0));
+                        if let Some(trace) = trace.as_deref_mut() {
+                            trace.step(format!("Visit {} from {}", graph.name(w), graph.name(v)))
+                                .edge(graph.edge_entity(e), Mark::Frontier)
+                                .vertex(graph.vertices[w], Mark::Active)
+                                .vertex_label(graph.vertices[w], label(&discovery, &low, w));
+                        }
+                    },
+                    // A back edge to an ancestor, edges to descendants were already seen from their side
+                    Some(w_discovery) if w_discovery < v_discovery => {
+                        edge_stack.push(e);
+                        if w_discovery < low[v] {
+                            low[v] = w_discovery;
+                            if let Some(trace) = trace.as_deref_mut() {
+                                trace.step(format!("Back edge {}-{} lowers low({}) to {}", graph.name(v), graph.name(w), graph.name(v), w_discovery))
+                                    .edge(graph.edge_entity(e), Mark::Highlight)
+                                    .vertex_label(graph.vertices[v], label(&discovery, &low, v));
+                            }
+                        }
+                    },
+                    Some(_) => {},
+                }
+                continue;
+            }
+
+            stack.pop();
+            let Some(tree_edge) = tree_edge else {continue};
+            let Some(&(u, _, _)) = stack.last() else {continue};
+            let u_discovery = discovery[u].expect("vertices on the stack are discovered");
+            let lowered = low[v] < low[u];
+            low[u] = low[u].min(low[v]);
+
+            if let Some(trace) = trace.as_deref_mut() {
+                let step = trace.step(format!("{} is finished with low({}) = {}, return to {}", graph.name(v), graph.name(v), low[v], graph.name(u)))
+                    .vertex(graph.vertices[v], Mark::Done);
+                if lowered {
+                    step.vertex_label(graph.vertices[u], label(&discovery, &low, u));
+                }
+            }
+
+            if low[v] < u_discovery {
+                continue;
+            }
+            let mut component = vec![];
+            while let Some(e) = edge_stack.pop() {
+                component.push(e);
+                if e == tree_edge {
+                    break;
+                }
+            }
+            let is_bridge = low[v] > u_discovery;
+            if is_bridge {
+                bridges.push(tree_edge);
+            }
+            let is_articulation = u != root || root_children > 1;
+            let newly_articulation = is_articulation && !articulation[u];
+            articulation[u] |= is_articulation;
+
+            if let Some(trace) = trace.as_deref_mut() {
+                let mut message = format!(
+                    "low({}) = {} >= disc({}) = {}, the edges since {}-{} form a biconnected component",
+                    graph.name(v), low[v], graph.name(u), u_discovery, graph.name(u), graph.name(v)
+                );
+                if is_bridge {
+                    message.push_str(&format!("\nlow({}) > disc({}) so {}-{} is a bridge", graph.name(v), graph.name(u), graph.name(u), graph.name(v)));
+                }
+                if newly_articulation {
+                    message.push_str(&format!("\n{} is an articulation point", graph.name(u)));
+                }
+                let step = trace.step(message);
+                for e in component.iter() {
+                    let mark = if is_bridge { Mark::Cycle } else { Mark::Group(components.len()) };
+                    step.edge(graph.edge_entity(*e), mark);
+                }
+                if newly_articulation {
+                    step.vertex(graph.vertices[u], Mark::Cycle);
+                }
+            }
+            components.push(component);
+        }
+    }
+
+    if let Some(trace) = trace {
+        let count = articulation.iter().filter(|a| **a).count();
+        let step = trace.step(format!(
+            "{} articulation points, {} bridges and {} biconnected components",
+            count, bridges.len(), components.len()
+        ));
+        for (v, is_articulation) in articulation.iter().enumerate() {
+            step.vertex_label(graph.vertices[v], "");
+            if *is_articulation {
+                step.vertex(graph.vertices[v], Mark::Cycle);
+            } else {
+                step.clear(graph.vertices[v]);
+            }
+        }
+    }
+    Biconnectivity { articulation, bridges, components }
+}
+
+// Recomputes the overlay from scratch whenever a vertex or an edge comes or goes
+#[allow(clippy::too_many_arguments)]
+fn update_overlay(
+    mut commands: Commands,
+    overlay: Res<ConnectivityOverlay>,
+    assets: Res<AlgorithmAssets>,
+    graph: GraphData,
+    q_added: Query<(), Added<Vertex>>,
+    q_added_edge: Query<(), Added<Edge>>,
+    mut removed_vertices: RemovedComponents<Vertex>,
+    mut removed_edges: RemovedComponents<Edge>,
+    q_halo: Query<Entity, With<Halo>>,
+) {
+    let removed = removed_vertices.read().count() + removed_edges.read().count() > 0;
+    let edited = removed || !q_added.is_empty() || !q_added_edge.is_empty();
+    if !overlay.is_changed() && !edited {
+        return;
+    }
+    for halo in q_halo.iter() {
+        commands.entity(halo).despawn_recursive();
+    }
+    if !overlay.enabled {
+        return;
+    }
+
+    let graph = graph.snapshot();
+    let result = biconnectivity(&graph, None);
+    for (v, _) in result.articulation.iter().enumerate().filter(|(_, a)| **a) {
+        commands.entity(graph.vertices[v]).with_children(|parent| {
+            parent.spawn((
+                ColorMesh2dBundle {
+                    mesh: Mesh2dHandle::from(overlay.halo_mesh.clone()),
+                    material: assets.material(Mark::Cycle),
+                    transform: Transform::from_translation(Vec3::new(0., 0., VERTEX_HALO_Z)),
+                    ..Default::default()
+                },
+                Halo,
+            ));
+        });
+    }
+    for (c, component) in result.components.iter().enumerate() {
+        let mark = if component.len() == 1 && result.bridges.contains(&component[0]) { Mark::Cycle } else { Mark::Group(c) };
+        for e in component {
+            commands.entity(graph.edge_entity(*e)).with_children(|parent| {
+                parent.spawn((
+                    ColorMesh2dBundle {
+                        material: assets.material(mark),
+                        transform: Transform::from_translation(Vec3::new(0., 0., EDGE_HALO_Z)),
+                        ..Default::default()
+                    },
+                    Halo,
+                ));
+            });
+        }
+    }
+}
+
+// Edge halos follow the shape of their edge
+fn update_halo_meshes(
+    q_vertex: Query<&Transform, With<Vertex>>,
+    q_edge: Query<(&Edge, Ref<Mesh2dHandle>), Without<Halo>>,
+    mut q_halo: Query<(Ref<Halo>, &Parent, &mut Mesh2dHandle)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (halo, parent, mut mesh) in q_halo.iter_mut() {
+        let Ok((edge, edge_mesh_handle)) = q_edge.get(parent.get()) else {continue};
+        if !halo.is_added() && !edge_mesh_handle.is_changed() {
+            continue;
+        }
+        let (Ok(start), Ok(end)) = (q_vertex.get(edge.start), q_vertex.get(edge.end)) else {continue};
+        let start: Vec2 = start.translation.truncate();
+        let end: Vec2 = end.translation.truncate();
+        *mesh = Mesh2dHandle::from(meshes.add(edge_mesh(start, end, edge.directed, HALO_EDGE_WIDTH)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::Trace};
+
+    use super::{biconnectivity, Biconnectivity};
+
+    fn articulation_points(result: &Biconnectivity) -> Vec<usize> {
+        (0..result.articulation.len()).filter(|v| result.articulation[*v]).collect()
+    }
+
+    // The edges of every component sorted, components in the order of their first edge
+    fn components(result: &Biconnectivity) -> Vec<Vec<usize>> {
+        let mut components: Vec<Vec<usize>> = result.components.iter()
+            .map(|component| {
+                let mut component = component.clone();
+                component.sort();
+                component
+            })
+            .collect();
+        components.sort();
+        components
+    }
+
+    #[test]
+    fn path() {
+        let graph = GraphSnapshot::test_graph(4, &[(0, 1, 1, false), (1, 2, 1, false), (2, 3, 1, false)]);
+        let result = biconnectivity(&graph, None);
+        assert_eq!(articulation_points(&result), vec![1, 2]);
+        let mut bridges = result.bridges.clone();
+        bridges.sort();
+        assert_eq!(bridges, vec![0, 1, 2]);
+        assert_eq!(components(&result), vec![vec![0], vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn cycle() {
+        let edges: Vec<(usize, usize, i32, bool)> = (0..5).map(|v| (v, (v + 1) % 5, 1, false)).collect();
+        let graph = GraphSnapshot::test_graph(5, &edges);
+        let result = biconnectivity(&graph, None);
+        assert!(articulation_points(&result).is_empty());
+        assert!(result.bridges.is_empty());
+        assert_eq!(components(&result), vec![vec![0, 1, 2, 3, 4]]);
+    }
+
+    #[test]
+    fn triangles_joined_by_a_bridge() {
+        let edges = [
+            (0, 1, 1, false), (1, 2, 1, false), (2, 0, 1, false),
+            (3, 4, 1, false), (4, 5, 1, false), (5, 3, 1, false),
+            (2, 3, 1, false),
+        ];
+        let graph = GraphSnapshot::test_graph(6, &edges);
+        let mut trace = Trace::new();
+        let result = biconnectivity(&graph, Some(&mut trace));
+        assert_eq!(articulation_points(&result), vec![2, 3]);
+        assert_eq!(result.bridges, vec![6]);
+        assert_eq!(components(&result), vec![vec![0, 1, 2], vec![3, 4, 5], vec![6]]);
+        assert!(trace.steps.last().unwrap().message.starts_with("2 articulation points, 1 bridges and 3 biconnected components"));
+    }
+
+    #[test]
+    fn triangles_sharing_a_vertex() {
+        // Directions are ignored, and an isolated vertex is no component
+        let edges = [(0, 1, 1, true), (1, 2, 1, false), (2, 0, 1, true), (0, 3, 1, false), (3, 4, 1, true), (4, 0, 1, false)];
+        let graph = GraphSnapshot::test_graph(6, &edges);
+        let result = biconnectivity(&graph, None);
+        assert_eq!(articulation_points(&result), vec![0]);
+        assert!(result.bridges.is_empty());
+        assert_eq!(result.components.len(), 2);
+    }
+}
@@ -0,0 +1,277 @@
+use bevy::{app::Plugin, prelude::{Res, ResMut}};
+
+use crate::app::ui::MenuAppExt;
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Trace}, targets::AlgorithmTargets};
+
+pub struct EulerPlugin;
+impl Plugin for EulerPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_menu_button("Eulerian Paths", "Hierholzer's algorithm", run_hierholzer)
+        ;
+    }
+}
+
+fn run_hierholzer(
+    graph: GraphData,
+    targets: Res<AlgorithmTargets>,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let graph = graph.snapshot();
+    let start = targets.start.and_then(|start| graph.index.get(&start).copied());
+    run.start("Hierholzer", hierholzer(&graph, start));
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EulerKind {
+    Circuit,
+    Path,
+}
+
+/**
+    # Eulerian Check
+    Shows the degrees that decide if the graph has an Eulerian walk and returns where the walk has to start.
+    An undirected graph needs zero or two vertices with odd degree, a directed graph needs in and out degree to be equal
+    except for one vertex with one more outgoing edge and one with one more incoming edge.
+    In both cases every edge has to lie in the same connected component.
+*/
+fn euler_start(graph: &GraphSnapshot, preferred: Option<usize>, trace: &mut Trace) -> Option<(usize, EulerKind)> {
+    let n = graph.len();
+    if graph.edges.is_empty() {
+        trace.step("The graph has no edges to walk");
+        return None;
+    }
+    let directed = graph.edges.iter().filter(|edge| edge.directed).count();
+    if directed != 0 && directed != graph.edges.len() {
+        trace.step("The graph mixes directed and undirected edges, make all edges directed or all undirected");
+        return None;
+    }
+    let directed = directed != 0;
+
+    let mut out_degree = vec![0i32; n];
+    let mut in_degree = vec![0i32; n];
+    for edge in graph.edges.iter() {
+        out_degree[edge.from] += 1;
+        in_degree[edge.to] += 1;
+    }
+
+    let step = trace.step(if directed {
+        "Compare the in and out degree of every vertex, marked vertices have more edges in one direction"
+    } else {
+        "Count the degree of every vertex, marked vertices have an odd degree"
+    });
+    let mut unbalanced = vec![];
+    for v in 0..n {
+        let (label, balanced) = if directed {
+            (format!("in {} out {}", in_degree[v], out_degree[v]), in_degree[v] == out_degree[v])
+        } else {
+            let degree = in_degree[v] + out_degree[v];
+            (format!("deg {degree}"), degree % 2 == 0)
+        };
+        step.vertex_label(graph.vertices[v], label);
+        if !balanced {
+            step.vertex(graph.vertices[v], Mark::Cycle);
+            unbalanced.push(v);
+        }
+    }
+
+    if let Some(v) = edge_outside_component(graph) {
+        trace.step(format!("{} has edges that aren't connected to the rest, there is no Eulerian walk", graph.name(v)))
+            .vertex(graph.vertices[v], Mark::Highlight);
+        return None;
+    }
+
+    let has_edges = |v: usize| in_degree[v] + out_degree[v] > 0;
+    let names = unbalanced.iter().map(|v| graph.name(*v)).collect::<Vec<_>>().join(", ");
+    if unbalanced.is_empty() {
+        let start = preferred.filter(|v| has_edges(*v)).or_else(|| (0..n).find(|v| has_edges(*v)))?;
+        trace.step(format!("Every vertex is balanced, there is an Eulerian circuit. Start at {}", graph.name(start)))
+            .vertex(graph.vertices[start], Mark::Active);
+        return Some((start, EulerKind::Circuit));
+    }
+
+    let start = if directed {
+        let starts: Vec<usize> = unbalanced.iter().copied().filter(|v| out_degree[*v] - in_degree[*v] == 1).collect();
+        let ends = unbalanced.iter().filter(|v| in_degree[**v] - out_degree[**v] == 1).count();
+        (unbalanced.len() == 2 && starts.len() == 1 && ends == 1).then(|| starts[0])
+    } else {
+        (unbalanced.len() == 2).then(|| preferred.filter(|v| unbalanced.contains(v)).unwrap_or(unbalanced[0]))
+    };
+    let Some(start) = start else {
+        let reason = if directed {
+            "the path needs exactly one vertex with one more outgoing edge and one with one more incoming edge"
+        } else {
+            "a path can only start and end at the two odd vertices"
+        };
+        trace.step(format!("{names} are unbalanced, there is no Eulerian walk because {reason}"));
+        return None;
+    };
+    trace.step(format!("Only {names} are unbalanced, there is an Eulerian path but no circuit. Start at {}", graph.name(start)))
+        .vertex(graph.vertices[start], Mark::Active);
+    Some((start, EulerKind::Path))
+}
+
+// A vertex with edges outside the component of the first vertex with edges, directions are ignored
+fn edge_outside_component(graph: &GraphSnapshot) -> Option<usize> {
+    let n = graph.len();
+    let has_edges = |v: usize| graph.neighbours(v).next().is_some();
+    let first = (0..n).find(|v| has_edges(*v))?;
+    let mut seen = vec![false; n];
+    seen[first] = true;
+    let mut stack = vec![first];
+    while let Some(v) = stack.pop() {
+        for (w, _) in graph.neighbours(v) {
+            if !seen[w] {
+                seen[w] = true;
+                stack.push(w);
+            }
+        }
+    }
+    (0..n).find(|v| has_edges(*v) && !seen[*v])
+}
+
+/**
+    # Hierholzer's Algorithm
+    Walks unused edges until it gets stuck, which can only happen where the current sub-walk started.
+    Backtracking then appends the stuck edges to the final walk and splices in a new sub-walk wherever a vertex
+    still has unused edges. The walk is built back to front, at the end every edge is numbered in walk order.
+*/
+pub fn hierholzer(graph: &GraphSnapshot, preferred: Option<usize>) -> Trace {
+    let mut trace = Trace::new();
+    let Some((start, kind)) = euler_start(graph, preferred, &mut trace) else {
+        return trace;
+    };
+    let step = trace.step("Walk along unused edges until the walk gets stuck");
+    for v in 0..graph.len() {
+        step.vertex_label(graph.vertices[v], "").clear(graph.vertices[v]);
+    }
+    step.vertex(graph.vertices[start], Mark::Active);
+
+    let mut used = vec![false; graph.edges.len()];
+    let mut next = vec![0usize; graph.len()];
+    // (Vertex, EdgeUsedToGetThere)
+    let mut stack: Vec<(usize, Option<usize>)> = vec![(start, None)];
+    // Edges of the walk from the end to the start
+    let mut walk_back: Vec<(usize, usize)> = vec![];
+    let mut walked = 0;
+
+    while let Some(&(v, by)) = stack.last() {
+        let out = &graph.out[v];
+        while next[v] < out.len() && used[out[next[v]].1] {
+            next[v] += 1;
+        }
+        if let Some(&(w, e)) = out.get(next[v]) {
+            used[e] = true;
+            walked += 1;
+            stack.push((w, Some(e)));
+            trace.step(format!("Walk {} → {}", graph.name(v), graph.name(w)))
+                .vertex(graph.vertices[v], Mark::Frontier)
+                .vertex(graph.vertices[w], Mark::Active)
+                .edge(graph.edge_entity(e), Mark::Frontier)
+                .edge_label(graph.edge_entity(e), format!("#{walked}"));
+            continue;
+        }
+
+        stack.pop();
+        let Some(e) = by else {continue};
+        let (u, _) = *stack.last().expect("an edge was walked from the vertex below");
+        walk_back.push((v, e));
+        let step = trace.step(format!("{} has no unused edges, {} → {} is the edge {} from the end of the walk", graph.name(v), graph.name(u), graph.name(v), walk_back.len()));
+        step.vertex(graph.vertices[v], Mark::Done)
+            .vertex(graph.vertices[u], Mark::Active)
+            .edge(graph.edge_entity(e), Mark::Done);
+        if next[u] < graph.out[u].len() && graph.out[u][next[u]..].iter().any(|(_, e)| !used[*e]) {
+            step.message.push_str(&format!("\n{} still has unused edges, a new sub-walk starts there", graph.name(u)));
+        }
+    }
+
+    let mut order = vec![start];
+    order.extend(walk_back.iter().rev().map(|(v, _)| *v));
+    let what = if kind == EulerKind::Circuit { "Eulerian circuit" } else { "Eulerian path" };
+    let step = trace.step(format!("{what}: {}", graph.path_names(&order)));
+    for (i, (_, e)) in walk_back.iter().rev().enumerate() {
+        step.edge(graph.edge_entity(*e), Mark::Done)
+            .edge_label(graph.edge_entity(*e), format!("{}", i + 1));
+    }
+    step.vertex(graph.vertices[start], Mark::Active);
+    trace
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::Trace};
+
+    use super::hierholzer;
+
+    // The kind of walk and its vertices from the last step, None if there is no walk
+    fn walk(trace: &Trace) -> Option<(String, Vec<usize>)> {
+        let message = &trace.steps.last()?.message;
+        let (kind, path) = message.split_once(": ")?;
+        let vertices = path.split(" → ").map(|name| name.trim_start_matches('v').parse().unwrap()).collect();
+        Some((kind.to_string(), vertices))
+    }
+
+    // Asserts that the walk uses every edge exactly once in its direction
+    fn assert_eulerian(graph: &GraphSnapshot, vertices: &[usize]) {
+        assert_eq!(vertices.len(), graph.edges.len() + 1);
+        let mut used = vec![false; graph.edges.len()];
+        for pair in vertices.windows(2) {
+            let e = (0..graph.edges.len()).find(|e| {
+                let edge = &graph.edges[*e];
+                !used[*e] && ((edge.from, edge.to) == (pair[0], pair[1]) || (!edge.directed && (edge.to, edge.from) == (pair[0], pair[1])))
+            });
+            used[e.expect("every step of the walk is an unused edge")] = true;
+        }
+    }
+
+    #[test]
+    fn undirected_circuit() {
+        // Two triangles sharing vertex 0
+        let edges = [(0, 1, 1, false), (1, 2, 1, false), (2, 0, 1, false), (0, 3, 1, false), (3, 4, 1, false), (4, 0, 1, false)];
+        let graph = GraphSnapshot::test_graph(5, &edges);
+        let (kind, vertices) = walk(&hierholzer(&graph, Some(3))).unwrap();
+        assert_eq!(kind, "Eulerian circuit");
+        assert_eq!(vertices.first(), Some(&3));
+        assert_eq!(vertices.last(), Some(&3));
+        assert_eulerian(&graph, &vertices);
+    }
+
+    #[test]
+    fn undirected_path_starts_at_an_odd_vertex() {
+        // A square with one diagonal, 1 and 3 have odd degree
+        let edges = [(0, 1, 1, false), (1, 2, 1, false), (2, 3, 1, false), (3, 0, 1, false), (1, 3, 1, false)];
+        let graph = GraphSnapshot::test_graph(4, &edges);
+        let (kind, vertices) = walk(&hierholzer(&graph, Some(0))).unwrap();
+        assert_eq!(kind, "Eulerian path");
+        assert!(vertices[0] == 1 || vertices[0] == 3);
+        assert_eulerian(&graph, &vertices);
+        let (_, vertices) = walk(&hierholzer(&graph, Some(3))).unwrap();
+        assert_eq!((vertices[0], vertices[vertices.len() - 1]), (3, 1));
+    }
+
+    #[test]
+    fn directed_path() {
+        let edges = [(0, 1, 1, true), (1, 2, 1, true), (2, 0, 1, true), (0, 3, 1, true)];
+        let graph = GraphSnapshot::test_graph(4, &edges);
+        let (kind, vertices) = walk(&hierholzer(&graph, None)).unwrap();
+        assert_eq!(kind, "Eulerian path");
+        assert_eq!((vertices[0], vertices[vertices.len() - 1]), (0, 3));
+        assert_eulerian(&graph, &vertices);
+    }
+
+    #[test]
+    fn no_walk() {
+        // A star with three leaves has four odd vertices
+        let star = GraphSnapshot::test_graph(4, &[(0, 1, 1, false), (0, 2, 1, false), (0, 3, 1, false)]);
+        let message = hierholzer(&star, None).steps.last().unwrap().message.clone();
+        assert!(message.contains("there is no Eulerian walk"), "{message}");
+        // Two separate triangles
+        let edges = [(0, 1, 1, false), (1, 2, 1, false), (2, 0, 1, false), (3, 4, 1, false), (4, 5, 1, false), (5, 3, 1, false)];
+        let message = hierholzer(&GraphSnapshot::test_graph(6, &edges), None).steps.last().unwrap().message.clone();
+        assert!(message.contains("aren't connected"), "{message}");
+        let mixed = GraphSnapshot::test_graph(3, &[(0, 1, 1, true), (1, 2, 1, false)]);
+        let message = hierholzer(&mixed, None).steps.last().unwrap().message.clone();
+        assert!(message.contains("mixes directed and undirected"), "{message}");
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::VecDeque;
+
+use bevy::{app::Plugin, prelude::{Res, ResMut, Resource}};
+
+use crate::app::ui::{InfoPanel, MenuAppExt};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Trace}, targets::AlgorithmTargets};
+
+pub struct MaxFlowPlugin;
+impl Plugin for MaxFlowPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<FlowSettings>()
+        .add_menu_button("Maximum Flow", "Edmonds-Karp", run_edmonds_karp)
+        .add_menu_button("Maximum Flow", "Dinic", run_dinic)
+        .add_menu_button("Maximum Flow", "Toggle residual view", toggle_residual_view)
+        ;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FlowAlgorithm {
+    EdmondsKarp,
+    Dinic,
+}
+
+impl FlowAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FlowAlgorithm::EdmondsKarp => "Edmonds-Karp",
+            FlowAlgorithm::Dinic => "Dinic",
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct FlowSettings {
+    // Label the edges with their residual capacities in both directions instead of flow/capacity
+    pub residual_view: bool,
+    // The last flow run, it is recomputed when the view is toggled
+    last: Option<FlowAlgorithm>,
+}
+
+fn run_flow(
+    algorithm: FlowAlgorithm,
+    graph: &GraphData,
+    targets: &AlgorithmTargets,
+    settings: &mut FlowSettings,
+) -> Trace {
+    settings.last = Some(algorithm);
+    let graph = graph.snapshot();
+    let source = targets.start.and_then(|source| graph.index.get(&source).copied());
+    let sink = targets.end.and_then(|sink| graph.index.get(&sink).copied());
+    flow_trace(algorithm, &graph, source, sink, settings.residual_view)
+}
+
+// The run for a source and a sink, explains what is missing instead
+pub fn flow_trace(algorithm: FlowAlgorithm, graph: &GraphSnapshot, source: Option<usize>, sink: Option<usize>, residual_view: bool) -> Trace {
+    let mut trace = Trace::new();
+    let (Some(source), Some(sink)) = (source, sink) else {
+        trace.step("Pick a start vertex as source and an end vertex as sink first");
+        return trace;
+    };
+    if source == sink {
+        trace.step("The source and the sink have to be different vertices");
+        return trace;
+    }
+    match algorithm {
+        FlowAlgorithm::EdmondsKarp => edmonds_karp(graph, source, sink, residual_view),
+        FlowAlgorithm::Dinic => dinic(graph, source, sink, residual_view),
+    }
+}
+
+fn run_edmonds_karp(
+    graph: GraphData,
+    targets: Res<AlgorithmTargets>,
+    mut settings: ResMut<FlowSettings>,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let trace = run_flow(FlowAlgorithm::EdmondsKarp, &graph, &targets, &mut settings);
+    run.start(FlowAlgorithm::EdmondsKarp.name(), trace);
+}
+
+fn run_dinic(
+    graph: GraphData,
+    targets: Res<AlgorithmTargets>,
+    mut settings: ResMut<FlowSettings>,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let trace = run_flow(FlowAlgorithm::Dinic, &graph, &targets, &mut settings);
+    run.start(FlowAlgorithm::Dinic.name(), trace);
+}
+
+fn toggle_residual_view(
+    graph: GraphData,
+    targets: Res<AlgorithmTargets>,
+    mut settings: ResMut<FlowSettings>,
+    mut run: ResMut<AlgorithmRun>,
+    mut info: ResMut<InfoPanel>,
+) {
+    settings.residual_view = !settings.residual_view;
+    let last_run = settings.last.filter(|algorithm| run.name == algorithm.name());
+    let Some(algorithm) = last_run else {
+        info.text = format!("Residual view: {}", if settings.residual_view {"on"} else {"off"});
+        return;
+    };
+    let cursor = run.cursor;
+    let trace = run_flow(algorithm, &graph, &targets, &mut settings);
+    run.restart_at(trace, cursor);
+}
+
+/**
+    # Flow Network
+    Every edge becomes a pair of arcs, arc 2e goes along edge e and arc 2e + 1 is its reverse.
+    The reverse of a directed edge has no capacity, an undirected edge has the capacity in both directions.
+    Weights are the capacities, negative weights count as zero.
+*/
+struct FlowNetwork {
+    out_arcs: Vec<Vec<usize>>,
+    head: Vec<usize>,
+    capacity: Vec<i64>,
+    flow: Vec<i64>,
+}
+
+impl FlowNetwork {
+    fn new(graph: &GraphSnapshot) -> Self {
+        let mut network = Self {
+            out_arcs: vec![vec![]; graph.len()],
+            head: vec![],
+            capacity: vec![],
+            flow: vec![],
+        };
+        for edge in graph.edges.iter() {
+            let capacity = edge.weight.max(0) as i64;
+            let arc = network.head.len();
+            network.out_arcs[edge.from].push(arc);
+            network.out_arcs[edge.to].push(arc + 1);
+            network.head.extend([edge.to, edge.from]);
+            network.capacity.extend([capacity, if edge.directed {0} else {capacity}]);
+            network.flow.extend([0, 0]);
+        }
+        network
+    }
+
+    fn residual(&self, arc: usize) -> i64 {
+        self.capacity[arc] - self.flow[arc]
+    }
+
+    fn push(&mut self, arc: usize, amount: i64) {
+        self.flow[arc] += amount;
+        self.flow[arc ^ 1] -= amount;
+    }
+
+    fn edge(arc: usize) -> usize {
+        arc / 2
+    }
+
+    // BFS distances from the source over arcs with residual capacity
+    fn levels(&self, source: usize) -> Vec<Option<usize>> {
+        let mut level = vec![None; self.out_arcs.len()];
+        level[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(v) = queue.pop_front() {
+            let next = level[v].map(|l| l + 1);
+            for &arc in self.out_arcs[v].iter() {
+                let w = self.head[arc];
+                if level[w].is_none() && self.residual(arc) > 0 {
+                    level[w] = next;
+                    queue.push_back(w);
+                }
+            }
+        }
+        level
+    }
+
+    fn label(&self, e: usize, residual_view: bool) -> String {
+        let forward = 2 * e;
+        if residual_view {
+            format!("→{} ←{}", self.residual(forward), self.residual(forward + 1))
+        } else {
+            format!("{}/{}", self.flow[forward].abs(), self.capacity[forward])
+        }
+    }
+
+    fn value(&self, source: usize) -> i64 {
+        self.out_arcs[source].iter().map(|arc| self.flow[*arc]).sum()
+    }
+}
+
+// Edges without flow lose their mark, used edges are frontier colored and saturated ones done
+fn record_flow_state(graph: &GraphSnapshot, network: &FlowNetwork, trace: &mut Trace, edges: &[usize], message: String, residual_view: bool) {
+    let step = trace.step(message);
+    for &e in edges {
+        let entity = graph.edge_entity(e);
+        step.edge_label(entity, network.label(e, residual_view));
+        let forward = 2 * e;
+        if network.flow[forward] == 0 {
+            step.clear(entity);
+        } else if network.residual(forward) == 0 || network.residual(forward + 1) == 0 {
+            step.edge(entity, Mark::Done);
+        } else {
+            step.edge(entity, Mark::Frontier);
+        }
+    }
+}
+
+fn record_path(graph: &GraphSnapshot, network: &FlowNetwork, trace: &mut Trace, path: &[usize], source: usize, message: String) {
+    let step = trace.step(message);
+    step.vertex(graph.vertices[source], Mark::Active);
+    for &arc in path {
+        step.edge(graph.edge_entity(FlowNetwork::edge(arc)), Mark::Active)
+            .vertex(graph.vertices[network.head[arc]], Mark::Active);
+    }
+}
+
+fn path_vertices(network: &FlowNetwork, path: &[usize], source: usize) -> Vec<usize> {
+    let mut vertices = vec![source];
+    vertices.extend(path.iter().map(|arc| network.head[*arc]));
+    vertices
+}
+
+fn init_trace(graph: &GraphSnapshot, network: &FlowNetwork, source: usize, sink: usize, residual_view: bool) -> Trace {
+    let mut trace = Trace::new();
+    let all_edges: Vec<usize> = (0..graph.edges.len()).collect();
+    record_flow_state(graph, network, &mut trace, &all_edges, format!("Find the maximum flow from {} to {}, the edge weights are the capacities", graph.name(source), graph.name(sink)), residual_view);
+    trace
+}
+
+/**
+    # Edmonds-Karp
+    Ford-Fulkerson with the augmenting paths found by BFS, so every path is a shortest path in the residual graph.
+*/
+pub fn edmonds_karp(graph: &GraphSnapshot, source: usize, sink: usize, residual_view: bool) -> Trace {
+    let mut network = FlowNetwork::new(graph);
+    let mut trace = init_trace(graph, &network, source, sink, residual_view);
+
+    loop {
+        let mut parent_arc: Vec<Option<usize>> = vec![None; graph.len()];
+        let mut seen = vec![false; graph.len()];
+        seen[source] = true;
+        let mut queue = VecDeque::from([source]);
+        while let Some(v) = queue.pop_front() {
+            for &arc in network.out_arcs[v].iter() {
+                let w = network.head[arc];
+                if !seen[w] && network.residual(arc) > 0 {
+                    seen[w] = true;
+                    parent_arc[w] = Some(arc);
+                    queue.push_back(w);
+                }
+            }
+        }
+        if !seen[sink] {
+            break;
+        }
+
+        let mut path: Vec<usize> = vec![];
+        let mut v = sink;
+        while let Some(arc) = parent_arc[v] {
+            path.push(arc);
+            v = network.head[arc ^ 1];
+        }
+        path.reverse();
+
+        let bottleneck = path.iter().map(|arc| network.residual(*arc)).min().expect("the path has an arc");
+        let vertices = path_vertices(&network, &path, source);
+        record_path(graph, &network, &mut trace, &path, source, format!("BFS finds the augmenting path {} with bottleneck {bottleneck}", graph.path_names(&vertices)));
+
+        for &arc in path.iter() {
+            network.push(arc, bottleneck);
+        }
+        let edges: Vec<usize> = path.iter().map(|arc| FlowNetwork::edge(*arc)).collect();
+        let step_message = format!("Push {bottleneck} along the path, the flow is now {}", network.value(source));
+        record_flow_state(graph, &network, &mut trace, &edges, step_message, residual_view);
+        clear_vertices(graph, &mut trace, &vertices);
+    }
+
+    record_min_cut(graph, &network, &mut trace, source);
+    trace
+}
+
+/**
+    # Dinic
+    Every phase builds the level graph by BFS and saturates it with a blocking flow, found by DFS
+    that never looks at an arc twice in a phase.
+*/
+pub fn dinic(graph: &GraphSnapshot, source: usize, sink: usize, residual_view: bool) -> Trace {
+    let mut network = FlowNetwork::new(graph);
+    let mut trace = init_trace(graph, &network, source, sink, residual_view);
+    let mut phase = 0;
+
+    loop {
+        let level = network.levels(source);
+        let Some(sink_level) = level[sink] else {break};
+        phase += 1;
+
+        let step = trace.step(format!("Phase {phase}: the level graph only keeps arcs from level i to level i + 1, the sink has level {sink_level}"));
+        for (v, level) in level.iter().enumerate() {
+            match level {
+                Some(l) => step.vertex_label(graph.vertices[v], format!("L{l}")),
+                None => step.vertex_label(graph.vertices[v], "L-"),
+            };
+        }
+        for e in 0..graph.edges.len() {
+            let in_level_graph = [2 * e, 2 * e + 1].iter().any(|arc| {
+                let from = network.head[arc ^ 1];
+                let to = network.head[*arc];
+                network.residual(*arc) > 0 && level[from].is_some() && level[to] == level[from].map(|l| l + 1)
+            });
+            if in_level_graph {
+                step.edge(graph.edge_entity(e), Mark::Highlight);
+            }
+        }
+
+        let mut next_arc = vec![0usize; graph.len()];
+        let mut phase_flow = 0;
+        while let Some(path) = blocking_path(&network, &level, &mut next_arc, source, sink) {
+            let bottleneck = path.iter().map(|arc| network.residual(*arc)).min().expect("the path has an arc");
+            let vertices = path_vertices(&network, &path, source);
+            record_path(graph, &network, &mut trace, &path, source, format!("DFS in the level graph finds {} with bottleneck {bottleneck}", graph.path_names(&vertices)));
+            for &arc in path.iter() {
+                network.push(arc, bottleneck);
+            }
+            phase_flow += bottleneck;
+            let edges: Vec<usize> = path.iter().map(|arc| FlowNetwork::edge(*arc)).collect();
+            record_flow_state(graph, &network, &mut trace, &edges, format!("Push {bottleneck}, the flow is now {}", network.value(source)), residual_view);
+            clear_vertices(graph, &mut trace, &vertices);
+        }
+
+        let all_edges: Vec<usize> = (0..graph.edges.len()).collect();
+        record_flow_state(graph, &network, &mut trace, &all_edges, format!("The blocking flow of phase {phase} pushes {phase_flow}"), residual_view);
+    }
+
+    record_min_cut(graph, &network, &mut trace, source);
+    trace
+}
+
+// Advances the current arc of each vertex past arcs that lead nowhere, so a phase takes O(VE)
+fn blocking_path(network: &FlowNetwork, level: &[Option<usize>], next_arc: &mut [usize], source: usize, sink: usize) -> Option<Vec<usize>> {
+    let mut path: Vec<usize> = vec![];
+    let mut v = source;
+    loop {
+        if v == sink {
+            return Some(path);
+        }
+        let mut advanced = false;
+        while next_arc[v] < network.out_arcs[v].len() {
+            let arc = network.out_arcs[v][next_arc[v]];
+            let w = network.head[arc];
+            if network.residual(arc) > 0 && level[v].is_some() && level[w] == level[v].map(|l| l + 1) {
+                path.push(arc);
+                v = w;
+                advanced = true;
+                break;
+            }
+            next_arc[v] += 1;
+        }
+        if advanced {
+            continue;
+        }
+        // Dead end, retreat and skip the arc that led here
+        let arc = path.pop()?;
+        v = network.head[arc ^ 1];
+        next_arc[v] += 1;
+    }
+}
+
+fn clear_vertices(graph: &GraphSnapshot, trace: &mut Trace, vertices: &[usize]) {
+    let Some(step) = trace.steps.last_mut() else {return};
+    for &v in vertices {
+        step.clear(graph.vertices[v]);
+    }
+}
+
+// The vertices reachable in the residual graph form the source side of a minimum cut
+fn record_min_cut(graph: &GraphSnapshot, network: &FlowNetwork, trace: &mut Trace, source: usize) {
+    let level = network.levels(source);
+    let source_side: Vec<bool> = level.iter().map(|l| l.is_some()).collect();
+
+    let mut cut_edges: Vec<usize> = vec![];
+    let mut cut_capacity = 0;
+    for (e, edge) in graph.edges.iter().enumerate() {
+        if source_side[edge.from] && !source_side[edge.to] {
+            cut_edges.push(e);
+            cut_capacity += network.capacity[2 * e];
+        } else if !edge.directed && source_side[edge.to] && !source_side[edge.from] {
+            cut_edges.push(e);
+            cut_capacity += network.capacity[2 * e + 1];
+        }
+    }
+
+    let side_names = |side: bool| -> String {
+        let names: Vec<&str> = (0..graph.len()).filter(|v| source_side[*v] == side).map(|v| graph.name(v)).collect();
+        names.join(", ")
+    };
+    let cut_names: Vec<String> = cut_edges.iter()
+        .map(|e| format!("{}-{}", graph.name(graph.edges[*e].from), graph.name(graph.edges[*e].to)))
+        .collect();
+    let step = trace.step(format!(
+        "Maximum flow {} equals the capacity {cut_capacity} of the minimum cut\nSource side: {}\nSink side: {}\nCut edges: {}",
+        network.value(source), side_names(true), side_names(false), cut_names.join(", ")
+    ));
+    for (v, source_side) in source_side.iter().enumerate() {
+        step.vertex(graph.vertices[v], Mark::Group(if *source_side {0} else {1}));
+    }
+    for e in cut_edges {
+        step.edge(graph.edge_entity(e), Mark::Cycle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::Trace};
+
+    use super::{dinic, edmonds_karp, flow_trace, FlowAlgorithm};
+
+    // The flow value and the cut capacity of the last step
+    fn result(trace: &Trace) -> (i64, i64) {
+        let message = &trace.steps.last().unwrap().message;
+        let numbers: Vec<i64> = message.lines().next().unwrap().split_whitespace().filter_map(|word| word.parse().ok()).collect();
+        (numbers[0], numbers[1])
+    }
+
+    fn both(graph: &GraphSnapshot, source: usize, sink: usize) -> [(i64, i64); 2] {
+        [result(&edmonds_karp(graph, source, sink, false)), result(&dinic(graph, source, sink, true))]
+    }
+
+    #[test]
+    fn textbook_network() {
+        // The network of CLRS figure 26.1 with a maximum flow of 23
+        let graph = GraphSnapshot::test_graph(6, &[
+            (0, 1, 16, true), (0, 2, 13, true), (2, 1, 4, true), (1, 3, 12, true), (3, 2, 9, true),
+            (2, 4, 14, true), (4, 3, 7, true), (3, 5, 20, true), (4, 5, 4, true),
+        ]);
+        assert_eq!(both(&graph, 0, 5), [(23, 23); 2]);
+    }
+
+    #[test]
+    fn undirected_edges_carry_flow_both_ways() {
+        let graph = GraphSnapshot::test_graph(4, &[(0, 1, 3, false), (0, 2, 2, true), (2, 1, 5, false), (1, 3, 10, true)]);
+        assert_eq!(both(&graph, 0, 3), [(5, 5); 2]);
+        // Backwards over the undirected edges only
+        assert_eq!(both(&graph, 1, 0), [(3, 3); 2]);
+    }
+
+    #[test]
+    fn unreachable_sink_has_no_flow() {
+        let graph = GraphSnapshot::test_graph(3, &[(0, 1, 4, true), (2, 1, 4, true)]);
+        assert_eq!(both(&graph, 0, 2), [(0, 0); 2]);
+    }
+
+    #[test]
+    fn negative_capacities_count_as_zero() {
+        let graph = GraphSnapshot::test_graph(3, &[(0, 1, -4, true), (1, 2, 4, true), (0, 2, 1, true)]);
+        assert_eq!(both(&graph, 0, 2), [(1, 1); 2]);
+    }
+
+    #[test]
+    fn missing_targets_are_explained() {
+        let graph = GraphSnapshot::test_graph(2, &[(0, 1, 1, true)]);
+        assert_eq!(flow_trace(FlowAlgorithm::Dinic, &graph, Some(0), None, false).steps.len(), 1);
+        assert!(flow_trace(FlowAlgorithm::EdmondsKarp, &graph, Some(1), Some(1), false).steps[0].message.contains("different"));
+    }
+}
@@ -0,0 +1,479 @@
+use bevy::{app::{Plugin, Update}, asset::Assets, math::{DVec2, Vec2}, prelude::{Added, Changed, Commands, Component, DespawnRecursiveExt, DetectChanges, Entity, Mesh, Or, Query, RemovedComponents, Res, ResMut, Resource, Transform, With}, sprite::{ColorMesh2dBundle, Mesh2dHandle}, utils::HashMap};
+
+use crate::app::{build_graph::{builder::GraphBuilder, components::{edge_mesh, Vertex, EDGE_WIDTH}, kdtree::TwoDTree, RADIUS}, ui::{InfoPanel, MenuAppExt}};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::{AlgorithmAssets, AlgorithmRun}, steps::{Mark, Trace}};
+
+pub struct GeometryPlugin;
+impl Plugin for GeometryPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<VoronoiOverlay>()
+        .add_systems(Update, update_voronoi)
+        .add_menu_button("Geometry", "Delaunay triangulation (Bowyer-Watson)", run_delaunay)
+        .add_menu_button("Geometry", "Add Delaunay edges", add_delaunay_edges)
+        .add_menu_button("Geometry", "Toggle Voronoi diagram", toggle_voronoi)
+        .add_menu_button("Geometry", "Add Euclidean MST edges", add_emst_edges)
+        .add_menu_button("Geometry", "Add Gabriel graph edges", add_gabriel_edges)
+        .add_menu_button("Geometry", "Add relative neighbourhood graph edges", add_rng_edges)
+        ;
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct VoronoiOverlay {
+    visible: bool,
+}
+
+#[derive(Component)]
+struct VoronoiSegment;
+
+// Below the edges and the segments of the algorithm runs
+const VORONOI_Z: f32 = -0.7;
+// Room around the vertices the cells of the outer vertices are cut off at
+const VORONOI_MARGIN: f32 = 6. * RADIUS;
+// The super triangle is this many times bigger than the points so its corners never get into a circumcircle they shouldn't
+const SUPER_TRIANGLE_SCALE: f64 = 100.;
+// Points are treated as equal and circles as touching within this distance
+const EPSILON: f64 = 1e-6;
+
+// A triangle with its circumcircle, the circle decides which triangles a new point breaks
+#[derive(Clone, Copy)]
+struct Triangle {
+    corners: [usize; 3],
+    center: DVec2,
+    radius_squared: f64,
+}
+
+impl Triangle {
+    fn new(corners: [usize; 3], points: &[DVec2]) -> Self {
+        let [a, b, c] = corners.map(|v| points[v]);
+        let d = 2. * (a.x * (b.y - c.y) + b.x * (c.y - a.y) + c.x * (a.y - b.y));
+        // Three points on a line have their center at infinity, no point is ever inside
+        let center = if d.abs() < EPSILON {
+            DVec2::splat(f64::MAX)
+        } else {
+            let (a2, b2, c2) = (a.length_squared(), b.length_squared(), c.length_squared());
+            DVec2::new(
+                (a2 * (b.y - c.y) + b2 * (c.y - a.y) + c2 * (a.y - b.y)) / d,
+                (a2 * (c.x - b.x) + b2 * (a.x - c.x) + c2 * (b.x - a.x)) / d,
+            )
+        };
+        Self {
+            corners,
+            center,
+            radius_squared: if d.abs() < EPSILON { f64::MAX } else { center.distance_squared(a) },
+        }
+    }
+
+    fn contains(&self, point: DVec2) -> bool {
+        self.radius_squared < f64::MAX && self.center.distance_squared(point) < self.radius_squared * (1. - EPSILON)
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.corners;
+        [(a.min(b), a.max(b)), (b.min(c), b.max(c)), (a.min(c), a.max(c))]
+    }
+}
+
+pub struct Triangulation {
+    // Triangles between vertices only, the ones touching the super triangle are gone
+    pub triangles: Vec<[usize; 3]>,
+    // Circumcenters of the triangles
+    pub centers: Vec<Vec2>,
+    // Every edge once with the lower vertex first, hull edges included
+    pub edges: Vec<(usize, usize)>,
+}
+
+fn real_edges(triangles: &[Triangle], n: usize) -> Vec<(usize, usize)> {
+    let mut edges: Vec<(usize, usize)> = triangles.iter()
+        .flat_map(|triangle| triangle.edges())
+        .filter(|(a, b)| *a < n && *b < n)
+        .collect();
+    edges.sort();
+    edges.dedup();
+    edges
+}
+
+fn edge_segments(graph: &GraphSnapshot, edges: &[(usize, usize)], mark: Mark) -> Vec<(Entity, Entity, Mark)> {
+    edges.iter().map(|(a, b)| (graph.vertices[*a], graph.vertices[*b], mark)).collect()
+}
+
+/**
+    # Bowyer-Watson
+    Starts with one huge triangle around every vertex. Every vertex in turn removes the triangles whose circumcircle
+    it lies in, which leaves a hole shaped like a star around it, and connects itself to the border of the hole.
+    Edges between two vertices of the final triangles are the Delaunay triangulation, the ones from triangles with a
+    corner of the huge triangle are on the convex hull. Vertices on top of each other are skipped.
+*/
+pub fn delaunay(graph: &GraphSnapshot, mut trace: Option<&mut Trace>) -> Triangulation {
+    let n = graph.len();
+    let mut points: Vec<DVec2> = graph.positions.iter().map(|position| position.as_dvec2()).collect();
+    let min = points.iter().copied().fold(DVec2::splat(f64::MAX), DVec2::min);
+    let max = points.iter().copied().fold(DVec2::splat(f64::MIN), DVec2::max);
+    let center = (min + max) / 2.;
+    let size = (max - min).max_element().max(1.) * SUPER_TRIANGLE_SCALE;
+    points.extend([
+        center + DVec2::new(-size, -size),
+        center + DVec2::new(size, -size),
+        center + DVec2::new(0., size),
+    ]);
+    let mut triangles = if n == 0 { vec![] } else { vec![Triangle::new([n, n + 1, n + 2], &points)] };
+
+    for v in 0..n {
+        if (0..v).any(|w| points[w].distance_squared(points[v]) < EPSILON) {
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.step(format!("{} lies on another vertex and is skipped", graph.name(v)))
+                    .vertex(graph.vertices[v], Mark::Cycle);
+            }
+            continue;
+        }
+        let (bad, good): (Vec<Triangle>, Vec<Triangle>) = triangles.iter().partition(|triangle| triangle.contains(points[v]));
+        // The border of the hole is made of the edges only one removed triangle has
+        let mut count: HashMap<(usize, usize), usize> = HashMap::new();
+        for edge in bad.iter().flat_map(|triangle| triangle.edges()) {
+            *count.entry(edge).or_insert(0) += 1;
+        }
+        let mut border: Vec<(usize, usize)> = count.into_iter().filter(|(_, c)| *c == 1).map(|(edge, _)| edge).collect();
+        border.sort();
+
+        if let Some(trace) = trace.as_deref_mut() {
+            let removed: Vec<(usize, usize)> = real_edges(&bad, n).into_iter().filter(|edge| !border.contains(edge)).collect();
+            let mut segments = edge_segments(graph, &real_edges(&good, n), Mark::Done);
+            segments.extend(edge_segments(graph, &removed, Mark::Cycle));
+            segments.extend(edge_segments(graph, &real_edges(&bad, n).into_iter().filter(|edge| border.contains(edge)).collect::<Vec<_>>(), Mark::Frontier));
+            trace.step(format!("{} lies in the circumcircle of {} triangles, their inner edges go", graph.name(v), bad.len()))
+                .vertex(graph.vertices[v], Mark::Active)
+                .segments(segments);
+        }
+
+        triangles = good;
+        triangles.extend(border.iter().map(|(a, b)| Triangle::new([*a, *b, v], &points)));
+
+        if let Some(trace) = trace.as_deref_mut() {
+            let added: Vec<(usize, usize)> = border.iter()
+                .flat_map(|(a, b)| [(v.min(*a), v.max(*a)), (v.min(*b), v.max(*b))])
+                .filter(|(a, b)| *a < n && *b < n)
+                .collect();
+            let mut segments = edge_segments(graph, &real_edges(&triangles, n).into_iter().filter(|edge| !added.contains(edge)).collect::<Vec<_>>(), Mark::Done);
+            segments.extend(edge_segments(graph, &added, Mark::Active));
+            trace.step(format!("{} is connected to the border of the hole", graph.name(v)))
+                .vertex(graph.vertices[v], Mark::Done)
+                .segments(segments);
+        }
+    }
+
+    let edges = real_edges(&triangles, n);
+    let inner: Vec<&Triangle> = triangles.iter().filter(|triangle| triangle.corners.iter().all(|v| *v < n)).collect();
+    if let Some(trace) = trace {
+        trace.step(format!("The Delaunay triangulation has {} triangles and {} edges", inner.len(), edges.len()))
+            .segments(edge_segments(graph, &edges, Mark::Done));
+    }
+    Triangulation {
+        triangles: inner.iter().map(|triangle| triangle.corners).collect(),
+        centers: inner.iter().map(|triangle| triangle.center.as_vec2()).collect(),
+        edges,
+    }
+}
+
+// Cuts the line origin + t * direction with t from start to end down to the box, None when it misses it
+fn clip(origin: Vec2, direction: Vec2, start: f32, end: f32, min: Vec2, max: Vec2) -> Option<(Vec2, Vec2)> {
+    let (mut low, mut high) = (start, end);
+    for axis in 0..2 {
+        if direction[axis].abs() < 1e-9 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let a = (min[axis] - origin[axis]) / direction[axis];
+        let b = (max[axis] - origin[axis]) / direction[axis];
+        low = low.max(a.min(b));
+        high = high.min(a.max(b));
+    }
+    (low < high).then(|| (origin + direction * low, origin + direction * high))
+}
+
+/**
+    # Voronoi Diagram
+    The dual of the triangulation. Every Delaunay edge gives the piece of the bisector of its ends between the
+    circumcenters of its two triangles. Hull edges have one triangle and give a ray outwards, the edges of vertices on a
+    line have none and give the whole bisector. Everything is cut to the box.
+*/
+pub fn voronoi(graph: &GraphSnapshot, triangulation: &Triangulation, min: Vec2, max: Vec2) -> Vec<(Vec2, Vec2)> {
+    let mut sides: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (t, [a, b, c]) in triangulation.triangles.iter().enumerate() {
+        for (x, y) in [(*a, *b), (*b, *c), (*a, *c)] {
+            sides.entry((x.min(y), x.max(y))).or_default().push(t);
+        }
+    }
+    triangulation.edges.iter()
+        .filter_map(|(a, b)| {
+            let (p, q) = (graph.positions[*a], graph.positions[*b]);
+            let normal = (q - p).perp().normalize_or_zero();
+            match sides.get(&(*a, *b)).map_or(&[][..], |triangles| triangles.as_slice()) {
+                [s, t] => {
+                    let (start, end) = (triangulation.centers[*s], triangulation.centers[*t]);
+                    clip(start, end - start, 0., 1., min, max)
+                },
+                [t] => {
+                    let [x, y, z] = triangulation.triangles[*t];
+                    let third = x + y + z - a - b;
+                    // Away from the third corner of the triangle
+                    let outwards = if normal.dot(graph.positions[third] - p) > 0. { -normal } else { normal };
+                    clip(triangulation.centers[*t], outwards, 0., f32::MAX, min, max)
+                },
+                _ => clip((p + q) / 2., normal, f32::MIN, f32::MAX, min, max),
+            }
+        })
+        .collect()
+}
+
+fn find(parent: &mut [usize], v: usize) -> usize {
+    let mut root = v;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[v] = root;
+    root
+}
+
+// Kruskal on the Delaunay edges, the Euclidean MST is always among them
+pub fn euclidean_mst(graph: &GraphSnapshot, triangulation: &Triangulation) -> Vec<(usize, usize)> {
+    let mut edges = triangulation.edges.clone();
+    edges.sort_by(|(a, b), (c, d)| graph.positions[*a].distance(graph.positions[*b]).total_cmp(&graph.positions[*c].distance(graph.positions[*d])));
+    let mut parent: Vec<usize> = (0..graph.len()).collect();
+    edges.into_iter()
+        .filter(|(a, b)| {
+            let (x, y) = (find(&mut parent, *a), find(&mut parent, *b));
+            parent[x] = y;
+            x != y
+        })
+        .collect()
+}
+
+// Delaunay edges whose circle with the edge as diameter has no other vertex inside, the kd-tree finds the candidates
+pub fn gabriel_graph(graph: &GraphSnapshot, triangulation: &Triangulation, kd: &TwoDTree) -> Vec<(usize, usize)> {
+    triangulation.edges.iter()
+        .filter(|(a, b)| {
+            let (p, q) = (graph.positions[*a], graph.positions[*b]);
+            let middle = (p + q) / 2.;
+            let radius = p.distance(q) / 2.;
+            !kd.radius_search(middle, radius).iter()
+                .filter_map(|item| graph.index.get(&item.0).copied())
+                .any(|c| c != *a && c != *b && graph.positions[c].distance(middle) < radius - EPSILON as f32)
+        })
+        .copied()
+        .collect()
+}
+
+// Delaunay edges without a vertex closer to both of their ends than they are to each other
+pub fn relative_neighbourhood_graph(graph: &GraphSnapshot, triangulation: &Triangulation, kd: &TwoDTree) -> Vec<(usize, usize)> {
+    triangulation.edges.iter()
+        .filter(|(a, b)| {
+            let (p, q) = (graph.positions[*a], graph.positions[*b]);
+            let length = p.distance(q);
+            !kd.radius_search(p, length).iter()
+                .filter_map(|item| graph.index.get(&item.0).copied())
+                .any(|c| c != *a && c != *b && graph.positions[c].distance(p).max(graph.positions[c].distance(q)) < length - EPSILON as f32)
+        })
+        .copied()
+        .collect()
+}
+
+fn kd_tree(graph: &GraphSnapshot) -> TwoDTree {
+    let mut kd = TwoDTree::new();
+    kd.insert_list(graph.vertices.iter().copied().zip(graph.positions.iter().copied()).collect());
+    kd
+}
+
+fn run_delaunay(graph: GraphData, mut run: ResMut<AlgorithmRun>) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    delaunay(&graph, Some(&mut trace));
+    run.start("Delaunay triangulation", trace);
+}
+
+// Undirected edges of weight 1, pairs that are connected already stay as they are
+fn add_edges(builder: &mut GraphBuilder, graph: &GraphSnapshot, edges: &[(usize, usize)], name: &str, info: &mut InfoPanel) {
+    let added = edges.iter()
+        .filter(|(a, b)| builder.add_edge(graph.vertices[*a], graph.vertices[*b], 1, false).is_some())
+        .count();
+    info.text = format!("{} has {} edges, {} of them are new", name, edges.len(), added);
+}
+
+fn add_delaunay_edges(graph: GraphData, mut builder: GraphBuilder, mut info: ResMut<InfoPanel>) {
+    let graph = graph.snapshot();
+    let triangulation = delaunay(&graph, None);
+    add_edges(&mut builder, &graph, &triangulation.edges, "The Delaunay triangulation", &mut info);
+}
+
+fn add_emst_edges(graph: GraphData, mut builder: GraphBuilder, mut info: ResMut<InfoPanel>) {
+    let graph = graph.snapshot();
+    let triangulation = delaunay(&graph, None);
+    add_edges(&mut builder, &graph, &euclidean_mst(&graph, &triangulation), "The Euclidean MST", &mut info);
+}
+
+fn add_gabriel_edges(graph: GraphData, mut builder: GraphBuilder, mut info: ResMut<InfoPanel>) {
+    let graph = graph.snapshot();
+    let triangulation = delaunay(&graph, None);
+    let edges = gabriel_graph(&graph, &triangulation, &kd_tree(&graph));
+    add_edges(&mut builder, &graph, &edges, "The Gabriel graph", &mut info);
+}
+
+fn add_rng_edges(graph: GraphData, mut builder: GraphBuilder, mut info: ResMut<InfoPanel>) {
+    let graph = graph.snapshot();
+    let triangulation = delaunay(&graph, None);
+    let edges = relative_neighbourhood_graph(&graph, &triangulation, &kd_tree(&graph));
+    add_edges(&mut builder, &graph, &edges, "The relative neighbourhood graph", &mut info);
+}
+
+fn toggle_voronoi(mut overlay: ResMut<VoronoiOverlay>) {
+    overlay.visible = !overlay.visible;
+}
+
+type MovedVertex = (With<Vertex>, Or<(Changed<Transform>, Added<Vertex>)>);
+
+// Redrawn whenever a vertex moves, is added or is removed while the overlay is shown
+#[allow(clippy::too_many_arguments)]
+fn update_voronoi(
+    mut commands: Commands,
+    overlay: Res<VoronoiOverlay>,
+    graph: GraphData,
+    assets: Res<AlgorithmAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    q_segment: Query<Entity, With<VoronoiSegment>>,
+    q_changed: Query<(), MovedVertex>,
+    mut removed_vertices: RemovedComponents<Vertex>,
+) {
+    let removed = removed_vertices.read().count() > 0;
+    if !overlay.is_changed() && (!overlay.visible || (q_changed.is_empty() && !removed)) {
+        return;
+    }
+    for entity in q_segment.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if !overlay.visible {
+        return;
+    }
+    let graph = graph.snapshot();
+    if graph.is_empty() {
+        return;
+    }
+    let min = graph.positions.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min) - VORONOI_MARGIN;
+    let max = graph.positions.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max) + VORONOI_MARGIN;
+    for (start, end) in voronoi(&graph, &delaunay(&graph, None), min, max) {
+        commands.spawn((
+            ColorMesh2dBundle {
+                mesh: Mesh2dHandle::from(meshes.add(edge_mesh(start, end, false, EDGE_WIDTH / 2.))),
+                material: assets.material(Mark::Highlight),
+                transform: Transform::from_translation(((start + end) / 2.).extend(VORONOI_Z)),
+                ..Default::default()
+            },
+            VoronoiSegment,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::Vec2, utils::HashSet};
+
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{delaunay, euclidean_mst, gabriel_graph, kd_tree, relative_neighbourhood_graph, voronoi};
+
+    fn points(n: usize, seed: u32) -> GraphSnapshot {
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 1000.
+        };
+        let positions = (0..n).map(|_| Vec2::new(next(), next())).collect();
+        GraphSnapshot::detached((0..n).map(|v| format!("v{v}")).collect(), positions, &[])
+    }
+
+    fn pairs(edges: &[(usize, usize)]) -> HashSet<(usize, usize)> {
+        edges.iter().map(|(a, b)| (*a.min(b), *a.max(b))).collect()
+    }
+
+    fn length(graph: &GraphSnapshot, edges: &[(usize, usize)]) -> f32 {
+        edges.iter().map(|(a, b)| graph.positions[*a].distance(graph.positions[*b])).sum()
+    }
+
+    #[test]
+    fn circumcircles_are_empty() {
+        for seed in 0..10 {
+            let graph = points(40, seed);
+            let triangulation = delaunay(&graph, None);
+            assert!(!triangulation.triangles.is_empty());
+            for (corners, center) in triangulation.triangles.iter().zip(&triangulation.centers) {
+                let radius = graph.positions[corners[0]].distance(*center);
+                for (v, position) in graph.positions.iter().enumerate() {
+                    if !corners.contains(&v) {
+                        assert!(position.distance(*center) > radius - 1e-2, "{v} lies in the circumcircle of {corners:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn voronoi_edges_are_between_two_nearest_vertices() {
+        let graph = points(30, 3);
+        let (min, max) = (Vec2::splat(-100.), Vec2::splat(1100.));
+        let segments = voronoi(&graph, &delaunay(&graph, None), min, max);
+        assert!(!segments.is_empty());
+        for (start, end) in segments {
+            let middle = (start + end) / 2.;
+            assert!(middle.cmpge(min - 1e-2).all() && middle.cmple(max + 1e-2).all());
+            let mut distances: Vec<f32> = graph.positions.iter().map(|position| position.distance(middle)).collect();
+            distances.sort_by(f32::total_cmp);
+            assert!(distances[1] - distances[0] < 1e-1, "{middle} is closer to one vertex");
+        }
+    }
+
+    // Kruskal over every pair of vertices
+    fn complete_mst_length(graph: &GraphSnapshot) -> f32 {
+        let n = graph.len();
+        let mut edges: Vec<(usize, usize)> = (0..n).flat_map(|a| (a + 1..n).map(move |b| (a, b))).collect();
+        edges.sort_by(|(a, b), (c, d)| graph.positions[*a].distance(graph.positions[*b]).total_cmp(&graph.positions[*c].distance(graph.positions[*d])));
+        let mut component: Vec<usize> = (0..n).collect();
+        let mut tree = vec![];
+        for (a, b) in edges {
+            let (x, y) = (component[a], component[b]);
+            if x != y {
+                component.iter_mut().filter(|c| **c == x).for_each(|c| *c = y);
+                tree.push((a, b));
+            }
+        }
+        length(graph, &tree)
+    }
+
+    #[test]
+    fn euclidean_mst_is_the_minimum_spanning_tree() {
+        for seed in 0..10 {
+            let graph = points(35, seed);
+            let tree = euclidean_mst(&graph, &delaunay(&graph, None));
+            assert_eq!(tree.len(), graph.len() - 1);
+            assert!((length(&graph, &tree) - complete_mst_length(&graph)).abs() < 1e-1);
+        }
+    }
+
+    #[test]
+    fn proximity_graphs_are_nested() {
+        for seed in 0..10 {
+            let graph = points(50, seed);
+            let triangulation = delaunay(&graph, None);
+            let kd = kd_tree(&graph);
+            let mst = pairs(&euclidean_mst(&graph, &triangulation));
+            let rng = pairs(&relative_neighbourhood_graph(&graph, &triangulation, &kd));
+            let gabriel = pairs(&gabriel_graph(&graph, &triangulation, &kd));
+            let delaunay = pairs(&triangulation.edges);
+            assert!(mst.is_subset(&rng));
+            assert!(rng.is_subset(&gabriel));
+            assert!(gabriel.is_subset(&delaunay));
+            assert!(gabriel.len() < delaunay.len());
+        }
+    }
+}
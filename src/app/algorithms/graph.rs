@@ -0,0 +1,211 @@
+use bevy::{ecs::system::SystemParam, math::Vec2, prelude::{Entity, Name, Query, Res, Transform, With}, utils::{HashMap, HashSet}};
+
+use crate::app::build_graph::{components::{Edge, Vertex}, res::AdjacencyList};
+
+pub struct SnapshotEdge {
+    pub entity: Entity,
+    pub from: usize,
+    pub to: usize,
+    pub weight: i32,
+    pub directed: bool,
+}
+
+/**
+    # Graph Snapshot
+    An index based copy of the graph so algorithms don't need to touch the ECS while they compute their steps.
+    Vertices are sorted by entity so the order is stable between snapshots of the same graph, it is not the
+    insertion order as despawned entities are reused. An undirected edge shows up in `out` and `inc` of both of its vertices.
+*/
+#[derive(Default)]
+pub struct GraphSnapshot {
+    pub vertices: Vec<Entity>,
+    pub names: Vec<String>,
+    pub positions: Vec<Vec2>,
+    pub index: HashMap<Entity, usize>,
+    pub edges: Vec<SnapshotEdge>,
+    // Vertex -> (AdjVertex, EdgeIndex) for the edges that can be walked from the vertex
+    pub out: Vec<Vec<(usize, usize)>>,
+    // Vertex -> (AdjVertex, EdgeIndex) for the edges that can be walked to the vertex
+    pub inc: Vec<Vec<(usize, usize)>>,
+}
+
+impl GraphSnapshot {
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn name(&self, v: usize) -> &str {
+        &self.names[v]
+    }
+
+    pub fn edge_entity(&self, e: usize) -> Entity {
+        self.edges[e].entity
+    }
+
+    // The incident edges of a vertex ignoring their direction, each edge is listed once per endpoint
+    pub fn neighbours(&self, v: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.out[v].iter().copied().chain(
+            self.inc[v].iter().copied().filter(move |(_, e)| self.edges[*e].directed)
+        )
+    }
+
+    // Adjacent vertices of every vertex ignoring the direction of the edges, sorted and without duplicates
+    pub fn simple_adjacency(&self) -> Vec<Vec<usize>> {
+        (0..self.len())
+            .map(|v| {
+                let mut adjacent: Vec<usize> = self.neighbours(v).map(|(w, _)| w).collect();
+                adjacent.sort();
+                adjacent.dedup();
+                adjacent
+            })
+            .collect()
+    }
+
+    pub fn path_names(&self, path: &[usize]) -> String {
+        path.iter().map(|v| self.name(*v)).collect::<Vec<_>>().join(" → ")
+    }
+
+    /**
+        # Detached Snapshot
+        A snapshot of a graph that was never spawned, like one read from a file without a window. Vertex i is
+        the placeholder entity i and edge e the entity n + e. Edges are skipped like the graph builder skips them:
+        loops and edges between vertices that are already connected in the same direction.
+    */
+    pub fn detached(names: Vec<String>, positions: Vec<Vec2>, edges: &[(usize, usize, i32, bool)]) -> GraphSnapshot {
+        let n = names.len();
+        let vertices: Vec<Entity> = (0..n).map(|i| Entity::from_raw(i as u32)).collect();
+        let mut graph = GraphSnapshot {
+            index: vertices.iter().enumerate().map(|(i, entity)| (*entity, i)).collect(),
+            vertices,
+            names,
+            positions,
+            out: vec![vec![]; n],
+            inc: vec![vec![]; n],
+            ..Default::default()
+        };
+        let mut connected: HashSet<(usize, usize)> = HashSet::new();
+        for &(from, to, weight, directed) in edges {
+            if from == to || connected.contains(&(from, to)) || (!directed && connected.contains(&(to, from))) {
+                continue;
+            }
+            connected.insert((from, to));
+            if !directed {
+                connected.insert((to, from));
+            }
+            let entity = Entity::from_raw((n + graph.edges.len()) as u32);
+            graph.push_edge(SnapshotEdge { entity, from, to, weight, directed });
+        }
+        graph
+    }
+
+    fn push_edge(&mut self, edge: SnapshotEdge) {
+        let e = self.edges.len();
+        self.out[edge.from].push((edge.to, e));
+        self.inc[edge.to].push((edge.from, e));
+        if !edge.directed {
+            self.out[edge.to].push((edge.from, e));
+            self.inc[edge.from].push((edge.to, e));
+        }
+        self.edges.push(edge);
+    }
+}
+
+// Everything needed to take a snapshot of the graph
+#[derive(SystemParam)]
+pub struct GraphData<'w, 's> {
+    q_vertex: Query<'w, 's, (Entity, &'static Transform, Option<&'static Name>), With<Vertex>>,
+    q_edge: Query<'w, 's, (Entity, &'static Edge)>,
+    adjacency: Res<'w, AdjacencyList>,
+}
+
+impl<'w, 's> GraphData<'w, 's> {
+    pub fn snapshot(&self) -> GraphSnapshot {
+        let mut vertices: Vec<(Entity, Vec2, String)> = self.q_vertex.iter()
+            .map(|(entity, transform, name)| {
+                let name = name.map(|name| name.to_string()).unwrap_or_else(|| format!("{entity}"));
+                (entity, transform.translation.truncate(), name)
+            })
+            .collect();
+        vertices.sort_by_key(|(entity, _, _)| *entity);
+
+        let n = vertices.len();
+        let mut graph = GraphSnapshot {
+            out: vec![vec![]; n],
+            inc: vec![vec![]; n],
+            ..Default::default()
+        };
+        for (i, (entity, position, name)) in vertices.into_iter().enumerate() {
+            graph.index.insert(entity, i);
+            graph.vertices.push(entity);
+            graph.positions.push(position);
+            graph.names.push(name);
+        }
+
+        let mut edges: Vec<(Entity, &Edge)> = self.q_edge.iter().collect();
+        edges.sort_by_key(|(entity, _)| *entity);
+        for (entity, edge) in edges {
+            let (Some(&from), Some(&to)) = (graph.index.get(&edge.start), graph.index.get(&edge.end)) else {continue};
+            let weight = self.adjacency.map.get(&edge.start)
+                .and_then(|adjacent| adjacent.iter().find(|(v, _)| *v == edge.end))
+                .map(|(_, weight)| *weight)
+                .unwrap_or(1);
+            graph.push_edge(SnapshotEdge { entity, from, to, weight, directed: edge.directed });
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+impl GraphSnapshot {
+    // A detached graph with the vertices v0, v1, ... on a horizontal line
+    pub fn test_graph(n: usize, edges: &[(usize, usize, i32, bool)]) -> GraphSnapshot {
+        let names = (0..n).map(|v| format!("v{v}")).collect();
+        let positions = (0..n).map(|v| Vec2::new(v as f32 * 100., 0.)).collect();
+        GraphSnapshot::detached(names, positions, edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Entity;
+
+    use super::GraphSnapshot;
+
+    #[test]
+    fn detached_indices() {
+        let graph = GraphSnapshot::test_graph(3, &[(0, 1, 5, true), (1, 2, 7, false)]);
+        assert_eq!(graph.len(), 3);
+        for v in 0..3 {
+            assert_eq!(graph.index[&graph.vertices[v]], v);
+        }
+        assert_eq!(graph.name(2), "v2");
+        assert_eq!(graph.edge_entity(1), Entity::from_raw(4));
+        assert_eq!(graph.out[0], vec![(1, 0)]);
+        assert!(graph.inc[0].is_empty());
+        // The undirected edge can be walked both ways
+        assert_eq!(graph.out[2], vec![(1, 1)]);
+        assert_eq!(graph.inc[1], vec![(0, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn skipped_edges() {
+        let graph = GraphSnapshot::test_graph(3, &[(0, 0, 1, true), (0, 1, 1, true), (0, 1, 2, true), (1, 0, 3, true), (1, 2, 1, false), (2, 1, 1, true)]);
+        // A loop and the repeated edges are left out, the opposite direction is its own edge
+        let edges: Vec<(usize, usize, i32)> = graph.edges.iter().map(|edge| (edge.from, edge.to, edge.weight)).collect();
+        assert_eq!(edges, vec![(0, 1, 1), (1, 0, 3), (1, 2, 1)]);
+        assert_eq!(graph.simple_adjacency(), vec![vec![1], vec![0, 2], vec![1]]);
+    }
+
+    #[test]
+    fn neighbours_list_every_edge_once() {
+        let graph = GraphSnapshot::test_graph(3, &[(0, 1, 1, true), (2, 1, 1, false)]);
+        let mut neighbours: Vec<(usize, usize)> = graph.neighbours(1).collect();
+        neighbours.sort();
+        assert_eq!(neighbours, vec![(0, 0), (2, 1)]);
+        assert_eq!(graph.path_names(&[0, 1, 2]), "v0 → v1 → v2");
+    }
+}
@@ -0,0 +1,121 @@
+use super::{centrality::{metric_trace, metric_values, CentralityTable, Metric}, cliques::{bron_kerbosch, independent_set, vertex_cover}, coloring::{dsatur, exact, greedy, Ordering}, community::{label_propagation, louvain}, connectivity::biconnectivity, euler::hierholzer, flow::{flow_trace, FlowAlgorithm}, geometry::delaunay, graph::GraphSnapshot, matching::{bipartition, blossom, hopcroft_karp}, spectral::{bisection_trace, fiedler_trace}, steps::Trace, toposort::{dfs_toposort, kahn}, tsp::{christofides, held_karp, nearest_neighbour_tour, or_opt, tour_start, two_opt}};
+
+// The algorithms that run without a window, by the name they are asked for on the command line
+pub const ALGORITHMS: [&str; 32] = [
+    "kahn",
+    "dfs-toposort",
+    "edmonds-karp",
+    "dinic",
+    "bipartite-check",
+    "hopcroft-karp",
+    "blossom",
+    "articulation-points",
+    "hierholzer",
+    "greedy-coloring",
+    "greedy-largest-first",
+    "greedy-smallest-last",
+    "dsatur",
+    "exact-coloring",
+    "nearest-neighbour",
+    "2-opt",
+    "or-opt",
+    "christofides",
+    "held-karp",
+    "bron-kerbosch",
+    "independent-set",
+    "vertex-cover",
+    "degree",
+    "closeness",
+    "betweenness",
+    "eigenvector",
+    "pagerank",
+    "label-propagation",
+    "louvain",
+    "fiedler-vector",
+    "spectral-bisection",
+    "delaunay",
+];
+
+fn metric(graph: &GraphSnapshot, metric: Metric) -> (&'static str, Trace) {
+    let damping = CentralityTable::default().damping;
+    (metric.name(), metric_trace(metric, graph, damping, &metric_values(metric, graph, damping)))
+}
+
+// The tour tools share their checks of the instance, build runs one of them from the start
+fn tour(graph: &GraphSnapshot, start: Option<usize>, name: &'static str, build: fn(&GraphSnapshot, usize, &mut Trace)) -> (&'static str, Trace) {
+    let mut trace = Trace::new();
+    if let Some(start) = tour_start(graph, start, &mut trace) {
+        build(graph, start, &mut trace);
+    }
+    (name, trace)
+}
+
+/**
+    # Headless Run
+    Runs an algorithm on a snapshot with the settings the app starts with and returns the name it shows and its
+    trace. start and end are the algorithm targets, like the source and sink of a flow.
+*/
+pub fn run_algorithm(name: &str, graph: &GraphSnapshot, start: Option<usize>, end: Option<usize>) -> Result<(&'static str, Trace), String> {
+    let mut trace = Trace::new();
+    let run = match name.to_ascii_lowercase().as_str() {
+        "kahn" => ("Kahn's algorithm", kahn(graph, false)),
+        "dfs-toposort" => ("DFS topological sort", dfs_toposort(graph, false)),
+        "edmonds-karp" => (FlowAlgorithm::EdmondsKarp.name(), flow_trace(FlowAlgorithm::EdmondsKarp, graph, start, end, false)),
+        "dinic" => (FlowAlgorithm::Dinic.name(), flow_trace(FlowAlgorithm::Dinic, graph, start, end, false)),
+        "bipartite-check" => {
+            bipartition(graph, &mut trace);
+            ("Bipartite check", trace)
+        },
+        "hopcroft-karp" => ("Hopcroft-Karp", hopcroft_karp(graph)),
+        "blossom" => ("Edmonds' blossom", blossom(graph)),
+        "articulation-points" => {
+            biconnectivity(graph, Some(&mut trace));
+            ("Articulation points and bridges", trace)
+        },
+        "hierholzer" => ("Hierholzer", hierholzer(graph, start)),
+        "greedy-coloring" => ("Greedy coloring", greedy(graph, Ordering::Insertion)),
+        "greedy-largest-first" => ("Greedy coloring", greedy(graph, Ordering::LargestFirst)),
+        "greedy-smallest-last" => ("Greedy coloring", greedy(graph, Ordering::SmallestLast)),
+        "dsatur" => ("DSatur", dsatur(graph)),
+        "exact-coloring" => ("Exact coloring", exact(graph)),
+        "nearest-neighbour" => tour(graph, start, "Nearest neighbour tour", |graph, start, trace| {
+            nearest_neighbour_tour(graph, start, Some(trace));
+        }),
+        "2-opt" => tour(graph, start, "2-opt", |graph, start, trace| {
+            two_opt(graph, nearest_neighbour_tour(graph, start, None), Some(trace));
+        }),
+        "or-opt" => tour(graph, start, "Or-opt", |graph, start, trace| {
+            or_opt(graph, nearest_neighbour_tour(graph, start, None), Some(trace));
+        }),
+        "christofides" => tour(graph, start, "Christofides", |graph, start, trace| {
+            christofides(graph, start, Some(trace));
+        }),
+        "held-karp" => tour(graph, start, "Held-Karp", |graph, start, trace| {
+            held_karp(graph, start, trace);
+        }),
+        "bron-kerbosch" => ("Bron-Kerbosch", bron_kerbosch(graph)),
+        "independent-set" => ("Maximum independent set", independent_set(graph)),
+        "vertex-cover" => ("Minimum vertex cover", vertex_cover(graph)),
+        "degree" => metric(graph, Metric::Degree),
+        "closeness" => metric(graph, Metric::Closeness),
+        "betweenness" => metric(graph, Metric::Betweenness),
+        "eigenvector" => metric(graph, Metric::Eigenvector),
+        "pagerank" => metric(graph, Metric::PageRank),
+        "label-propagation" => {
+            label_propagation(graph, Some(&mut trace));
+            ("Label propagation", trace)
+        },
+        "louvain" => {
+            louvain(graph, Some(&mut trace));
+            ("Louvain", trace)
+        },
+        "fiedler-vector" => ("Fiedler vector", fiedler_trace(graph)),
+        "spectral-bisection" => ("Spectral bisection", bisection_trace(graph)),
+        "delaunay" => {
+            delaunay(graph, Some(&mut trace));
+            ("Delaunay triangulation", trace)
+        },
+        _ => return Err(format!("Unknown algorithm {name}, the algorithms are {}", ALGORITHMS.join(", "))),
+    };
+    Ok(run)
+}
@@ -0,0 +1,512 @@
+use std::collections::VecDeque;
+
+use bevy::{app::Plugin, prelude::ResMut};
+
+use crate::app::ui::MenuAppExt;
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Step, Trace}};
+
+pub struct MatchingPlugin;
+impl Plugin for MatchingPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_menu_button("Matching", "Bipartite check", run_bipartite_check)
+        .add_menu_button("Matching", "Hopcroft-Karp", run_hopcroft_karp)
+        .add_menu_button("Matching", "Edmonds' blossom", run_blossom)
+        ;
+    }
+}
+
+fn run_bipartite_check(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    bipartition(&graph, &mut trace);
+    run.start("Bipartite check", trace);
+}
+
+fn run_hopcroft_karp(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let graph = graph.snapshot();
+    run.start("Hopcroft-Karp", hopcroft_karp(&graph));
+}
+
+fn run_blossom(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let graph = graph.snapshot();
+    run.start("Edmonds' blossom", blossom(&graph));
+}
+
+/**
+    # Bipartition
+    Colors the graph by BFS with alternating colors, the direction of the edges is ignored.
+    An edge between two vertices of the same color closes an odd cycle through their lowest common BFS ancestor.
+    Returns the side of every vertex or None if the graph is not bipartite.
+*/
+pub fn bipartition(graph: &GraphSnapshot, trace: &mut Trace) -> Option<Vec<usize>> {
+    let n = graph.len();
+    let mut side: Vec<Option<usize>> = vec![None; n];
+    let mut parent: Vec<Option<(usize, usize)>> = vec![None; n];
+    let mut depth = vec![0usize; n];
+
+    for root in 0..n {
+        if side[root].is_some() {
+            continue;
+        }
+        side[root] = Some(0);
+        trace.step(format!("Color {} with the first color", graph.name(root)))
+            .vertex(graph.vertices[root], Mark::Group(0));
+        let mut queue = VecDeque::from([root]);
+
+        while let Some(v) = queue.pop_front() {
+            let v_side = side[v].expect("queued vertices are colored");
+            for (w, e) in graph.neighbours(v) {
+                match side[w] {
+                    None => {
+                        side[w] = Some(1 - v_side);
+                        parent[w] = Some((v, e));
+                        depth[w] = depth[v] + 1;
+                        trace.step(format!("{} gets the other color than {}", graph.name(w), graph.name(v)))
+                            .edge(graph.edge_entity(e), Mark::Done)
+                            .vertex(graph.vertices[w], Mark::Group(1 - v_side));
+                        queue.push_back(w);
+                    },
+                    Some(w_side) if w_side == v_side => {
+                        let cycle = odd_cycle(&parent, &depth, v, w, e);
+                        let path: Vec<usize> = cycle.iter().map(|(u, _)| *u).collect();
+                        let step = trace.step(format!(
+                            "{} and {} have the same color, the graph is not bipartite.\nOdd cycle of length {}: {}",
+                            graph.name(v), graph.name(w), cycle.len() - 1, graph.path_names(&path)
+                        ));
+                        for (u, by) in cycle {
+                            step.vertex(graph.vertices[u], Mark::Cycle);
+                            if let Some(by) = by {
+                                step.edge(graph.edge_entity(by), Mark::Cycle);
+                            }
+                        }
+                        return None;
+                    },
+                    Some(_) => {},
+                }
+            }
+        }
+    }
+
+    let side: Vec<usize> = side.into_iter().map(|s| s.expect("every vertex is colored")).collect();
+    let count = side.iter().filter(|s| **s == 0).count();
+    trace.step(format!("The graph is bipartite with {} and {} vertices on the two sides", count, n - count));
+    Some(side)
+}
+
+// The cycle closed by the edge e between u and w, as vertices with the edge they are reached by
+fn odd_cycle(parent: &[Option<(usize, usize)>], depth: &[usize], u: usize, w: usize, e: usize) -> Vec<(usize, Option<usize>)> {
+    let mut up_u: Vec<(usize, Option<usize>)> = vec![(u, None)];
+    let mut up_w: Vec<(usize, usize)> = vec![];
+    let (mut a, mut b) = (u, w);
+    while a != b {
+        if depth[a] >= depth[b] {
+            let (p, by) = parent[a].expect("a deeper vertex has a parent");
+            up_u.push((p, Some(by)));
+            a = p;
+        } else {
+            let (p, by) = parent[b].expect("a deeper vertex has a parent");
+            up_w.push((b, by));
+            b = p;
+        }
+    }
+    up_u.extend(up_w.into_iter().rev().map(|(v, by)| (v, Some(by))));
+    up_u.push((u, Some(e)));
+    up_u
+}
+
+/**
+    # Hopcroft-Karp
+    Every phase finds the length of the shortest augmenting paths by BFS from the free left vertices
+    and then augments along a maximal set of vertex disjoint shortest paths found by DFS.
+*/
+pub fn hopcroft_karp(graph: &GraphSnapshot) -> Trace {
+    let mut trace = Trace::new();
+    let Some(side) = bipartition(graph, &mut trace) else {
+        if let Some(step) = trace.steps.last_mut() {
+            step.message.push_str("\nHopcroft-Karp needs a bipartite graph, try Edmonds' blossom algorithm");
+        }
+        return trace;
+    };
+    // Only the result of the coloring is shown
+    let coloring: Vec<_> = trace.steps.drain(..).flat_map(|step| step.changes).collect();
+    let step = trace.step("The first color is the left side, the second the right side");
+    step.changes = coloring;
+
+    let n = graph.len();
+    let mut matched: Vec<Option<(usize, usize)>> = vec![None; n];
+    let mut phase = 0;
+
+    loop {
+        let dist = matching_layers(graph, &side, &matched);
+        let Some(length) = shortest_augmenting_length(graph, &side, &matched, &dist) else {break};
+        phase += 1;
+
+        let step = trace.step(format!("Phase {phase}: BFS from the free left vertices, the shortest augmenting paths have {} edges", 2 * length + 1));
+        for v in (0..n).filter(|v| side[*v] == 0) {
+            match dist[v] {
+                Some(d) => step.vertex_label(graph.vertices[v], format!("L{d}")),
+                None => step.vertex_label(graph.vertices[v], ""),
+            };
+        }
+
+        let mut dist = dist;
+        let mut next = vec![0usize; n];
+        let free: Vec<usize> = (0..n).filter(|u| side[*u] == 0 && matched[*u].is_none()).collect();
+        for u in free {
+            let Some(path) = layered_path(graph, u, &mut dist, &mut next, &matched, length) else {continue};
+            augment(graph, &mut trace, &mut matched, &path);
+        }
+    }
+
+    let size = (0..n).filter(|v| side[*v] == 0 && matched[*v].is_some()).count();
+    let step = trace.step(format!("No augmenting path is left, the maximum matching has {size} edges"));
+    for v in 0..n {
+        step.vertex_label(graph.vertices[v], "");
+    }
+    trace
+}
+
+// BFS layers of the left vertices along alternating paths starting at the free left vertices
+fn matching_layers(graph: &GraphSnapshot, side: &[usize], matched: &[Option<(usize, usize)>]) -> Vec<Option<usize>> {
+    let mut dist: Vec<Option<usize>> = vec![None; graph.len()];
+    let mut queue = VecDeque::new();
+    for u in (0..graph.len()).filter(|u| side[*u] == 0 && matched[*u].is_none()) {
+        dist[u] = Some(0);
+        queue.push_back(u);
+    }
+    while let Some(u) = queue.pop_front() {
+        for (v, _) in graph.neighbours(u) {
+            let Some((partner, _)) = matched[v] else {continue};
+            if dist[partner].is_none() {
+                dist[partner] = dist[u].map(|d| d + 1);
+                queue.push_back(partner);
+            }
+        }
+    }
+    dist
+}
+
+fn shortest_augmenting_length(graph: &GraphSnapshot, side: &[usize], matched: &[Option<(usize, usize)>], dist: &[Option<usize>]) -> Option<usize> {
+    (0..graph.len())
+        .filter(|u| side[*u] == 0)
+        .filter_map(|u| {
+            let d = dist[u]?;
+            graph.neighbours(u).any(|(v, _)| matched[v].is_none()).then_some(d)
+        })
+        .min()
+}
+
+// DFS along the layers, returns the path as (left, right, edge) triples from the free right vertex back to u
+fn layered_path(
+    graph: &GraphSnapshot,
+    u: usize,
+    dist: &mut [Option<usize>],
+    next: &mut [usize],
+    matched: &[Option<(usize, usize)>],
+    length: usize,
+) -> Option<Vec<(usize, usize, usize)>> {
+    let neighbours: Vec<(usize, usize)> = graph.neighbours(u).collect();
+    while next[u] < neighbours.len() {
+        let (v, e) = neighbours[next[u]];
+        next[u] += 1;
+        match matched[v] {
+            None if dist[u] == Some(length) => return Some(vec![(u, v, e)]),
+            Some((partner, _)) if dist[u] < Some(length) && dist[partner] == dist[u].map(|d| d + 1) => {
+                if let Some(mut path) = layered_path(graph, partner, dist, next, matched, length) {
+                    path.push((u, v, e));
+                    return Some(path);
+                }
+            },
+            _ => {},
+        }
+    }
+    // Nothing can be reached from u anymore in this phase
+    dist[u] = None;
+    None
+}
+
+// Flips the edges along the path, the unmatched edges become matched and the matched ones unmatched
+fn augment(graph: &GraphSnapshot, trace: &mut Trace, matched: &mut [Option<(usize, usize)>], path: &[(usize, usize, usize)]) {
+    let mut vertices: Vec<usize> = vec![];
+    for (left, right, _) in path.iter().rev() {
+        vertices.push(*left);
+        vertices.push(*right);
+    }
+    let step = trace.step(format!("Augmenting path {}", graph.path_names(&vertices)));
+    for (left, right, e) in path {
+        step.edge(graph.edge_entity(*e), Mark::Active)
+            .vertex(graph.vertices[*left], Mark::Active)
+            .vertex(graph.vertices[*right], Mark::Active);
+        if let Some((_, old)) = matched[*left] {
+            step.edge(graph.edge_entity(old), Mark::Active);
+        }
+    }
+
+    let step = trace.step(format!("Flip the path, the matching grows to {} edges", matched.iter().flatten().count() / 2 + 1));
+    for (left, right, _) in path {
+        if let Some((_, old)) = matched[*left] {
+            step.clear(graph.edge_entity(old));
+        }
+        step.clear(graph.vertices[*left]).clear(graph.vertices[*right]);
+    }
+    for &(left, right, e) in path {
+        matched[left] = Some((right, e));
+        matched[right] = Some((left, e));
+        step.edge(graph.edge_entity(e), Mark::Matched);
+    }
+}
+
+/**
+    # Edmonds' Blossom Algorithm
+    Grows an alternating tree from every free vertex. An edge between two even vertices of the tree closes an odd cycle,
+    the blossom, which is contracted into its base so the search continues as if it was a single vertex.
+*/
+pub fn blossom(graph: &GraphSnapshot) -> Trace {
+    let mut trace = Trace::new();
+    let n = graph.len();
+    let mut matched: Vec<Option<(usize, usize)>> = vec![None; n];
+
+    for root in 0..n {
+        if matched[root].is_some() {
+            continue;
+        }
+        trace.step(format!("Grow an alternating tree from the free vertex {}", graph.name(root)))
+            .vertex(graph.vertices[root], Mark::Frontier);
+        let Some((end, parent)) = find_augmenting_path(graph, root, &matched, &mut trace) else {
+            let step = trace.step(format!("No augmenting path starts at {}", graph.name(root)));
+            clear_search(graph, step, &matched);
+            continue;
+        };
+
+        // Walk back from the end, alternating between the tree edge and the matched edge
+        let mut path: Vec<(usize, usize, usize)> = vec![];
+        let mut v = Some(end);
+        while let Some(current) = v {
+            let (p, e) = parent[current].expect("every vertex on the path has a tree parent");
+            let next = matched[p].map(|(partner, _)| partner);
+            path.push((current, p, e));
+            v = next;
+        }
+
+        let vertices: Vec<usize> = path.iter().flat_map(|(a, b, _)| [*a, *b]).collect();
+        let step = trace.step(format!("Augmenting path {}", graph.path_names(&vertices)));
+        for (a, b, e) in path.iter() {
+            step.edge(graph.edge_entity(*e), Mark::Active)
+                .vertex(graph.vertices[*a], Mark::Active)
+                .vertex(graph.vertices[*b], Mark::Active);
+        }
+
+        for &(a, b, e) in path.iter() {
+            matched[a] = Some((b, e));
+            matched[b] = Some((a, e));
+        }
+        let size = matched.iter().flatten().count() / 2;
+        let step = trace.step(format!("Flip the path, the matching has {size} edges"));
+        clear_search(graph, step, &matched);
+    }
+
+    let size = matched.iter().flatten().count() / 2;
+    trace.step(format!("Every free vertex was searched, the maximum matching has {size} edges"));
+    trace
+}
+
+// Removes the marks of the search but keeps the matching
+fn clear_search(graph: &GraphSnapshot, step: &mut Step, matched: &[Option<(usize, usize)>]) {
+    for v in 0..graph.len() {
+        step.clear(graph.vertices[v]).vertex_label(graph.vertices[v], "");
+    }
+    for e in 0..graph.edges.len() {
+        let is_matched = matched[graph.edges[e].from].is_some_and(|(_, m)| m == e);
+        if is_matched {
+            step.edge(graph.edge_entity(e), Mark::Matched);
+        } else {
+            step.clear(graph.edge_entity(e));
+        }
+    }
+}
+
+/**
+    # Find Augmenting Path
+    BFS over the alternating tree where base[v] is the base of the blossom v was contracted into.
+    Returns the free vertex at the end of the path and the tree parents, parent[v] leads from an odd vertex
+    to the even vertex it was reached from.
+*/
+#[allow(clippy::type_complexity)]
+fn find_augmenting_path(
+    graph: &GraphSnapshot,
+    root: usize,
+    matched: &[Option<(usize, usize)>],
+    trace: &mut Trace,
+) -> Option<(usize, Vec<Option<(usize, usize)>>)> {
+    let n = graph.len();
+    let mut used = vec![false; n];
+    let mut parent: Vec<Option<(usize, usize)>> = vec![None; n];
+    let mut base: Vec<usize> = (0..n).collect();
+    let mut queue = VecDeque::from([root]);
+    used[root] = true;
+
+    let partner = |v: usize| matched[v].map(|(p, _)| p);
+
+    while let Some(v) = queue.pop_front() {
+        for (to, e) in graph.neighbours(v) {
+            if base[v] == base[to] || partner(v) == Some(to) {
+                continue;
+            }
+            let closes_blossom = to == root || partner(to).is_some_and(|p| parent[p].is_some());
+            if closes_blossom {
+                let current_base = lowest_common_base(&base, &parent, matched, v, to);
+                let mut in_blossom = vec![false; n];
+                mark_blossom_path(&mut base, &mut parent, matched, &mut in_blossom, v, current_base, to, e);
+                mark_blossom_path(&mut base, &mut parent, matched, &mut in_blossom, to, current_base, v, e);
+
+                let members: Vec<usize> = (0..n).filter(|i| in_blossom[base[*i]] || base[*i] == current_base).collect();
+                let step = trace.step(format!(
+                    "The edge {}-{} closes an odd cycle, contract the blossom {{{}}} into its base {}",
+                    graph.name(v), graph.name(to),
+                    members.iter().map(|m| graph.name(*m)).collect::<Vec<_>>().join(", "),
+                    graph.name(current_base)
+                ));
+                step.edge(graph.edge_entity(e), Mark::Highlight);
+                for &i in members.iter() {
+                    base[i] = current_base;
+                    step.vertex(graph.vertices[i], Mark::Highlight)
+                        .vertex_label(graph.vertices[i], format!("B {}", graph.name(current_base)));
+                    if !used[i] {
+                        used[i] = true;
+                        queue.push_back(i);
+                    }
+                }
+            } else if parent[to].is_none() {
+                parent[to] = Some((v, e));
+                let Some(next) = partner(to) else {
+                    return Some((to, parent));
+                };
+                used[next] = true;
+                queue.push_back(next);
+                trace.step(format!("{} is matched to {}, extend the tree", graph.name(to), graph.name(next)))
+                    .edge(graph.edge_entity(e), Mark::Frontier)
+                    .vertex(graph.vertices[to], Mark::Done)
+                    .vertex(graph.vertices[next], Mark::Frontier);
+            }
+        }
+    }
+    None
+}
+
+fn lowest_common_base(base: &[usize], parent: &[Option<(usize, usize)>], matched: &[Option<(usize, usize)>], a: usize, b: usize) -> usize {
+    let mut on_path = vec![false; base.len()];
+    let mut a = a;
+    loop {
+        a = base[a];
+        on_path[a] = true;
+        let Some((partner, _)) = matched[a] else {break};
+        let Some((p, _)) = parent[partner] else {break};
+        a = p;
+    }
+    let mut b = b;
+    loop {
+        b = base[b];
+        if on_path[b] {
+            return b;
+        }
+        let (partner, _) = matched[b].expect("the path to the root passes matched vertices");
+        let (p, _) = parent[partner].expect("an odd vertex has a parent");
+        b = p;
+    }
+}
+
+// Marks the blossom bases between v and the base and lets the odd vertices point across the closing edge
+#[allow(clippy::too_many_arguments)]
+fn mark_blossom_path(
+    base: &mut [usize],
+    parent: &mut [Option<(usize, usize)>],
+    matched: &[Option<(usize, usize)>],
+    in_blossom: &mut [bool],
+    v: usize,
+    blossom_base: usize,
+    child: usize,
+    edge: usize,
+) {
+    let mut v = v;
+    let mut child = child;
+    let mut edge = edge;
+    while base[v] != blossom_base {
+        let (partner, _) = matched[v].expect("vertices inside a blossom are matched");
+        in_blossom[base[v]] = true;
+        in_blossom[base[partner]] = true;
+        parent[v] = Some((child, edge));
+        child = partner;
+        (v, edge) = parent[partner].expect("an odd vertex has a parent");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::Trace};
+
+    use super::{bipartition, blossom, hopcroft_karp};
+
+    // The size the last step reports
+    fn size(trace: &Trace) -> usize {
+        let message = &trace.steps.last().unwrap().message;
+        message.split_whitespace().rev().find_map(|word| word.parse().ok()).expect("the size is reported")
+    }
+
+    fn undirected(n: usize, edges: &[(usize, usize)]) -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = edges.iter().map(|(a, b)| (*a, *b, 1, false)).collect();
+        GraphSnapshot::test_graph(n, &edges)
+    }
+
+    #[test]
+    fn bipartite_matching() {
+        // Left 0..4, right 4..8, the largest matching leaves one vertex on each side free
+        let graph = undirected(8, &[(0, 4), (0, 5), (1, 4), (2, 4), (2, 6), (3, 6), (3, 7), (2, 7)]);
+        assert_eq!(size(&hopcroft_karp(&graph)), 4);
+        assert_eq!(size(&blossom(&graph)), 4);
+        let graph = undirected(6, &[(0, 3), (1, 3), (2, 3), (2, 4), (2, 5)]);
+        assert_eq!(size(&hopcroft_karp(&graph)), 2);
+        assert_eq!(size(&blossom(&graph)), 2);
+    }
+
+    #[test]
+    fn odd_cycle_is_not_bipartite() {
+        let graph = undirected(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (4, 0)]);
+        assert!(bipartition(&graph, &mut Trace::new()).is_none());
+        assert!(hopcroft_karp(&graph).steps.last().unwrap().message.contains("needs a bipartite graph"));
+        assert_eq!(size(&blossom(&graph)), 2);
+    }
+
+    #[test]
+    fn blossom_is_contracted() {
+        // A triangle with a path and a pendant vertex, every vertex can be matched
+        let graph = undirected(6, &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (0, 5)]);
+        assert_eq!(size(&blossom(&graph)), 3);
+    }
+
+    #[test]
+    fn petersen_graph_has_a_perfect_matching() {
+        let outer = (0..5).map(|i| (i, (i + 1) % 5));
+        let spokes = (0..5).map(|i| (i, i + 5));
+        let inner = (0..5).map(|i| (i + 5, (i + 2) % 5 + 5));
+        let edges: Vec<(usize, usize)> = outer.chain(spokes).chain(inner).collect();
+        assert_eq!(size(&blossom(&undirected(10, &edges))), 5);
+    }
+
+    #[test]
+    fn empty_graph() {
+        let graph = undirected(3, &[]);
+        assert_eq!(size(&hopcroft_karp(&graph)), 0);
+        assert_eq!(size(&blossom(&graph)), 0);
+    }
+}
@@ -0,0 +1,57 @@
+pub mod graph;
+pub mod steps;
+pub mod playback;
+pub mod targets;
+pub mod spectral;
+pub mod headless;
+mod centrality;
+mod cliques;
+mod coloring;
+mod community;
+mod connectivity;
+mod euler;
+mod flow;
+mod geometry;
+mod matching;
+mod toposort;
+mod tsp;
+
+use bevy::prelude::{App, Plugin};
+use centrality::CentralityPlugin;
+use cliques::CliquesPlugin;
+use coloring::ColoringPlugin;
+use community::CommunityPlugin;
+use connectivity::ConnectivityPlugin;
+use euler::EulerPlugin;
+use flow::MaxFlowPlugin;
+use geometry::GeometryPlugin;
+use matching::MatchingPlugin;
+use playback::PlaybackPlugin;
+use spectral::SpectralPlugin;
+use targets::TargetsPlugin;
+use toposort::TopologicalSortPlugin;
+use tsp::TspPlugin;
+
+pub struct AlgorithmPlugin;
+impl Plugin for AlgorithmPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_plugins((
+            PlaybackPlugin,
+            TargetsPlugin,
+            TopologicalSortPlugin,
+            MaxFlowPlugin,
+            MatchingPlugin,
+            ConnectivityPlugin,
+            EulerPlugin,
+            ColoringPlugin,
+            TspPlugin,
+            CliquesPlugin,
+            CentralityPlugin,
+            CommunityPlugin,
+            SpectralPlugin,
+            GeometryPlugin,
+        ))
+        ;
+    }
+}
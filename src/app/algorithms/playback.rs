@@ -0,0 +1,384 @@
+use bevy::{app::{Plugin, Startup, Update}, asset::{Assets, Handle}, color::{Color, Mix}, ecs::system::SystemParam, math::Vec3, prelude::{BuildChildren, Children, Commands, Component, DespawnRecursiveExt, Entity, Has, IntoSystemConfigs, Local, Mesh, Or, Query, Res, ResMut, Resource, Transform, With}, sprite::{ColorMaterial, ColorMesh2dBundle, Mesh2dHandle}, text::Text, time::Time, utils::HashMap};
+
+use crate::app::{build_graph::{components::{edge_mesh, label_text, resting_material, BaseMaterial, Edge, EdgeWidth, OwnMaterial, Vertex, EDGE_WIDTH}, movement::VertexTween, init_mesh, res::GraphAssets, PALETTE, RADIUS}, ui::{InfoPanel, MenuAppExt}};
+
+use super::steps::{Change, Mark, Step, Trace, HEAT_LEVELS};
+
+pub struct PlaybackPlugin;
+impl Plugin for PlaybackPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<AlgorithmRun>()
+        .init_resource::<PlaybackSettings>()
+        .add_systems(Startup, init_algorithm_assets.after(init_mesh))
+        .add_systems(Update, (reset_run_visuals, advance_run).chain())
+        .add_menu_button("Playback", "Play / Pause", toggle_play)
+        .add_menu_button("Playback", "Step", step_once)
+        .add_menu_button("Playback", "Reset", reset_run)
+        .add_menu_button("Playback", "Faster", faster)
+        .add_menu_button("Playback", "Slower", slower)
+        ;
+    }
+}
+
+// A vertex or an edge an algorithm marked, the mark is removed when the run is reset
+#[derive(Component)]
+pub struct AlgorithmMark;
+
+// Text child of a vertex or an edge written by an algorithm
+#[derive(Component)]
+pub struct Annotation;
+
+// A line an algorithm draws between two vertices that don't need to share an edge
+#[derive(Component)]
+struct Segment;
+
+#[derive(Resource)]
+pub struct AlgorithmAssets {
+    pub active_material: Handle<ColorMaterial>,
+    pub frontier_material: Handle<ColorMaterial>,
+    pub done_material: Handle<ColorMaterial>,
+    pub cycle_material: Handle<ColorMaterial>,
+    pub highlight_material: Handle<ColorMaterial>,
+    pub matched_material: Handle<ColorMaterial>,
+    // The palette of the graph assets
+    pub group_materials: Vec<Handle<ColorMaterial>>,
+    pub heat_materials: Vec<Handle<ColorMaterial>>,
+}
+
+impl AlgorithmAssets {
+    pub fn material(&self, mark: Mark) -> Handle<ColorMaterial> {
+        match mark {
+            Mark::Active => self.active_material.clone(),
+            Mark::Frontier => self.frontier_material.clone(),
+            Mark::Done => self.done_material.clone(),
+            Mark::Cycle => self.cycle_material.clone(),
+            Mark::Highlight => self.highlight_material.clone(),
+            Mark::Matched => self.matched_material.clone(),
+            Mark::Group(group) => self.group_materials[group % self.group_materials.len()].clone(),
+            Mark::Heat(level) => self.heat_materials[level.min(HEAT_LEVELS - 1)].clone(),
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct AlgorithmRun {
+    pub name: String,
+    pub steps: Vec<Step>,
+    pub cursor: usize,
+    pub playing: bool,
+    // Steps requested by the step button while paused
+    pending: usize,
+    // The marks of the previous run still have to be removed
+    fresh: bool,
+    // Steps applied at once right after the reset
+    skip: usize,
+}
+
+impl AlgorithmRun {
+    pub fn start(&mut self, name: impl Into<String>, trace: Trace) {
+        self.name = name.into();
+        self.steps = trace.steps;
+        self.cursor = 0;
+        self.pending = 0;
+        self.playing = true;
+        self.fresh = true;
+        self.skip = 0;
+    }
+
+    // Replaces the steps but shows the same point of the run, used when a view of the run changes
+    pub fn restart_at(&mut self, trace: Trace, cursor: usize) {
+        let playing = self.playing;
+        self.steps = trace.steps;
+        self.cursor = 0;
+        self.pending = 0;
+        self.playing = playing;
+        self.fresh = true;
+        self.skip = cursor.min(self.steps.len());
+    }
+
+    // One more step on the next frame, the run pauses after it
+    pub fn step(&mut self) {
+        self.playing = false;
+        self.pending += 1;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.steps.len()
+    }
+}
+
+#[derive(Resource)]
+pub struct PlaybackSettings {
+    pub step_seconds: f32,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            step_seconds: 0.6,
+        }
+    }
+}
+
+pub const VERTEX_ANNOTATION_OFFSET: Vec3 = Vec3::new(0., RADIUS + 30., 2.);
+pub const EDGE_ANNOTATION_OFFSET: Vec3 = Vec3::new(0., -30., 2.);
+// Between the edges and the vertices
+const SEGMENT_Z: f32 = -0.5;
+pub const MATCHED_EDGE_WIDTH: f32 = 3. * EDGE_WIDTH;
+pub const ANNOTATION_COLOR: Color = Color::srgb(1., 0.9, 0.4);
+
+// Blue over yellow to red
+fn heat_color(t: f32) -> Color {
+    let cold = Color::srgb(0.2, 0.35, 0.95);
+    let warm = Color::srgb(1., 0.9, 0.25);
+    let hot = Color::srgb(0.95, 0.15, 0.1);
+    if t < 0.5 {
+        cold.mix(&warm, t * 2.)
+    } else {
+        warm.mix(&hot, t * 2. - 1.)
+    }
+}
+
+// The color of a mark, also used where the run is drawn without materials
+pub fn mark_color(mark: Mark) -> Color {
+    match mark {
+        Mark::Active => Color::srgb(1., 0.85, 0.1),
+        Mark::Frontier => Color::srgb(0.3, 0.6, 1.),
+        Mark::Done => Color::srgb(0.1, 0.7, 0.6),
+        Mark::Cycle => Color::srgb(1., 0.35, 0.),
+        Mark::Highlight => Color::srgb(0.9, 0.2, 0.9),
+        Mark::Matched => Color::srgb(0.95, 0.55, 0.75),
+        Mark::Group(group) => PALETTE[group % PALETTE.len()],
+        Mark::Heat(level) => heat_color(level.min(HEAT_LEVELS - 1) as f32 / (HEAT_LEVELS - 1) as f32),
+    }
+}
+
+fn init_algorithm_assets(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    graph_assets: Res<GraphAssets>,
+) {
+    let mut material = |mark: Mark| materials.add(ColorMaterial::from_color(mark_color(mark)));
+    commands.insert_resource(AlgorithmAssets {
+        active_material: material(Mark::Active),
+        frontier_material: material(Mark::Frontier),
+        done_material: material(Mark::Done),
+        cycle_material: material(Mark::Cycle),
+        highlight_material: material(Mark::Highlight),
+        matched_material: material(Mark::Matched),
+        group_materials: graph_assets.palette.clone(),
+        heat_materials: (0..HEAT_LEVELS).map(|level| material(Mark::Heat(level))).collect(),
+    });
+}
+
+// Marked vertices and edges, the transform of a vertex holds its scale
+type Marked<'a> = (Entity, Has<Edge>, Option<&'a mut Transform>, Option<&'a OwnMaterial>);
+// Everything an algorithm drew on top of the graph
+type Drawn = Or<(With<Annotation>, With<Segment>)>;
+
+fn reset_run_visuals(
+    mut commands: Commands,
+    mut run: ResMut<AlgorithmRun>,
+    graph_assets: Res<GraphAssets>,
+    mut q_marked: Query<Marked, With<AlgorithmMark>>,
+    q_annotation: Query<Entity, Drawn>,
+) {
+    if !run.fresh {
+        return;
+    }
+    run.fresh = false;
+    for (entity, is_edge, transform, own) in q_marked.iter_mut() {
+        if let Some(mut transform) = transform.filter(|transform| transform.scale != Vec3::ONE) {
+            transform.scale = Vec3::ONE;
+        }
+        let mut entity_commands = commands.entity(entity);
+        entity_commands
+            .remove::<AlgorithmMark>()
+            .insert(BaseMaterial(resting_material(own, &graph_assets)));
+        if is_edge {
+            entity_commands.insert(EdgeWidth(EDGE_WIDTH));
+        }
+    }
+    for entity in q_annotation.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn advance_run(
+    mut visuals: RunVisuals,
+    mut run: ResMut<AlgorithmRun>,
+    mut info: ResMut<InfoPanel>,
+    mut elapsed: Local<f32>,
+    settings: Res<PlaybackSettings>,
+    time: Res<Time>,
+) {
+    if run.skip > 0 {
+        let skip = std::mem::take(&mut run.skip);
+        run.cursor = skip;
+        visuals.apply(run.steps[..skip].iter().flat_map(|step| step.changes.iter()));
+        info.text = format!("{} ({}/{})\n{}", run.name, skip, run.steps.len(), run.steps[skip - 1].message);
+        return;
+    }
+    if run.is_finished() {
+        run.playing = false;
+        return;
+    }
+    if run.pending == 0 {
+        if !run.playing {
+            return;
+        }
+        *elapsed += time.delta_seconds();
+        if *elapsed < settings.step_seconds && run.cursor != 0 {
+            return;
+        }
+    }
+    *elapsed = 0.;
+    run.pending = run.pending.saturating_sub(1);
+
+    let cursor = run.cursor;
+    run.cursor += 1;
+    let step = &run.steps[cursor];
+    visuals.apply(step.changes.iter());
+
+    info.text = format!("{} ({}/{})\n{}", run.name, cursor + 1, run.steps.len(), step.message);
+}
+
+// Everything the changes of a step are applied to
+#[derive(SystemParam)]
+struct RunVisuals<'w, 's> {
+    commands: Commands<'w, 's>,
+    assets: Res<'w, AlgorithmAssets>,
+    graph_assets: Res<'w, GraphAssets>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    q_children: Query<'w, 's, &'static Children>,
+    q_annotation: Query<'w, 's, &'static mut Text, With<Annotation>>,
+    q_transform: Query<'w, 's, &'static mut Transform, With<Vertex>>,
+    q_edge: Query<'w, 's, (), With<Edge>>,
+    q_own: Query<'w, 's, &'static OwnMaterial>,
+    q_segment: Query<'w, 's, Entity, With<Segment>>,
+}
+
+impl<'w, 's> RunVisuals<'w, 's> {
+    fn apply<'a>(&mut self, changes: impl Iterator<Item = &'a Change>) {
+        // Later labels of the same entity win, so a step never spawns two annotations for one entity
+        let mut labels: HashMap<Entity, &str> = HashMap::new();
+        // Only the last set of segments is drawn
+        let mut segments: Option<&Vec<(Entity, Entity, Mark)>> = None;
+        for change in changes {
+            match change {
+                Change::Vertex(entity, mark) => {
+                    if let Some(mut entity_commands) = self.commands.get_entity(*entity) {
+                        entity_commands.insert((AlgorithmMark, BaseMaterial(self.assets.material(*mark))));
+                    }
+                },
+                Change::Edge(entity, mark) => {
+                    if let Some(mut entity_commands) = self.commands.get_entity(*entity) {
+                        let width = if *mark == Mark::Matched { MATCHED_EDGE_WIDTH } else { EDGE_WIDTH };
+                        entity_commands.insert((AlgorithmMark, BaseMaterial(self.assets.material(*mark)), EdgeWidth(width)));
+                    }
+                },
+                Change::Clear(entity) => {
+                    if let Ok(mut transform) = self.q_transform.get_mut(*entity) {
+                        if transform.scale != Vec3::ONE {
+                            transform.scale = Vec3::ONE;
+                        }
+                    }
+                    if let Some(mut entity_commands) = self.commands.get_entity(*entity) {
+                        let material = resting_material(self.q_own.get(*entity).ok(), &self.graph_assets);
+                        entity_commands.remove::<AlgorithmMark>().insert(BaseMaterial(material));
+                        if self.q_edge.contains(*entity) {
+                            entity_commands.insert(EdgeWidth(EDGE_WIDTH));
+                        }
+                    }
+                },
+                Change::VertexLabel(entity, label) | Change::EdgeLabel(entity, label) => {
+                    labels.insert(*entity, label);
+                },
+                Change::Position(entity, position) => {
+                    let Ok(transform) = self.q_transform.get(*entity) else {continue};
+                    self.commands.entity(*entity).insert(VertexTween::new(transform.translation.truncate(), *position));
+                },
+                Change::Scale(entity, scale) => {
+                    let Ok(mut transform) = self.q_transform.get_mut(*entity) else {continue};
+                    transform.scale = Vec3::new(*scale, *scale, 1.);
+                },
+                Change::Segments(lines) => {
+                    segments = Some(lines);
+                },
+            }
+        }
+
+        for (entity, label) in labels {
+            let existing = self.q_children.get(entity).ok()
+                .and_then(|children| children.iter().find(|child| self.q_annotation.contains(**child)).copied());
+            if let Some(child) = existing {
+                if let Ok(mut text) = self.q_annotation.get_mut(child) {
+                    text.sections[0].value = label.to_string();
+                }
+                continue;
+            }
+            if label.is_empty() {
+                continue;
+            }
+            let offset = if self.q_edge.contains(entity) { EDGE_ANNOTATION_OFFSET } else { VERTEX_ANNOTATION_OFFSET };
+            if let Some(mut entity_commands) = self.commands.get_entity(entity) {
+                entity_commands.with_children(|parent| {
+                    parent.spawn((label_text(label, ANNOTATION_COLOR, offset), Annotation));
+                });
+            }
+        }
+
+        let Some(segments) = segments else {return};
+        for entity in self.q_segment.iter() {
+            self.commands.entity(entity).despawn_recursive();
+        }
+        for (start, end, mark) in segments {
+            let (Ok(start), Ok(end)) = (self.q_transform.get(*start), self.q_transform.get(*end)) else {continue};
+            let start = start.translation.truncate();
+            let end = end.translation.truncate();
+            let mid = (start + end) / 2.;
+            self.commands.spawn((
+                ColorMesh2dBundle {
+                    mesh: Mesh2dHandle::from(self.meshes.add(edge_mesh(start, end, false, EDGE_WIDTH))),
+                    material: self.assets.material(*mark),
+                    transform: Transform::from_translation(mid.extend(SEGMENT_Z)),
+                    ..Default::default()
+                },
+                Segment,
+            ));
+        }
+    }
+}
+
+fn toggle_play(
+    mut run: ResMut<AlgorithmRun>,
+) {
+    run.playing = !run.playing;
+}
+
+fn step_once(
+    mut run: ResMut<AlgorithmRun>,
+) {
+    run.step();
+}
+
+fn reset_run(
+    mut run: ResMut<AlgorithmRun>,
+    mut info: ResMut<InfoPanel>,
+) {
+    run.start("", Trace::new());
+    info.text.clear();
+}
+
+fn faster(
+    mut settings: ResMut<PlaybackSettings>,
+) {
+    settings.step_seconds = (settings.step_seconds / 1.5).max(0.02);
+}
+
+fn slower(
+    mut settings: ResMut<PlaybackSettings>,
+) {
+    settings.step_seconds = (settings.step_seconds * 1.5).min(5.);
+}
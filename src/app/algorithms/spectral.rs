@@ -0,0 +1,510 @@
+use bevy::{app::{Plugin, PostStartup, Update}, color::Color, math::Vec2, prelude::{default, Added, BuildChildren, Children, Commands, Component, DespawnRecursiveExt, DetectChanges, Entity, NodeBundle, Query, RemovedComponents, Res, ResMut, Resource, TextBundle, With}, text::Text, ui::{AlignItems, Display, FlexDirection, Interaction, PositionType, Style, UiRect, Val}};
+
+use crate::app::{build_graph::components::{Edge, Vertex}, ui::{text_style, MenuAppExt, PANEL_COLOR}};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Step, Trace, HEAT_LEVELS}};
+
+pub struct SpectralPlugin;
+impl Plugin for SpectralPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<SpectrumPanel>()
+        .add_systems(PostStartup, spawn_panel)
+        .add_systems(Update, (refresh_panel, update_panel))
+        .add_menu_button("Spectral", "Fiedler vector", run_fiedler)
+        .add_menu_button("Spectral", "Spectral bisection", run_bisection)
+        .add_menu_button("Spectral", "Toggle spectrum panel", toggle_panel)
+        ;
+    }
+}
+
+/**
+    # Spectrum
+    Eigenvalues of the Laplacian L = D - A in ascending order with their unit eigenvectors. Directions and multi edges are ignored.
+    Small graphs get the whole spectrum, big graphs only the smallest few.
+*/
+pub struct Spectrum {
+    pub values: Vec<f32>,
+    pub vectors: Vec<Vec<f32>>,
+}
+
+// Bigger graphs use the sparse solver for the few smallest eigenpairs
+const DENSE_LIMIT: usize = 120;
+const JACOBI_SWEEPS: usize = 50;
+// Size of the Krylov basis for big graphs
+const LANCZOS_STEPS: usize = 120;
+const TOLERANCE: f64 = 1e-9;
+// Eigenvalues below this count as zero, one per connected component
+const ZERO: f32 = 1e-4;
+const MAX_BARS: usize = 64;
+const BAR_HEIGHT: f32 = 80.;
+const BAR_COLOR: Color = Color::srgb(0.45, 0.65, 0.95);
+
+#[derive(Resource, Default)]
+pub struct SpectrumPanel {
+    visible: bool,
+    values: Vec<f32>,
+    summary: String,
+}
+
+#[derive(Component)]
+struct PanelNode;
+
+#[derive(Component)]
+struct Bars;
+
+#[derive(Component)]
+struct SummaryText;
+
+fn laplacian(graph: &GraphSnapshot) -> Vec<Vec<usize>> {
+    graph.simple_adjacency()
+        .into_iter()
+        .enumerate()
+        .map(|(v, adjacent)| adjacent.into_iter().filter(|w| *w != v).collect())
+        .collect()
+}
+
+/**
+    # Jacobi Eigenvalue Algorithm
+    Every rotation zeroes one off-diagonal entry of the symmetric matrix, the rotations multiplied up are the eigenvectors.
+    Cyclic sweeps over all entries until the off-diagonal part is gone.
+*/
+fn jacobi(mut a: Vec<Vec<f64>>) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    // Columns are the eigenvectors
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1. } else { 0. }).collect()).collect();
+    for _ in 0..JACOBI_SWEEPS {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j))).map(|(i, j)| a[i][j] * a[i][j]).sum();
+        if off < TOLERANCE * TOLERANCE {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-15 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2. * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.).sqrt());
+                let c = 1. / (t * t + 1.).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                // p < q, so row p lies before the split and row q starts it
+                let (before, after) = a.split_at_mut(q);
+                for (apk, aqk) in before[p].iter_mut().zip(after[0].iter_mut()) {
+                    (*apk, *aqk) = (c * *apk - s * *aqk, s * *apk + c * *aqk);
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let values = (0..n).map(|i| a[i][i]).collect();
+    let vectors = (0..n).map(|j| (0..n).map(|i| v[i][j]).collect()).collect();
+    (values, vectors)
+}
+
+fn norm(x: &[f64]) -> f64 {
+    x.iter().map(|value| value * value).sum::<f64>().sqrt()
+}
+
+fn normalize(x: &mut [f64]) {
+    let norm = norm(x);
+    if norm > 0. {
+        x.iter_mut().for_each(|value| *value /= norm);
+    }
+}
+
+/**
+    # Lanczos
+    Builds an orthonormal basis of the Krylov space of L in which L is tridiagonal, the eigenpairs of the small tridiagonal
+    matrix from Jacobi approximate the extreme eigenpairs of L. Every new vector is orthogonalized against the whole basis,
+    when the space runs out a fresh start vector continues it so repeated eigenvalues like the zeros of several components show up.
+    Only the sparse adjacency is touched.
+*/
+fn lanczos(adjacency: &[Vec<usize>], count: usize) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = adjacency.len();
+    let steps = n.min(LANCZOS_STEPS);
+    let multiply = |x: &[f64]| -> Vec<f64> {
+        (0..n)
+            .map(|v| adjacency[v].len() as f64 * x[v] - adjacency[v].iter().map(|w| x[*w]).sum::<f64>())
+            .collect()
+    };
+    // Deterministic start vectors that aren't orthogonal to anything in particular
+    let start = |seed: usize| -> Vec<f64> {
+        (0..n).map(|v| ((v * 7919 + seed * 104729 + 13) % 1009) as f64 / 1009. - 0.5).collect()
+    };
+    let orthogonalize = |x: &mut Vec<f64>, basis: &[Vec<f64>]| {
+        // Twice is enough to stay orthogonal in floating point
+        for _ in 0..2 {
+            for q in basis.iter() {
+                let dot: f64 = x.iter().zip(q.iter()).map(|(a, b)| a * b).sum();
+                x.iter_mut().zip(q.iter()).for_each(|(a, b)| *a -= dot * b);
+            }
+        }
+    };
+
+    let mut basis: Vec<Vec<f64>> = vec![];
+    let mut alpha: Vec<f64> = vec![];
+    // beta[j] couples the basis vectors j and j + 1
+    let mut beta: Vec<f64> = vec![];
+    let mut next = start(0);
+    let mut seed = 1;
+    while basis.len() < steps {
+        let length = norm(&next);
+        if length < 1e-10 {
+            if !beta.is_empty() {
+                *beta.last_mut().expect("checked") = 0.;
+            }
+            next = start(seed);
+            seed += 1;
+            orthogonalize(&mut next, &basis);
+            continue;
+        }
+        next.iter_mut().for_each(|x| *x /= length);
+        let q = std::mem::take(&mut next);
+        let mut w = multiply(&q);
+        alpha.push(w.iter().zip(q.iter()).map(|(a, b)| a * b).sum());
+        basis.push(q);
+        orthogonalize(&mut w, &basis);
+        beta.push(norm(&w));
+        next = w;
+    }
+    beta.pop();
+
+    let k = basis.len();
+    let mut tridiagonal = vec![vec![0f64; k]; k];
+    for i in 0..k {
+        tridiagonal[i][i] = alpha[i];
+        if i + 1 < k {
+            tridiagonal[i][i + 1] = beta[i];
+            tridiagonal[i + 1][i] = beta[i];
+        }
+    }
+    let (values, vectors) = jacobi(tridiagonal);
+    let mut order: Vec<usize> = (0..k).collect();
+    order.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+    order.truncate(count);
+    let ritz = order.iter()
+        .map(|i| {
+            let mut x = vec![0f64; n];
+            for (q, weight) in basis.iter().zip(vectors[*i].iter()) {
+                x.iter_mut().zip(q.iter()).for_each(|(a, b)| *a += weight * b);
+            }
+            normalize(&mut x);
+            x
+        })
+        .collect();
+    (order.iter().map(|i| values[*i]).collect(), ritz)
+}
+
+// The whole spectrum for small graphs, the `count` smallest eigenpairs otherwise
+pub fn laplacian_spectrum(graph: &GraphSnapshot, count: usize) -> Spectrum {
+    let adjacency = laplacian(graph);
+    let n = adjacency.len();
+    let (values, vectors) = if n <= DENSE_LIMIT {
+        let mut matrix = vec![vec![0f64; n]; n];
+        for (v, adjacent) in adjacency.iter().enumerate() {
+            matrix[v][v] = adjacent.len() as f64;
+            for w in adjacent {
+                matrix[v][*w] = -1.;
+            }
+        }
+        jacobi(matrix)
+    } else {
+        lanczos(&adjacency, count.min(n))
+    };
+    let mut pairs: Vec<(f64, Vec<f64>)> = values.into_iter().zip(vectors).collect();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Spectrum {
+        values: pairs.iter().map(|(value, _)| (*value as f32).max(0.)).collect(),
+        vectors: pairs.into_iter().map(|(_, vector)| vector.into_iter().map(|x| x as f32).collect()).collect(),
+    }
+}
+
+// The Fiedler vector, the eigenvector of the second smallest eigenvalue, with a sign that makes the first vertex non-positive
+pub fn fiedler_vector(graph: &GraphSnapshot) -> Option<(f32, Vec<f32>)> {
+    if graph.len() < 2 {
+        return None;
+    }
+    let spectrum = laplacian_spectrum(graph, 2);
+    let mut vector = spectrum.vectors.get(1)?.clone();
+    if vector[0] > 0. {
+        vector.iter_mut().for_each(|x| *x = -*x);
+    }
+    Some((spectrum.values[1], vector))
+}
+
+// Vertices at the 2nd and 3rd eigenvector of the Laplacian, without a third one the vertices lie on a line
+pub fn spectral_layout(graph: &GraphSnapshot, size: f32) -> Vec<Vec2> {
+    let n = graph.len();
+    if n < 2 {
+        return vec![Vec2::ZERO; n];
+    }
+    let spectrum = laplacian_spectrum(graph, 3);
+    let x = &spectrum.vectors[1];
+    let zero = vec![0.; n];
+    let y = spectrum.vectors.get(2).unwrap_or(&zero);
+    let extent = x.iter().chain(y.iter()).map(|value| value.abs()).fold(0., f32::max).max(f32::EPSILON);
+    (0..n).map(|v| Vec2::new(x[v], y[v]) / extent * size).collect()
+}
+
+fn gradient_step(graph: &GraphSnapshot, step: &mut Step, vector: &[f32]) {
+    let min = vector.iter().copied().fold(f32::MAX, f32::min);
+    let max = vector.iter().copied().fold(f32::MIN, f32::max);
+    let range = (max - min).max(f32::EPSILON);
+    for (v, value) in vector.iter().enumerate() {
+        let level = ((value - min) / range * (HEAT_LEVELS - 1) as f32).round() as usize;
+        step.vertex(graph.vertices[v], Mark::Heat(level))
+            .vertex_label(graph.vertices[v], format!("{value:.3}"));
+    }
+}
+
+fn run_fiedler(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    run.start("Fiedler vector", fiedler_trace(&graph.snapshot()));
+}
+
+pub fn fiedler_trace(graph: &GraphSnapshot) -> Trace {
+    let mut trace = Trace::new();
+    match fiedler_vector(graph) {
+        None => {
+            trace.step("The Fiedler vector needs at least two vertices");
+        },
+        Some((value, vector)) => {
+            let step = trace.step(format!(
+                "The Fiedler vector belongs to the second smallest Laplacian eigenvalue λ2 = {value:.4}, the algebraic connectivity.\n{}",
+                if value < ZERO { "λ2 is 0 because the graph is disconnected" } else { "Blue vertices sit on one end of the graph, red ones on the other" }
+            ));
+            gradient_step(graph, step, &vector);
+        },
+    }
+    trace
+}
+
+fn run_bisection(
+    graph: GraphData,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    run.start("Spectral bisection", bisection_trace(&graph.snapshot()));
+}
+
+// Splits at the median of the Fiedler vector so both halves have the same size
+pub fn bisection_trace(graph: &GraphSnapshot) -> Trace {
+    let mut trace = Trace::new();
+    let Some((value, vector)) = fiedler_vector(graph) else {
+        trace.step("Spectral bisection needs at least two vertices");
+        return trace;
+    };
+    let step = trace.step(format!("The Fiedler vector with λ2 = {value:.4} orders the vertices along the graph"));
+    gradient_step(graph, step, &vector);
+
+    let mut order: Vec<usize> = (0..graph.len()).collect();
+    order.sort_by(|a, b| vector[*a].total_cmp(&vector[*b]));
+    let mut side = vec![1; graph.len()];
+    for v in order[..graph.len() / 2].iter() {
+        side[*v] = 0;
+    }
+    let step = trace.step("");
+    let mut cut = 0;
+    for (v, group) in side.iter().enumerate() {
+        step.vertex(graph.vertices[v], Mark::Group(*group)).vertex_label(graph.vertices[v], "");
+    }
+    for (e, edge) in graph.edges.iter().enumerate() {
+        if side[edge.from] != side[edge.to] {
+            step.edge(graph.edge_entity(e), Mark::Cycle);
+            cut += 1;
+        } else {
+            step.edge(graph.edge_entity(e), Mark::Group(side[edge.from]));
+        }
+    }
+    step.message = format!(
+        "Splitting at the median gives halves of {} and {} vertices with {cut} edges across",
+        graph.len() / 2,
+        graph.len() - graph.len() / 2
+    );
+    trace
+}
+
+fn fill_panel(graph: &GraphSnapshot, panel: &mut SpectrumPanel) {
+    if graph.len() < 2 {
+        panel.values.clear();
+        panel.summary = "The spectrum needs at least two vertices".to_string();
+        return;
+    }
+    let spectrum = laplacian_spectrum(graph, 3);
+    let components = spectrum.values.iter().filter(|value| **value < ZERO).count();
+    let shown: Vec<String> = spectrum.values.iter().take(8).map(|value| format!("{value:.3}")).collect();
+    panel.summary = format!(
+        "Laplacian spectrum{}\nλ = {}{}\nλ2 = {:.4}, {} connected component{}",
+        if spectrum.values.len() < graph.len() { format!(", smallest {} of {}", spectrum.values.len(), graph.len()) } else { String::new() },
+        shown.join(", "),
+        if spectrum.values.len() > 8 { ", ..." } else { "" },
+        spectrum.values[1],
+        components,
+        if components == 1 { "" } else { "s" },
+    );
+    panel.values = spectrum.values;
+}
+
+fn toggle_panel(
+    graph: GraphData,
+    mut panel: ResMut<SpectrumPanel>,
+) {
+    panel.visible = !panel.visible;
+    if panel.visible {
+        fill_panel(&graph.snapshot(), &mut panel);
+    }
+}
+
+fn refresh_panel(
+    graph: GraphData,
+    mut panel: ResMut<SpectrumPanel>,
+    q_added: Query<(), Added<Vertex>>,
+    q_added_edge: Query<(), Added<Edge>>,
+    mut removed_vertices: RemovedComponents<Vertex>,
+    mut removed_edges: RemovedComponents<Edge>,
+) {
+    let removed = removed_vertices.read().count() + removed_edges.read().count() > 0;
+    if !panel.visible || (!removed && q_added.is_empty() && q_added_edge.is_empty()) {
+        return;
+    }
+    fill_panel(&graph.snapshot(), &mut panel);
+}
+
+fn spawn_panel(
+    mut commands: Commands,
+) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            left: Val::Percent(35.),
+            top: Val::Px(0.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        background_color: PANEL_COLOR.into(),
+        ..default()
+    }, Interaction::default(), PanelNode))
+    .with_children(|panel| {
+        panel.spawn((NodeBundle {
+            style: Style {
+                height: Val::Px(BAR_HEIGHT),
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::FlexEnd,
+                margin: UiRect::bottom(Val::Px(4.)),
+                ..default()
+            },
+            ..default()
+        }, Bars));
+        panel.spawn((TextBundle::from_section("", text_style()), SummaryText));
+    });
+}
+
+// One bar per eigenvalue, big spectra are sampled evenly
+fn update_panel(
+    mut commands: Commands,
+    panel: Res<SpectrumPanel>,
+    mut q_panel: Query<&mut Style, With<PanelNode>>,
+    q_bars: Query<(Entity, Option<&Children>), With<Bars>>,
+    mut q_text: Query<&mut Text, With<SummaryText>>,
+) {
+    if !panel.is_changed() {
+        return;
+    }
+    if let Ok(mut style) = q_panel.get_single_mut() {
+        style.display = if panel.visible { Display::Flex } else { Display::None };
+    }
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value = panel.summary.clone();
+    }
+    let Ok((bars, children)) = q_bars.get_single() else {return};
+    for child in children.into_iter().flatten() {
+        commands.entity(*child).despawn_recursive();
+    }
+    let max = panel.values.iter().copied().fold(0., f32::max).max(f32::EPSILON);
+    let count = panel.values.len().min(MAX_BARS);
+    commands.entity(bars).with_children(|parent| {
+        for i in 0..count {
+            let value = panel.values[i * panel.values.len() / count];
+            parent.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(6.),
+                    height: Val::Px((value / max * BAR_HEIGHT).max(1.)),
+                    margin: UiRect::right(Val::Px(1.)),
+                    ..default()
+                },
+                background_color: BAR_COLOR.into(),
+                ..default()
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{fiedler_vector, laplacian_spectrum, DENSE_LIMIT};
+
+    fn cycle(n: usize) -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = (0..n).map(|v| (v, (v + 1) % n, 1, false)).collect();
+        GraphSnapshot::test_graph(n, &edges)
+    }
+
+    // The cycle on n vertices has the eigenvalues 2 - 2cos(2πk / n)
+    fn cycle_values(n: usize) -> Vec<f32> {
+        let mut values: Vec<f32> = (0..n).map(|k| 2. - 2. * (2. * PI * k as f32 / n as f32).cos()).collect();
+        values.sort_by(f32::total_cmp);
+        values
+    }
+
+    #[test]
+    fn dense_spectrum_of_a_cycle() {
+        let spectrum = laplacian_spectrum(&cycle(9), 9);
+        for (value, expected) in spectrum.values.iter().zip(cycle_values(9)) {
+            assert!((value - expected).abs() < 1e-4, "{:?}", spectrum.values);
+        }
+        // Every eigenvector is a unit vector
+        for vector in spectrum.vectors.iter() {
+            assert!((vector.iter().map(|x| x * x).sum::<f32>() - 1.).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn sparse_spectrum_of_a_cycle() {
+        let n = DENSE_LIMIT + 30;
+        let spectrum = laplacian_spectrum(&cycle(n), 3);
+        for (value, expected) in spectrum.values.iter().take(3).zip(cycle_values(n)) {
+            assert!((value - expected).abs() < 1e-3, "{:?}", &spectrum.values[..3]);
+        }
+    }
+
+    #[test]
+    fn zero_per_component() {
+        let edges = [(0, 1, 1, false), (1, 2, 1, false), (3, 4, 1, false)];
+        let spectrum = laplacian_spectrum(&GraphSnapshot::test_graph(5, &edges), 5);
+        let zeros = spectrum.values.iter().filter(|value| **value < 1e-4).count();
+        assert_eq!(zeros, 2);
+    }
+
+    #[test]
+    fn fiedler_vector_orders_a_path() {
+        let edges: Vec<(usize, usize, i32, bool)> = (0..5).map(|v| (v, v + 1, 1, false)).collect();
+        let (value, vector) = fiedler_vector(&GraphSnapshot::test_graph(6, &edges)).unwrap();
+        assert!((value - (2. - 2. * (PI / 6.).cos())).abs() < 1e-4);
+        assert!(vector.windows(2).all(|pair| pair[0] < pair[1]), "{vector:?}");
+    }
+}
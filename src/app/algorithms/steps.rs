@@ -0,0 +1,105 @@
+use bevy::{math::Vec2, prelude::Entity};
+
+// How an algorithm colors a vertex or an edge
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mark {
+    Active,
+    Frontier,
+    Done,
+    Cycle,
+    Highlight,
+    // Edges of a matching, drawn thicker
+    Matched,
+    // Vertices or edges that belong together, like the two sides of a cut
+    Group(usize),
+    // A value on a gradient from cold to hot, 0 to HEAT_LEVELS - 1
+    Heat(usize),
+}
+
+pub const HEAT_LEVELS: usize = 11;
+
+pub enum Change {
+    Vertex(Entity, Mark),
+    Edge(Entity, Mark),
+    VertexLabel(Entity, String),
+    EdgeLabel(Entity, String),
+    Position(Entity, Vec2),
+    // Scales a vertex, the scale is reset with its mark
+    Scale(Entity, f32),
+    // Removes the mark of a vertex or an edge
+    Clear(Entity),
+    // Lines between vertices, replaces the lines drawn before
+    Segments(Vec<(Entity, Entity, Mark)>),
+}
+
+// One animation frame of an algorithm run
+#[derive(Default)]
+pub struct Step {
+    pub changes: Vec<Change>,
+    pub message: String,
+}
+
+impl Step {
+    pub fn vertex(&mut self, entity: Entity, mark: Mark) -> &mut Self {
+        self.changes.push(Change::Vertex(entity, mark));
+        self
+    }
+
+    pub fn edge(&mut self, entity: Entity, mark: Mark) -> &mut Self {
+        self.changes.push(Change::Edge(entity, mark));
+        self
+    }
+
+    pub fn vertex_label(&mut self, entity: Entity, label: impl Into<String>) -> &mut Self {
+        self.changes.push(Change::VertexLabel(entity, label.into()));
+        self
+    }
+
+    pub fn edge_label(&mut self, entity: Entity, label: impl Into<String>) -> &mut Self {
+        self.changes.push(Change::EdgeLabel(entity, label.into()));
+        self
+    }
+
+    pub fn position(&mut self, entity: Entity, position: Vec2) -> &mut Self {
+        self.changes.push(Change::Position(entity, position));
+        self
+    }
+
+    pub fn scale(&mut self, entity: Entity, scale: f32) -> &mut Self {
+        self.changes.push(Change::Scale(entity, scale));
+        self
+    }
+
+    pub fn clear(&mut self, entity: Entity) -> &mut Self {
+        self.changes.push(Change::Clear(entity));
+        self
+    }
+
+    pub fn segments(&mut self, segments: Vec<(Entity, Entity, Mark)>) -> &mut Self {
+        self.changes.push(Change::Segments(segments));
+        self
+    }
+}
+
+/**
+    # Trace
+    The recorded steps of an algorithm, algorithms compute their whole run up front and the playback animates it.
+*/
+#[derive(Default)]
+pub struct Trace {
+    pub steps: Vec<Step>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn step(&mut self, message: impl Into<String>) -> &mut Step {
+        self.steps.push(Step {
+            changes: vec![],
+            message: message.into(),
+        });
+        self.steps.last_mut().expect("a step was just pushed")
+    }
+}
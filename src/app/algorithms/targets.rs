@@ -0,0 +1,135 @@
+use bevy::{app::{Plugin, Update}, color::Color, math::Vec3, prelude::{BuildChildren, Commands, Component, DespawnRecursiveExt, DetectChanges, Entity, NextState, Query, Res, ResMut, Resource, State, With}};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::app::{build_graph::{components::{label_text, EditorState, Vertex}, res::NearestPoints, RADIUS}, input::NormalInput, ui::{InfoPanel, MenuAppExt}};
+
+pub struct TargetsPlugin;
+impl Plugin for TargetsPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<AlgorithmTargets>()
+        .add_systems(Update, (pick_target, show_targets))
+        .add_menu_button("Targets", "Pick start vertex", pick_start)
+        .add_menu_button("Targets", "Pick end vertex", pick_end)
+        .add_menu_button("Targets", "Clear targets", clear_targets)
+        ;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Target {
+    Start,
+    End,
+}
+
+// The start and end vertex an algorithm runs with, a source and a sink for flows
+#[derive(Resource, Default)]
+pub struct AlgorithmTargets {
+    pub start: Option<Entity>,
+    pub end: Option<Entity>,
+    // The next selected vertex becomes this target, while picking the editor is in edit mode
+    picking: Option<(Target, EditorState)>,
+}
+
+impl AlgorithmTargets {
+    fn set(&mut self, target: Target, entity: Option<Entity>) {
+        match target {
+            Target::Start => self.start = entity,
+            Target::End => self.end = entity,
+        }
+    }
+}
+
+// Text child of a vertex showing which target it is
+#[derive(Component)]
+struct TargetLabel;
+
+const TARGET_LABEL_OFFSET: Vec3 = Vec3::new(0., -RADIUS - 30., 2.);
+const TARGET_LABEL_COLOR: Color = Color::srgb(0.6, 1., 0.6);
+
+fn start_picking(
+    target: Target,
+    targets: &mut AlgorithmTargets,
+    state: &State<EditorState>,
+    next_state: &mut NextState<EditorState>,
+    info: &mut InfoPanel,
+) {
+    let previous = targets.picking.map_or(*state.get(), |(_, previous)| previous);
+    targets.picking = Some((target, previous));
+    next_state.set(EditorState::Edit);
+    info.text = format!("Select the {} vertex", if target == Target::Start {"start"} else {"end"});
+}
+
+fn pick_start(
+    mut targets: ResMut<AlgorithmTargets>,
+    state: Res<State<EditorState>>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    start_picking(Target::Start, &mut targets, &state, &mut next_state, &mut info);
+}
+
+fn pick_end(
+    mut targets: ResMut<AlgorithmTargets>,
+    state: Res<State<EditorState>>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    start_picking(Target::End, &mut targets, &state, &mut next_state, &mut info);
+}
+
+fn clear_targets(
+    mut targets: ResMut<AlgorithmTargets>,
+) {
+    targets.start = None;
+    targets.end = None;
+}
+
+fn pick_target(
+    mut targets: ResMut<AlgorithmTargets>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+    nearest_points: Res<NearestPoints>,
+    q_my_action: Query<&ActionState<NormalInput>>,
+) {
+    let Some((target, previous)) = targets.picking else {return};
+    let my_action = q_my_action.single();
+    if !my_action.just_pressed(&NormalInput::Select) {
+        return;
+    }
+    let Some(vertex) = nearest_points.hovered() else {return};
+
+    targets.set(target, Some(vertex));
+    targets.picking = None;
+    next_state.set(previous);
+    info.text.clear();
+}
+
+fn show_targets(
+    mut commands: Commands,
+    mut targets: ResMut<AlgorithmTargets>,
+    q_vertex: Query<(), With<Vertex>>,
+    q_label: Query<Entity, With<TargetLabel>>,
+) {
+    // Forget targets whose vertex is gone
+    for target in [Target::Start, Target::End] {
+        let entity = if target == Target::Start { targets.start } else { targets.end };
+        if entity.is_some_and(|entity| !q_vertex.contains(entity)) {
+            targets.set(target, None);
+        }
+    }
+    if !targets.is_changed() {
+        return;
+    }
+
+    for label in q_label.iter() {
+        commands.entity(label).despawn_recursive();
+    }
+    let labels = [(targets.start, "start"), (targets.end, "end")];
+    for (entity, text) in labels {
+        let Some(entity) = entity else {continue};
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((label_text(text, TARGET_LABEL_COLOR, TARGET_LABEL_OFFSET), TargetLabel));
+        });
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::VecDeque;
+
+use bevy::{app::Plugin, math::Vec2, prelude::{Res, ResMut, Resource}};
+
+use crate::app::ui::{InfoPanel, MenuAppExt};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Trace}};
+
+pub struct TopologicalSortPlugin;
+impl Plugin for TopologicalSortPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<TopologicalSortSettings>()
+        .add_menu_button("Topological Sort", "Kahn's algorithm", run_kahn)
+        .add_menu_button("Topological Sort", "DFS topological sort", run_dfs_toposort)
+        .add_menu_button("Topological Sort", "Toggle layered layout", toggle_layered_layout)
+        ;
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct TopologicalSortSettings {
+    // Move the vertices into layers from left to right once an order was found
+    pub layered_layout: bool,
+}
+
+pub const LAYER_SPACING: f32 = 250.0;
+pub const VERTEX_SPACING: f32 = 150.0;
+
+fn run_kahn(
+    graph: GraphData,
+    settings: Res<TopologicalSortSettings>,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let graph = graph.snapshot();
+    run.start("Kahn's algorithm", kahn(&graph, settings.layered_layout));
+}
+
+fn run_dfs_toposort(
+    graph: GraphData,
+    settings: Res<TopologicalSortSettings>,
+    mut run: ResMut<AlgorithmRun>,
+) {
+    let graph = graph.snapshot();
+    run.start("DFS topological sort", dfs_toposort(&graph, settings.layered_layout));
+}
+
+fn toggle_layered_layout(
+    mut settings: ResMut<TopologicalSortSettings>,
+    mut info: ResMut<InfoPanel>,
+) {
+    settings.layered_layout = !settings.layered_layout;
+    info.text = format!("Layered layout after sorting: {}", if settings.layered_layout {"on"} else {"off"});
+}
+
+/**
+    # Kahn's Algorithm
+    Repeatedly removes a vertex without incoming edges. If vertices remain when no such vertex is left,
+    every remaining vertex has a remaining predecessor and following them backwards must run into a cycle.
+*/
+pub fn kahn(graph: &GraphSnapshot, layered_layout: bool) -> Trace {
+    let mut trace = Trace::new();
+    let n = graph.len();
+    let mut in_degree: Vec<usize> = (0..n).map(|v| graph.inc[v].len()).collect();
+
+    let step = trace.step("Count the incoming edges of every vertex");
+    for (v, degree) in in_degree.iter().enumerate() {
+        step.vertex_label(graph.vertices[v], format!("in {degree}"));
+    }
+
+    let mut queue: VecDeque<usize> = (0..n).filter(|v| in_degree[*v] == 0).collect();
+    let step = trace.step(format!("Vertices without incoming edges: {}", names(graph, queue.iter())));
+    for v in queue.iter() {
+        step.vertex(graph.vertices[*v], Mark::Frontier);
+    }
+
+    let mut order: Vec<usize> = Vec::with_capacity(n);
+    while let Some(v) = queue.pop_front() {
+        order.push(v);
+        trace.step(format!("Take {} as number {} of the order", graph.name(v), order.len()))
+            .vertex(graph.vertices[v], Mark::Active)
+            .vertex_label(graph.vertices[v], format!("#{}", order.len()));
+
+        for &(w, e) in graph.out[v].iter() {
+            in_degree[w] -= 1;
+            let step = trace.step(format!("Remove the edge {} → {}, {} has {} incoming edges left", graph.name(v), graph.name(w), graph.name(w), in_degree[w]));
+            step.edge(graph.edge_entity(e), Mark::Done)
+                .vertex_label(graph.vertices[w], format!("in {}", in_degree[w]));
+            if in_degree[w] == 0 {
+                step.vertex(graph.vertices[w], Mark::Frontier);
+                queue.push_back(w);
+            }
+        }
+        trace.step(format!("{} is done", graph.name(v)))
+            .vertex(graph.vertices[v], Mark::Done);
+    }
+
+    if order.len() < n {
+        let remaining: Vec<bool> = in_degree.iter().map(|d| *d > 0).collect();
+        let start = (0..n).find(|v| remaining[*v]).expect("a vertex has to remain");
+        let cycle = backwards_cycle(graph, start, &remaining);
+        record_cycle(graph, &mut trace, &cycle, &format!("{} vertices still have incoming edges", n - order.len()));
+        return trace;
+    }
+
+    record_order(graph, &mut trace, &order, layered_layout);
+    trace
+}
+
+/**
+    # DFS Topological Sort
+    A vertex gets its place in the order when all of its successors are finished, so the reversed finishing order
+    is topological. Reaching a vertex that is still on the stack means the stack contains a cycle.
+*/
+pub fn dfs_toposort(graph: &GraphSnapshot, layered_layout: bool) -> Trace {
+    const WHITE: u8 = 0;
+    const GREY: u8 = 1;
+    const BLACK: u8 = 2;
+
+    let mut trace = Trace::new();
+    let n = graph.len();
+    let mut color = vec![WHITE; n];
+    let mut finished: Vec<usize> = Vec::with_capacity(n);
+    let mut time = 0;
+
+    for root in 0..n {
+        if color[root] != WHITE {
+            continue;
+        }
+        // (Vertex, next out edge to look at, edge the vertex was reached by)
+        let mut stack: Vec<(usize, usize, Option<usize>)> = vec![(root, 0, None)];
+        color[root] = GREY;
+        time += 1;
+        trace.step(format!("Start a search at {}", graph.name(root)))
+            .vertex(graph.vertices[root], Mark::Frontier)
+            .vertex_label(graph.vertices[root], format!("d{time}"));
+
+        while let Some(top) = stack.last_mut() {
+            let (v, next, reached_by) = *top;
+            let Some(&(w, e)) = graph.out[v].get(next) else {
+                stack.pop();
+                color[v] = BLACK;
+                time += 1;
+                finished.push(v);
+                let step = trace.step(format!("{} is finished, put it in front of the order", graph.name(v)));
+                step.vertex(graph.vertices[v], Mark::Done)
+                    .vertex_label(graph.vertices[v], format!("f{time}"));
+                if let Some(by) = reached_by {
+                    step.edge(graph.edge_entity(by), Mark::Done);
+                }
+                continue;
+            };
+            top.1 += 1;
+
+            match color[w] {
+                WHITE => {
+                    color[w] = GREY;
+                    time += 1;
+                    trace.step(format!("Follow {} → {}", graph.name(v), graph.name(w)))
+                        .edge(graph.edge_entity(e), Mark::Active)
+                        .vertex(graph.vertices[w], Mark::Frontier)
+                        .vertex_label(graph.vertices[w], format!("d{time}"));
+                    stack.push((w, 0, Some(e)));
+                },
+                GREY => {
+                    let position = stack.iter().position(|(u, _, _)| *u == w).expect("a grey vertex is on the stack");
+                    let mut cycle: Vec<(usize, Option<usize>)> = stack[position..].iter()
+                        .map(|(u, _, by)| (*u, *by))
+                        .collect();
+                    cycle[0].1 = None;
+                    cycle.push((w, Some(e)));
+                    record_cycle(graph, &mut trace, &cycle, &format!("{} is still on the stack", graph.name(w)));
+                    return trace;
+                },
+                _ => {
+                    trace.step(format!("{} is already finished", graph.name(w)))
+                        .edge(graph.edge_entity(e), Mark::Done);
+                },
+            }
+        }
+    }
+
+    finished.reverse();
+    record_order(graph, &mut trace, &finished, layered_layout);
+    trace
+}
+
+// Walks incoming edges between remaining vertices until a vertex repeats, returns the cycle in edge direction
+fn backwards_cycle(graph: &GraphSnapshot, start: usize, remaining: &[bool]) -> Vec<(usize, Option<usize>)> {
+    let mut walk: Vec<(usize, usize)> = vec![];
+    let mut seen_at: Vec<Option<usize>> = vec![None; graph.len()];
+    let mut v = start;
+    let mut came_by: Option<usize> = None;
+    loop {
+        if let Some(position) = seen_at[v] {
+            // walk[i] = (vertex, edge from its predecessor), reversed it is a path in edge direction
+            let mut cycle: Vec<(usize, Option<usize>)> = vec![(v, None)];
+            cycle.extend(walk[position..].iter().rev().map(|(u, e)| (*u, Some(*e))));
+            return cycle;
+        }
+        seen_at[v] = Some(walk.len());
+        // Prefer not to walk an undirected edge straight back
+        let predecessors = graph.inc[v].iter().filter(|(u, _)| remaining[*u]);
+        let &(u, e) = predecessors.clone()
+            .find(|(_, e)| Some(*e) != came_by)
+            .or_else(|| predecessors.clone().next())
+            .expect("a remaining vertex has a remaining predecessor");
+        walk.push((v, e));
+        came_by = Some(e);
+        v = u;
+    }
+}
+
+fn record_cycle(graph: &GraphSnapshot, trace: &mut Trace, cycle: &[(usize, Option<usize>)], reason: &str) {
+    let path: Vec<usize> = cycle.iter().map(|(v, _)| *v).collect();
+    let undirected = cycle.iter().filter_map(|(_, e)| *e).any(|e| !graph.edges[e].directed);
+    let mut message = format!("Not a DAG, {reason}.\nCycle: {}", graph.path_names(&path));
+    if undirected {
+        message.push_str("\nUndirected edges can be walked in both directions");
+    }
+    let step = trace.step(message);
+    for (v, e) in cycle {
+        step.vertex(graph.vertices[*v], Mark::Cycle);
+        if let Some(e) = e {
+            step.edge(graph.edge_entity(*e), Mark::Cycle);
+        }
+    }
+}
+
+fn record_order(graph: &GraphSnapshot, trace: &mut Trace, order: &[usize], layered_layout: bool) {
+    let step = trace.step(format!("Topological order: {}", graph.path_names(order)));
+    for (i, v) in order.iter().enumerate() {
+        step.vertex_label(graph.vertices[*v], format!("#{}", i + 1));
+    }
+    if !layered_layout {
+        return;
+    }
+    let positions = layered_positions(graph, order);
+    let step = trace.step("Lay out the vertices in layers from left to right");
+    for (v, position) in positions.into_iter().enumerate() {
+        step.position(graph.vertices[v], position);
+    }
+}
+
+/**
+    # Layered Positions
+    Every vertex is put into the layer of its longest path from a source, the layers are centered around the
+    current center of the graph. The order has to be topological.
+*/
+pub fn layered_positions(graph: &GraphSnapshot, order: &[usize]) -> Vec<Vec2> {
+    let n = graph.len();
+    let mut layer = vec![0usize; n];
+    for &v in order {
+        for &(w, e) in graph.out[v].iter() {
+            if graph.edges[e].directed {
+                layer[w] = layer[w].max(layer[v] + 1);
+            }
+        }
+    }
+
+    let layer_count = layer.iter().max().map_or(0, |max| max + 1);
+    let mut layers: Vec<Vec<usize>> = vec![vec![]; layer_count];
+    for &v in order {
+        layers[layer[v]].push(v);
+    }
+
+    let center = graph.positions.iter().copied().sum::<Vec2>() / n.max(1) as f32;
+    let mut positions = graph.positions.clone();
+    for (i, vertices) in layers.iter().enumerate() {
+        let x = center.x + (i as f32 - (layer_count - 1) as f32 / 2.) * LAYER_SPACING;
+        for (j, v) in vertices.iter().enumerate() {
+            let y = center.y + ((vertices.len() - 1) as f32 / 2. - j as f32) * VERTEX_SPACING;
+            positions[*v] = Vec2::new(x, y);
+        }
+    }
+    positions
+}
+
+fn names<'a>(graph: &GraphSnapshot, vertices: impl Iterator<Item = &'a usize>) -> String {
+    let names: Vec<&str> = vertices.map(|v| graph.name(*v)).collect();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::{Change, Mark, Trace}};
+
+    use super::{dfs_toposort, kahn};
+
+    fn last_message(trace: &Trace) -> &str {
+        &trace.steps.last().expect("the run has steps").message
+    }
+
+    // The order of the last step as vertex indices
+    fn order(graph: &GraphSnapshot, trace: &Trace) -> Vec<usize> {
+        let order = last_message(trace).strip_prefix("Topological order: ").expect("the graph is a DAG");
+        order.split(" → ").map(|name| graph.names.iter().position(|known| known == name).unwrap()).collect()
+    }
+
+    fn cycle_vertices(graph: &GraphSnapshot, trace: &Trace) -> Vec<usize> {
+        let mut cycle: Vec<usize> = trace.steps.last().unwrap().changes.iter()
+            .filter_map(|change| match change {
+                Change::Vertex(entity, Mark::Cycle) => Some(graph.index[entity]),
+                _ => None,
+            })
+            .collect();
+        cycle.sort();
+        cycle.dedup();
+        cycle
+    }
+
+    fn dag() -> GraphSnapshot {
+        GraphSnapshot::test_graph(6, &[(5, 2, 1, true), (5, 0, 1, true), (4, 0, 1, true), (4, 1, 1, true), (2, 3, 1, true), (3, 1, 1, true)])
+    }
+
+    fn assert_topological(graph: &GraphSnapshot, order: &[usize]) {
+        assert_eq!(order.len(), graph.len());
+        let place = |v: usize| order.iter().position(|u| *u == v).unwrap();
+        for edge in graph.edges.iter() {
+            assert!(place(edge.from) < place(edge.to), "{} comes after {}", graph.name(edge.from), graph.name(edge.to));
+        }
+    }
+
+    #[test]
+    fn kahn_orders_a_dag() {
+        let graph = dag();
+        assert_topological(&graph, &order(&graph, &kahn(&graph, false)));
+    }
+
+    #[test]
+    fn dfs_orders_a_dag() {
+        let graph = dag();
+        assert_topological(&graph, &order(&graph, &dfs_toposort(&graph, false)));
+    }
+
+    #[test]
+    fn layered_layout_moves_every_vertex() {
+        let graph = dag();
+        let trace = kahn(&graph, true);
+        let positions = trace.steps.last().unwrap().changes.iter().filter(|change| matches!(change, Change::Position(..))).count();
+        assert_eq!(positions, graph.len());
+    }
+
+    #[test]
+    fn cycle_is_found() {
+        // v0 leads into the cycle v1 → v2 → v3 → v1
+        let graph = GraphSnapshot::test_graph(5, &[(0, 1, 1, true), (1, 2, 1, true), (2, 3, 1, true), (3, 1, 1, true), (3, 4, 1, true)]);
+        for trace in [kahn(&graph, false), dfs_toposort(&graph, false)] {
+            assert!(last_message(&trace).starts_with("Not a DAG"));
+            assert_eq!(cycle_vertices(&graph, &trace), vec![1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn undirected_edge_is_a_cycle() {
+        let graph = GraphSnapshot::test_graph(2, &[(0, 1, 1, false)]);
+        for trace in [kahn(&graph, false), dfs_toposort(&graph, false)] {
+            assert!(last_message(&trace).contains("Undirected edges"));
+            assert_eq!(cycle_vertices(&graph, &trace), vec![0, 1]);
+        }
+    }
+}
@@ -0,0 +1,617 @@
+use bevy::{app::Plugin, prelude::{Entity, Res, ResMut}};
+
+use crate::app::{build_graph::kdtree::TwoDTree, ui::MenuAppExt};
+
+use super::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::{Mark, Trace}, targets::AlgorithmTargets};
+
+pub struct TspPlugin;
+impl Plugin for TspPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_menu_button("Travelling Salesman", "Nearest neighbour tour", run_nearest_neighbour)
+        .add_menu_button("Travelling Salesman", "2-opt", run_two_opt)
+        .add_menu_button("Travelling Salesman", "Or-opt", run_or_opt)
+        .add_menu_button("Travelling Salesman", "Christofides", run_christofides)
+        .add_menu_button("Travelling Salesman", "Held-Karp (exact)", run_held_karp)
+        ;
+    }
+}
+
+// The dynamic program needs 2^n * n entries
+const HELD_KARP_LIMIT: usize = 15;
+// Odd vertices of the spanning tree that are still matched exactly, beyond that the matching is greedy
+const EXACT_MATCHING_LIMIT: usize = 20;
+// Improvement moves shown before the local search stops
+const MOVE_LIMIT: usize = 300;
+const EPSILON: f32 = 1e-3;
+
+fn run_nearest_neighbour(graph: GraphData, targets: Res<AlgorithmTargets>, mut run: ResMut<AlgorithmRun>) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    if let Some(start) = tsp_start(&graph, &targets, &mut trace) {
+        nearest_neighbour_tour(&graph, start, Some(&mut trace));
+    }
+    run.start("Nearest neighbour tour", trace);
+}
+
+fn run_two_opt(graph: GraphData, targets: Res<AlgorithmTargets>, mut run: ResMut<AlgorithmRun>) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    if let Some(start) = tsp_start(&graph, &targets, &mut trace) {
+        let tour = nearest_neighbour_tour(&graph, start, None);
+        two_opt(&graph, tour, Some(&mut trace));
+    }
+    run.start("2-opt", trace);
+}
+
+fn run_or_opt(graph: GraphData, targets: Res<AlgorithmTargets>, mut run: ResMut<AlgorithmRun>) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    if let Some(start) = tsp_start(&graph, &targets, &mut trace) {
+        let tour = nearest_neighbour_tour(&graph, start, None);
+        or_opt(&graph, tour, Some(&mut trace));
+    }
+    run.start("Or-opt", trace);
+}
+
+fn run_christofides(graph: GraphData, targets: Res<AlgorithmTargets>, mut run: ResMut<AlgorithmRun>) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    if let Some(start) = tsp_start(&graph, &targets, &mut trace) {
+        christofides(&graph, start, Some(&mut trace));
+    }
+    run.start("Christofides", trace);
+}
+
+fn run_held_karp(graph: GraphData, targets: Res<AlgorithmTargets>, mut run: ResMut<AlgorithmRun>) {
+    let graph = graph.snapshot();
+    let mut trace = Trace::new();
+    if let Some(start) = tsp_start(&graph, &targets, &mut trace) {
+        held_karp(&graph, start, &mut trace);
+    }
+    run.start("Held-Karp", trace);
+}
+
+// The tour of the menu starts at the start target
+fn tsp_start(graph: &GraphSnapshot, targets: &AlgorithmTargets, trace: &mut Trace) -> Option<usize> {
+    tour_start(graph, targets.start.and_then(|start| graph.index.get(&start).copied()), trace)
+}
+
+/**
+    # TSP Instance
+    The tour runs through every vertex with the Euclidean distances as costs, so the graph has to be complete
+    or have no edges at all, then the edges are implied. The tour starts at the preferred vertex if there is one.
+*/
+pub fn tour_start(graph: &GraphSnapshot, preferred: Option<usize>, trace: &mut Trace) -> Option<usize> {
+    let n = graph.len();
+    if n < 3 {
+        trace.step("A tour needs at least 3 vertices");
+        return None;
+    }
+    let mut pairs: Vec<(usize, usize)> = graph.edges.iter()
+        .map(|edge| (edge.from.min(edge.to), edge.from.max(edge.to)))
+        .collect();
+    pairs.sort();
+    pairs.dedup();
+    if !graph.edges.is_empty() && pairs.len() != n * (n - 1) / 2 {
+        trace.step("The tour tools need a complete graph or a graph without edges, the distances between the vertices are the costs");
+        return None;
+    }
+    Some(preferred.unwrap_or(0))
+}
+
+fn distance(graph: &GraphSnapshot, a: usize, b: usize) -> f32 {
+    graph.positions[a].distance(graph.positions[b])
+}
+
+pub fn tour_length(graph: &GraphSnapshot, tour: &[usize]) -> f32 {
+    (0..tour.len()).map(|i| distance(graph, tour[i], tour[(i + 1) % tour.len()])).sum()
+}
+
+// The lines of a closed tour, the given legs are drawn with their own mark
+fn tour_segments(graph: &GraphSnapshot, tour: &[usize], marked: &[(usize, usize, Mark)]) -> Vec<(Entity, Entity, Mark)> {
+    (0..tour.len())
+        .map(|i| {
+            let (a, b) = (tour[i], tour[(i + 1) % tour.len()]);
+            let mark = marked.iter()
+                .find(|(x, y, _)| (*x == a && *y == b) || (*x == b && *y == a))
+                .map_or(Mark::Done, |(_, _, mark)| *mark);
+            (graph.vertices[a], graph.vertices[b], mark)
+        })
+        .collect()
+}
+
+fn show_tour(graph: &GraphSnapshot, trace: &mut Trace, tour: &[usize], message: String) {
+    let step = trace.step(message);
+    step.segments(tour_segments(graph, tour, &[]));
+    for v in tour {
+        step.vertex(graph.vertices[*v], Mark::Done);
+    }
+}
+
+/**
+    # Nearest Neighbour Tour
+    Always travels to the closest unvisited vertex. The kd-tree answers the nearest queries, it can't remove points
+    so the number of neighbours asked for doubles until an unvisited one shows up.
+*/
+pub fn nearest_neighbour_tour(graph: &GraphSnapshot, start: usize, mut trace: Option<&mut Trace>) -> Vec<usize> {
+    let n = graph.len();
+    let mut kd = TwoDTree::new();
+    kd.insert_list(graph.vertices.iter().copied().zip(graph.positions.iter().copied()).collect());
+
+    let mut visited = vec![false; n];
+    visited[start] = true;
+    let mut tour = vec![start];
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.step(format!("Start the tour at {}", graph.name(start)))
+            .vertex(graph.vertices[start], Mark::Active);
+    }
+
+    while tour.len() < n {
+        let current = *tour.last().expect("the tour has a start");
+        let mut k = 2;
+        let next = loop {
+            let nearest = kd.n_nearest_neighboors_search(graph.positions[current], k.min(n)).unwrap_or_default();
+            let unvisited = nearest.into_iter()
+                .filter_map(|item| graph.index.get(&item.0).map(|v| (*v, item.1)))
+                .filter(|(v, _)| !visited[*v])
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((v, _)) = unvisited {
+                break v;
+            }
+            k *= 2;
+        };
+        visited[next] = true;
+        tour.push(next);
+
+        if let Some(trace) = trace.as_deref_mut() {
+            let mut segments: Vec<(Entity, Entity, Mark)> = tour.windows(2)
+                .map(|leg| (graph.vertices[leg[0]], graph.vertices[leg[1]], Mark::Done))
+                .collect();
+            if let Some(last) = segments.last_mut() {
+                last.2 = Mark::Active;
+            }
+            trace.step(format!("{} is the closest unvisited vertex to {} at distance {:.0}", graph.name(next), graph.name(current), distance(graph, current, next)))
+                .segments(segments)
+                .vertex(graph.vertices[current], Mark::Done)
+                .vertex(graph.vertices[next], Mark::Active);
+        }
+    }
+
+    if let Some(trace) = trace {
+        show_tour(graph, trace, &tour, format!("Return to {}, the tour has length {:.0}", graph.name(start), tour_length(graph, &tour)));
+    }
+    tour
+}
+
+/**
+    # 2-opt
+    Removes two legs of the tour and reconnects the two paths the other way around, which reverses the part in between.
+    Moves are taken as soon as they shorten the tour until no move helps anymore.
+*/
+pub fn two_opt(graph: &GraphSnapshot, mut tour: Vec<usize>, mut trace: Option<&mut Trace>) -> Vec<usize> {
+    let n = tour.len();
+    if let Some(trace) = trace.as_deref_mut() {
+        show_tour(graph, trace, &tour, format!("Start with the nearest neighbour tour of length {:.0}", tour_length(graph, &tour)));
+    }
+    let mut moves = 0;
+    let mut improved = true;
+    while improved && moves < MOVE_LIMIT {
+        improved = false;
+        for i in 0..n - 1 {
+            for j in i + 2..n {
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+                let (a, b, c, d) = (tour[i], tour[i + 1], tour[j], tour[(j + 1) % n]);
+                let delta = distance(graph, a, c) + distance(graph, b, d) - distance(graph, a, b) - distance(graph, c, d);
+                if delta > -EPSILON {
+                    continue;
+                }
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.step(format!("Remove {}-{} and {}-{}", graph.name(a), graph.name(b), graph.name(c), graph.name(d)))
+                        .segments(tour_segments(graph, &tour, &[(a, b, Mark::Cycle), (c, d, Mark::Cycle)]));
+                }
+                tour[i + 1..=j].reverse();
+                moves += 1;
+                improved = true;
+                if let Some(trace) = trace.as_deref_mut() {
+                    trace.step(format!(
+                        "Reconnect with {}-{} and {}-{}, saves {:.0}, the tour has length {:.0}",
+                        graph.name(a), graph.name(c), graph.name(b), graph.name(d), -delta, tour_length(graph, &tour)
+                    ))
+                        .segments(tour_segments(graph, &tour, &[(a, c, Mark::Active), (b, d, Mark::Active)]));
+                }
+            }
+        }
+    }
+    if let Some(trace) = trace {
+        show_tour(graph, trace, &tour, format!("No 2-opt move shortens the tour after {moves} moves, length {:.0}", tour_length(graph, &tour)));
+    }
+    tour
+}
+
+/**
+    # Or-opt
+    Moves a chain of up to three consecutive vertices to another place of the tour, in the same or reversed direction.
+*/
+pub fn or_opt(graph: &GraphSnapshot, mut tour: Vec<usize>, mut trace: Option<&mut Trace>) -> Vec<usize> {
+    let n = tour.len();
+    if let Some(trace) = trace.as_deref_mut() {
+        show_tour(graph, trace, &tour, format!("Start with the nearest neighbour tour of length {:.0}", tour_length(graph, &tour)));
+    }
+    let mut moves = 0;
+    let mut improved = true;
+    while improved && moves < MOVE_LIMIT {
+        improved = false;
+        'search: for length in 1..=3 {
+            if n < length + 3 {
+                break;
+            }
+            for i in 0..n {
+                // The chain tour[i..i+length] wraps around the end of the tour
+                let chain: Vec<usize> = (0..length).map(|k| tour[(i + k) % n]).collect();
+                let before = tour[(i + n - 1) % n];
+                let after = tour[(i + length) % n];
+                let removed = distance(graph, before, chain[0]) + distance(graph, chain[length - 1], after) - distance(graph, before, after);
+                let rest: Vec<usize> = (0..n - length).map(|k| tour[(i + length + k) % n]).collect();
+
+                for p in 0..rest.len() {
+                    let (x, y) = (rest[p], rest[(p + 1) % rest.len()]);
+                    if x == before && y == after {
+                        continue;
+                    }
+                    let (first, last) = (chain[0], chain[length - 1]);
+                    let forward = distance(graph, x, first) + distance(graph, last, y) - distance(graph, x, y);
+                    let backward = distance(graph, x, last) + distance(graph, first, y) - distance(graph, x, y);
+                    let reversed = backward < forward;
+                    let added = forward.min(backward);
+                    if added - removed > -EPSILON {
+                        continue;
+                    }
+
+                    let chain_names: Vec<&str> = chain.iter().map(|v| graph.name(*v)).collect();
+                    if let Some(trace) = trace.as_deref_mut() {
+                        let step = trace.step(format!("Take out the chain {}", chain_names.join(" → ")))
+                            .segments(tour_segments(graph, &tour, &[(before, first, Mark::Cycle), (last, after, Mark::Cycle), (x, y, Mark::Cycle)]));
+                        for v in chain.iter() {
+                            step.vertex(graph.vertices[*v], Mark::Active);
+                        }
+                    }
+
+                    let mut moved = chain.clone();
+                    if reversed {
+                        moved.reverse();
+                    }
+                    let mut next: Vec<usize> = rest[..=p].to_vec();
+                    next.extend(moved.iter());
+                    next.extend(rest[p + 1..].iter());
+                    tour = next;
+                    moves += 1;
+                    improved = true;
+
+                    if let Some(trace) = trace.as_deref_mut() {
+                        let step = trace.step(format!(
+                            "Insert it{} between {} and {}, saves {:.0}, the tour has length {:.0}",
+                            if reversed { " reversed" } else { "" }, graph.name(x), graph.name(y), removed - added, tour_length(graph, &tour)
+                        ));
+                        step.segments(tour_segments(graph, &tour, &[(before, after, Mark::Active), (x, moved[0], Mark::Active), (moved[length - 1], y, Mark::Active)]));
+                        for v in chain.iter() {
+                            step.vertex(graph.vertices[*v], Mark::Done);
+                        }
+                    }
+                    break 'search;
+                }
+            }
+        }
+    }
+    if let Some(trace) = trace {
+        show_tour(graph, trace, &tour, format!("No chain can be moved to shorten the tour after {moves} moves, length {:.0}", tour_length(graph, &tour)));
+    }
+    tour
+}
+
+/**
+    # Christofides
+    A minimum spanning tree plus a minimum weight perfect matching of its odd degree vertices has an Eulerian circuit.
+    Skipping vertices that were already visited gives a tour at most 1.5 times the optimum for metric distances.
+*/
+pub fn christofides(graph: &GraphSnapshot, start: usize, mut trace: Option<&mut Trace>) -> Vec<usize> {
+    let n = graph.len();
+
+    // Prim on the implied complete graph
+    let mut in_tree = vec![false; n];
+    let mut best: Vec<(f32, usize)> = (0..n).map(|v| (distance(graph, start, v), start)).collect();
+    in_tree[start] = true;
+    let mut tree: Vec<(usize, usize)> = vec![];
+    for _ in 1..n {
+        let v = (0..n).filter(|v| !in_tree[*v]).min_by(|a, b| best[*a].0.total_cmp(&best[*b].0)).expect("a vertex is outside the tree");
+        in_tree[v] = true;
+        tree.push((best[v].1, v));
+        for w in 0..n {
+            let d = distance(graph, v, w);
+            if !in_tree[w] && d < best[w].0 {
+                best[w] = (d, v);
+            }
+        }
+    }
+    let tree_length: f32 = tree.iter().map(|(a, b)| distance(graph, *a, *b)).sum();
+    let segments_of = |legs: &[(usize, usize)], mark: Mark| -> Vec<(Entity, Entity, Mark)> {
+        legs.iter().map(|(a, b)| (graph.vertices[*a], graph.vertices[*b], mark)).collect()
+    };
+    if let Some(trace) = trace.as_deref_mut() {
+        trace.step(format!("Minimum spanning tree of length {tree_length:.0}, no tour can be shorter"))
+            .segments(segments_of(&tree, Mark::Done));
+    }
+
+    let mut degree = vec![0usize; n];
+    for (a, b) in tree.iter() {
+        degree[*a] += 1;
+        degree[*b] += 1;
+    }
+    let odd: Vec<usize> = (0..n).filter(|v| degree[*v] % 2 == 1).collect();
+    if let Some(trace) = trace.as_deref_mut() {
+        let step = trace.step(format!("{} vertices have an odd degree in the tree", odd.len()));
+        for v in odd.iter() {
+            step.vertex(graph.vertices[*v], Mark::Cycle);
+        }
+    }
+
+    let exact = odd.len() <= EXACT_MATCHING_LIMIT;
+    let matching = if exact { exact_matching(graph, &odd) } else { greedy_matching(graph, &odd) };
+    if let Some(trace) = trace.as_deref_mut() {
+        let mut segments = segments_of(&tree, Mark::Done);
+        segments.extend(segments_of(&matching, Mark::Matched));
+        let kind = if exact { "Minimum weight perfect matching" } else { "Too many odd vertices for the exact matching, greedy matching" };
+        let length: f32 = matching.iter().map(|(a, b)| distance(graph, *a, *b)).sum();
+        trace.step(format!("{kind} of the odd vertices with length {length:.0}"))
+            .segments(segments);
+    }
+
+    // Hierholzer on the multigraph of the tree and the matching
+    let legs: Vec<(usize, usize)> = tree.iter().chain(matching.iter()).copied().collect();
+    let mut adjacent: Vec<Vec<(usize, usize)>> = vec![vec![]; n];
+    for (e, (a, b)) in legs.iter().enumerate() {
+        adjacent[*a].push((*b, e));
+        adjacent[*b].push((*a, e));
+    }
+    let mut used = vec![false; legs.len()];
+    let mut stack = vec![start];
+    let mut circuit = vec![];
+    while let Some(&v) = stack.last() {
+        while adjacent[v].last().is_some_and(|(_, e)| used[*e]) {
+            adjacent[v].pop();
+        }
+        match adjacent[v].pop() {
+            Some((w, e)) => {
+                used[e] = true;
+                stack.push(w);
+            },
+            None => {
+                circuit.push(v);
+                stack.pop();
+            },
+        }
+    }
+    circuit.reverse();
+    if let Some(trace) = trace.as_deref_mut() {
+        let walk: Vec<(usize, usize)> = circuit.windows(2).map(|leg| (leg[0], leg[1])).collect();
+        trace.step(format!("Eulerian circuit of tree and matching: {}", graph.path_names(&circuit)))
+            .segments(segments_of(&walk, Mark::Frontier));
+    }
+
+    let mut seen = vec![false; n];
+    let tour: Vec<usize> = circuit.into_iter().filter(|v| !std::mem::replace(&mut seen[*v], true)).collect();
+    if let Some(trace) = trace {
+        let length = tour_length(graph, &tour);
+        show_tour(graph, trace, &tour, format!(
+            "Skip the vertices that were visited before, the tour has length {length:.0}, {:.2} times the spanning tree",
+            length / tree_length.max(EPSILON)
+        ));
+    }
+    tour
+}
+
+// Minimum weight perfect matching by a dynamic program over the subsets, the lowest unmatched vertex picks its partner
+fn exact_matching(graph: &GraphSnapshot, odd: &[usize]) -> Vec<(usize, usize)> {
+    let k = odd.len();
+    let full = (1usize << k) - 1;
+    let mut cost = vec![f32::INFINITY; 1 << k];
+    let mut choice = vec![0usize; 1 << k];
+    cost[0] = 0.;
+    for mask in 1..=full {
+        if mask.count_ones() % 2 == 1 {
+            continue;
+        }
+        let i = mask.trailing_zeros() as usize;
+        for j in i + 1..k {
+            if mask & (1 << j) == 0 {
+                continue;
+            }
+            let rest = mask & !(1 << i) & !(1 << j);
+            let c = cost[rest] + distance(graph, odd[i], odd[j]);
+            if c < cost[mask] {
+                cost[mask] = c;
+                choice[mask] = j;
+            }
+        }
+    }
+    let mut matching = vec![];
+    let mut mask = full;
+    while mask != 0 {
+        let i = mask.trailing_zeros() as usize;
+        let j = choice[mask];
+        matching.push((odd[i], odd[j]));
+        mask &= !(1 << i) & !(1 << j);
+    }
+    matching
+}
+
+fn greedy_matching(graph: &GraphSnapshot, odd: &[usize]) -> Vec<(usize, usize)> {
+    let mut pairs: Vec<(f32, usize, usize)> = vec![];
+    for (i, a) in odd.iter().enumerate() {
+        for b in odd[i + 1..].iter() {
+            pairs.push((distance(graph, *a, *b), *a, *b));
+        }
+    }
+    pairs.sort_by(|x, y| x.0.total_cmp(&y.0));
+    let mut matched = vec![false; graph.len()];
+    let mut matching = vec![];
+    for (_, a, b) in pairs {
+        if !matched[a] && !matched[b] {
+            matched[a] = true;
+            matched[b] = true;
+            matching.push((a, b));
+        }
+    }
+    matching
+}
+
+/**
+    # Held-Karp
+    cost[S][v] is the shortest path that starts at the start vertex, visits exactly the set S and ends in v.
+    The optimal tour is compared with the heuristics to show how far they are off.
+*/
+pub fn held_karp(graph: &GraphSnapshot, start: usize, trace: &mut Trace) -> Vec<usize> {
+    let n = graph.len();
+    if n > HELD_KARP_LIMIT {
+        trace.step(format!("Held-Karp is limited to {HELD_KARP_LIMIT} vertices, its table grows with 2^n"));
+        return vec![];
+    }
+    // Vertices other than the start, numbered for the bit sets
+    let others: Vec<usize> = (0..n).filter(|v| *v != start).collect();
+    let k = others.len();
+    let mut cost = vec![f32::INFINITY; (1 << k) * k];
+    let mut parent = vec![usize::MAX; (1 << k) * k];
+    for (i, v) in others.iter().enumerate() {
+        cost[(1 << i) * k + i] = distance(graph, start, *v);
+    }
+    for set in 1usize..1 << k {
+        for last in 0..k {
+            if set & (1 << last) == 0 || cost[set * k + last].is_infinite() {
+                continue;
+            }
+            let current = cost[set * k + last];
+            for next in 0..k {
+                if set & (1 << next) != 0 {
+                    continue;
+                }
+                let grown = set | (1 << next);
+                let c = current + distance(graph, others[last], others[next]);
+                if c < cost[grown * k + next] {
+                    cost[grown * k + next] = c;
+                    parent[grown * k + next] = last;
+                }
+            }
+        }
+    }
+    let full = (1 << k) - 1;
+    let last = (0..k)
+        .min_by(|a, b| {
+            let ca = cost[full * k + a] + distance(graph, others[*a], start);
+            let cb = cost[full * k + b] + distance(graph, others[*b], start);
+            ca.total_cmp(&cb)
+        })
+        .expect("there are other vertices");
+
+    let mut tour = vec![];
+    let (mut set, mut v) = (full, last);
+    while v != usize::MAX {
+        tour.push(others[v]);
+        let previous = parent[set * k + v];
+        set &= !(1 << v);
+        v = previous;
+    }
+    tour.push(start);
+    tour.reverse();
+
+    let optimum = tour_length(graph, &tour);
+    let nearest = nearest_neighbour_tour(graph, start, None);
+    let heuristics = [
+        ("nearest neighbour", tour_length(graph, &nearest)),
+        ("2-opt", tour_length(graph, &two_opt(graph, nearest.clone(), None))),
+        ("Or-opt", tour_length(graph, &or_opt(graph, nearest, None))),
+        ("Christofides", tour_length(graph, &christofides(graph, start, None))),
+    ];
+    let gaps: Vec<String> = heuristics.iter()
+        .map(|(name, length)| format!("{name} {length:.0} (+{:.1}%)", (length / optimum.max(EPSILON) - 1.) * 100.))
+        .collect();
+    trace.step(format!("Fill the table of {} subsets and end vertices", 1usize << k));
+    show_tour(graph, trace, &tour, format!("The optimal tour has length {optimum:.0}\nHeuristics: {}", gaps.join(", ")));
+    tour
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use crate::app::algorithms::{graph::GraphSnapshot, steps::Trace};
+
+    use super::{christofides, held_karp, nearest_neighbour_tour, or_opt, tour_length, tour_start, two_opt};
+
+    // Vertices at pseudo random positions without edges, the distances are the costs
+    fn points(n: usize, seed: u32) -> GraphSnapshot {
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32 * 1000.
+        };
+        let positions = (0..n).map(|_| Vec2::new(next(), next())).collect();
+        GraphSnapshot::detached((0..n).map(|v| format!("v{v}")).collect(), positions, &[])
+    }
+
+    fn assert_visits_every_vertex_once(graph: &GraphSnapshot, tour: &[usize]) {
+        let mut sorted = tour.to_vec();
+        sorted.sort();
+        assert_eq!(sorted, (0..graph.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn held_karp_is_never_beaten() {
+        for seed in 0..12 {
+            let graph = points(4 + seed as usize % 6, seed);
+            let optimum = held_karp(&graph, 0, &mut Trace::new());
+            assert_visits_every_vertex_once(&graph, &optimum);
+            assert_eq!(optimum[0], 0);
+            let nearest = nearest_neighbour_tour(&graph, 0, None);
+            for tour in [nearest.clone(), two_opt(&graph, nearest.clone(), None), or_opt(&graph, nearest, None), christofides(&graph, 0, None)] {
+                assert_visits_every_vertex_once(&graph, &tour);
+                assert!(tour_length(&graph, &optimum) <= tour_length(&graph, &tour) + 1e-2, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn two_opt_uncrosses_a_square() {
+        let positions = vec![Vec2::new(0., 0.), Vec2::new(100., 0.), Vec2::new(100., 100.), Vec2::new(0., 100.)];
+        let graph = GraphSnapshot::detached((0..4).map(|v| format!("v{v}")).collect(), positions, &[]);
+        let crossing = vec![0, 2, 1, 3];
+        assert!(tour_length(&graph, &crossing) > 480.);
+        let tour = two_opt(&graph, crossing, None);
+        assert_visits_every_vertex_once(&graph, &tour);
+        assert!((tour_length(&graph, &tour) - 400.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bigger_tours_visit_every_vertex_once() {
+        let graph = points(60, 7);
+        let nearest = nearest_neighbour_tour(&graph, 5, None);
+        assert_eq!(nearest[0], 5);
+        let improved = two_opt(&graph, nearest.clone(), None);
+        assert!(tour_length(&graph, &improved) <= tour_length(&graph, &nearest));
+        for tour in [nearest.clone(), improved, or_opt(&graph, nearest, None), christofides(&graph, 5, None)] {
+            assert_visits_every_vertex_once(&graph, &tour);
+        }
+    }
+
+    #[test]
+    fn instances_need_a_complete_graph_or_no_edges() {
+        let mut trace = Trace::new();
+        assert_eq!(tour_start(&points(5, 1), Some(3), &mut trace), Some(3));
+        assert_eq!(tour_start(&points(2, 1), None, &mut trace), None);
+        let path = GraphSnapshot::test_graph(3, &[(0, 1, 1, false), (1, 2, 1, false)]);
+        assert_eq!(tour_start(&path, None, &mut trace), None);
+        let triangle = GraphSnapshot::test_graph(3, &[(0, 1, 1, false), (1, 2, 1, false), (2, 0, 1, true)]);
+        assert_eq!(tour_start(&triangle, None, &mut trace), Some(0));
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Mutex;
+
+use bevy::{app::{Plugin, Update}, prelude::{Added, Changed, DetectChanges, IntoSystemConfigs, Local, Name, NextState, Or, Query, Res, ResMut, Transform, With}};
+
+use super::{algorithms::{graph::GraphData, headless::run_algorithm, playback::AlgorithmRun, targets::AlgorithmTargets}, build_graph::{components::{Edge, EditorState, Vertex}, res::AdjacencyList}, files::{json::{quote, write_json}, queue_file, target_indices, Import}, ui::InfoPanel};
+
+// The names run_algorithm takes
+pub use super::algorithms::headless::ALGORITHMS;
+
+/**
+    # API Plugin
+    Lets the page drive the simulator while the app loop runs. Calls from JavaScript only queue a command, the
+    commands are applied by a system at the start of the next frame and what happens in the world is sent back
+    as events to the callbacks the page registered.
+*/
+pub struct ApiPlugin;
+impl Plugin for ApiPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_systems(Update, (
+            apply_commands,
+            (publish_graph, send_vertex_events, send_edge_events, send_finished_event),
+        ).chain())
+        ;
+    }
+}
+
+pub enum ApiCommand {
+    SetEditorMode(EditorState),
+    // Vertex names of the start and end target, the picked targets are used without them
+    RunAlgorithm { name: String, start: Option<String>, end: Option<String> },
+    Step,
+}
+
+static COMMANDS: Mutex<Vec<ApiCommand>> = Mutex::new(vec![]);
+// The graph as JSON, written whenever it changes so the page can read it at any time
+static GRAPH: Mutex<String> = Mutex::new(String::new());
+
+// A graph loaded by the page goes the way of a dropped file
+pub const API_GRAPH: &str = "graph.json";
+
+pub fn send(command: ApiCommand) {
+    if let Ok(mut commands) = COMMANDS.lock() {
+        commands.push(command);
+    }
+}
+
+pub fn load_graph(json: &str) {
+    queue_file(API_GRAPH.to_string(), json.as_bytes().to_vec(), Import::Replace);
+}
+
+pub fn exported_graph() -> String {
+    GRAPH.lock().map(|graph| graph.clone()).unwrap_or_default()
+}
+
+pub fn editor_mode(name: &str) -> Option<EditorState> {
+    EditorState::from_name(name)
+}
+
+// Sends an event with a JSON object to the callbacks of the page
+fn emit(event: &str, detail: String) {
+    crate::wasm_module::emit_js(event, &detail);
+}
+
+fn apply_commands(
+    graph: GraphData,
+    targets: Res<AlgorithmTargets>,
+    mut run: ResMut<AlgorithmRun>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let commands: Vec<ApiCommand> = match COMMANDS.lock() {
+        Ok(mut commands) if !commands.is_empty() => std::mem::take(&mut *commands),
+        _ => return,
+    };
+    for command in commands {
+        match command {
+            ApiCommand::SetEditorMode(state) => next_state.set(state),
+            ApiCommand::Step => run.step(),
+            ApiCommand::RunAlgorithm { name, start, end } => {
+                let graph = graph.snapshot();
+                let (picked_start, picked_end) = target_indices(&graph, &targets);
+                let find = |name: Option<String>, picked: Option<usize>| match name {
+                    Some(name) => graph.names.iter().position(|known| *known == name).map(Some).ok_or(format!("There is no vertex {name}")),
+                    None => Ok(picked),
+                };
+                let result = find(start, picked_start)
+                    .and_then(|start| Ok((start, find(end, picked_end)?)))
+                    .and_then(|(start, end)| run_algorithm(&name, &graph, start, end));
+                match result {
+                    Ok((name, trace)) => run.start(name, trace),
+                    Err(error) => {
+                        emit("error", format!("{{\"message\": {}}}", quote(&error)));
+                        info.text = error;
+                    },
+                }
+            },
+        }
+    }
+}
+
+fn publish_graph(
+    graph: GraphData,
+    adjacency: Res<AdjacencyList>,
+    targets: Res<AlgorithmTargets>,
+    q_moved: Query<(), (With<Vertex>, Or<(Changed<Transform>, Changed<Name>)>)>,
+) {
+    if !adjacency.is_changed() && !targets.is_changed() && q_moved.is_empty() {
+        return;
+    }
+    let graph = graph.snapshot();
+    let (start, end) = target_indices(&graph, &targets);
+    if let Ok(mut published) = GRAPH.lock() {
+        *published = write_json(&graph, start, end);
+    }
+}
+
+fn send_vertex_events(
+    q_added: Query<(&Name, &Transform), Added<Vertex>>,
+) {
+    for (name, transform) in q_added.iter() {
+        let position = transform.translation;
+        emit("vertex_added", format!("{{\"name\": {}, \"x\": {:.2}, \"y\": {:.2}}}", quote(name.as_str()), position.x, position.y));
+    }
+}
+
+fn send_edge_events(
+    q_added: Query<&Edge, Added<Edge>>,
+    q_name: Query<&Name>,
+    adjacency: Res<AdjacencyList>,
+) {
+    for edge in q_added.iter() {
+        let (Ok(from), Ok(to)) = (q_name.get(edge.start), q_name.get(edge.end)) else {continue};
+        let weight = adjacency.map.get(&edge.start)
+            .and_then(|adjacent| adjacent.iter().find(|(vertex, _)| *vertex == edge.end))
+            .map_or(1, |(_, weight)| *weight);
+        emit("edge_added", format!(
+            "{{\"from\": {}, \"to\": {}, \"weight\": {weight}, \"directed\": {}}}",
+            quote(from.as_str()),
+            quote(to.as_str()),
+            edge.directed,
+        ));
+    }
+}
+
+// Sent once when the last step of a run is shown
+fn send_finished_event(
+    run: Res<AlgorithmRun>,
+    mut was_running: Local<bool>,
+) {
+    let running = !run.steps.is_empty() && !run.is_finished();
+    if *was_running && !running && !run.steps.is_empty() {
+        emit("algorithm_finished", format!("{{\"name\": {}, \"steps\": {}}}", quote(&run.name), run.steps.len()));
+    }
+    *was_running = running;
+}
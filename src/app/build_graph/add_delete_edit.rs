@@ -1,9 +1,9 @@
-use bevy::{app::Update, math::Vec3, prelude::{in_state, AppExtStates, Changed, Commands, Entity, IntoSystemConfigs, Plugin, Query, Res, ResMut, With}};
+use bevy::{app::Update, prelude::{in_state, AppExtStates, Changed, Commands, DetectChanges, Entity, IntoSystemConfigs, Local, Plugin, Query, Res, ResMut, State, With}};
 use leafwing_input_manager::prelude::ActionState;
 
-use crate::app::{build_graph::components::default_vertex, input::{NormalInput}};
+use crate::app::{input::NormalInput, ui::{InfoPanel, MenuAppExt}};
 
-use super::{components::{Edge, EditorState, GraphInteraction, Vertex}, res::{GraphAssets, InputCoords, Trees}};
+use super::{builder::GraphBuilder, components::{resting_material, BaseMaterial, Edge, EditorState, GraphInteraction, OwnMaterial, Vertex}, res::{GraphAssets, GraphSettings, InputCoords, NearestPoints}};
 
 #[cfg(target_arch = "wasm32")]
 use crate::wasm_module::log_js;
@@ -16,24 +16,31 @@ impl Plugin for AddDeleteEditPlugin {
             (
                 (add_vertex, add_edge).run_if(in_state(EditorState::Add)),
                 (delete_vertex, delete_edge).run_if(in_state(EditorState::Delete)),
+                update_status,
         ),
         )
+        .add_menu_button("Editor", "Toggle directed edges", toggle_directed)
+        .add_menu_button("Editor", "Edge weight +1", increase_weight)
+        .add_menu_button("Editor", "Edge weight -1", decrease_weight)
         ;
     }
 }
 
 
 fn add_vertex(
-    mut commands: Commands,
-    graph_assets: Res<GraphAssets>,
-    mut trees: ResMut<Trees>,
+    mut builder: GraphBuilder,
     input_pos: Res<InputCoords>,
+    nearest_points: Res<NearestPoints>,
     q_my_action: Query<&ActionState<NormalInput>>,
 ) {
     let my_action = q_my_action.single();
     if !my_action.just_pressed(&NormalInput::Select) {
         return;
     } 
+    // Selecting a vertex starts or finishes an edge instead
+    if nearest_points.hovered().is_some() {
+        return;
+    }
 
     #[cfg(target_arch = "wasm32")] 
     log_js("just pressed select");
@@ -42,11 +49,7 @@ fn add_vertex(
     
     let Some( position ) = input_pos.world else {return};
 
-    let entity = commands.spawn(default_vertex(graph_assets, Vec3::new(position.x, position.y, 0.))).id();
-    let kd_tree = &mut trees.kd;
-    if !kd_tree.insert(entity, position) {
-        println!("could not insert the vertex");
-    }
+    builder.add_vertex(position);
 }
 
 
@@ -65,9 +68,42 @@ fn edit_vertex(
 }
 
 fn add_edge(
-    mut commands: Commands,
+    mut builder: GraphBuilder,
+    settings: Res<GraphSettings>,
+    graph_assets: Res<GraphAssets>,
+    nearest_points: Res<NearestPoints>,
+    q_my_action: Query<&ActionState<NormalInput>>,
+    mut q_base_material: Query<(&mut BaseMaterial, Option<&OwnMaterial>)>,
+    mut edge_start: Local<Option<Entity>>,
 ) {
-    
+    let my_action = q_my_action.single();
+    if !my_action.just_pressed(&NormalInput::Select) {
+        return;
+    }
+
+    let hovered = nearest_points.hovered();
+    // The start vertex could have been removed in the meantime
+    let start = edge_start.take().filter(|start| q_base_material.contains(*start));
+    if let Some(start) = start {
+        if let Ok((mut base, own)) = q_base_material.get_mut(start) {
+            base.0 = resting_material(own, &graph_assets);
+        }
+    }
+    let Some(hovered) = hovered else {return};
+
+    match start {
+        // An edge that already exists is not added twice
+        Some(start) if start != hovered => {
+            builder.add_edge(start, hovered, settings.weight, settings.directed);
+        },
+        Some(_) => {},
+        None => {
+            if let Ok((mut base, _)) = q_base_material.get_mut(hovered) {
+                base.0 = graph_assets.pressed_material.clone();
+            }
+            *edge_start = Some(hovered);
+        },
+    }
 }
 
 fn delete_edge(
@@ -82,4 +118,34 @@ fn edit_edge(
     q_edge_interaction: Query<(&GraphInteraction, Entity), (With<Edge>, Changed<GraphInteraction>)>
 ) {
     
-}
\ No newline at end of file
+}
+
+fn update_status(
+    settings: Res<GraphSettings>,
+    state: Res<State<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    if !settings.is_changed() && !state.is_changed() {
+        return;
+    }
+    let kind = if settings.directed {"directed"} else {"undirected"};
+    info.status = format!("Mode: {:?} | new edges: {kind}, weight {}", state.get(), settings.weight);
+}
+
+fn toggle_directed(
+    mut settings: ResMut<GraphSettings>,
+) {
+    settings.directed = !settings.directed;
+}
+
+fn increase_weight(
+    mut settings: ResMut<GraphSettings>,
+) {
+    settings.weight += 1;
+}
+
+fn decrease_weight(
+    mut settings: ResMut<GraphSettings>,
+) {
+    settings.weight -= 1;
+}
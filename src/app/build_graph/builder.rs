@@ -0,0 +1,93 @@
+use bevy::{ecs::system::SystemParam, math::{Vec2, Vec3}, prelude::{BuildChildren, Color, Commands, DespawnRecursiveExt, Entity, Name, Res, ResMut}, utils::HashSet};
+
+use super::{components::{default_edge, default_vertex, label_text, Edge, WeightLabel}, res::{AdjacencyList, EdgeMapping, GraphAssets, Trees, VertexCounter}};
+
+/**
+    # Graph Builder
+    Every vertex and edge is spawned through this so the kd-tree, the AdjacencyList and the EdgeMapping stay consistent.
+*/
+#[derive(SystemParam)]
+pub struct GraphBuilder<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    graph_assets: Res<'w, GraphAssets>,
+    trees: ResMut<'w, Trees>,
+    adjacency: ResMut<'w, AdjacencyList>,
+    edge_mapping: ResMut<'w, EdgeMapping>,
+    counter: ResMut<'w, VertexCounter>,
+}
+
+impl<'w, 's> GraphBuilder<'w, 's> {
+    pub fn add_vertex(&mut self, position: Vec2) -> Entity {
+        let name = format!("v{}", self.counter.0);
+        self.add_named_vertex(position, name)
+    }
+
+    // Imported vertices keep their names, the counter still moves on so later vertices get fresh ones
+    pub fn add_named_vertex(&mut self, position: Vec2, name: String) -> Entity {
+        let entity = self.spawn_vertex(position, name);
+        self.trees.kd.insert(entity, position);
+        entity
+    }
+
+    // Many vertices at once, an empty kd-tree is built balanced instead of growing one vertex at a time
+    pub fn add_named_vertices(&mut self, vertices: Vec<(Vec2, String)>) -> Vec<Entity> {
+        let points: Vec<(Entity, Vec2)> = vertices.into_iter()
+            .map(|(position, name)| (self.spawn_vertex(position, name), position))
+            .collect();
+        let entities = points.iter().map(|(entity, _)| *entity).collect();
+        self.trees.kd.insert_list(points);
+        entities
+    }
+
+    fn spawn_vertex(&mut self, position: Vec2, name: String) -> Entity {
+        self.counter.0 += 1;
+
+        let entity = self.commands
+            .spawn((default_vertex(&self.graph_assets, Vec3::new(position.x, position.y, 0.)), Name::new(name.clone())))
+            .with_children(|parent| {
+                parent.spawn(label_text(name, Color::BLACK, Vec3::Z));
+            })
+            .id();
+        self.adjacency.map.insert(entity, vec![]);
+        entity
+    }
+
+    // Returns None if the vertices are equal or already connected in this direction
+    pub fn add_edge(&mut self, start: Entity, end: Entity, weight: i32, directed: bool) -> Option<Entity> {
+        if start == end || self.edge_mapping.map.contains_key(&(start, end)) {
+            return None;
+        }
+        if !directed && self.edge_mapping.map.contains_key(&(end, start)) {
+            return None;
+        }
+
+        let entity = self.commands
+            .spawn(default_edge(&self.graph_assets, Edge { start, end, directed }))
+            .with_children(|parent| {
+                parent.spawn((label_text(weight.to_string(), Color::WHITE, Vec3::new(0., 30., 1.)), WeightLabel));
+            })
+            .id();
+
+        self.edge_mapping.map.insert((start, end), entity);
+        self.adjacency.map.entry(start).or_default().push((end, weight));
+        if !directed {
+            self.edge_mapping.map.insert((end, start), entity);
+            self.adjacency.map.entry(end).or_default().push((start, weight));
+        }
+        Some(entity)
+    }
+
+    // Despawns every vertex and edge and starts the vertex names from v0 again
+    pub fn clear(&mut self) {
+        let entities: HashSet<Entity> = self.adjacency.map.keys().chain(self.edge_mapping.map.values()).copied().collect();
+        for entity in entities {
+            if let Some(entity_commands) = self.commands.get_entity(entity) {
+                entity_commands.despawn_recursive();
+            }
+        }
+        self.adjacency.map.clear();
+        self.edge_mapping.map.clear();
+        self.trees.kd.clear();
+        self.counter.0 = 0;
+    }
+}
@@ -1,6 +1,6 @@
-use bevy::{asset::{Assets, Handle}, math::Vec3, prelude::{default, Bundle, Component, Mesh, Res, ResMut, States, Transform }, render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages}, sprite::{ColorMaterial, ColorMesh2dBundle, MaterialMesh2dBundle, Mesh2dHandle}, ui::{Interaction, Node}};
+use bevy::{asset::{Assets, Handle}, color::Color, math::{Vec2, Vec3}, prelude::{default, Bundle, Component, Entity, Mesh, Res, ResMut, States, Transform }, render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages}, sprite::{ColorMaterial, ColorMesh2dBundle, Mesh2dHandle}, text::{Text, Text2dBundle, TextStyle}};
 
-use super::res::GraphAssets;
+use super::{res::GraphAssets, RADIUS};
 
 #[derive(Default, States, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum EditorState {
@@ -10,11 +10,33 @@ pub enum EditorState {
     Delete,
 }
 
+impl EditorState {
+    const ALL: [EditorState; 3] = [EditorState::Add, EditorState::Edit, EditorState::Delete];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditorState::Add => "add",
+            EditorState::Edit => "edit",
+            EditorState::Delete => "delete",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EditorState> {
+        let name = name.to_ascii_lowercase();
+        EditorState::ALL.into_iter().find(|state| state.name() == name)
+    }
+}
+
 #[derive(Component)]
 pub struct Vertex;
 
-#[derive(Component)]
-pub struct Edge;
+// An undirected edge is stored in both directions in AdjacencyList and EdgeMapping but only has one entity
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Edge {
+    pub start: Entity,
+    pub end: Entity,
+    pub directed: bool,
+}
 
 #[derive(Component)]
 pub enum GraphInteraction {
@@ -23,21 +45,49 @@ pub enum GraphInteraction {
     Pressed,
 }
 
+// The material shown while the entity is not hovered or pressed, algorithms swap it to color the graph
+#[derive(Component, Clone)]
+pub struct BaseMaterial(pub Handle<ColorMaterial>);
+
+// The material of a color the entity was given by a file, algorithms go back to it instead of the plain material
+#[derive(Component, Clone)]
+pub struct OwnMaterial(pub Handle<ColorMaterial>);
+
+// The material an entity shows when nothing marks it
+pub fn resting_material(own: Option<&OwnMaterial>, graph_assets: &GraphAssets) -> Handle<ColorMaterial> {
+    own.map_or_else(|| graph_assets.none_material.clone(), |own| own.0.clone())
+}
+
+// Thickness of the edge mesh, algorithms widen edges to emphasise them
+#[derive(Component, Clone, Copy)]
+pub struct EdgeWidth(pub f32);
+
+// Text child of an edge showing its weight
+#[derive(Component)]
+pub struct WeightLabel;
+
 //Node acts as an identifier for the UI system to query over it and change the interaction state
 #[derive(Bundle)]
 pub struct GraphComponentBundle {
     graph_interaction: GraphInteraction,    
+    base_material: BaseMaterial,
     color_mesh_bundle: ColorMesh2dBundle,
 }
 
+pub const EDGE_WIDTH: f32 = 6.0;
+const ARROW_LENGTH: f32 = 30.0;
+const ARROW_WIDTH: f32 = 14.0;
+pub const LABEL_FONT_SIZE: f32 = 40.0;
+
 pub fn default_vertex(
-    graph_assets: Res<GraphAssets>,
+    graph_assets: &GraphAssets,
     pos: Vec3,
 ) -> (Vertex, GraphComponentBundle) {
     (
         Vertex,
         GraphComponentBundle {
             graph_interaction: GraphInteraction::None,
+            base_material: BaseMaterial(graph_assets.none_material.clone()),
             color_mesh_bundle: ColorMesh2dBundle {
                 mesh: Mesh2dHandle::from(graph_assets.vertex.clone()),
                 material: graph_assets.none_material.clone(),
@@ -51,6 +101,72 @@ pub fn default_vertex(
     )    
 }
 
+// The mesh is built by update_edge_meshes once both vertices have a transform
+pub fn default_edge(
+    graph_assets: &GraphAssets,
+    edge: Edge,
+) -> (Edge, EdgeWidth, GraphComponentBundle) {
+    (
+        edge,
+        EdgeWidth(EDGE_WIDTH),
+        GraphComponentBundle {
+            graph_interaction: GraphInteraction::None,
+            base_material: BaseMaterial(graph_assets.none_material.clone()),
+            color_mesh_bundle: ColorMesh2dBundle {
+                material: graph_assets.none_material.clone(),
+                transform: Transform::from_xyz(0., 0., -1.),
+                ..default()
+            }
+        }
+    )
+}
+
+pub fn label_text(text: impl Into<String>, color: Color, offset: Vec3) -> Text2dBundle {
+    Text2dBundle {
+        text: Text::from_section(text, TextStyle {
+            font_size: LABEL_FONT_SIZE,
+            color,
+            ..default()
+        }),
+        transform: Transform::from_translation(offset),
+        ..default()
+    }
+}
+
+// The quad of an edge and the triangle of its arrowhead relative to the midpoint of the edge
+pub fn edge_outline(start: Vec2, end: Vec2, directed: bool, width: f32) -> ([Vec2; 4], Option<[Vec2; 3]>) {
+    let mid = (start + end) / 2.;
+    let dir = (end - start).normalize_or_zero();
+    let normal = dir.perp() * width / 2.;
+    let from = start - mid + dir * RADIUS;
+    let tip = end - mid - dir * RADIUS;
+    let to = if directed { tip - dir * ARROW_LENGTH } else { tip };
+    let side = dir.perp() * ARROW_WIDTH;
+    let arrow = directed.then_some([to + side, to - side, tip]);
+    ([from + normal, from - normal, to - normal, to + normal], arrow)
+}
+
+/**
+    # Edge Mesh
+    A quad of the given width between the two vertex circles relative to the midpoint of the edge,
+    directed edges get an arrowhead touching the end vertex.
+*/
+pub fn edge_mesh(start: Vec2, end: Vec2, directed: bool, width: f32) -> Mesh {
+    let (quad, arrow) = edge_outline(start, end, directed, width);
+    let mut vertices: Vec<Vec3> = quad.iter().map(|p| p.extend(0.)).collect();
+    let mut indices: Vec<u32> = vec![0, 1, 2, 0, 2, 3];
+
+    if let Some(arrow) = arrow {
+        vertices.extend(arrow.iter().map(|p| p.extend(0.)));
+        indices.extend([4, 5, 6]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vertices);
+    mesh.insert_indices(Indices::U32(indices));
+    mesh
+}
+
 pub fn line_mesh(
     graph_assets: Res<GraphAssets>,
     origin: Vec3,
@@ -1,11 +1,11 @@
 use std::{borrow::Borrow, collections::BinaryHeap, default, process::Command};
 
-use bevy::{app::Plugin, asset::{Assets, Handle}, color::Color, input::ButtonInput, math::Vec3, pbr::graph, prelude::{default, Commands, DespawnRecursiveExt, DetectChanges, Entity, Local, Mesh, MouseButton, Or, Query, Res, ResMut, Transform, Update, With}, sprite::{ColorMaterial, ColorMesh2dBundle, Mesh2dHandle}};
+use bevy::{app::Plugin, asset::{Assets, Handle}, color::Color, input::ButtonInput, math::Vec3, pbr::graph, prelude::{default, Changed, Commands, DespawnRecursiveExt, DetectChanges, Entity, Local, Mesh, MouseButton, Or, Query, Res, ResMut, Transform, Update, With}, sprite::{ColorMaterial, ColorMesh2dBundle, Mesh2dHandle}};
 use leafwing_input_manager::prelude::ActionState;
 
 use crate::app::input::NormalInput;
 
-use super::{components::{line_mesh, Edge, GraphInteraction, Vertex}, res::{DistanceItem, GraphAssets, InputCoords, NearestPoints, Trees}, RADIUS};
+use super::{components::{line_mesh, BaseMaterial, Edge, GraphInteraction, Vertex}, res::{DistanceItem, GraphAssets, InputCoords, NearestPoints, Trees}, RADIUS};
 
 pub struct GraphInteractionPlugin; 
 impl Plugin for GraphInteractionPlugin {
@@ -58,17 +58,17 @@ fn update_interactions(
     *last_nearest = nearest_points.heap.clone();
 }
 
+type RecoloredElement = Or<(Changed<GraphInteraction>, Changed<BaseMaterial>)>;
+
 fn color_interactions(
-    mut q_color: Query<(&GraphInteraction, &mut Handle<ColorMaterial>), Or<(With<Vertex>, With<Edge>)>>,
+    mut q_color: Query<(&GraphInteraction, &BaseMaterial, &mut Handle<ColorMaterial>), RecoloredElement>,
     materials: Res<GraphAssets>,
-    nearest_points: Res<NearestPoints>
 ) {
-    for DistanceItem(entity, _) in nearest_points.heap.iter() {
-        let Ok((interaction, mut color_handle)) = q_color.get_mut(*entity) else {continue};
+    for (interaction, base, mut color_handle) in q_color.iter_mut() {
         *color_handle = match interaction {
             GraphInteraction::Hovered => materials.hovered_material.clone(),
             GraphInteraction::Pressed => materials.pressed_material.clone(),
-            GraphInteraction::None => materials.none_material.clone(),
+            GraphInteraction::None => base.0.clone(),
         }
     } 
 }
@@ -48,6 +48,12 @@ pub struct TwoDTree {
 unsafe impl Sync for TwoDTree {}
 unsafe impl Send for TwoDTree {}
 
+impl Drop for TwoDTree {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
 
 impl TwoDTree {
     pub fn new() -> Self {
@@ -56,9 +62,71 @@ impl TwoDTree {
         }
     }
     
-    #[allow(unused)]
-    pub fn insert_list(&mut self, list: Vec<(Entity, Vec2)>) {
-            
+    /**
+        # Insert List
+        Inserts every point of the list. An empty tree is built balanced by splitting at the median of the cut dimension,
+        otherwise the points are inserted one by one.
+    */
+    pub fn insert_list(&mut self, mut list: Vec<(Entity, Vec2)>) {
+        if self.root.is_some() {
+            for (entity, point) in list {
+                self.insert(entity, point);
+            }
+            return;
+        }
+        if list.is_empty() {
+            return;
+        }
+        let median = Self::split_at_median(&mut list, 0);
+        let (entity, point) = list[median];
+        self.root = TreeNode::root_from(entity, point);
+        let root = self.root.as_mut().expect("root was just created");
+        let Some(mut guard) = Self::get_write_guard(root) else {return};
+        let (left, right) = list.split_at_mut(median);
+        guard.branch[0] = Self::build_balanced(left, 1);
+        guard.branch[1] = Self::build_balanced(&mut right[1..], 1);
+    }
+
+    pub fn rebuild(&mut self, list: Vec<(Entity, Vec2)>) {
+        self.clear();
+        self.insert_list(list);
+    }
+
+    pub fn clear(&mut self) {
+        let Some(mut root) = self.root.take() else {return};
+        let Some(mut guard) = Self::get_write_guard(&mut root) else {return};
+        let mut stack: Vec<TreeNodePointer> = guard.branch.iter_mut().filter_map(|b| b.take()).collect();
+        while let Some(node) = stack.pop() {
+            let boxed = unsafe { Box::from_raw(node.as_ptr()) };
+            stack.extend(boxed.branch.iter().flatten());
+        }
+    }
+
+    // Sorts by the cut dimension and returns the index of the last point equal to the median,
+    // so the left part only holds points that are <= and the right part points that are > the median
+    fn split_at_median(list: &mut [(Entity, Vec2)], depth: usize) -> usize {
+        let cut_dim = depth % DIMENSION;
+        list.sort_by(|a, b| a.1[cut_dim].partial_cmp(&b.1[cut_dim]).unwrap_or(std::cmp::Ordering::Equal));
+        let mut median = list.len() / 2;
+        while median + 1 < list.len() && list[median + 1].1[cut_dim] <= list[median].1[cut_dim] {
+            median += 1;
+        }
+        median
+    }
+
+    fn build_balanced(list: &mut [(Entity, Vec2)], depth: usize) -> Option<TreeNodePointer> {
+        if list.is_empty() {
+            return None;
+        }
+        let median = Self::split_at_median(list, depth);
+        let (entity, point) = list[median];
+        let mut node = TreeNode::from(entity, point, depth)?;
+        let (left, right) = list.split_at_mut(median);
+        unsafe {
+            node.as_mut().branch[0] = Self::build_balanced(left, depth + 1);
+            node.as_mut().branch[1] = Self::build_balanced(&mut right[1..], depth + 1);
+        }
+        Some(node)
     }
     
     pub fn insert(&mut self, entity: Entity, point: Vec2) -> bool {
@@ -121,6 +189,32 @@ impl TwoDTree {
         }
     }
     
+    // Every point at most the radius away from the given point, in no particular order
+    pub fn radius_search(&self, point: Vec2, radius: f32) -> Vec<DistanceItem> {
+        let Some(root) = self.root.as_ref() else {return vec![]};
+        let Some(guard) = Self::get_read_guard(root) else {return vec![]};
+        let c_point: [f32; 2] = point.into();
+        let mut found = vec![];
+        let mut stack: Vec<&TreeNode> = vec![&guard];
+        while let Some(curr_node) = stack.pop() {
+            let dist_to_curr = curr_node.location.distance(point);
+            if dist_to_curr <= radius {
+                found.push(DistanceItem(curr_node.entity, dist_to_curr));
+            }
+            let cut_dim = curr_node.depth % DIMENSION;
+            let curr_loc: [f32; 2] = curr_node.location.into();
+            let offset = c_point[cut_dim] - curr_loc[cut_dim];
+            // The left side holds points <= the cut and the right side points > the cut
+            let sides = [offset <= radius, offset > -radius];
+            for (side, reachable) in sides.into_iter().enumerate() {
+                if let (true, Some(child)) = (reachable, curr_node.branch[side]) {
+                    stack.push(unsafe { child.as_ref() });
+                }
+            }
+        }
+        found
+    }
+
     fn get_side(point: [f32; 2], current_location: [f32; 2], cut_dimension: usize) -> usize {
         if point[cut_dimension] <= current_location[cut_dimension] {
             0
@@ -348,4 +442,69 @@ impl TwoDTree {
         Some(mesh)
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Entity, Vec2};
+
+    use super::TwoDTree;
+
+    // Pseudo random points on a coarse grid, so some share a coordinate with the cut
+    fn points(n: usize, seed: u32) -> Vec<(Entity, Vec2)> {
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state >> 8) % 50) as f32 * 20.
+        };
+        (0..n).map(|i| (Entity::from_raw(i as u32), Vec2::new(next(), next()))).collect()
+    }
+
+    fn assert_matches_brute_force(tree: &TwoDTree, list: &[(Entity, Vec2)], seed: u32) {
+        for (_, query) in points(30, seed) {
+            for radius in [0., 35., 150., 400.] {
+                let mut found: Vec<u32> = tree.radius_search(query, radius).iter().map(|item| item.0.index()).collect();
+                found.sort();
+                let mut expected: Vec<u32> = list.iter().filter(|(_, p)| p.distance(query) <= radius).map(|(e, _)| e.index()).collect();
+                expected.sort();
+                assert_eq!(found, expected, "radius {radius} around {query}");
+            }
+            for n in [1, 5, 20] {
+                let heap = tree.n_nearest_neighboors_search(query, n).unwrap();
+                // Ties can pick other entities, the distances have to be the same
+                let mut found: Vec<f32> = heap.into_iter().map(|item| item.1).collect();
+                found.sort_by(f32::total_cmp);
+                let mut expected: Vec<f32> = list.iter().map(|(_, p)| p.distance(query)).collect();
+                expected.sort_by(f32::total_cmp);
+                expected.truncate(n);
+                assert_eq!(found, expected, "{n} nearest to {query}");
+            }
+        }
+    }
+
+    #[test]
+    fn inserted_one_by_one() {
+        let list = points(200, 1);
+        let mut tree = TwoDTree::new();
+        for (entity, point) in list.iter() {
+            tree.insert(*entity, *point);
+        }
+        assert_matches_brute_force(&tree, &list, 2);
+    }
+
+    #[test]
+    fn built_balanced_and_rebuilt() {
+        let list = points(200, 3);
+        let mut tree = TwoDTree::new();
+        tree.insert_list(list.clone());
+        assert_matches_brute_force(&tree, &list, 4);
+
+        let moved = points(150, 5);
+        tree.rebuild(moved.clone());
+        assert_matches_brute_force(&tree, &moved, 6);
+
+        tree.rebuild(vec![]);
+        assert!(tree.radius_search(Vec2::ZERO, f32::MAX).is_empty());
+        assert!(tree.n_nearest_neighboors_search(Vec2::ZERO, 3).is_none());
+    }
+}
@@ -1,13 +1,16 @@
-mod components;
+pub mod components;
 pub mod res;
+pub mod builder;
+pub mod movement;
 mod graph_interaction;
-mod kdtree;
+pub mod kdtree;
 mod add_delete_edit;
 
 
 use bevy::{app::Startup, asset::Assets, color::Color, prelude::{App, Camera2dBundle, Circle, Commands, Mesh, Plugin, ResMut, Update}, render::camera::CameraPlugin, sprite::ColorMaterial};
 use graph_interaction::GraphInteractionPlugin;
-use res::{GraphAssets, InputCoords, Trees};
+use movement::MovementPlugin;
+use res::{AdjacencyList, EdgeMapping, GraphAssets, GraphSettings, InputCoords, Trees, VertexCounter};
 use add_delete_edit::AddDeleteEditPlugin;
 
 pub struct BuildGraphPlugin;
@@ -18,17 +21,36 @@ impl Plugin for BuildGraphPlugin {
             (
                 AddDeleteEditPlugin,
                 GraphInteractionPlugin,
+                MovementPlugin,
             )
         )
         .init_resource::<InputCoords>()
         .init_resource::<Trees>()
+        .init_resource::<AdjacencyList>()
+        .init_resource::<EdgeMapping>()
+        .init_resource::<GraphSettings>()
+        .init_resource::<VertexCounter>()
         .add_systems(Startup, init_mesh);
     }
 }
 
 pub const RADIUS: f32 = 50.0;
+pub const PALETTE: [Color; 12] = [
+    Color::srgb(0.95, 0.45, 0.45),
+    Color::srgb(0.45, 0.65, 0.95),
+    Color::srgb(0.5, 0.85, 0.45),
+    Color::srgb(0.95, 0.8, 0.35),
+    Color::srgb(0.75, 0.5, 0.95),
+    Color::srgb(0.35, 0.85, 0.85),
+    Color::srgb(0.95, 0.6, 0.25),
+    Color::srgb(0.6, 0.6, 0.6),
+    Color::srgb(0.95, 0.55, 0.8),
+    Color::srgb(0.6, 0.4, 0.25),
+    Color::srgb(0.7, 0.9, 0.3),
+    Color::srgb(0.25, 0.4, 0.75),
+];
 
-fn init_mesh(
+pub fn init_mesh(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
@@ -38,6 +60,9 @@ fn init_mesh(
         none_material: materials.add(ColorMaterial::from_color(Color::WHITE)),
         hovered_material: materials.add(ColorMaterial::from_color(Color::linear_rgb(1., 0., 0.))),
         pressed_material: materials.add(ColorMaterial::from_color(Color::linear_rgb(0., 1., 0.))),
+        palette: PALETTE.iter()
+            .map(|color| materials.add(ColorMaterial::from_color(*color)))
+            .collect(),
     };
     
     commands.insert_resource(graph_mesh);
@@ -0,0 +1,94 @@
+use bevy::{app::{Plugin, Update}, asset::Assets, math::Vec2, prelude::{Commands, Component, DetectChanges, Entity, IntoSystemConfigs, Mesh, Query, Ref, Res, ResMut, Transform, With, Without}, sprite::Mesh2dHandle, time::Time};
+
+use super::{components::{edge_mesh, Edge, EdgeWidth, Vertex}, res::Trees};
+
+pub struct MovementPlugin;
+impl Plugin for MovementPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_systems(Update, (
+            tween_vertices,
+            sync_kd_tree,
+            update_edge_meshes,
+        ).chain())
+        ;
+    }
+}
+
+// Moves a vertex smoothly to a new position, the component removes itself when arrived
+#[derive(Component)]
+pub struct VertexTween {
+    pub from: Vec2,
+    pub to: Vec2,
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+impl VertexTween {
+    pub const DURATION: f32 = 0.8;
+
+    pub fn new(from: Vec2, to: Vec2) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0.,
+            duration: Self::DURATION,
+        }
+    }
+}
+
+fn tween_vertices(
+    mut commands: Commands,
+    mut q_tween: Query<(Entity, &mut VertexTween, &mut Transform), With<Vertex>>,
+    time: Res<Time>,
+) {
+    for (entity, mut tween, mut transform) in q_tween.iter_mut() {
+        tween.elapsed += time.delta_seconds();
+        let t = (tween.elapsed / tween.duration).clamp(0., 1.);
+        // Smoothstep so the vertices ease in and out
+        let eased = t * t * (3. - 2. * t);
+        let position = tween.from.lerp(tween.to, eased);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        if t >= 1. {
+            commands.entity(entity).remove::<VertexTween>();
+        }
+    }
+}
+
+// Rebuilds the kd-tree balanced whenever a vertex moved so nearest searches stay correct
+pub fn sync_kd_tree(
+    q_vertex: Query<(Entity, Ref<Transform>), With<Vertex>>,
+    mut trees: ResMut<Trees>,
+) {
+    let moved = q_vertex.iter().any(|(_, transform)| transform.is_changed() && !transform.is_added());
+    if !moved {
+        return;
+    }
+    let points = q_vertex.iter()
+        .map(|(entity, transform)| (entity, transform.translation.truncate()))
+        .collect();
+    trees.kd.rebuild(points);
+}
+
+// An edge with what its mesh is built from
+type EdgeShape<'a> = (Ref<'a, Edge>, Ref<'a, EdgeWidth>, &'a mut Transform, &'a mut Mesh2dHandle);
+
+fn update_edge_meshes(
+    q_vertex: Query<Ref<Transform>, With<Vertex>>,
+    mut q_edge: Query<EdgeShape, Without<Vertex>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for (edge, width, mut transform, mut mesh) in q_edge.iter_mut() {
+        let (Ok(start), Ok(end)) = (q_vertex.get(edge.start), q_vertex.get(edge.end)) else {continue};
+        if !edge.is_changed() && !width.is_changed() && !start.is_changed() && !end.is_changed() {
+            continue;
+        }
+        let start = start.translation.truncate();
+        let end = end.translation.truncate();
+        let mid = (start + end) / 2.;
+        transform.translation.x = mid.x;
+        transform.translation.y = mid.y;
+        *mesh = Mesh2dHandle::from(meshes.add(edge_mesh(start, end, edge.directed, width.0)));
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::BinaryHeap;
 
 use bevy::{prelude::{Camera, Entity, GlobalTransform, Handle, Mesh, Query, ResMut, Resource, Vec2, Window, With }, sprite::ColorMaterial, utils::HashMap, window::PrimaryWindow};
 
-use super::kdtree::{TwoDTree};
+use super::{kdtree::TwoDTree, RADIUS};
 
 #[derive(Resource, Default)]
 pub struct InputCoords {
@@ -21,12 +21,34 @@ pub struct EdgeMapping {
     pub map: HashMap<(Entity, Entity), Entity>,
 }
 
+// Settings used for newly placed edges
+#[derive(Resource)]
+pub struct GraphSettings {
+    pub directed: bool,
+    pub weight: i32,
+}
+
+impl Default for GraphSettings {
+    fn default() -> Self {
+        Self {
+            directed: true,
+            weight: 1,
+        }
+    }
+}
+
+// Counts the spawned vertices to give each one a unique name
+#[derive(Resource, Default)]
+pub struct VertexCounter(pub usize);
+
 #[derive(Resource)]
 pub struct GraphAssets {
     pub vertex: Handle<Mesh>,
     pub none_material: Handle<ColorMaterial>,
     pub hovered_material: Handle<ColorMaterial>,
     pub pressed_material: Handle<ColorMaterial>,
+    // One material per color of a vertex coloring, also used for groups found by algorithms
+    pub palette: Vec<Handle<ColorMaterial>>,
 }
 
 #[derive(Resource)]
@@ -70,6 +92,16 @@ pub struct NearestPoints {
     pub heap: BinaryHeap<DistanceItem>,
 }
 
+impl NearestPoints {
+    // The nearest vertex if the input is inside of its circle
+    pub fn hovered(&self) -> Option<Entity> {
+        self.heap.iter()
+            .filter(|DistanceItem(_, dist)| *dist <= RADIUS)
+            .min()
+            .map(|DistanceItem(entity, _)| *entity)
+    }
+}
+
 impl Default for NearestPoints {
     fn default() -> Self {
         Self {
@@ -1,4 +1,4 @@
-use bevy::{app::{Main, PreStartup, Update}, prelude::{Camera2dBundle, Commands, Component, OrthographicProjection, Plugin, Query, Res, Startup, With}, time::Time};
+use bevy::{app::{Main, PreStartup, Update}, math::Vec2, prelude::{Camera2dBundle, Commands, Component, OrthographicProjection, Plugin, Query, Res, Startup, With}, render::camera::ScalingMode, time::Time, window::{PrimaryWindow, Window}};
 use leafwing_input_manager::prelude::ActionState;
 
 use super::input::CameraMovement;
@@ -15,10 +15,22 @@ impl Plugin for MyCameraPlugin {
 #[derive(Component)]
 pub struct MainCamera;
 
+// The size of the world region seen at zoom 1 when the window has no size yet
+const DEFAULT_REGION: Vec2 = Vec2::new(1280., 720.);
+
+// The camera shows the region the window starts with at any window size, a resized window or a rotated phone keeps the same part of the world in view
 pub fn spawn_camera(
-    mut commands: Commands
+    mut commands: Commands,
+    q_window: Query<&Window, With<PrimaryWindow>>,
 ) {
-    commands.spawn((Camera2dBundle::default(), MainCamera));
+    let region = q_window.get_single()
+        .map(|window| Vec2::new(window.width(), window.height()))
+        .ok()
+        .filter(|size| size.min_element() > 0.)
+        .unwrap_or(DEFAULT_REGION);
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::AutoMin { min_width: region.x, min_height: region.y };
+    commands.spawn((camera, MainCamera));
 }
 
 
@@ -0,0 +1,417 @@
+// The checksum of PNG chunks and gzip
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+// The checksum of zlib streams
+pub fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+// Deflate writes bits starting at the lowest bit of every byte
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u32, count: u32) {
+        self.buffer |= (bits as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // Huffman codes are stored with their highest bit first
+    fn write_code(&mut self, code: u32, length: u32) {
+        self.write(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+pub const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+pub const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+pub const DISTANCE_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+pub const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// The code of a literal or length symbol in the fixed Huffman table
+fn fixed_code(symbol: u16) -> (u32, u32) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => (0x30 + symbol, 8),
+        144..=255 => (0x190 + symbol - 144, 9),
+        256..=279 => (symbol - 256, 7),
+        _ => (0xC0 + symbol - 280, 8),
+    }
+}
+
+// Index of the last base that is <= value
+fn bucket(bases: &[u16], value: u16) -> usize {
+    bases.iter().rposition(|base| *base <= value).unwrap_or_default()
+}
+
+const WINDOW: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+// Candidates tried per position, more compress better and take longer
+const MAX_CHAIN: usize = 48;
+const HASH_BITS: u32 = 15;
+
+/**
+    # Deflate
+    A raw deflate stream in one block with the fixed Huffman codes. Repeats are found with hash chains over
+    the last 32 KiB, which is most of what the rows of a flat colored image or a serialized graph need.
+*/
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter { bytes: Vec::with_capacity(data.len() / 4), buffer: 0, count: 0 };
+    // The last block, fixed Huffman codes
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    let hash = |i: usize| {
+        let key = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+        (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    };
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; WINDOW];
+    let insert = |i: usize, head: &mut [usize], previous: &mut [usize]| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(i);
+            previous[i % WINDOW] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let limit = MAX_MATCH.min(data.len() - i);
+                let length = (0..limit).take_while(|k| data[candidate + k] == data[i + k]).count();
+                if length > best.0 {
+                    best = (length, i - candidate);
+                    if length == limit {
+                        break;
+                    }
+                }
+                let next = previous[candidate % WINDOW];
+                // The slot was overwritten by a newer position
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        let (length, distance) = best;
+        if length >= MIN_MATCH {
+            let code = bucket(&LENGTH_BASE, length as u16);
+            let (bits, count) = fixed_code(257 + code as u16);
+            writer.write_code(bits, count);
+            writer.write((length - LENGTH_BASE[code] as usize) as u32, LENGTH_EXTRA[code] as u32);
+            let code = bucket(&DISTANCE_BASE, distance as u16);
+            writer.write_code(code as u32, 5);
+            writer.write((distance - DISTANCE_BASE[code] as usize) as u32, DISTANCE_EXTRA[code] as u32);
+            for k in i..i + length {
+                insert(k, &mut head, &mut previous);
+            }
+            i += length;
+        } else {
+            let (bits, count) = fixed_code(data[i] as u16);
+            writer.write_code(bits, count);
+            insert(i, &mut head, &mut previous);
+            i += 1;
+        }
+    }
+    let (bits, count) = fixed_code(256);
+    writer.write_code(bits, count);
+    writer.finish()
+}
+
+// A zlib stream: the header, the deflated data and the Adler-32 of the data
+pub fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x9C];
+    stream.extend(deflate(data));
+    stream.extend(adler32(data).to_be_bytes());
+    stream
+}
+
+// Deflate reads bits starting at the lowest bit of every byte
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Result<u32, String> {
+        let byte = self.bytes.get(self.position / 8).ok_or("The compressed data ends early")?;
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+        Ok(bit as u32)
+    }
+
+    fn read(&mut self, count: u32) -> Result<u32, String> {
+        let mut bits = 0;
+        for i in 0..count {
+            bits |= self.bit()? << i;
+        }
+        Ok(bits)
+    }
+
+    // Huffman codes are stored with their highest bit first, codes of one length are consecutive in symbol order
+    fn decode(&mut self, huffman: &Huffman) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for count in huffman.counts.iter().skip(1) {
+            code |= self.bit()? as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("The compressed data has an invalid code".to_string())
+    }
+}
+
+// A canonical Huffman code: how many codes there are of every length and the symbols sorted by code
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|s| lengths[*s as usize] > 0).collect();
+        symbols.sort_by_key(|s| lengths[*s as usize]);
+        Self { counts, symbols }
+    }
+}
+
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// The literal and distance codes of a block with dynamic Huffman codes
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literals = reader.read(5)? as usize + 257;
+    let distances = reader.read(5)? as usize + 1;
+    let code_lengths = reader.read(4)? as usize + 4;
+    let mut lengths = [0u8; 19];
+    for i in CODE_LENGTH_ORDER.iter().take(code_lengths) {
+        lengths[*i] = reader.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&lengths);
+
+    let mut lengths: Vec<u8> = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (value, repeat) = match reader.decode(&code_lengths)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("The compressed data repeats a missing code length")?, 3 + reader.read(2)?),
+            17 => (0, 3 + reader.read(3)?),
+            _ => (0, 11 + reader.read(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literals + distances {
+        return Err("The compressed data has too many code lengths".to_string());
+    }
+    Ok((Huffman::new(&lengths[..literals]), Huffman::new(&lengths[literals..])))
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [8u8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+/**
+    # Inflate
+    Decompresses a raw deflate stream with stored, fixed and dynamic blocks. The data comes from outside, like a
+    link someone shared, so it stops with an error once the output would grow past limit bytes.
+*/
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut reader = BitReader { bytes: data, position: 0 };
+    let mut output: Vec<u8> = vec![];
+    let too_big = || format!("The compressed data is bigger than {limit} bytes");
+    loop {
+        let last = reader.bit()? == 1;
+        let (literals, distances) = match reader.read(2)? {
+            0 => {
+                let start = reader.position.div_ceil(8);
+                let header = data.get(start..start + 4).ok_or("The compressed data ends early")?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err("The compressed data has a broken stored block".to_string());
+                }
+                let stored = data.get(start + 4..start + 4 + length as usize).ok_or("The compressed data ends early")?;
+                if output.len() + stored.len() > limit {
+                    return Err(too_big());
+                }
+                output.extend(stored);
+                reader.position = (start + 4 + length as usize) * 8;
+                if last {
+                    return Ok(output);
+                }
+                continue;
+            },
+            1 => fixed_codes(),
+            2 => dynamic_codes(&mut reader)?,
+            _ => return Err("The compressed data has an invalid block type".to_string()),
+        };
+        loop {
+            let symbol = reader.decode(&literals)? as usize;
+            if symbol < 256 {
+                output.push(symbol as u8);
+            } else if symbol == 256 {
+                break;
+            } else {
+                let code = symbol - 257;
+                let (Some(base), Some(extra)) = (LENGTH_BASE.get(code), LENGTH_EXTRA.get(code)) else {
+                    return Err("The compressed data has an invalid length".to_string());
+                };
+                let length = *base as usize + reader.read(*extra as u32)? as usize;
+                let code = reader.decode(&distances)? as usize;
+                let (Some(base), Some(extra)) = (DISTANCE_BASE.get(code), DISTANCE_EXTRA.get(code)) else {
+                    return Err("The compressed data has an invalid distance".to_string());
+                };
+                let distance = *base as usize + reader.read(*extra as u32)? as usize;
+                if distance > output.len() {
+                    return Err("The compressed data refers back before its start".to_string());
+                }
+                // The copy can overlap what it writes, like a run of one byte
+                for _ in 0..length {
+                    output.push(output[output.len() - distance]);
+                }
+            }
+            if output.len() > limit {
+                return Err(too_big());
+            }
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{adler32, crc32, deflate, inflate, zlib};
+
+    fn round_trip(data: &[u8]) {
+        assert_eq!(inflate(&deflate(data), data.len()).unwrap(), data);
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(&zlib(b"")[..2], &[0x78, 0x9C]);
+    }
+
+    #[test]
+    fn short_inputs() {
+        round_trip(b"");
+        round_trip(b"x");
+        round_trip(b"abc");
+        round_trip(b"abcabcabcabc");
+    }
+
+    #[test]
+    fn long_run() {
+        let run = vec![7; 100_000];
+        let deflated = deflate(&run);
+        // A run is one literal and repeats of the longest match
+        assert!(deflated.len() < 1000, "{} bytes", deflated.len());
+        round_trip(&run);
+    }
+
+    #[test]
+    fn beyond_the_window() {
+        // Text that repeats both within and further apart than the 32 KiB window
+        let mut state = 1u32;
+        let words: Vec<Vec<u8>> = (0..500)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                format!("w{} ", state >> 20).into_bytes()
+            })
+            .collect();
+        let text: Vec<u8> = (0..40_000).flat_map(|i| words[i * 7 % 500].clone()).collect();
+        assert!(text.len() > 3 * (1 << 15));
+        round_trip(&text);
+    }
+
+    #[test]
+    fn stored_and_dynamic_blocks() {
+        // A stored block of "hi" and an empty last stored block
+        let stored = [0x00, 0x02, 0x00, 0xFD, 0xFF, b'h', b'i', 0x01, 0x00, 0x00, 0xFF, 0xFF];
+        assert_eq!(inflate(&stored, 10).unwrap(), b"hi");
+
+        // What zlib writes at level 9 for this text
+        let dynamic = [
+            0x6d, 0xc6, 0xd1, 0x0d, 0x80, 0x20, 0x0c, 0x05, 0xc0, 0x55, 0xde, 0x00, 0x2e, 0xf5, 0x68, 0x2b, 0x10, 0x91,
+            0x1a, 0xa8, 0x89, 0x6c, 0xaf, 0x03, 0xf8, 0x75, 0xc7, 0x34, 0x28, 0x54, 0x7e, 0x80, 0xff, 0xdf, 0x40, 0xe8,
+            0xea, 0x3c, 0xab, 0x20, 0x35, 0x97, 0x03, 0xdd, 0x4c, 0x27, 0xac, 0xfb, 0x9d, 0x0b, 0xc2, 0x9e, 0x40, 0x38,
+            0x2e, 0x2e, 0xec, 0x3e, 0x50, 0x63, 0x42, 0x5c, 0x0d, 0xcd, 0x7a, 0x8e, 0x32, 0x5f,
+        ];
+        let text = "abracadabra abracadabra abracadabra, a dynamic block needs enough text to pay for its code lengths";
+        assert_eq!(inflate(&dynamic, 1000).unwrap(), text.as_bytes());
+    }
+
+    #[test]
+    fn bombs_stop_at_the_limit() {
+        let bomb = deflate(&vec![0; 1 << 20]);
+        assert!(bomb.len() < 10_000);
+        assert!(inflate(&bomb, 1 << 16).is_err());
+        assert_eq!(inflate(&bomb, 1 << 20).unwrap().len(), 1 << 20);
+
+        let stored = [0x01, 0x02, 0x00, 0xFD, 0xFF, b'h', b'i'];
+        assert!(inflate(&stored, 1).is_err());
+    }
+
+    #[test]
+    fn broken_streams_are_errors() {
+        assert!(inflate(&[], 10).is_err());
+        // The reserved block type 3
+        assert!(inflate(&[0x07], 10).is_err());
+        // A stored block whose length doesn't match its complement
+        assert!(inflate(&[0x01, 0x02, 0x00, 0x00, 0x00, b'h', b'i'], 10).is_err());
+        let deflated = deflate(b"a longer text that ends too early");
+        assert!(inflate(&deflated[..deflated.len() / 2], 100).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use bevy::math::Vec2;
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::ImportedGraph;
+
+fn number<T: std::str::FromStr>(token: Option<&&str>, line: usize) -> Result<T, String> {
+    let token = token.ok_or(format!("Line {line} is too short"))?;
+    token.parse().map_err(|_| format!("Line {line}: {token} is not a number"))
+}
+
+/**
+    # DIMACS
+    The problem line "p <problem> n m" creates the vertices 1 to n. "a u v w" is an arc of the shortest path and
+    max flow problems, "e u v" an undirected edge of the edge and coloring problems. "n v s" and "n v t" mark
+    the source and sink of a max flow problem and "v id x y" gives a vertex coordinates. Comments start with c.
+*/
+pub fn parse_dimacs(text: &str) -> Result<ImportedGraph, String> {
+    let mut graph = ImportedGraph::default();
+    let mut problem = false;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(kind) = tokens.first() else {continue};
+        match *kind {
+            "p" => {
+                if problem {
+                    return Err(format!("Line {line_number}: a second problem line"));
+                }
+                problem = true;
+                let n: usize = number(tokens.get(2), line_number)?;
+                for v in 1..=n {
+                    graph.vertex(&v.to_string());
+                }
+            },
+            "a" | "e" => {
+                let from = graph.vertex(&number::<usize>(tokens.get(1), line_number)?.to_string());
+                let to = graph.vertex(&number::<usize>(tokens.get(2), line_number)?.to_string());
+                let weight = match tokens.get(3) {
+                    Some(_) => number::<f32>(tokens.get(3), line_number)?.round() as i32,
+                    None => 1,
+                };
+                graph.add_edge(from, to, weight, *kind == "a");
+            },
+            "n" => {
+                let v = graph.vertex(&number::<usize>(tokens.get(1), line_number)?.to_string());
+                match tokens.get(2) {
+                    Some(&"s") => graph.start = Some(v),
+                    Some(&"t") => graph.end = Some(v),
+                    // Supplies of a min cost flow problem
+                    _ => {},
+                }
+            },
+            "v" => {
+                let v = graph.vertex(&number::<usize>(tokens.get(1), line_number)?.to_string());
+                graph.vertices[v].position = Some(Vec2::new(number(tokens.get(2), line_number)?, number(tokens.get(3), line_number)?));
+            },
+            _ => {},
+        }
+    }
+    if !problem {
+        return Err("The problem line p is missing".to_string());
+    }
+    Ok(graph)
+}
+
+// The problem a graph is written as, also the usual file extension
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Problem {
+    // Arcs with capacities between a source and a sink
+    Max,
+    // Unweighted undirected edges
+    Edge,
+    // Weighted arcs
+    ShortestPath,
+}
+
+impl Problem {
+    pub fn of(graph: &GraphSnapshot, start: Option<usize>, end: Option<usize>) -> Problem {
+        if start.is_some() && end.is_some() && start != end {
+            Problem::Max
+        } else if graph.edges.iter().all(|edge| !edge.directed && edge.weight == 1) {
+            Problem::Edge
+        } else {
+            Problem::ShortestPath
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Problem::Max => "max",
+            Problem::Edge => "col",
+            Problem::ShortestPath => "gr",
+        }
+    }
+}
+
+// Vertices are numbered from 1 in the order of the graph, undirected edges become two arcs in the arc problems
+pub fn write_dimacs(graph: &GraphSnapshot, start: Option<usize>, end: Option<usize>) -> String {
+    let problem = Problem::of(graph, start, end);
+    let arcs: usize = graph.edges.iter().map(|edge| if edge.directed || problem == Problem::Edge { 1 } else { 2 }).sum();
+    let name = match problem {
+        Problem::Max => "max",
+        Problem::Edge => "edge",
+        Problem::ShortestPath => "sp",
+    };
+    let mut dimacs = format!("p {name} {} {arcs}\n", graph.len());
+    if let (Problem::Max, Some(start), Some(end)) = (problem, start, end) {
+        dimacs.push_str(&format!("n {} s\nn {} t\n", start + 1, end + 1));
+    }
+    for edge in graph.edges.iter() {
+        let (from, to) = (edge.from + 1, edge.to + 1);
+        if problem == Problem::Edge {
+            dimacs.push_str(&format!("e {from} {to}\n"));
+            continue;
+        }
+        dimacs.push_str(&format!("a {from} {to} {}\n", edge.weight));
+        if !edge.directed {
+            dimacs.push_str(&format!("a {to} {from} {}\n", edge.weight));
+        }
+    }
+    dimacs
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{parse_dimacs, write_dimacs, Problem};
+
+    #[test]
+    fn problem_lines() {
+        let text = "c a shortest path problem\np sp 3 2\nc an arc\na 1 2 4\na 2 3 -1\nv 3 1.5 -2\n";
+        let imported = parse_dimacs(text).unwrap();
+        assert_eq!(imported.names(), vec!["1", "2", "3"]);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 4, true), (1, 2, -1, true)]);
+        assert_eq!(imported.vertices[2].position, Some(Vec2::new(1.5, -2.)));
+
+        let coloring = parse_dimacs("p edge 4 2\ne 1 2\ne 2 4\n").unwrap();
+        assert_eq!(coloring.names(), vec!["1", "2", "3", "4"]);
+        assert_eq!(coloring.edge_tuples(), vec![(0, 1, 1, false), (1, 3, 1, false)]);
+    }
+
+    #[test]
+    fn max_flow_round_trip() {
+        let graph = GraphSnapshot::test_graph(3, &[(0, 1, 4, true), (1, 2, 2, false)]);
+        assert_eq!(Problem::of(&graph, Some(0), Some(2)), Problem::Max);
+        let imported = parse_dimacs(&write_dimacs(&graph, Some(0), Some(2))).unwrap();
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 4, true), (1, 2, 2, true), (2, 1, 2, true)]);
+        assert_eq!((imported.start, imported.end), (Some(0), Some(2)));
+    }
+
+    #[test]
+    fn edge_problem_round_trip() {
+        let graph = GraphSnapshot::test_graph(4, &[(0, 1, 1, false), (2, 3, 1, false)]);
+        assert_eq!(Problem::of(&graph, None, None), Problem::Edge);
+        let imported = parse_dimacs(&write_dimacs(&graph, None, None)).unwrap();
+        assert_eq!(imported.names().len(), 4);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 1, false), (2, 3, 1, false)]);
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        assert!(parse_dimacs("a 1 2 3\n").is_err());
+        assert!(parse_dimacs("p sp 2 1\np sp 2 1\n").is_err());
+        assert!(parse_dimacs("p sp 2 1\na 1 x 3\n").is_err());
+        assert!(parse_dimacs("p sp 2 1\na 1\n").is_err());
+    }
+}
@@ -0,0 +1,450 @@
+use bevy::math::Vec2;
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::ImportedGraph;
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    // The text and whether it was quoted, quoted keywords are plain IDs
+    Id(String, bool),
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Equals,
+    Semicolon,
+    Comma,
+    Colon,
+    Plus,
+    // -> or --
+    EdgeOp,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    // Lines starting with # are preprocessor output and ignored
+    let mut line_start = true;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == '#' && line_start {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        line_start = false;
+        let next = chars.get(i + 1).copied();
+        match c {
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            },
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            },
+            '{' | '}' | '[' | ']' | '=' | ';' | ',' | ':' | '+' => {
+                tokens.push(match c {
+                    '{' => Token::OpenBrace,
+                    '}' => Token::CloseBrace,
+                    '[' => Token::OpenBracket,
+                    ']' => Token::CloseBracket,
+                    '=' => Token::Equals,
+                    ';' => Token::Semicolon,
+                    ',' => Token::Comma,
+                    ':' => Token::Colon,
+                    _ => Token::Plus,
+                });
+                i += 1;
+            },
+            '-' if next == Some('>') || next == Some('-') => {
+                tokens.push(Token::EdgeOp);
+                i += 2;
+            },
+            '"' => {
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("A quoted string is never closed".to_string()),
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.get(i + 1), Some('"') | Some('\\')) => {
+                            value.push(chars[i + 1]);
+                            i += 1;
+                        },
+                        // A backslash before the end of the line continues the string
+                        Some('\\') if chars.get(i + 1) == Some(&'\n') => i += 1,
+                        Some(c) => value.push(*c),
+                    }
+                    i += 1;
+                }
+                tokens.push(Token::Id(value, true));
+                i += 1;
+            },
+            '<' => {
+                // HTML strings nest their angle brackets
+                let mut depth = 0;
+                let start = i;
+                loop {
+                    match chars.get(i) {
+                        None => return Err("An HTML string is never closed".to_string()),
+                        Some('<') => depth += 1,
+                        Some('>') => depth -= 1,
+                        _ => {},
+                    }
+                    i += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                tokens.push(Token::Id(chars[start + 1..i - 1].iter().collect(), true));
+            },
+            c if c == '-' || c == '.' || c.is_ascii_digit() => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                tokens.push(Token::Id(chars[start..i].iter().collect(), false));
+            },
+            c if c.is_alphabetic() || c == '_' || !c.is_ascii() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || !chars[i].is_ascii()) {
+                    i += 1;
+                }
+                tokens.push(Token::Id(chars[start..i].iter().collect(), false));
+            },
+            c => return Err(format!("Unexpected character '{c}'")),
+        }
+    }
+    Ok(tokens)
+}
+
+type Attributes = Vec<(String, String)>;
+
+fn attribute<'a>(attributes: &'a Attributes, key: &str) -> Option<&'a str> {
+    attributes.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
+
+// The default attributes of node and edge statements, a subgraph starts with a copy of its parent's
+#[derive(Clone, Default)]
+struct Defaults {
+    node: Attributes,
+    edge: Attributes,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    cursor: usize,
+    directed: bool,
+    graph: ImportedGraph,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.cursor)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.cursor).cloned();
+        self.cursor += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.cursor += 1;
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), String> {
+        match self.next() {
+            Some(found) if found == token => Ok(()),
+            found => Err(format!("Expected {token:?} but found {found:?}")),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(id, false)) if id.eq_ignore_ascii_case(keyword))
+    }
+
+    // An ID, quoted parts joined with + are one ID
+    fn id(&mut self) -> Result<String, String> {
+        let Some(Token::Id(mut id, quoted)) = self.next() else {
+            return Err(format!("Expected an ID but found {:?}", self.tokens.get(self.cursor - 1)));
+        };
+        while quoted && self.peek() == Some(&Token::Plus) {
+            self.cursor += 1;
+            id.push_str(&self.id()?);
+        }
+        Ok(id)
+    }
+
+    // Any number of [a = b, c = d; e] lists
+    fn attributes(&mut self) -> Result<Attributes, String> {
+        let mut attributes = vec![];
+        while self.eat(&Token::OpenBracket) {
+            while !self.eat(&Token::CloseBracket) {
+                let key = self.id()?;
+                let value = if self.eat(&Token::Equals) { self.id()? } else { "true".to_string() };
+                attributes.push((key, value));
+                if !self.eat(&Token::Comma) {
+                    self.eat(&Token::Semicolon);
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    fn node(&mut self, name: String, attributes: &Attributes) -> usize {
+        let v = self.graph.vertex(&name);
+        if let Some(position) = attribute(attributes, "pos").and_then(parse_position) {
+            self.graph.vertices[v].position = Some(position);
+        }
+        if let Some(label) = attribute(attributes, "label").filter(|label| !label.is_empty() && *label != "\\N") {
+            self.graph.vertices[v].name = label.to_string();
+        }
+        v
+    }
+
+    // A node ID with its port, the port is dropped
+    fn node_id(&mut self) -> Result<String, String> {
+        let id = self.id()?;
+        while self.eat(&Token::Colon) {
+            self.id()?;
+        }
+        Ok(id)
+    }
+
+    fn statements(&mut self, defaults: &mut Defaults) -> Result<Vec<usize>, String> {
+        let mut members = vec![];
+        while !self.eat(&Token::CloseBrace) {
+            if self.peek().is_none() {
+                return Err("A { is never closed".to_string());
+            }
+            if self.eat(&Token::Semicolon) {
+                continue;
+            }
+            if self.is_keyword("node") || self.is_keyword("edge") || self.is_keyword("graph") {
+                let Some(Token::Id(keyword, _)) = self.next() else {unreachable!()};
+                let attributes = self.attributes()?;
+                match keyword.to_ascii_lowercase().as_str() {
+                    "node" => defaults.node.extend(attributes),
+                    "edge" => defaults.edge.extend(attributes),
+                    _ => {},
+                }
+                continue;
+            }
+            // The first operand of an edge statement, a node or a subgraph
+            let operand = if self.is_keyword("subgraph") || self.peek() == Some(&Token::OpenBrace) {
+                self.subgraph(defaults)?
+            } else {
+                let id = self.node_id()?;
+                if self.eat(&Token::Equals) {
+                    // A graph attribute
+                    self.id()?;
+                    continue;
+                }
+                if self.peek() != Some(&Token::EdgeOp) {
+                    let mut attributes = defaults.node.clone();
+                    attributes.extend(self.attributes()?);
+                    members.push(self.node(id, &attributes));
+                    continue;
+                }
+                vec![self.node(id, &defaults.node)]
+            };
+            members.extend(operand.iter().copied());
+            let mut operands = vec![operand];
+            while self.eat(&Token::EdgeOp) {
+                let operand = if self.is_keyword("subgraph") || self.peek() == Some(&Token::OpenBrace) {
+                    self.subgraph(defaults)?
+                } else {
+                    let id = self.node_id()?;
+                    vec![self.node(id, &defaults.node)]
+                };
+                members.extend(operand.iter().copied());
+                operands.push(operand);
+            }
+            let mut attributes = defaults.edge.clone();
+            attributes.extend(self.attributes()?);
+            for pair in operands.windows(2) {
+                for from in pair[0].iter() {
+                    for to in pair[1].iter() {
+                        self.edge(*from, *to, &attributes);
+                    }
+                }
+            }
+        }
+        Ok(members)
+    }
+
+    // Subgraphs are flattened, only their nodes are kept to be the ends of edges
+    fn subgraph(&mut self, defaults: &Defaults) -> Result<Vec<usize>, String> {
+        if self.is_keyword("subgraph") {
+            self.cursor += 1;
+            if matches!(self.peek(), Some(Token::Id(..))) {
+                self.id()?;
+            }
+        }
+        self.expect(Token::OpenBrace)?;
+        self.statements(&mut defaults.clone())
+    }
+
+    fn edge(&mut self, from: usize, to: usize, attributes: &Attributes) {
+        let label = attribute(attributes, "label");
+        let weight = attribute(attributes, "weight")
+            .or(label)
+            .and_then(|weight| weight.trim().parse::<f32>().ok())
+            .map_or(1, |weight| weight.round() as i32);
+        let (from, to, directed) = match attribute(attributes, "dir") {
+            Some("none") | Some("both") => (from, to, false),
+            Some("back") => (to, from, true),
+            Some("forward") => (from, to, true),
+            _ => (from, to, self.directed),
+        };
+        self.graph.add_edge(from, to, weight, directed);
+    }
+}
+
+// "x,y" in points, neato adds a ! to pinned nodes
+fn parse_position(pos: &str) -> Option<Vec2> {
+    let mut parts = pos.trim().trim_end_matches('!').split(',');
+    let x = parts.next()?.trim().parse().ok()?;
+    let y = parts.next()?.trim().parse().ok()?;
+    Some(Vec2::new(x, y))
+}
+
+/**
+    # DOT
+    Reads the first graph of the text. Node and edge statements, default attributes and subgraphs are understood,
+    subgraphs are flattened into the graph. pos sets the position, label the name of a node and weight, or a
+    numeric label, the weight of an edge. dir=none or both makes an edge of a digraph undirected.
+*/
+pub fn parse_dot(text: &str) -> Result<ImportedGraph, String> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        cursor: 0,
+        directed: false,
+        graph: ImportedGraph::default(),
+    };
+    if parser.is_keyword("strict") {
+        parser.cursor += 1;
+    }
+    parser.directed = if parser.is_keyword("digraph") {
+        true
+    } else if parser.is_keyword("graph") {
+        false
+    } else {
+        return Err("The file doesn't start with graph or digraph".to_string());
+    };
+    parser.cursor += 1;
+    if matches!(parser.peek(), Some(Token::Id(..))) {
+        parser.id()?;
+    }
+    parser.expect(Token::OpenBrace)?;
+    parser.statements(&mut Defaults::default())?;
+    Ok(parser.graph)
+}
+
+// Backslashes are doubled like Graphviz shows them in labels, so a name ending in one doesn't escape the closing quote
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// A digraph as soon as one edge is directed, its undirected edges get dir=none. Names can repeat,
+// so the nodes are n0, n1, ... with the name as their label. Graphviz rejects negative weights,
+// the label always holds the weight and weight is only written when it is at least 0
+pub fn write_dot(graph: &GraphSnapshot) -> String {
+    let directed = graph.edges.iter().any(|edge| edge.directed);
+    let mut dot = format!("{} G {{\n", if directed { "digraph" } else { "graph" });
+    for (v, position) in graph.positions.iter().enumerate() {
+        dot.push_str(&format!("    n{v} [label={}, pos=\"{:.2},{:.2}\"];\n", quote(graph.name(v)), position.x, position.y));
+    }
+    for edge in graph.edges.iter() {
+        dot.push_str(&format!(
+            "    n{} {} n{} [{}label=\"{}\"{}];\n",
+            edge.from,
+            if directed { "->" } else { "--" },
+            edge.to,
+            if edge.weight >= 0 { format!("weight={}, ", edge.weight) } else { String::new() },
+            edge.weight,
+            if directed && !edge.directed { ", dir=none" } else { "" },
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::files::{snapshot_edges, tricky_graph};
+
+    use super::{parse_dot, write_dot};
+
+    #[test]
+    fn round_trip() {
+        let graph = tricky_graph();
+        let text = write_dot(&graph);
+        // Negative weights only go in the label
+        assert!(text.contains("n1 -> n2 [label=\"-3\"];"));
+        assert!(text.contains("n0 -> n1 [weight=5, label=\"5\", dir=none];"));
+        let imported = parse_dot(&text).unwrap();
+        assert_eq!(imported.names(), graph.names.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(imported.edge_tuples(), snapshot_edges(&graph));
+        for (vertex, position) in imported.vertices.iter().zip(graph.positions.iter()) {
+            assert_eq!(vertex.position, Some(*position));
+        }
+    }
+
+    #[test]
+    fn quoted_ids_and_comments() {
+        let text = r#"
+            # preprocessor line
+            strict digraph "my graph" {
+                // a comment
+                node [shape=circle];
+                "a b" -> "c\"d" [weight=4]; /* block
+                comment */
+                "long" + " name" -> e [label="-2"];
+                e -> f [dir=none];
+                g [label="Gee", pos="1,2!"];
+                rankdir = LR;
+            }
+        "#;
+        let imported = parse_dot(text).unwrap();
+        assert_eq!(imported.names(), vec!["a b", "c\"d", "long name", "e", "f", "Gee"]);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 4, true), (2, 3, -2, true), (3, 4, 1, false)]);
+        assert_eq!(imported.vertices[5].position, Some(bevy::math::Vec2::new(1., 2.)));
+    }
+
+    #[test]
+    fn subgraphs_connect_every_member() {
+        let imported = parse_dot("graph { a -- {b c} -- subgraph s { d } }").unwrap();
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 1, false), (0, 2, 1, false), (1, 3, 1, false), (2, 3, 1, false)]);
+    }
+
+    #[test]
+    fn broken_files_are_errors() {
+        assert!(parse_dot("graph { a -- b").is_err());
+        assert!(parse_dot("graph { \"a -- b }").is_err());
+        assert!(parse_dot("network { a }").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::ImportedGraph;
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with('%') || line.starts_with("//")
+}
+
+// The comment "# vertex <id> <name>" names the vertex with that ID, names can hold whitespace there
+fn vertex_name(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix('#')?.trim_start().strip_prefix("vertex ")?.trim();
+    let (id, name) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((id, name.trim())).filter(|(id, _)| !id.is_empty())
+}
+
+// A competitive programming header "n m" is followed by exactly m edges between vertices up to n
+fn is_header(lines: &[Vec<&str>]) -> bool {
+    let Some((first, edges)) = lines.split_first() else {return false};
+    let [n, m] = first[..] else {return false};
+    let (Ok(n), Ok(m)) = (n.parse::<usize>(), m.parse::<usize>()) else {return false};
+    edges.len() == m && edges.iter().all(|tokens| {
+        tokens.len() >= 2 && tokens[..2].iter().all(|token| token.parse::<usize>().is_ok_and(|v| v <= n))
+    })
+}
+
+/**
+    # Edge List
+    One edge "u v" or "u v w" per line, a lone "u" is a vertex without edges. Lines starting with #, % or // are comments,
+    the graph is directed if a comment says so like the SNAP datasets do. A comment "# vertex <id> <name>" gives a vertex
+    its name. A first line "n m" followed by m edges is the header of a competitive programming input, it creates
+    the vertices 1 to n, or 0 to n - 1 if a vertex 0 is used.
+*/
+pub fn parse_edge_list(text: &str) -> Result<ImportedGraph, String> {
+    let mut directed = false;
+    let mut lines: Vec<(usize, Vec<&str>)> = vec![];
+    let mut graph = ImportedGraph::default();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some((id, name)) = vertex_name(line) {
+            let v = graph.vertex(id);
+            if !name.is_empty() {
+                graph.vertices[v].name = name.to_string();
+            }
+        } else if is_comment(line) {
+            let comment = line.to_ascii_lowercase();
+            directed |= comment.contains("directed") && !comment.contains("undirected");
+        } else if !line.is_empty() {
+            lines.push((number + 1, line.split_whitespace().collect()));
+        }
+    }
+
+    let tokens: Vec<Vec<&str>> = lines.iter().map(|(_, tokens)| tokens.clone()).collect();
+    if is_header(&tokens) {
+        let n: usize = tokens[0][0].parse().unwrap_or_default();
+        let zero_based = tokens[1..].iter().any(|tokens| tokens[..2].contains(&"0"));
+        let first = if zero_based { 0 } else { 1 };
+        for v in first..first + n {
+            graph.vertex(&v.to_string());
+        }
+        lines.remove(0);
+    }
+
+    for (number, tokens) in lines {
+        let from = graph.vertex(tokens[0]);
+        let Some(to) = tokens.get(1) else {continue};
+        let to = graph.vertex(to);
+        let weight = match tokens.get(2) {
+            Some(weight) => weight.parse::<f32>().map_err(|_| format!("Line {number}: the weight {weight} is not a number"))?.round() as i32,
+            None => 1,
+        };
+        graph.add_edge(from, to, weight, directed);
+    }
+    Ok(graph)
+}
+
+// Vertices are written as their index so that repeated names and names with whitespace stay apart,
+// the vertices with their names follow the header. A directed graph writes its undirected edges in both directions
+pub fn write_edge_list(graph: &GraphSnapshot) -> String {
+    let directed = graph.edges.iter().any(|edge| edge.directed);
+    let mut list = format!(
+        "# {} graph with {} vertices and {} edges\n",
+        if directed { "Directed" } else { "Undirected" },
+        graph.len(),
+        graph.edges.len(),
+    );
+    for v in 0..graph.len() {
+        let name = graph.name(v).lines().map(str::trim).collect::<Vec<_>>().join(" ");
+        list.push_str(&format!("# vertex {v} {name}\n"));
+    }
+    for edge in graph.edges.iter() {
+        let (from, to) = (edge.from, edge.to);
+        list.push_str(&format!("{from} {to} {}\n", edge.weight));
+        if directed && !edge.directed {
+            list.push_str(&format!("{to} {from} {}\n", edge.weight));
+        }
+    }
+    for v in (0..graph.len()).filter(|v| graph.out[*v].is_empty() && graph.inc[*v].is_empty()) {
+        list.push_str(&format!("{v}\n"));
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::graph::GraphSnapshot;
+    use crate::app::files::tricky_graph;
+
+    use super::{parse_edge_list, write_edge_list};
+
+    #[test]
+    fn round_trip() {
+        let graph = GraphSnapshot::test_graph(5, &[(0, 1, 5, false), (1, 2, -3, false), (3, 0, 0, false)]);
+        let imported = parse_edge_list(&write_edge_list(&graph)).unwrap();
+        assert_eq!(imported.names(), vec!["v0", "v1", "v2", "v3", "v4"]);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 5, false), (1, 2, -3, false), (3, 0, 0, false)]);
+    }
+
+    #[test]
+    fn repeated_names_stay_apart() {
+        let graph = tricky_graph();
+        let imported = parse_edge_list(&write_edge_list(&graph)).unwrap();
+        assert_eq!(imported.names(), graph.names.iter().map(String::as_str).collect::<Vec<_>>());
+        // Undirected edges of a directed graph come back as two arcs
+        assert_eq!(imported.edge_tuples(), vec![
+            (0, 1, 5, true), (1, 0, 5, true), (1, 2, -3, true), (2, 3, 0, true), (3, 2, 0, true),
+            (3, 0, 7, true), (4, 5, 1, true), (5, 4, 1, true), (5, 0, -12, true),
+        ]);
+    }
+
+    #[test]
+    fn comments_and_lone_vertices() {
+        let text = "% Directed graph from SNAP\n# FromNodeId ToNodeId\n\n1 2\n// a comment\n2 3 4.4\n5\n";
+        let imported = parse_edge_list(text).unwrap();
+        assert_eq!(imported.names(), vec!["1", "2", "3", "5"]);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 1, true), (1, 2, 4, true)]);
+
+        let undirected = parse_edge_list("# Undirected graph\na b\n").unwrap();
+        assert_eq!(undirected.edge_tuples(), vec![(0, 1, 1, false)]);
+    }
+
+    #[test]
+    fn competitive_programming_header() {
+        let one_based = parse_edge_list("4 2\n1 2\n2 3\n").unwrap();
+        assert_eq!(one_based.names(), vec!["1", "2", "3", "4"]);
+        assert_eq!(one_based.edge_tuples(), vec![(0, 1, 1, false), (1, 2, 1, false)]);
+
+        let zero_based = parse_edge_list("3 1\n0 2 7\n").unwrap();
+        assert_eq!(zero_based.names(), vec!["0", "1", "2"]);
+        assert_eq!(zero_based.edge_tuples(), vec![(0, 2, 7, false)]);
+
+        // Three edges after "2 1" are not a header but an edge and two more
+        let no_header = parse_edge_list("2 1\n1 2\n2 3\n").unwrap();
+        assert_eq!(no_header.names(), vec!["2", "1", "3"]);
+    }
+
+    #[test]
+    fn bad_weights_are_errors() {
+        let Err(error) = parse_edge_list("a b\nb c heavy\n") else {panic!("the weight is not a number")};
+        assert!(error.starts_with("Line 2"), "{error}");
+    }
+}
@@ -0,0 +1,436 @@
+use bevy::{color::Color, ecs::system::SystemParam, math::{Rect, Vec2}, prelude::{ClearColor, Entity, Query, Res}, utils::HashMap};
+
+use crate::app::{algorithms::{graph::{GraphData, GraphSnapshot}, playback::{mark_color, AlgorithmRun, PlaybackSettings, ANNOTATION_COLOR, EDGE_ANNOTATION_OFFSET, MATCHED_EDGE_WIDTH, VERTEX_ANNOTATION_OFFSET}, steps::{Change, Mark, Step}}, build_graph::{components::{edge_outline, EDGE_WIDTH, LABEL_FONT_SIZE}, RADIUS}};
+
+use super::{gif::encode_gif, png::{encode_apng, encode_png}, raster::{Canvas, LINE_SPACING}, FileAttributes};
+
+pub const DEFAULT_WIDTH: usize = 1024;
+const MAX_WIDTH: usize = 4096;
+// Frames are at most this many times as high as they are wide
+const MAX_ASPECT: f32 = 2.;
+// Room around the vertices for the annotations above them
+const MARGIN: f32 = RADIUS + 60.;
+// The height of the glyphs of the default font relative to its size
+const GLYPH_FACTOR: f32 = 0.7;
+const WEIGHT_OFFSET: Vec2 = Vec2::new(0., 30.);
+// The caption under the graph shows the run and the message of the step, sizes in pixels
+const CAPTION_SIZE: f32 = 14.;
+const CAPTION_PADDING: f32 = 12.;
+const CAPTION_LINES: usize = 6;
+const CAPTION_BACKGROUND: Color = Color::srgb(0.08, 0.08, 0.1);
+// Labels smaller than this many pixels are not drawn
+const MIN_TEXT_SIZE: f32 = 4.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrameFormat {
+    // One file per step
+    Png,
+    Gif,
+    Apng,
+}
+
+impl FrameFormat {
+    pub fn from_name(name: &str) -> Option<FrameFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(FrameFormat::Png),
+            "gif" => Some(FrameFormat::Gif),
+            "apng" => Some(FrameFormat::Apng),
+            _ => None,
+        }
+    }
+
+    // The files of the frames, name without extension
+    pub fn encode(&self, name: &str, frames: &[Canvas], step_seconds: f32) -> Vec<(String, Vec<u8>)> {
+        let delay = (step_seconds * 100.).round().clamp(1., u16::MAX as f32) as u16;
+        match self {
+            FrameFormat::Png => frames.iter()
+                .enumerate()
+                .map(|(i, frame)| (format!("{name}_{:03}.png", i + 1), encode_png(frame)))
+                .collect(),
+            FrameFormat::Gif => vec![(format!("{name}.gif"), encode_gif(frames, delay))],
+            FrameFormat::Apng => vec![(format!("{name}.png"), encode_apng(frames, delay))],
+        }
+    }
+}
+
+// What a vertex or an edge of the run looks like, the label is the annotation of the algorithm
+#[derive(Clone)]
+struct Look {
+    color: Color,
+    label: String,
+}
+
+/**
+    # Frame State
+    The graph as the playback shows it after some steps. The changes are applied like the playback applies
+    them to the entities, except that moved vertices jump to their new position instead of gliding there.
+*/
+#[derive(Clone)]
+struct FrameState {
+    positions: Vec<Vec2>,
+    scales: Vec<f32>,
+    vertices: Vec<Look>,
+    edges: Vec<Look>,
+    edge_widths: Vec<f32>,
+    segments: Vec<(usize, usize, Mark)>,
+}
+
+enum Target {
+    Vertex(usize),
+    Edge(usize),
+}
+
+/**
+    # Run Frames
+    Everything needed to draw an algorithm run without a window: the graph, its colors before the run and the
+    steps. The colors are the ones of the file or white, like the vertices and edges the app spawns.
+*/
+pub struct RunFrames<'a> {
+    pub graph: &'a GraphSnapshot,
+    pub vertex_colors: Vec<Color>,
+    pub edge_colors: Vec<Color>,
+    pub name: String,
+    pub steps: &'a [Step],
+    pub background: Color,
+}
+
+impl<'a> RunFrames<'a> {
+    fn target(&self, edges: &HashMap<Entity, usize>, entity: Entity) -> Option<Target> {
+        match self.graph.index.get(&entity) {
+            Some(v) => Some(Target::Vertex(*v)),
+            None => edges.get(&entity).map(|e| Target::Edge(*e)),
+        }
+    }
+
+    fn start(&self) -> FrameState {
+        let look = |color: &Color| Look { color: *color, label: String::new() };
+        FrameState {
+            positions: self.graph.positions.clone(),
+            scales: vec![1.; self.graph.len()],
+            vertices: self.vertex_colors.iter().map(look).collect(),
+            edges: self.edge_colors.iter().map(look).collect(),
+            edge_widths: vec![EDGE_WIDTH; self.graph.edges.len()],
+            segments: vec![],
+        }
+    }
+
+    fn apply(&self, state: &mut FrameState, edges: &HashMap<Entity, usize>, step: &Step) {
+        for change in step.changes.iter() {
+            match change {
+                Change::Vertex(entity, mark) | Change::Edge(entity, mark) => {
+                    match self.target(edges, *entity) {
+                        Some(Target::Vertex(v)) => state.vertices[v].color = mark_color(*mark),
+                        Some(Target::Edge(e)) => {
+                            state.edges[e].color = mark_color(*mark);
+                            // Only an edge mark sets the width
+                            if let Change::Edge(..) = change {
+                                state.edge_widths[e] = if *mark == Mark::Matched { MATCHED_EDGE_WIDTH } else { EDGE_WIDTH };
+                            }
+                        },
+                        None => {},
+                    }
+                },
+                Change::Clear(entity) => match self.target(edges, *entity) {
+                    Some(Target::Vertex(v)) => {
+                        state.scales[v] = 1.;
+                        state.vertices[v].color = Color::WHITE;
+                    },
+                    Some(Target::Edge(e)) => {
+                        state.edges[e].color = Color::WHITE;
+                        state.edge_widths[e] = EDGE_WIDTH;
+                    },
+                    None => {},
+                },
+                Change::VertexLabel(entity, label) | Change::EdgeLabel(entity, label) => match self.target(edges, *entity) {
+                    Some(Target::Vertex(v)) => state.vertices[v].label = label.clone(),
+                    Some(Target::Edge(e)) => state.edges[e].label = label.clone(),
+                    None => {},
+                },
+                Change::Position(entity, position) => {
+                    if let Some(v) = self.graph.index.get(entity) {
+                        state.positions[*v] = *position;
+                    }
+                },
+                Change::Scale(entity, scale) => {
+                    if let Some(v) = self.graph.index.get(entity) {
+                        state.scales[*v] = *scale;
+                    }
+                },
+                Change::Segments(lines) => {
+                    state.segments = lines.iter()
+                        .filter_map(|(start, end, mark)| Some((*self.graph.index.get(start)?, *self.graph.index.get(end)?, *mark)))
+                        .collect();
+                },
+            }
+        }
+    }
+
+    // The state after every step, or the graph as it is if the run has no steps
+    fn states(&self) -> Vec<FrameState> {
+        let edges: HashMap<Entity, usize> = self.graph.edges.iter().enumerate().map(|(e, edge)| (edge.entity, e)).collect();
+        let mut state = self.start();
+        if self.steps.is_empty() {
+            return vec![state];
+        }
+        self.steps.iter()
+            .map(|step| {
+                self.apply(&mut state, &edges, step);
+                state.clone()
+            })
+            .collect()
+    }
+
+    // The caption of every frame, the name of the run with the step and its message wrapped to the width
+    fn caption(&self, i: usize, width: usize) -> Vec<String> {
+        let mut lines = vec![if self.steps.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} ({}/{})", self.name, i + 1, self.steps.len())
+        }];
+        let Some(step) = self.steps.get(i) else {return lines};
+        let room = width as f32 - 2. * CAPTION_PADDING;
+        for paragraph in step.message.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() { word.to_string() } else { format!("{line} {word}") };
+                if !line.is_empty() && Canvas::text_width(&candidate, CAPTION_SIZE) > room {
+                    lines.push(std::mem::replace(&mut line, word.to_string()));
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+        lines.truncate(CAPTION_LINES);
+        lines
+    }
+
+    // The box around all the positions the vertices take during the run with room for the annotations
+    fn bounds(states: &[FrameState]) -> Rect {
+        let mut bounds = Rect::EMPTY;
+        for state in states.iter() {
+            for (position, scale) in state.positions.iter().zip(state.scales.iter()) {
+                bounds = bounds.union(Rect::from_center_half_size(*position, Vec2::splat(RADIUS * scale.max(1.))));
+            }
+        }
+        if bounds.is_empty() {
+            bounds = Rect::from_center_half_size(Vec2::ZERO, Vec2::splat(RADIUS));
+        }
+        bounds.inflate(MARGIN)
+    }
+
+    // The graph in a state, scale is pixels per world unit. Text too small to read is left out
+    fn draw(&self, canvas: &mut Canvas, state: &FrameState, pixel: &impl Fn(Vec2) -> Vec2, scale: f32) {
+        let text = |size: f32| size * GLYPH_FACTOR * scale;
+        for (e, edge) in self.graph.edges.iter().enumerate() {
+            let (start, end) = (state.positions[edge.from], state.positions[edge.to]);
+            draw_edge(canvas, pixel, (start, end), edge.directed, state.edge_widths[e], state.edges[e].color);
+        }
+        for (start, end, mark) in state.segments.iter() {
+            draw_edge(canvas, pixel, (state.positions[*start], state.positions[*end]), false, EDGE_WIDTH, mark_color(*mark));
+        }
+        for (v, position) in state.positions.iter().enumerate() {
+            canvas.fill_circle(pixel(*position), RADIUS * state.scales[v] * scale, state.vertices[v].color);
+        }
+        if text(LABEL_FONT_SIZE) < MIN_TEXT_SIZE {
+            return;
+        }
+        for (v, position) in state.positions.iter().enumerate() {
+            let vertex_scale = state.scales[v];
+            canvas.draw_text(pixel(*position), self.graph.name(v), text(LABEL_FONT_SIZE * vertex_scale), Color::BLACK);
+            let label = &state.vertices[v].label;
+            if !label.is_empty() {
+                let at = *position + VERTEX_ANNOTATION_OFFSET.truncate() * vertex_scale;
+                canvas.draw_text(pixel(at), label, text(LABEL_FONT_SIZE * vertex_scale), ANNOTATION_COLOR);
+            }
+        }
+        for (e, edge) in self.graph.edges.iter().enumerate() {
+            let mid = (state.positions[edge.from] + state.positions[edge.to]) / 2.;
+            canvas.draw_text(pixel(mid + WEIGHT_OFFSET), &edge.weight.to_string(), text(LABEL_FONT_SIZE), Color::WHITE);
+            let label = &state.edges[e].label;
+            if !label.is_empty() {
+                canvas.draw_text(pixel(mid + EDGE_ANNOTATION_OFFSET.truncate()), label, text(LABEL_FONT_SIZE), ANNOTATION_COLOR);
+            }
+        }
+    }
+
+    /**
+        # Render
+        One frame per step. Every frame shows the same part of the world, the box around all the positions
+        the vertices take during the run, scaled to the width in pixels.
+    */
+    pub fn render(&self, width: usize) -> Vec<Canvas> {
+        let states = self.states();
+        let width = width.clamp(1, MAX_WIDTH);
+        let bounds = Self::bounds(&states);
+        let scale = (width as f32 / bounds.width()).min(width as f32 * MAX_ASPECT / bounds.height());
+        let graph_height = (bounds.height() * scale).ceil() as usize;
+        let offset = (width as f32 - bounds.width() * scale) / 2.;
+        let pixel = |p: Vec2| Vec2::new(offset + (p.x - bounds.min.x) * scale, (bounds.max.y - p.y) * scale);
+
+        let captions: Vec<Vec<String>> = (0..states.len()).map(|i| self.caption(i, width)).collect();
+        let caption_lines = captions.iter().map(|lines| lines.len()).max().unwrap_or(1);
+        let caption_height = (2. * CAPTION_PADDING + (caption_lines as f32 * LINE_SPACING - (LINE_SPACING - 1.)) * CAPTION_SIZE).ceil() as usize;
+
+        states.iter().zip(captions.iter())
+            .map(|(state, caption)| {
+                let mut canvas = Canvas::new(width, graph_height + caption_height, self.background);
+                self.draw(&mut canvas, state, &pixel, scale);
+
+                let top = graph_height as f32;
+                canvas.fill_rect(Vec2::new(0., top), Vec2::new(width as f32, top + caption_height as f32), CAPTION_BACKGROUND);
+                for (i, line) in caption.iter().enumerate() {
+                    let y = top + CAPTION_PADDING + (i as f32 * LINE_SPACING + 0.5) * CAPTION_SIZE;
+                    let center = Vec2::new(CAPTION_PADDING + Canvas::text_width(line, CAPTION_SIZE) / 2., y);
+                    canvas.draw_text(center, line, CAPTION_SIZE, if i == 0 { ANNOTATION_COLOR } else { Color::WHITE });
+                }
+                canvas
+            })
+            .collect()
+    }
+
+    // The graph before the run centered in a small image without a caption
+    pub fn thumbnail(&self, width: usize, height: usize) -> Canvas {
+        let state = self.start();
+        let bounds = Self::bounds(std::slice::from_ref(&state));
+        let scale = (width as f32 / bounds.width()).min(height as f32 / bounds.height());
+        let offset = (Vec2::new(width as f32, height as f32) - bounds.size() * scale) / 2.;
+        let pixel = |p: Vec2| offset + Vec2::new(p.x - bounds.min.x, bounds.max.y - p.y) * scale;
+        let mut canvas = Canvas::new(width, height, self.background);
+        self.draw(&mut canvas, &state, &pixel, scale);
+        canvas
+    }
+}
+
+// An edge or a segment with its arrowhead, like the mesh of an edge
+fn draw_edge(canvas: &mut Canvas, pixel: &impl Fn(Vec2) -> Vec2, (start, end): (Vec2, Vec2), directed: bool, width: f32, color: Color) {
+    let mid = (start + end) / 2.;
+    let (quad, arrow) = edge_outline(start, end, directed, width);
+    canvas.fill_polygon(&quad.map(|p| pixel(p + mid)), color);
+    if let Some(arrow) = arrow {
+        canvas.fill_polygon(&arrow.map(|p| pixel(p + mid)), color);
+    }
+}
+
+// Everything needed to draw the frames of the current run
+#[derive(SystemParam)]
+pub struct RunFrameData<'w, 's> {
+    pub graph: GraphData<'w, 's>,
+    run: Res<'w, AlgorithmRun>,
+    q_attributes: Query<'w, 's, (Entity, &'static FileAttributes)>,
+    clear_color: Res<'w, ClearColor>,
+    pub settings: Res<'w, PlaybackSettings>,
+}
+
+impl<'w, 's> RunFrameData<'w, 's> {
+    fn run_frames<'a>(&self, graph: &'a GraphSnapshot, name: String, steps: &'a [Step]) -> RunFrames<'a> {
+        let color = |entity: Entity| self.q_attributes.get(entity).ok()
+            .and_then(|(_, attributes)| attributes.color)
+            .unwrap_or(Color::WHITE);
+        RunFrames {
+            graph,
+            vertex_colors: graph.vertices.iter().map(|entity| color(*entity)).collect(),
+            edge_colors: graph.edges.iter().map(|edge| color(edge.entity)).collect(),
+            name,
+            steps,
+            background: self.clear_color.0,
+        }
+    }
+
+    // The frames of the current run from its first step, the name is the one of the exported files
+    pub fn frames(&self, width: usize) -> (String, Vec<Canvas>) {
+        let graph = self.graph.snapshot();
+        let name = if self.run.name.is_empty() { "Graph".to_string() } else { self.run.name.clone() };
+        let frames = self.run_frames(&graph, name.clone(), &self.run.steps).render(width);
+        (file_stem(&name), frames)
+    }
+
+    // The graph in its own colors, without the marks of a run
+    pub fn thumbnail(&self, graph: &GraphSnapshot, width: usize, height: usize) -> Canvas {
+        self.run_frames(graph, String::new(), &[]).thumbnail(width, height)
+    }
+}
+
+// A name that is safe to use for a file, like kahn_s_algorithm
+pub fn file_stem(name: &str) -> String {
+    let stem: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let stem = stem.split('_').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("_");
+    if stem.is_empty() { "run".to_string() } else { stem }
+}
+
+/**
+    # Headless Render
+    The frames of an algorithm run on a graph file, without a window or a GPU:
+    `--render <graph file> <algorithm> [--start NAME] [--end NAME] [--format png|gif|apng] [--width PX] [--out DIR]`.
+    Vertices without a position are placed like on import. The start and end default to the source and sink of the file.
+*/
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render_headless(args: &[String]) -> Result<String, String> {
+    use crate::app::algorithms::headless::run_algorithm;
+    use super::{place_missing, Format};
+
+    let mut positional = vec![];
+    let (mut start, mut end, mut format, mut width, mut out) = (None, None, FrameFormat::Gif, DEFAULT_WIDTH, "exports".to_string());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().ok_or(format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--start" => start = Some(value()?),
+            "--end" => end = Some(value()?),
+            "--format" => {
+                let name = value()?;
+                format = FrameFormat::from_name(&name).ok_or(format!("Unknown format {name}, the formats are png, gif and apng"))?;
+            },
+            "--width" => {
+                let pixels = value()?;
+                width = pixels.parse().map_err(|_| format!("{pixels} is not a width in pixels"))?;
+            },
+            "--out" => out = value()?,
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let [path, algorithm] = &positional[..] else {
+        return Err("Usage: --render <graph file> <algorithm> [--start NAME] [--end NAME] [--format png|gif|apng] [--width PX] [--out DIR]".to_string());
+    };
+
+    let file_format = Format::from_name(path).ok_or(format!("{path} has an unknown file extension"))?;
+    let contents = std::fs::read(path).map_err(|error| format!("Could not read {path}: {error}"))?;
+    let imported = file_format.parse(&String::from_utf8_lossy(&contents)).map_err(|error| format!("Could not import {path}: {error}"))?;
+    let positions = place_missing(&imported, Vec2::ZERO);
+    let names: Vec<String> = imported.vertices.iter().map(|vertex| vertex.name.clone()).collect();
+    let edges: Vec<(usize, usize, i32, bool)> = imported.edges.iter().map(|edge| (edge.from, edge.to, edge.weight, edge.directed)).collect();
+    let graph = GraphSnapshot::detached(names, positions, &edges);
+
+    let find = |name: &Option<String>, default: Option<usize>| match name {
+        Some(name) => graph.names.iter().position(|known| known == name).map(Some).ok_or(format!("There is no vertex {name}")),
+        None => Ok(default),
+    };
+    let (start, end) = (find(&start, imported.start)?, find(&end, imported.end)?);
+    let (name, trace) = run_algorithm(algorithm, &graph, start, end)?;
+
+    // The first of parallel edges is the one the snapshot keeps
+    let mut edge_colors: HashMap<(usize, usize), Color> = HashMap::new();
+    for edge in imported.edges.iter() {
+        edge_colors.entry((edge.from, edge.to)).or_insert(edge.attributes.color.unwrap_or(Color::WHITE));
+    }
+    let frames = RunFrames {
+        graph: &graph,
+        vertex_colors: imported.vertices.iter().map(|vertex| vertex.attributes.color.unwrap_or(Color::WHITE)).collect(),
+        edge_colors: graph.edges.iter().map(|edge| edge_colors.get(&(edge.from, edge.to)).copied().unwrap_or(Color::WHITE)).collect(),
+        name: name.to_string(),
+        steps: &trace.steps,
+        background: ClearColor::default().0,
+    }
+    .render(width);
+
+    let folder = std::path::PathBuf::from(&out);
+    std::fs::create_dir_all(&folder).map_err(|error| format!("Could not create {out}: {error}"))?;
+    let files = format.encode(&file_stem(algorithm), &frames, PlaybackSettings::default().step_seconds);
+    for (file, bytes) in files.iter() {
+        let path = folder.join(file);
+        std::fs::write(&path, bytes).map_err(|error| format!("Could not write {}: {error}", path.display()))?;
+    }
+    Ok(format!("{name}: wrote {} frames to {} files in {out}", frames.len(), files.len()))
+}
@@ -0,0 +1,237 @@
+use bevy::{color::{Color, Srgba}, math::Vec2, utils::HashMap};
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::{extra_keys, parse_color, xml::{escape, parse_xml, Element}, Attribute, AttributeMap, FileAttributes, ImportedGraph};
+
+// The <attributes> of a class, attribute ID -> (title, type, default)
+type Declarations = HashMap<String, (String, String, Option<String>)>;
+
+fn declarations(graph: &Element, class: &str) -> Declarations {
+    graph.children("attributes")
+        .filter(|attributes| attributes.attribute("class").is_some_and(|name| name.eq_ignore_ascii_case(class)))
+        .flat_map(|attributes| attributes.children("attribute"))
+        .filter_map(|attribute| {
+            let id = attribute.attribute("id")?;
+            let title = attribute.attribute("title").unwrap_or(id).to_string();
+            let kind = attribute.attribute("type").unwrap_or("string").to_string();
+            let default = attribute.child("default").map(|default| default.text.clone());
+            Some((id.to_string(), (title, kind, default)))
+        })
+        .collect()
+}
+
+// The defaults of the class, then the attvalues of the element. GEXF 1.1 names the attribute with id, later versions with for
+fn read_attributes(element: &Element, declared: &Declarations) -> FileAttributes {
+    let mut attributes = FileAttributes::default();
+    let mut defaults: Vec<_> = declared.iter().collect();
+    defaults.sort_by_key(|(id, _)| *id);
+    for (_, (title, kind, default)) in defaults {
+        let Some(default) = default else {continue};
+        attributes.set(Attribute { name: title.clone(), kind: kind.clone(), value: default.clone() });
+    }
+    for value in element.child("attvalues").into_iter().flat_map(|values| values.children("attvalue")) {
+        let id = value.attribute("for").or(value.attribute("id"));
+        let (Some((title, kind, _)), Some(value)) = (id.and_then(|id| declared.get(id)), value.attribute("value")) else {continue};
+        attributes.set(Attribute { name: title.clone(), kind: kind.clone(), value: value.to_string() });
+    }
+    if let Some(color) = element.child("color") {
+        attributes.color = color.attribute("hex").and_then(parse_color).or_else(|| {
+            let channel = |name: &str| color.attribute(name).and_then(|value| value.trim().parse::<u8>().ok());
+            let alpha = color.attribute("a").and_then(|value| value.trim().parse::<f32>().ok()).unwrap_or(1.);
+            Some(Color::srgba_u8(channel("r")?, channel("g")?, channel("b")?, (alpha * 255.).round() as u8))
+        });
+    }
+    attributes
+}
+
+/**
+    # GEXF
+    Reads the nodes and edges of a Gephi file, nodes inside nodes are flattened. The label is the name of a node,
+    viz:position its position and viz:color the color of either. The attvalues are kept with their declared
+    title and type to be written back on export, so is the label of an edge.
+*/
+pub fn parse_gexf(text: &str) -> Result<ImportedGraph, String> {
+    let root = parse_xml(text)?;
+    if !root.is("gexf") {
+        return Err(format!("Expected <gexf> but found <{}>", root.name));
+    }
+    let graph = root.child("graph").ok_or("The file has no <graph>")?;
+    let directed = graph.attribute("defaultedgetype").is_some_and(|default| default.eq_ignore_ascii_case("directed"));
+    let node_declarations = declarations(graph, "node");
+    let edge_declarations = declarations(graph, "edge");
+
+    let mut imported = ImportedGraph::default();
+    for node in graph.descendants("node") {
+        let Some(id) = node.attribute("id") else {continue};
+        let v = imported.vertex(id);
+        if let Some(label) = node.attribute("label").filter(|label| !label.trim().is_empty()) {
+            imported.vertices[v].name = label.trim().to_string();
+        }
+        imported.vertices[v].position = node.child("position").and_then(|position| {
+            let coordinate = |name: &str| position.attribute(name).and_then(|value| value.trim().parse::<f32>().ok());
+            Some(Vec2::new(coordinate("x")?, coordinate("y")?))
+        });
+        imported.vertices[v].attributes = read_attributes(node, &node_declarations);
+    }
+    for edge in graph.descendants("edge") {
+        let (Some(source), Some(target)) = (edge.attribute("source"), edge.attribute("target")) else {continue};
+        let from = imported.vertex(source);
+        let to = imported.vertex(target);
+        let directed = edge.attribute("type").map_or(directed, |kind| kind.eq_ignore_ascii_case("directed"));
+        let weight = edge.attribute("weight")
+            .and_then(|weight| weight.trim().parse::<f32>().ok())
+            .map_or(1, |weight| weight.round() as i32);
+        let mut attributes = read_attributes(edge, &edge_declarations);
+        if let Some(label) = edge.attribute("label") {
+            attributes.set(Attribute { name: "label".to_string(), kind: "string".to_string(), value: label.to_string() });
+        }
+        let e = imported.add_edge(from, to, weight, directed);
+        imported.edges[e].attributes = attributes;
+    }
+    Ok(imported)
+}
+
+// GraphML calls integers int
+fn gexf_kind(kind: &str) -> &str {
+    match kind {
+        "int" => "integer",
+        kind => kind,
+    }
+}
+
+fn write_declarations(gexf: &mut String, class: &str, extra: &[(String, String)]) {
+    if extra.is_empty() {
+        return;
+    }
+    gexf.push_str(&format!("    <attributes class=\"{class}\" mode=\"static\">\n"));
+    for (i, (name, kind)) in extra.iter().enumerate() {
+        gexf.push_str(&format!("      <attribute id=\"{i}\" title=\"{}\" type=\"{}\"/>\n", escape(name), escape(gexf_kind(kind))));
+    }
+    gexf.push_str("    </attributes>\n");
+}
+
+fn write_attributes(gexf: &mut String, attributes: Option<&FileAttributes>, extra: &[(String, String)]) {
+    let Some(attributes) = attributes else {return};
+    let values: Vec<(usize, &str)> = attributes.extra.iter()
+        .filter_map(|attribute| Some((extra.iter().position(|(name, _)| *name == attribute.name)?, attribute.value.as_str())))
+        .collect();
+    if !values.is_empty() {
+        gexf.push_str("        <attvalues>\n");
+        for (i, value) in values {
+            gexf.push_str(&format!("          <attvalue for=\"{i}\" value=\"{}\"/>\n", escape(value)));
+        }
+        gexf.push_str("        </attvalues>\n");
+    }
+    if let Some(color) = attributes.color {
+        let color = Srgba::from(color);
+        let channel = |value: f32| (value * 255.).round() as u8;
+        gexf.push_str(&format!(
+            "        <viz:color r=\"{}\" g=\"{}\" b=\"{}\" a=\"{}\"/>\n",
+            channel(color.red),
+            channel(color.green),
+            channel(color.blue),
+            color.alpha,
+        ));
+    }
+}
+
+pub fn write_gexf(graph: &GraphSnapshot, attributes: &AttributeMap) -> String {
+    let directed = graph.edges.iter().any(|edge| edge.directed);
+    let node_extra = extra_keys(graph.vertices.iter().filter_map(|vertex| attributes.get(vertex)));
+    // The label of an edge is an attribute of the element, not an attvalue
+    let mut edge_extra = extra_keys(graph.edges.iter().filter_map(|edge| attributes.get(&edge.entity)));
+    edge_extra.retain(|(name, _)| name != "label");
+
+    let mut gexf = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    gexf.push_str("<gexf xmlns=\"http://gexf.net/1.3\" xmlns:viz=\"http://gexf.net/1.3/viz\" version=\"1.3\">\n");
+    gexf.push_str(&format!("  <graph defaultedgetype=\"{}\" mode=\"static\">\n", if directed { "directed" } else { "undirected" }));
+    write_declarations(&mut gexf, "node", &node_extra);
+    write_declarations(&mut gexf, "edge", &edge_extra);
+    gexf.push_str("    <nodes>\n");
+    for v in 0..graph.len() {
+        gexf.push_str(&format!("      <node id=\"n{v}\" label=\"{}\">\n", escape(graph.name(v))));
+        write_attributes(&mut gexf, attributes.get(&graph.vertices[v]), &node_extra);
+        let position = graph.positions[v];
+        gexf.push_str(&format!("        <viz:position x=\"{:.2}\" y=\"{:.2}\" z=\"0.0\"/>\n", position.x, position.y));
+        gexf.push_str("      </node>\n");
+    }
+    gexf.push_str("    </nodes>\n    <edges>\n");
+    for (e, edge) in graph.edges.iter().enumerate() {
+        let edge_attributes = attributes.get(&edge.entity);
+        let label = edge_attributes
+            .and_then(|attributes| attributes.extra.iter().find(|attribute| attribute.name == "label"))
+            .map_or(String::new(), |label| format!(" label=\"{}\"", escape(&label.value)));
+        gexf.push_str(&format!(
+            "      <edge id=\"e{e}\" source=\"n{}\" target=\"n{}\" weight=\"{}\"{}{}>\n",
+            edge.from,
+            edge.to,
+            edge.weight,
+            if directed && !edge.directed { " type=\"undirected\"" } else { "" },
+            label,
+        ));
+        write_attributes(&mut gexf, edge_attributes, &edge_extra);
+        gexf.push_str("      </edge>\n");
+    }
+    gexf.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    gexf
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{color::Color, math::Vec2};
+
+    use crate::app::files::{snapshot_edges, tricky_attributes, tricky_graph, Attribute};
+
+    use super::{parse_gexf, write_gexf};
+
+    #[test]
+    fn round_trip() {
+        let graph = tricky_graph();
+        let mut attributes = tricky_attributes(&graph);
+        let label = Attribute { name: "label".to_string(), kind: "string".to_string(), value: "main \"road\"".to_string() };
+        attributes.get_mut(&graph.edge_entity(1)).unwrap().extra.push(label);
+        let imported = parse_gexf(&write_gexf(&graph, &attributes)).unwrap();
+        assert_eq!(imported.names(), graph.names.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(imported.edge_tuples(), snapshot_edges(&graph));
+        for (vertex, position) in imported.vertices.iter().zip(graph.positions.iter()) {
+            assert_eq!(vertex.position, Some(*position));
+        }
+        assert_eq!(imported.vertices[0].attributes.color, Some(Color::srgb_u8(255, 0, 0)));
+        assert_eq!(imported.vertices[0].attributes.extra, attributes[&graph.vertices[0]].extra);
+        assert_eq!(imported.vertices[3].attributes.extra, attributes[&graph.vertices[3]].extra);
+        assert_eq!(imported.edges[1].attributes.color, Some(Color::srgb_u8(0, 128, 255)));
+        assert_eq!(imported.edges[1].attributes.extra, attributes[&graph.edge_entity(1)].extra);
+    }
+
+    #[test]
+    fn gephi_file() {
+        let text = r##"<?xml version="1.0" encoding="UTF-8"?>
+            <gexf xmlns:viz="http://www.gexf.net/1.1draft/viz" version="1.1">
+              <graph defaultedgetype="undirected">
+                <attributes class="node"><attribute id="0" title="size" type="integer"><default>1</default></attribute></attributes>
+                <nodes>
+                  <node id="x" label="Ex">
+                    <attvalues><attvalue id="0" value="4"/></attvalues>
+                    <viz:color hex="#0000ff"/><viz:position x="1.5" y="-2"/>
+                    <nodes><node id="y" label=" "/></nodes>
+                  </node>
+                </nodes>
+                <edges><edge source="x" target="y" weight="2.4" type="directed"/></edges>
+              </graph>
+            </gexf>"##;
+        let imported = parse_gexf(text).unwrap();
+        assert_eq!(imported.names(), vec!["Ex", "y"]);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 2, true)]);
+        assert_eq!(imported.vertices[0].position, Some(Vec2::new(1.5, -2.)));
+        assert_eq!(imported.vertices[0].attributes.color, Some(Color::srgb_u8(0, 0, 255)));
+        assert_eq!(imported.vertices[0].attributes.extra[0].value, "4");
+        assert_eq!(imported.vertices[1].attributes.extra[0].value, "1");
+    }
+
+    #[test]
+    fn a_graph_is_needed() {
+        assert!(parse_gexf("<gexf version=\"1.3\"></gexf>").is_err());
+        assert!(parse_gexf("<graphml/>").is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use bevy::utils::HashMap;
+
+use super::raster::Canvas;
+
+const PALETTE_SIZE: usize = 256;
+// Pixels counted for the palette, every pixel of small frames and a regular sample of big ones
+const PALETTE_SAMPLES: usize = 1 << 20;
+const MAX_CODE: usize = 4095;
+
+/**
+    # Palette
+    The 256 most common colors of all frames. The flat colors of a drawing are by far the most common ones,
+    the blended pixels along the edges of the shapes get the closest of them.
+*/
+fn palette(frames: &[Canvas]) -> Vec<[u8; 3]> {
+    let total: usize = frames.iter().map(|frame| frame.pixels.len()).sum();
+    let stride = total.div_ceil(PALETTE_SAMPLES).max(1);
+    let mut counts: HashMap<[u8; 3], usize> = HashMap::new();
+    for pixel in frames.iter().flat_map(|frame| frame.pixels.iter()).step_by(stride) {
+        *counts.entry(*pixel).or_default() += 1;
+    }
+    let mut colors: Vec<([u8; 3], usize)> = counts.into_iter().collect();
+    colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut palette: Vec<[u8; 3]> = colors.into_iter().take(PALETTE_SIZE).map(|(color, _)| color).collect();
+    palette.resize(PALETTE_SIZE, [0, 0, 0]);
+    palette
+}
+
+fn closest(palette: &[[u8; 3]], color: [u8; 3]) -> u8 {
+    let distance = |other: &[u8; 3]| (0..3).map(|c| (color[c] as i32 - other[c] as i32).pow(2)).sum::<i32>();
+    (0..palette.len()).min_by_key(|i| distance(&palette[*i])).unwrap_or_default() as u8
+}
+
+// GIF packs the codes starting at the lowest bit
+struct CodeWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u32,
+    width: u32,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: usize) {
+        self.buffer |= (code as u32) << self.count;
+        self.count += self.width;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    // The last byte holds the bits that are left
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/**
+    # LZW
+    The variable width LZW of GIF with 8 bit pixels. Codes start 9 bits wide and grow with the table, when the
+    table is full a clear code starts a new one.
+*/
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    let clear = 1 << 8;
+    let end = clear + 1;
+    // Prefix code * 256 + pixel -> code, 0 is no entry as the first free code is above the clear code
+    let mut table = vec![0u16; (MAX_CODE + 1) << 8];
+    let mut writer = CodeWriter { bytes: vec![], buffer: 0, count: 0, width: 9 };
+    let mut high = end;
+    let mut overflow = clear << 1;
+    writer.write(clear);
+
+    let Some((first, rest)) = indices.split_first() else {
+        writer.write(end);
+        return writer.finish();
+    };
+    let mut code = *first as usize;
+    for pixel in rest {
+        let key = code << 8 | *pixel as usize;
+        if table[key] != 0 {
+            code = table[key] as usize;
+            continue;
+        }
+        writer.write(code);
+        code = *pixel as usize;
+        high += 1;
+        if high == overflow {
+            writer.width += 1;
+            overflow <<= 1;
+        }
+        if high == MAX_CODE {
+            writer.write(clear);
+            writer.width = 9;
+            high = end;
+            overflow = clear << 1;
+            table.fill(0);
+            continue;
+        }
+        table[key] = high as u16;
+    }
+    writer.write(code);
+    high += 1;
+    if high == overflow {
+        writer.width += 1;
+    }
+    if high == MAX_CODE {
+        writer.write(clear);
+        writer.width = 9;
+    }
+    writer.write(end);
+    writer.finish()
+}
+
+/**
+    # GIF
+    An animated GIF89a that loops forever, every frame stays for delay hundredths of a second. All frames share
+    one palette and have the size of the first.
+*/
+pub fn encode_gif(frames: &[Canvas], delay: u16) -> Vec<u8> {
+    let Some(first) = frames.first() else {return vec![]};
+    let (width, height) = (first.width as u16, first.height as u16);
+    let palette = palette(frames);
+    let mut gif = b"GIF89a".to_vec();
+    gif.extend(width.to_le_bytes());
+    gif.extend(height.to_le_bytes());
+    // A global color table of 2^8 colors with 8 bits per channel
+    gif.extend([0xF7, 0, 0]);
+    gif.extend(palette.iter().flatten());
+    gif.extend(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+    let mut cache: HashMap<[u8; 3], u8> = palette.iter().enumerate().map(|(i, color)| (*color, i as u8)).collect();
+    for frame in frames {
+        // Graphic control: the frame stays for the delay and is not disposed
+        gif.extend([0x21, 0xF9, 0x04, 0x04]);
+        gif.extend(delay.to_le_bytes());
+        gif.extend([0, 0]);
+        gif.push(0x2C);
+        gif.extend([0, 0, 0, 0]);
+        gif.extend(width.to_le_bytes());
+        gif.extend(height.to_le_bytes());
+        gif.push(0);
+
+        let size = first.width * first.height;
+        let indices: Vec<u8> = frame.pixels.iter()
+            .take(size)
+            .map(|pixel| *cache.entry(*pixel).or_insert_with(|| closest(&palette, *pixel)))
+            .collect();
+        gif.push(8);
+        for block in lzw(&indices).chunks(255) {
+            gif.push(block.len() as u8);
+            gif.extend(block);
+        }
+        gif.push(0);
+    }
+    gif.push(0x3B);
+    gif
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_gif, lzw, MAX_CODE};
+    use crate::app::files::raster::Canvas;
+
+    // A GIF decoder for 8 bit codes, it adds to the table and widens the codes the way GIF readers do
+    fn unlzw(bytes: &[u8]) -> Vec<u8> {
+        let (clear, end) = (256, 257);
+        let reset = || (0..=end).map(|code| vec![code as u8]).collect::<Vec<Vec<u8>>>();
+        let mut table = reset();
+        let (mut width, mut position) = (9, 0);
+        let mut previous: Option<Vec<u8>> = None;
+        let mut output = vec![];
+        loop {
+            let code = (0..width).fold(0, |code, bit| {
+                let set = bytes[(position + bit) / 8] >> ((position + bit) % 8) & 1;
+                code | (set as usize) << bit
+            });
+            position += width;
+            if code == clear {
+                table = reset();
+                width = 9;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return output;
+            }
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) if code == table.len() => [&previous[..], &previous[..1]].concat(),
+                _ => panic!("the code {code} is not in the table of {}", table.len()),
+            };
+            output.extend(&entry);
+            if let Some(previous) = previous {
+                if table.len() <= MAX_CODE {
+                    table.push([&previous[..], &entry[..1]].concat());
+                }
+            }
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        assert_eq!(unlzw(&lzw(&[])), Vec::<u8>::new());
+        assert_eq!(unlzw(&lzw(&[7])), vec![7]);
+        let run = vec![3; 100_000];
+        assert_eq!(unlzw(&lzw(&run)), run);
+    }
+
+    #[test]
+    fn lzw_clears_a_full_table() {
+        // Pseudo random pixels make a new table entry for almost every code, so the table fills up several times
+        let mut state = 12345u32;
+        let pixels: Vec<u8> = (0..60_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let compressed = lzw(&pixels);
+        assert_eq!(unlzw(&compressed), pixels);
+        // Several runs of 12 bit codes at about one code per 1.1 pixels
+        assert!(compressed.len() > 50_000);
+    }
+
+    #[test]
+    fn frames_use_the_palette() {
+        let frame = || Canvas::from_pixels(40, 30, (0..40 * 30).map(|i| [(i % 7 * 30) as u8, (i % 5 * 50) as u8, 0]).collect());
+        let gif = encode_gif(&[frame(), frame()], 20);
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(gif.last(), Some(&0x3B));
+
+        let palette: Vec<[u8; 3]> = gif[13..13 + 768].chunks(3).map(|color| [color[0], color[1], color[2]]).collect();
+        // The first frame starts after the palette, the loop extension, the graphic control and the image descriptor
+        let mut position = 13 + 768 + 19 + 8 + 10;
+        assert_eq!(gif[position], 8);
+        position += 1;
+        let mut compressed = vec![];
+        while gif[position] != 0 {
+            let length = gif[position] as usize;
+            compressed.extend(&gif[position + 1..position + 1 + length]);
+            position += length + 1;
+        }
+        let colors: Vec<[u8; 3]> = unlzw(&compressed).iter().map(|index| palette[*index as usize]).collect();
+        assert_eq!(colors, frame().pixels);
+    }
+}
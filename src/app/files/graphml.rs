@@ -0,0 +1,313 @@
+use bevy::{color::Color, math::Vec2, utils::HashMap};
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::{color_hex, extra_keys, parse_color, xml::{escape, parse_xml, Element}, Attribute, AttributeMap, FileAttributes, ImportedGraph};
+
+// A <key>, the declaration of an attribute
+struct Key {
+    domain: String,
+    name: String,
+    kind: String,
+    default: Option<String>,
+}
+
+impl Key {
+    fn applies_to(&self, domain: &str) -> bool {
+        self.domain == domain || self.domain == "all"
+    }
+}
+
+// What the data of a node or an edge say, before it is added to the graph
+#[derive(Default)]
+struct Values {
+    name: Option<String>,
+    x: Option<f32>,
+    y: Option<f32>,
+    weight: Option<i32>,
+    // Gephi writes the color as three r, g and b values
+    rgb: [Option<u8>; 3],
+    attributes: FileAttributes,
+}
+
+impl Values {
+    fn read(element: &Element, domain: &str, keys: &HashMap<String, Key>) -> Values {
+        let mut values = Values::default();
+        // Defaults first so the data of the element replace them
+        let mut defaults: Vec<(&String, &Key)> = keys.iter().filter(|(_, key)| key.applies_to(domain)).collect();
+        defaults.sort_by_key(|(id, _)| *id);
+        for (_, key) in defaults {
+            if let Some(default) = &key.default {
+                values.set(key, domain, default);
+            }
+        }
+        for data in element.children("data") {
+            let Some(key) = data.attribute("key").and_then(|id| keys.get(id)) else {continue};
+            if data.children.is_empty() {
+                values.set(key, domain, &data.text);
+            } else {
+                values.read_yed(data);
+            }
+        }
+        values
+    }
+
+    fn set(&mut self, key: &Key, domain: &str, value: &str) {
+        let number = value.trim().parse::<f32>().ok();
+        match key.name.to_ascii_lowercase().as_str() {
+            "x" if number.is_some() => self.x = number,
+            "y" if number.is_some() => self.y = number,
+            "label" | "name" if domain == "node" => self.name = Some(value.trim().to_string()),
+            "weight" if number.is_some() => self.weight = number.map(|weight| weight.round() as i32),
+            "r" | "g" | "b" if value.trim().parse::<u8>().is_ok() => {
+                let channel = "rgb".find(key.name.to_ascii_lowercase().as_str()).unwrap_or_default();
+                self.rgb[channel] = value.trim().parse().ok();
+            },
+            "color" | "colour" if parse_color(value).is_some() => self.attributes.color = parse_color(value),
+            _ => self.attributes.set(Attribute {
+                name: key.name.clone(),
+                kind: key.kind.clone(),
+                value: value.to_string(),
+            }),
+        }
+    }
+
+    // The graphics of yEd: the geometry is the top left corner with y pointing down, the fill and line have the color
+    fn read_yed(&mut self, data: &Element) {
+        for geometry in data.descendants("Geometry") {
+            let number = |name: &str| geometry.attribute(name).and_then(|value| value.parse::<f32>().ok()).unwrap_or_default();
+            self.x = Some(number("x") + number("width") / 2.);
+            self.y = Some(-(number("y") + number("height") / 2.));
+        }
+        for paint in data.descendants("Fill").into_iter().chain(data.descendants("LineStyle")) {
+            if let Some(color) = paint.attribute("color").and_then(parse_color) {
+                self.attributes.color = Some(color);
+            }
+        }
+        for label in data.descendants("NodeLabel") {
+            if !label.text.trim().is_empty() {
+                self.name = Some(label.text.trim().to_string());
+            }
+        }
+        for label in data.descendants("EdgeLabel") {
+            let text = label.text.trim();
+            match text.parse::<f32>() {
+                Ok(weight) => self.weight = self.weight.or(Some(weight.round() as i32)),
+                Err(_) if !text.is_empty() => self.attributes.set(Attribute {
+                    name: "label".to_string(),
+                    kind: "string".to_string(),
+                    value: text.to_string(),
+                }),
+                Err(_) => {},
+            }
+        }
+    }
+
+    fn position(&self) -> Option<Vec2> {
+        Some(Vec2::new(self.x?, self.y?))
+    }
+
+    fn attributes(mut self) -> FileAttributes {
+        if let [Some(r), Some(g), Some(b)] = self.rgb {
+            self.attributes.color = Some(Color::srgb_u8(r, g, b));
+        }
+        self.attributes
+    }
+}
+
+fn read_graph(graph: &Element, keys: &HashMap<String, Key>, imported: &mut ImportedGraph) {
+    let directed = graph.attribute("edgedefault").is_some_and(|default| default.eq_ignore_ascii_case("directed"));
+    for node in graph.children("node") {
+        let Some(id) = node.attribute("id") else {continue};
+        let v = imported.vertex(id);
+        let values = Values::read(node, "node", keys);
+        if let Some(name) = values.name.clone() {
+            imported.vertices[v].name = name;
+        }
+        imported.vertices[v].position = values.position();
+        imported.vertices[v].attributes = values.attributes();
+        // A node can hold a whole graph, it is flattened into this one
+        for inner in node.children("graph") {
+            read_graph(inner, keys, imported);
+        }
+    }
+    for edge in graph.children("edge") {
+        let (Some(source), Some(target)) = (edge.attribute("source"), edge.attribute("target")) else {continue};
+        let from = imported.vertex(source);
+        let to = imported.vertex(target);
+        let directed = edge.attribute("directed").map_or(directed, |directed| directed.eq_ignore_ascii_case("true"));
+        let values = Values::read(edge, "edge", keys);
+        let e = imported.add_edge(from, to, values.weight.unwrap_or(1), directed);
+        imported.edges[e].attributes = values.attributes();
+    }
+}
+
+/**
+    # GraphML
+    Reads the nodes and edges of every graph in the file, nested graphs are flattened. Data named x and y set the
+    position, label or name the name of a node, weight the weight of an edge and color the color. The graphics of yEd
+    are understood too. Every other data value is kept with its key to be written back on export.
+*/
+pub fn parse_graphml(text: &str) -> Result<ImportedGraph, String> {
+    let root = parse_xml(text)?;
+    if !root.is("graphml") {
+        return Err(format!("Expected <graphml> but found <{}>", root.name));
+    }
+    let keys: HashMap<String, Key> = root.children("key")
+        .filter_map(|key| {
+            let id = key.attribute("id")?;
+            let key = Key {
+                domain: key.attribute("for").unwrap_or("all").to_ascii_lowercase(),
+                name: key.attribute("attr.name").unwrap_or(id).to_string(),
+                kind: key.attribute("attr.type").unwrap_or("string").to_string(),
+                default: key.child("default").map(|default| default.text.clone()),
+            };
+            Some((id.to_string(), key))
+        })
+        .collect();
+    let mut imported = ImportedGraph::default();
+    for graph in root.children("graph") {
+        read_graph(graph, &keys, &mut imported);
+    }
+    Ok(imported)
+}
+
+// The types GraphML knows, GEXF has a few more
+fn graphml_kind(kind: &str) -> &str {
+    match kind {
+        "integer" => "int",
+        "int" | "long" | "float" | "double" | "boolean" | "string" => kind,
+        _ => "string",
+    }
+}
+
+fn write_data(graphml: &mut String, key: &str, value: &str) {
+    graphml.push_str(&format!("      <data key=\"{}\">{}</data>\n", escape(key), escape(value)));
+}
+
+fn write_attributes(graphml: &mut String, attributes: Option<&FileAttributes>, prefix: &str, extra: &[(String, String)]) {
+    let Some(attributes) = attributes else {return};
+    if let Some(color) = attributes.color {
+        write_data(graphml, "color", &color_hex(color));
+    }
+    for attribute in attributes.extra.iter() {
+        let Some(i) = extra.iter().position(|(name, _)| *name == attribute.name) else {continue};
+        write_data(graphml, &format!("{prefix}{i}"), &attribute.value);
+    }
+}
+
+pub fn write_graphml(graph: &GraphSnapshot, attributes: &AttributeMap) -> String {
+    let directed = graph.edges.iter().any(|edge| edge.directed);
+    let node_extra = extra_keys(graph.vertices.iter().filter_map(|vertex| attributes.get(vertex)));
+    let edge_extra = extra_keys(graph.edges.iter().filter_map(|edge| attributes.get(&edge.entity)));
+
+    let mut graphml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    graphml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    graphml.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    graphml.push_str("  <key id=\"x\" for=\"node\" attr.name=\"x\" attr.type=\"double\"/>\n");
+    graphml.push_str("  <key id=\"y\" for=\"node\" attr.name=\"y\" attr.type=\"double\"/>\n");
+    graphml.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n");
+    graphml.push_str("  <key id=\"color\" for=\"all\" attr.name=\"color\" attr.type=\"string\"/>\n");
+    for (prefix, domain, extra) in [("node", "node", &node_extra), ("edge", "edge", &edge_extra)] {
+        for (i, (name, kind)) in extra.iter().enumerate() {
+            graphml.push_str(&format!(
+                "  <key id=\"{prefix}{i}\" for=\"{domain}\" attr.name=\"{}\" attr.type=\"{}\"/>\n",
+                escape(name),
+                graphml_kind(kind),
+            ));
+        }
+    }
+    graphml.push_str(&format!("  <graph id=\"G\" edgedefault=\"{}\">\n", if directed { "directed" } else { "undirected" }));
+    for v in 0..graph.len() {
+        graphml.push_str(&format!("    <node id=\"n{v}\">\n"));
+        write_data(&mut graphml, "label", graph.name(v));
+        write_data(&mut graphml, "x", &format!("{:.2}", graph.positions[v].x));
+        write_data(&mut graphml, "y", &format!("{:.2}", graph.positions[v].y));
+        write_attributes(&mut graphml, attributes.get(&graph.vertices[v]), "node", &node_extra);
+        graphml.push_str("    </node>\n");
+    }
+    for (e, edge) in graph.edges.iter().enumerate() {
+        graphml.push_str(&format!(
+            "    <edge id=\"e{e}\" source=\"n{}\" target=\"n{}\"{}>\n",
+            edge.from,
+            edge.to,
+            if directed && !edge.directed { " directed=\"false\"" } else { "" },
+        ));
+        write_data(&mut graphml, "weight", &edge.weight.to_string());
+        write_attributes(&mut graphml, attributes.get(&edge.entity), "edge", &edge_extra);
+        graphml.push_str("    </edge>\n");
+    }
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::{color::Color, math::Vec2};
+
+    use crate::app::files::{snapshot_edges, tricky_attributes, tricky_graph, Attribute};
+
+    use super::{parse_graphml, write_graphml};
+
+    #[test]
+    fn round_trip() {
+        let graph = tricky_graph();
+        let attributes = tricky_attributes(&graph);
+        let imported = parse_graphml(&write_graphml(&graph, &attributes)).unwrap();
+        assert_eq!(imported.names(), graph.names.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(imported.edge_tuples(), snapshot_edges(&graph));
+        for (vertex, position) in imported.vertices.iter().zip(graph.positions.iter()) {
+            assert_eq!(vertex.position, Some(*position));
+        }
+        assert_eq!(imported.vertices[0].attributes.color, Some(Color::srgb_u8(255, 0, 0)));
+        assert_eq!(imported.vertices[0].attributes.extra, attributes[&graph.vertices[0]].extra);
+        assert_eq!(imported.vertices[3].attributes.extra, attributes[&graph.vertices[3]].extra);
+        assert!(imported.vertices[1].attributes.is_empty());
+        assert_eq!(imported.edges[1].attributes.color, Some(Color::srgb_u8(0, 128, 255)));
+        assert_eq!(imported.edges[1].attributes.extra, attributes[&graph.edge_entity(1)].extra);
+    }
+
+    #[test]
+    fn defaults_and_nested_graphs() {
+        let text = r#"<?xml version="1.0"?>
+            <!-- a comment -->
+            <graphml>
+              <key id="d0" for="node" attr.name="group" attr.type="int"><default>7</default></key>
+              <key id="d1" for="edge" attr.name="weight" attr.type="double"/>
+              <graph edgedefault="directed">
+                <node id="a"><data key="d0">3</data></node>
+                <node id="b">
+                  <graph edgedefault="undirected"><node id="c"/><edge source="c" target="b"/></graph>
+                </node>
+                <edge source="a" target="b" directed="false"><data key="d1">2.6</data></edge>
+                <edge source="b" target="a"/>
+              </graph>
+            </graphml>"#;
+        let imported = parse_graphml(text).unwrap();
+        assert_eq!(imported.names(), vec!["a", "b", "c"]);
+        assert_eq!(imported.edge_tuples(), vec![(2, 1, 1, false), (0, 1, 3, false), (1, 0, 1, true)]);
+        let group = |value: &str| vec![Attribute { name: "group".to_string(), kind: "int".to_string(), value: value.to_string() }];
+        assert_eq!(imported.vertices[0].attributes.extra, group("3"));
+        assert_eq!(imported.vertices[1].attributes.extra, group("7"));
+    }
+
+    #[test]
+    fn yed_graphics() {
+        let text = r##"<graphml><key id="g" for="node" yfiles.type="nodegraphics"/><graph>
+            <node id="n0"><data key="g"><y:ShapeNode>
+                <y:Geometry x="10" y="20" width="30" height="40"/><y:Fill color="#00FF00"/><y:NodeLabel>Top</y:NodeLabel>
+            </y:ShapeNode></data></node>
+        </graph></graphml>"##;
+        let imported = parse_graphml(text).unwrap();
+        assert_eq!(imported.names(), vec!["Top"]);
+        assert_eq!(imported.vertices[0].position, Some(Vec2::new(25., -40.)));
+        assert_eq!(imported.vertices[0].attributes.color, Some(Color::srgb_u8(0, 255, 0)));
+    }
+
+    #[test]
+    fn other_roots_are_errors() {
+        assert!(parse_graphml("<gexf></gexf>").is_err());
+        assert!(parse_graphml("<graphml><graph>").is_err());
+    }
+}
@@ -0,0 +1,383 @@
+use bevy::math::Vec2;
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::ImportedGraph;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // Keys in the order of the text, the first of a repeated key wins
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(super) fn get(&self, key: &str) -> Option<&Json> {
+        let Json::Object(entries) = self else {return None};
+        entries.iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    // The first of the keys that is set, for the names other tools give the same field
+    fn any(&self, keys: &[&str]) -> Option<&Json> {
+        keys.iter().find_map(|key| self.get(key)).filter(|value| **value != Json::Null)
+    }
+
+    pub(super) fn number(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            Json::String(text) => text.trim().parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("At character {}: {message}", self.position)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), String> {
+        if !self.text[self.position..].starts_with(literal) {
+            return Err(self.error(&format!("{literal} was expected")));
+        }
+        self.position += literal.len();
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Json, String> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("the values are nested too deep"));
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => {
+                self.position += 1;
+                let mut entries = vec![];
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.position += 1;
+                    return Ok(Json::Object(entries));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    entries.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.position += 1,
+                        Some('}') => {
+                            self.position += 1;
+                            return Ok(Json::Object(entries));
+                        },
+                        _ => return Err(self.error(", or } was expected")),
+                    }
+                }
+            },
+            Some('[') => {
+                self.position += 1;
+                let mut values = vec![];
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.position += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.position += 1,
+                        Some(']') => {
+                            self.position += 1;
+                            return Ok(Json::Array(values));
+                        },
+                        _ => return Err(self.error(", or ] was expected")),
+                    }
+                }
+            },
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.expect("true").map(|_| Json::Bool(true)),
+            Some('f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some('n') => self.expect("null").map(|_| Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let rest = &self.text[self.position..];
+                let length = rest.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c))).unwrap_or(rest.len());
+                let number = rest[..length].parse().map_err(|_| self.error(&format!("{} is not a number", &rest[..length])))?;
+                self.position += length;
+                Ok(Json::Number(number))
+            },
+            Some(c) => Err(self.error(&format!("{c} can't start a value"))),
+            None => Err(self.error("the text ends early")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut string = String::new();
+        loop {
+            let rest = &self.text[self.position..];
+            let Some(end) = rest.find(['"', '\\']) else {
+                return Err(self.error("a string is never closed"));
+            };
+            string.push_str(&rest[..end]);
+            self.position += end + 1;
+            if rest.as_bytes()[end] == b'"' {
+                return Ok(string);
+            }
+            let Some(escape) = self.peek() else {
+                return Err(self.error("a string is never closed"));
+            };
+            self.position += 1;
+            let character = match escape {
+                '"' | '\\' | '/' => escape,
+                'b' => '\u{8}',
+                'f' => '\u{c}',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                'u' => {
+                    let high = self.code_unit()?;
+                    // Characters outside the basic plane are a surrogate pair
+                    let code = if (0xD800..0xDC00).contains(&high) && self.text[self.position..].starts_with("\\u") {
+                        self.position += 2;
+                        let low = self.code_unit()?;
+                        0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                    } else {
+                        high
+                    };
+                    char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+                },
+                _ => return Err(self.error(&format!("\\{escape} is not an escape"))),
+            };
+            string.push(character);
+        }
+    }
+
+    fn code_unit(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.position..self.position + 4).ok_or(self.error("\\u needs four hex digits"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("\\u needs four hex digits"))?;
+        self.position += 4;
+        Ok(code)
+    }
+}
+
+// Deeper values are not a graph, the limit keeps the recursion off the end of the stack
+const MAX_DEPTH: usize = 64;
+
+pub(super) fn parse_value(text: &str) -> Result<Json, String> {
+    let mut parser = Parser { text, position: 0 };
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.position < text.len() {
+        return Err(parser.error("the text goes on after the value"));
+    }
+    Ok(value)
+}
+
+pub fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/**
+    # JSON
+    An object with a list of vertices with a name and an optional x and y, and a list of edges with the index
+    or the name of their vertices, a weight and whether they are directed, which they are unless the graph says
+    "directed": false. start and end are the algorithm targets. The nodes and links of D3 with id, source,
+    target and value are read as well.
+*/
+pub fn parse_json(text: &str) -> Result<ImportedGraph, String> {
+    graph_from_json(&parse_value(text)?)
+}
+
+pub(super) fn graph_from_json(root: &Json) -> Result<ImportedGraph, String> {
+    let Some(Json::Array(vertices)) = root.any(&["vertices", "nodes"]) else {
+        return Err("The graph has no list of vertices".to_string());
+    };
+    let directed = root.get("directed") != Some(&Json::Bool(false));
+
+    let mut graph = ImportedGraph::default();
+    for (v, vertex) in vertices.iter().enumerate() {
+        let name = match vertex.any(&["name", "id", "label"]) {
+            Some(Json::String(name)) => name.clone(),
+            Some(Json::Number(number)) => number.to_string(),
+            _ => v.to_string(),
+        };
+        // The IDs are the indices so names can repeat, lookups by name take the first vertex
+        graph.vertex(&v.to_string());
+        graph.vertices[v].name = name;
+        if let (Some(x), Some(y)) = (vertex.get("x").and_then(Json::number), vertex.get("y").and_then(Json::number)) {
+            graph.vertices[v].position = Some(Vec2::new(x as f32, y as f32));
+        }
+    }
+
+    let names: Vec<String> = graph.vertices.iter().map(|vertex| vertex.name.clone()).collect();
+    let find = |value: Option<&Json>| -> Result<Option<usize>, String> {
+        match value {
+            None => Ok(None),
+            Some(Json::Number(index)) if index.fract() == 0. && *index >= 0. && (*index as usize) < names.len() => Ok(Some(*index as usize)),
+            Some(Json::String(name)) => names.iter().position(|known| known == name).map(Some).ok_or(format!("There is no vertex {name}")),
+            Some(value) => Err(format!("{value:?} is not a vertex")),
+        }
+    };
+
+    let edges = match root.any(&["edges", "links"]) {
+        Some(Json::Array(edges)) => edges.as_slice(),
+        None => &[],
+        Some(_) => return Err("The edges are not a list".to_string()),
+    };
+    for (e, edge) in edges.iter().enumerate() {
+        let (Some(from), Some(to)) = (find(edge.any(&["from", "source"]))?, find(edge.any(&["to", "target"]))?) else {
+            return Err(format!("Edge {e} needs the vertices it connects"));
+        };
+        let weight = edge.any(&["weight", "value"]).and_then(Json::number).map_or(1, |weight| weight.round() as i32);
+        let directed = match edge.get("directed") {
+            Some(Json::Bool(directed)) => *directed,
+            _ => directed,
+        };
+        graph.add_edge(from, to, weight, directed);
+    }
+    graph.start = find(root.any(&["start", "source"]))?;
+    graph.end = find(root.any(&["end", "sink"]))?;
+    Ok(graph)
+}
+
+pub fn write_json(graph: &GraphSnapshot, start: Option<usize>, end: Option<usize>) -> String {
+    let mut json = String::from("{\n  \"vertices\": [");
+    for v in 0..graph.len() {
+        let position = graph.positions[v];
+        json.push_str(if v == 0 { "\n" } else { ",\n" });
+        json.push_str(&format!("    {{\"name\": {}, \"x\": {:.2}, \"y\": {:.2}}}", quote(graph.name(v)), position.x, position.y));
+    }
+    json.push_str("\n  ],\n  \"edges\": [");
+    for (e, edge) in graph.edges.iter().enumerate() {
+        json.push_str(if e == 0 { "\n" } else { ",\n" });
+        json.push_str(&format!(
+            "    {{\"from\": {}, \"to\": {}, \"weight\": {}, \"directed\": {}}}",
+            edge.from, edge.to, edge.weight, edge.directed,
+        ));
+    }
+    json.push_str("\n  ]");
+    for (key, target) in [("start", start), ("end", end)] {
+        if let Some(v) = target {
+            json.push_str(&format!(",\n  \"{key}\": {v}"));
+        }
+    }
+    json.push_str("\n}\n");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use crate::app::algorithms::graph::GraphSnapshot;
+    use crate::app::files::{snapshot_edges, tricky_graph};
+
+    use super::{parse_json, parse_value, quote, write_json, Json};
+
+    #[test]
+    fn round_trip() {
+        let graph = tricky_graph();
+        let imported = parse_json(&write_json(&graph, Some(2), Some(5))).unwrap();
+        assert_eq!(imported.names(), graph.names.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(imported.edge_tuples(), snapshot_edges(&graph));
+        for (vertex, position) in imported.vertices.iter().zip(graph.positions.iter()) {
+            assert_eq!(vertex.position, Some(*position));
+        }
+        assert_eq!((imported.start, imported.end), (Some(2), Some(5)));
+    }
+
+    #[test]
+    fn empty_graph_round_trip() {
+        let graph = GraphSnapshot::test_graph(0, &[]);
+        let imported = parse_json(&write_json(&graph, None, None)).unwrap();
+        assert!(imported.vertices.is_empty() && imported.edges.is_empty());
+        assert_eq!((imported.start, imported.end), (None, None));
+    }
+
+    #[test]
+    fn d3_nodes_and_links() {
+        let text = r#"{
+            "directed": false,
+            "nodes": [{"id": "a", "x": "1.5", "y": -2}, {"id": 7}, {"label": "c"}],
+            "links": [{"source": "a", "target": 1, "value": 2.6}, {"source": 1, "target": "c", "directed": true}]
+        }"#;
+        let imported = parse_json(text).unwrap();
+        assert_eq!(imported.names(), vec!["a", "7", "c"]);
+        assert_eq!(imported.vertices[0].position, Some(Vec2::new(1.5, -2.)));
+        assert_eq!(imported.vertices[1].position, None);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 3, false), (1, 2, 1, true)]);
+        // Numbers are indices and not names
+        assert!(parse_json(r#"{"nodes": [{"id": 7}], "links": [{"source": 7, "target": 0}]}"#).is_err());
+    }
+
+    #[test]
+    fn strings_and_escapes() {
+        let text = "say \"hi\" \\ \n\t\u{1} Zoë 🎈";
+        assert_eq!(parse_value(&quote(text)).unwrap(), Json::String(text.to_string()));
+        assert_eq!(parse_value(r#""é🎈\/""#).unwrap(), Json::String("é🎈/".to_string()));
+        assert!(parse_value(r#""\q""#).is_err());
+        assert!(parse_value(r#""\u12""#).is_err());
+        assert!(parse_value("\"never closed").is_err());
+    }
+
+    #[test]
+    fn values() {
+        let value = parse_value(r#" {"a": [1, -2.5e1, true, null], "a": false, "b": {}} "#).unwrap();
+        assert_eq!(value.get("a"), Some(&Json::Array(vec![Json::Number(1.), Json::Number(-25.), Json::Bool(true), Json::Null])));
+        assert_eq!(value.get("b"), Some(&Json::Object(vec![])));
+        assert!(parse_value("[1, 2] 3").is_err());
+        assert!(parse_value("[1 2]").is_err());
+        assert!(parse_value("{\"a\" 1}").is_err());
+        assert!(parse_value("").is_err());
+        assert!(parse_value("-").is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        assert!(parse_value(&format!("{}{}", "[".repeat(60), "]".repeat(60))).is_ok());
+        assert!(parse_value(&format!("{}{}", "[".repeat(10_000), "]".repeat(10_000))).is_err());
+    }
+
+    #[test]
+    fn broken_graphs_are_errors() {
+        assert!(parse_json("[]").is_err());
+        assert!(parse_json(r#"{"vertices": [{}], "edges": {}}"#).is_err());
+        assert!(parse_json(r#"{"vertices": [{}], "edges": [{"from": 0}]}"#).is_err());
+        assert!(parse_json(r#"{"vertices": [{}], "edges": [{"from": 0, "to": 1.5}]}"#).is_err());
+        assert!(parse_json(r#"{"vertices": [{"name": "a"}], "edges": [{"from": "a", "to": "b"}]}"#).is_err());
+    }
+}
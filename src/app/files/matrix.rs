@@ -0,0 +1,175 @@
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::ImportedGraph;
+
+// The cells of a line, quoted cells can hold the delimiter and "" is a quote
+fn split_row(line: &str, delimiter: char) -> Vec<String> {
+    let mut cells = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let cell = cells.last_mut().expect("there is always a cell");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            },
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => cells.push(String::new()),
+            c => cell.push(c),
+        }
+    }
+    cells.into_iter().map(|cell| cell.trim().to_string()).collect()
+}
+
+fn is_number(cell: &str) -> bool {
+    cell.parse::<f32>().is_ok()
+}
+
+/**
+    # Adjacency Matrix
+    A square matrix of weights separated by commas, semicolons or tabs where 0 or an empty cell is no edge.
+    A first row or a first column of names labels the vertices. Entries mirrored with the same weight
+    are one undirected edge, every other entry is a directed edge from the row to the column.
+*/
+pub fn parse_matrix(text: &str) -> Result<ImportedGraph, String> {
+    let first = text.lines().find(|line| !line.trim().is_empty()).unwrap_or_default();
+    let delimiter = [',', ';', '\t'].into_iter().find(|delimiter| first.contains(*delimiter)).unwrap_or(',');
+    let mut rows: Vec<Vec<String>> = text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| split_row(line, delimiter))
+        .collect();
+
+    let header = rows.first()
+        .filter(|row| row.iter().skip(1).any(|cell| !cell.is_empty() && !is_number(cell)))
+        .cloned();
+    if header.is_some() {
+        rows.remove(0);
+    }
+    let n = rows.len();
+    let mut names: Vec<String> = match &header {
+        // The cell above a column of names is left out
+        Some(header) if header.len() == n + 1 => header[1..].to_vec(),
+        Some(header) => header.clone(),
+        None => vec![],
+    };
+    names.resize(n, String::new());
+
+    let mut weights = vec![vec![0.; n]; n];
+    for (i, row) in rows.iter().enumerate() {
+        let labelled = row.first().is_some_and(|cell| !is_number(cell)) && (row.len() == n + 1 || !row[0].is_empty());
+        let values = if labelled {
+            if names[i].is_empty() {
+                names[i] = row[0].clone();
+            }
+            &row[1..]
+        } else {
+            &row[..]
+        };
+        if values.len() != n {
+            return Err(format!("Row {} has {} values but the matrix has {} rows", i + 1, values.len(), n));
+        }
+        for (j, value) in values.iter().enumerate() {
+            if !value.is_empty() {
+                weights[i][j] = value.parse::<f32>().map_err(|_| format!("Row {}: {value} is not a number", i + 1))?;
+            }
+        }
+    }
+
+    let mut graph = ImportedGraph::default();
+    let vertices: Vec<usize> = names.iter().enumerate()
+        .map(|(i, name)| if name.is_empty() { graph.vertex(&format!("v{i}")) } else { graph.vertex(name) })
+        .collect();
+    if vertices.iter().enumerate().any(|(i, v)| *v != i) {
+        return Err("Two vertices have the same name".to_string());
+    }
+    for (i, row) in weights.iter().enumerate() {
+        for (j, &weight) in row.iter().enumerate() {
+            if i == j || weight == 0. || (j < i && weights[j][i] == weight) {
+                continue;
+            }
+            let directed = weights[j][i] != weight;
+            graph.add_edge(i, j, weight.round() as i32, directed);
+        }
+    }
+    Ok(graph)
+}
+
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+pub fn write_matrix(graph: &GraphSnapshot) -> String {
+    let n = graph.len();
+    let mut weights = vec![vec![0; n]; n];
+    for edge in graph.edges.iter() {
+        weights[edge.from][edge.to] = edge.weight;
+        if !edge.directed {
+            weights[edge.to][edge.from] = edge.weight;
+        }
+    }
+    let mut csv: String = (0..n).map(|v| format!(",{}", quote(graph.name(v)))).collect();
+    csv.push('\n');
+    for (v, row) in weights.iter().enumerate() {
+        csv.push_str(&quote(graph.name(v)));
+        for weight in row {
+            csv.push_str(&format!(",{weight}"));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::graph::GraphSnapshot;
+    use crate::app::files::tricky_graph;
+
+    use super::{parse_matrix, write_matrix};
+
+    #[test]
+    fn round_trip() {
+        let mut graph = GraphSnapshot::test_graph(4, &[(0, 1, 5, false), (1, 2, -3, true), (2, 3, 2, false), (3, 0, 7, true)]);
+        graph.names[2] = "with, comma and \"quote\"".to_string();
+        let imported = parse_matrix(&write_matrix(&graph)).unwrap();
+        assert_eq!(imported.names(), vec!["v0", "v1", "with, comma and \"quote\"", "v3"]);
+        assert_eq!(imported.edge_tuples(), vec![(0, 1, 5, false), (1, 2, -3, true), (2, 3, 2, false), (3, 0, 7, true)]);
+    }
+
+    #[test]
+    fn repeated_names_are_errors() {
+        assert!(parse_matrix(&write_matrix(&tricky_graph())).is_err());
+    }
+
+    #[test]
+    fn without_a_header() {
+        // Empty cells are no edge and don't make the first row a header
+        let imported = parse_matrix("0;;2\n;0;\n2;;0\n").unwrap();
+        assert_eq!(imported.names(), vec!["v0", "v1", "v2"]);
+        assert_eq!(imported.edge_tuples(), vec![(0, 2, 2, false)]);
+
+        let directed = parse_matrix("0\t1\n3\t0").unwrap();
+        assert_eq!(directed.edge_tuples(), vec![(0, 1, 1, true), (1, 0, 3, true)]);
+    }
+
+    #[test]
+    fn names_in_a_row_or_a_column() {
+        let top = parse_matrix("a,b\n0,1\n0,0\n").unwrap();
+        assert_eq!(top.names(), vec!["a", "b"]);
+        assert_eq!(top.edge_tuples(), vec![(0, 1, 1, true)]);
+
+        let left = parse_matrix("a,0,4\nb,4,0\n").unwrap();
+        assert_eq!(left.names(), vec!["a", "b"]);
+        assert_eq!(left.edge_tuples(), vec![(0, 1, 4, false)]);
+    }
+
+    #[test]
+    fn ragged_rows_are_errors() {
+        assert!(parse_matrix("0,1\n1,0,0\n").is_err());
+        assert!(parse_matrix("0,x\n1,0\n").is_err());
+    }
+}
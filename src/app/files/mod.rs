@@ -0,0 +1,595 @@
+mod dimacs;
+mod deflate;
+mod dot;
+mod edge_list;
+mod font;
+mod frames;
+mod gif;
+mod gexf;
+mod graphml;
+pub mod json;
+mod matrix;
+mod png;
+mod raster;
+pub mod session;
+mod share;
+mod vector;
+mod xml;
+
+use std::sync::{atomic::{AtomicBool, Ordering}, Mutex};
+
+use bevy::{app::{Plugin, Update}, asset::Assets, color::{Color, Srgba}, math::Vec2, prelude::{Component, Entity, EventReader, Query, Res, ResMut, Transform, With}, sprite::ColorMaterial, utils::HashMap, window::FileDragAndDrop};
+
+use crate::app::{algorithms::{graph::{GraphData, GraphSnapshot}, playback::AlgorithmRun, steps::Trace, targets::AlgorithmTargets}, build_graph::{builder::GraphBuilder, components::{BaseMaterial, OwnMaterial, Vertex}}, camera::MainCamera, layout::{force::settle_layout, SIBLING_DISTANCE}, ui::{InfoPanel, MenuAppExt}};
+
+use dimacs::{parse_dimacs, write_dimacs, Problem};
+use dot::{parse_dot, write_dot};
+use edge_list::{parse_edge_list, write_edge_list};
+use frames::{FrameFormat, RunFrameData, DEFAULT_WIDTH};
+use gexf::{parse_gexf, write_gexf};
+use graphml::{parse_graphml, write_graphml};
+use json::{parse_json, write_json};
+use matrix::{parse_matrix, write_matrix};
+use session::parse_session;
+use share::{parse_link, write_link};
+use vector::{write_svg, write_tikz, DrawingData};
+
+pub use frames::file_stem;
+#[cfg(not(target_arch = "wasm32"))]
+pub use frames::render_headless;
+#[cfg(target_arch = "wasm32")]
+pub use share::{base64url, parse_base64url, LINK_PREFIX};
+
+pub struct FilesPlugin;
+impl Plugin for FilesPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_systems(Update, (
+            queue_dropped_files,
+            import_queued_files,
+            share_requested_link,
+        ))
+        .add_menu_button("File", "Export DOT", export_dot)
+        .add_menu_button("File", "Export GraphML", export_graphml)
+        .add_menu_button("File", "Export GEXF", export_gexf)
+        .add_menu_button("File", "Export JSON", export_json)
+        .add_menu_button("File", "Export edge list", |graph: GraphData, mut info: ResMut<InfoPanel>| {
+            export(&graph.snapshot(), "graph.txt", write_edge_list, &mut info);
+        })
+        .add_menu_button("File", "Export adjacency matrix", |graph: GraphData, mut info: ResMut<InfoPanel>| {
+            export(&graph.snapshot(), "graph.csv", write_matrix, &mut info);
+        })
+        .add_menu_button("File", "Export DIMACS", export_dimacs)
+        .add_menu_button("File", "Copy share link", |graph: GraphData, targets: Res<AlgorithmTargets>, mut info: ResMut<InfoPanel>| {
+            share_link(&graph.snapshot(), &targets, &mut info);
+        })
+        .add_menu_button("File", "Export SVG", |drawing: DrawingData, mut info: ResMut<InfoPanel>| {
+            save_and_show("graph.svg", write_svg(&drawing.drawing(false)).as_bytes(), &mut info);
+        })
+        .add_menu_button("File", "Export SVG of the view", |drawing: DrawingData, mut info: ResMut<InfoPanel>| {
+            save_and_show("view.svg", write_svg(&drawing.drawing(true)).as_bytes(), &mut info);
+        })
+        .add_menu_button("File", "Export TikZ", |drawing: DrawingData, mut info: ResMut<InfoPanel>| {
+            save_and_show("graph.tex", write_tikz(&drawing.drawing(false)).as_bytes(), &mut info);
+        })
+        .add_menu_button("File", "Export TikZ of the view", |drawing: DrawingData, mut info: ResMut<InfoPanel>| {
+            save_and_show("view.tex", write_tikz(&drawing.drawing(true)).as_bytes(), &mut info);
+        })
+        .add_menu_button("File", "Export run as PNG frames", |frames: RunFrameData, mut info: ResMut<InfoPanel>| {
+            export_frames(&frames, FrameFormat::Png, &mut info);
+        })
+        .add_menu_button("File", "Export run as GIF", |frames: RunFrameData, mut info: ResMut<InfoPanel>| {
+            export_frames(&frames, FrameFormat::Gif, &mut info);
+        })
+        .add_menu_button("File", "Export run as APNG", |frames: RunFrameData, mut info: ResMut<InfoPanel>| {
+            export_frames(&frames, FrameFormat::Apng, &mut info);
+        })
+        ;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Format {
+    Dot,
+    GraphMl,
+    Gexf,
+    EdgeList,
+    Matrix,
+    Dimacs,
+    Json,
+    Link,
+    Session,
+}
+
+impl Format {
+    const ALL: [Format; 9] = [Format::Dot, Format::GraphMl, Format::Gexf, Format::EdgeList, Format::Matrix, Format::Dimacs, Format::Json, Format::Link, Format::Session];
+
+    fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Format::Dot => &["dot", "gv"],
+            Format::GraphMl => &["graphml", "xml"],
+            Format::Gexf => &["gexf"],
+            Format::EdgeList => &["txt", "edges", "edgelist", "el"],
+            Format::Matrix => &["csv"],
+            Format::Dimacs => &["dimacs", "gr", "col", "max", "clq"],
+            Format::Json => &["json"],
+            Format::Link => &["link"],
+            Format::Session => &["session"],
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Format> {
+        let extension = name.rsplit_once('.')?.1.to_ascii_lowercase();
+        Format::ALL.into_iter().find(|format| format.extensions().contains(&extension.as_str()))
+    }
+
+    fn parse(&self, text: &str) -> Result<ImportedGraph, String> {
+        match self {
+            Format::Dot => parse_dot(text),
+            Format::GraphMl => parse_graphml(text),
+            Format::Gexf => parse_gexf(text),
+            Format::EdgeList => parse_edge_list(text),
+            Format::Matrix => parse_matrix(text),
+            Format::Dimacs => parse_dimacs(text),
+            Format::Json => parse_json(text),
+            Format::Link => parse_link(text),
+            Format::Session => parse_session(text).map(|session| session.graph),
+        }
+    }
+}
+
+// A typed value of a vertex or an edge, the type is the one of the file like int, double or string
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub kind: String,
+    pub value: String,
+}
+
+/**
+    # File Attributes
+    What a file said about a vertex or an edge beyond its name, position and weight. The color is shown,
+    the rest is only kept to be written back when the graph is exported again.
+*/
+#[derive(Component, Clone, Default, Debug)]
+pub struct FileAttributes {
+    pub color: Option<Color>,
+    pub extra: Vec<Attribute>,
+}
+
+impl FileAttributes {
+    fn is_empty(&self) -> bool {
+        self.color.is_none() && self.extra.is_empty()
+    }
+
+    // A later value of the same name replaces the earlier one
+    pub fn set(&mut self, attribute: Attribute) {
+        match self.extra.iter_mut().find(|known| known.name == attribute.name) {
+            Some(known) => *known = attribute,
+            None => self.extra.push(attribute),
+        }
+    }
+}
+
+// #rgb, #rrggbb, #rrggbbaa or r,g,b from 0 to 255
+pub fn parse_color(text: &str) -> Option<Color> {
+    let text = text.trim();
+    if text.contains(',') {
+        let channels: Vec<u8> = text.split(',').map(|channel| channel.trim().parse().ok()).collect::<Option<_>>()?;
+        let [r, g, b] = channels[..] else {return None};
+        return Some(Color::srgb_u8(r, g, b));
+    }
+    Srgba::hex(text).ok().map(Color::from)
+}
+
+pub fn color_hex(color: Color) -> String {
+    Srgba::from(color).to_hex()
+}
+
+// The files of every vertex and edge that has them
+pub type AttributeMap = HashMap<Entity, FileAttributes>;
+
+// The names and types of the extra attributes in the order they are first seen
+pub fn extra_keys<'a>(attributes: impl Iterator<Item = &'a FileAttributes>) -> Vec<(String, String)> {
+    let mut keys: Vec<(String, String)> = vec![];
+    for attribute in attributes.flat_map(|attributes| attributes.extra.iter()) {
+        if !keys.iter().any(|(name, _)| *name == attribute.name) {
+            keys.push((attribute.name.clone(), attribute.kind.clone()));
+        }
+    }
+    keys
+}
+
+pub struct ImportedVertex {
+    pub name: String,
+    // None places the vertex with the others that have no position
+    pub position: Option<Vec2>,
+    pub attributes: FileAttributes,
+}
+
+pub struct ImportedEdge {
+    pub from: usize,
+    pub to: usize,
+    pub weight: i32,
+    pub directed: bool,
+    pub attributes: FileAttributes,
+}
+
+// A graph read from a file, vertices are numbered in the order they first appear
+#[derive(Default)]
+pub struct ImportedGraph {
+    pub vertices: Vec<ImportedVertex>,
+    pub edges: Vec<ImportedEdge>,
+    // The source and sink of a flow problem become the algorithm targets
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+    // File ID -> vertex
+    ids: HashMap<String, usize>,
+}
+
+impl ImportedGraph {
+    // The vertex with this ID in the file, a new one the first time
+    pub fn vertex(&mut self, id: &str) -> usize {
+        if let Some(v) = self.ids.get(id) {
+            return *v;
+        }
+        self.vertices.push(ImportedVertex { name: id.to_string(), position: None, attributes: FileAttributes::default() });
+        self.ids.insert(id.to_string(), self.vertices.len() - 1);
+        self.vertices.len() - 1
+    }
+
+    pub fn add_edge(&mut self, from: usize, to: usize, weight: i32, directed: bool) -> usize {
+        self.edges.push(ImportedEdge { from, to, weight, directed, attributes: FileAttributes::default() });
+        self.edges.len() - 1
+    }
+}
+
+// Whether an imported graph takes the place of the drawn one or is added next to it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Import {
+    Replace,
+    Add,
+}
+
+// Files waiting to be imported, dropped on the window or handed over by the page
+static QUEUED_FILES: Mutex<Vec<(String, Vec<u8>, Import)>> = Mutex::new(vec![]);
+
+pub fn queue_file(name: String, contents: Vec<u8>, import: Import) {
+    if let Ok(mut queue) = QUEUED_FILES.lock() {
+        queue.push((name, contents, import));
+    }
+}
+
+// The name a shared link is imported under
+pub const SHARED_LINK: &str = "shared.link";
+
+// The page asked for a link to the graph, it is made on the next frame
+static LINK_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_arch = "wasm32")]
+pub fn request_link() {
+    LINK_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+fn queue_dropped_files(
+    mut events: EventReader<FileDragAndDrop>,
+    mut info: ResMut<InfoPanel>,
+) {
+    for event in events.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {continue};
+        let name = path_buf.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        match std::fs::read(path_buf) {
+            Ok(contents) => queue_file(name, contents, Import::Add),
+            Err(error) => info.text = format!("Could not read {name}: {error}"),
+        }
+    }
+}
+
+/**
+    # Import
+    Reads the queued files. Dropped and picked files are added right of the drawn graph like the library graphs,
+    sessions, shared links and graphs loaded by the page replace it. Vertices keep the positions of the file,
+    the ones without a position go on a circle next to them.
+*/
+fn import_queued_files(
+    mut builder: GraphBuilder,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    q_camera: Query<&Transform, With<MainCamera>>,
+    q_vertex: Query<&Transform, With<Vertex>>,
+    mut run: ResMut<AlgorithmRun>,
+    mut targets: ResMut<AlgorithmTargets>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let files: Vec<(String, Vec<u8>, Import)> = match QUEUED_FILES.lock() {
+        Ok(mut queue) if !queue.is_empty() => std::mem::take(&mut *queue),
+        _ => return,
+    };
+    // Vertices added by an earlier file of this frame aren't spawned yet, their positions are tracked here
+    let mut drawn: Vec<Vec2> = q_vertex.iter().map(|transform| transform.translation.truncate()).collect();
+    for (name, contents, import) in files {
+        let Some(format) = Format::from_name(&name) else {
+            info.text = format!("{name} has an unknown file extension");
+            continue;
+        };
+        let imported = match format.parse(&String::from_utf8_lossy(&contents)) {
+            Ok(imported) => imported,
+            Err(error) => {
+                info.text = format!("Could not import {name}: {error}");
+                continue;
+            },
+        };
+        let center = q_camera.get_single().map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+        let mut positions = place_missing(&imported, center);
+        if import == Import::Replace {
+            builder.clear();
+            run.start("", Trace::new());
+            drawn.clear();
+        } else {
+            place_beside(&drawn, &mut positions);
+        }
+        drawn.extend(positions.iter().copied());
+        let vertices = builder.add_named_vertices(imported.vertices.iter().zip(positions).map(|(vertex, position)| (position, vertex.name.clone())).collect());
+        for (vertex, entity) in imported.vertices.iter().zip(vertices.iter()) {
+            attach(&mut builder, &mut materials, *entity, &vertex.attributes);
+        }
+        // An added graph only moves the targets it brings along
+        if import == Import::Replace || imported.start.is_some() {
+            targets.start = imported.start.map(|v| vertices[v]);
+        }
+        if import == Import::Replace || imported.end.is_some() {
+            targets.end = imported.end.map(|v| vertices[v]);
+        }
+        let mut edges = 0;
+        for edge in imported.edges.iter() {
+            let Some(entity) = builder.add_edge(vertices[edge.from], vertices[edge.to], edge.weight, edge.directed) else {continue};
+            attach(&mut builder, &mut materials, entity, &edge.attributes);
+            edges += 1;
+        }
+        let verb = if import == Import::Replace { "Imported" } else { "Added" };
+        info.text = format!("{verb} {} with {} vertices and {} edges", name, vertices.len(), edges);
+    }
+}
+
+// Colored entities get their own material, an algorithm run paints over it and goes back to it when it is reset
+fn attach(builder: &mut GraphBuilder, materials: &mut Assets<ColorMaterial>, entity: Entity, attributes: &FileAttributes) {
+    if attributes.is_empty() {
+        return;
+    }
+    let mut entity_commands = builder.commands.entity(entity);
+    entity_commands.insert(attributes.clone());
+    if let Some(color) = attributes.color {
+        let material = materials.add(ColorMaterial::from_color(color));
+        entity_commands.insert((BaseMaterial(material.clone()), OwnMaterial(material)));
+    }
+}
+
+// Vertices without a position are laid out by force with the placed ones pinned, graphs too big for that get a grid
+const SETTLE_LIMIT: usize = 1000;
+const SETTLE_ITERATIONS: usize = 200;
+
+// The vertices without a position start on a circle right of the placed ones, or around the center if none is placed
+fn place_missing(imported: &ImportedGraph, center: Vec2) -> Vec<Vec2> {
+    let placed: Vec<Vec2> = imported.vertices.iter().filter_map(|vertex| vertex.position).collect();
+    let missing = imported.vertices.len() - placed.len();
+    if missing == 0 {
+        return placed;
+    }
+    let settle = missing <= SETTLE_LIMIT;
+    let columns = (missing as f32).sqrt().ceil() as usize;
+    let radius = if settle {
+        (missing as f32 * SIBLING_DISTANCE / std::f32::consts::TAU).max(SIBLING_DISTANCE)
+    } else {
+        columns as f32 * SIBLING_DISTANCE / 2.
+    };
+    let free_center = if placed.is_empty() {
+        center
+    } else {
+        let max = placed.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max);
+        let min = placed.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min);
+        Vec2::new(max.x + SIBLING_DISTANCE + radius, (min.y + max.y) / 2.)
+    };
+    let mut next = 0;
+    let positions = imported.vertices.iter()
+        .map(|vertex| vertex.position.unwrap_or_else(|| {
+            let position = if settle {
+                let angle = std::f32::consts::FRAC_PI_2 - next as f32 * std::f32::consts::TAU / missing as f32;
+                free_center + Vec2::from_angle(angle) * radius
+            } else {
+                free_center + (Vec2::new((next % columns) as f32, -((next / columns) as f32)) * SIBLING_DISTANCE - Vec2::new(radius, -radius))
+            };
+            next += 1;
+            position
+        }))
+        .collect();
+    if !settle || imported.edges.is_empty() {
+        return positions;
+    }
+    let pinned: Vec<bool> = imported.vertices.iter().map(|vertex| vertex.position.is_some()).collect();
+    let edges: Vec<(usize, usize)> = imported.edges.iter().map(|edge| (edge.from, edge.to)).collect();
+    settle_layout(positions, &edges, &pinned, SETTLE_ITERATIONS)
+}
+
+// Moves the positions right of the drawn vertices, centered on them vertically
+fn place_beside(drawn: &[Vec2], positions: &mut [Vec2]) {
+    if drawn.is_empty() || positions.is_empty() {
+        return;
+    }
+    let bounds = |points: &[Vec2]| {
+        let min = points.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min);
+        let max = points.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max);
+        (min, max)
+    };
+    let (drawn_min, drawn_max) = bounds(drawn);
+    let (min, max) = bounds(positions);
+    let shift = Vec2::new(
+        drawn_max.x + 2. * SIBLING_DISTANCE - min.x,
+        (drawn_min.y + drawn_max.y) / 2. - (min.y + max.y) / 2.,
+    );
+    positions.iter_mut().for_each(|position| *position += shift);
+}
+
+// Native builds write to the exports folder, the browser downloads the file. Both results are a message for the info panel
+fn save(name: &str, contents: &[u8]) -> Result<String, String> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        crate::app::platform::save_file(name, contents)
+            .map(|path| format!("Saved {}", path.display()))
+            .map_err(|error| format!("Could not save {name}: {error}"))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::wasm_module::download_js(name, contents);
+        Ok(format!("Downloading {name}"))
+    }
+}
+
+fn save_and_show(name: &str, contents: &[u8], info: &mut InfoPanel) {
+    info.text = match save(name, contents) {
+        Ok(message) | Err(message) => message,
+    };
+}
+
+fn export(graph: &GraphSnapshot, name: &str, write: fn(&GraphSnapshot) -> String, info: &mut InfoPanel) {
+    save_and_show(name, write(graph).as_bytes(), info);
+}
+
+fn export_dot(graph: GraphData, mut info: ResMut<InfoPanel>) {
+    export(&graph.snapshot(), "graph.dot", write_dot, &mut info);
+}
+
+// The targets as indices of the snapshot
+pub fn target_indices(graph: &GraphSnapshot, targets: &AlgorithmTargets) -> (Option<usize>, Option<usize>) {
+    let start = targets.start.and_then(|start| graph.index.get(&start).copied());
+    let end = targets.end.and_then(|end| graph.index.get(&end).copied());
+    (start, end)
+}
+
+fn export_json(graph: GraphData, targets: Res<AlgorithmTargets>, mut info: ResMut<InfoPanel>) {
+    let graph = graph.snapshot();
+    let (start, end) = target_indices(&graph, &targets);
+    save_and_show("graph.json", write_json(&graph, start, end).as_bytes(), &mut info);
+}
+
+// A max flow problem when both targets are set, the targets are its source and sink
+fn export_dimacs(graph: GraphData, targets: Res<AlgorithmTargets>, mut info: ResMut<InfoPanel>) {
+    let graph = graph.snapshot();
+    let (start, end) = target_indices(&graph, &targets);
+    let name = format!("graph.{}", Problem::of(&graph, start, end).extension());
+    save_and_show(&name, write_dimacs(&graph, start, end).as_bytes(), &mut info);
+}
+
+// The browser puts the link in the address bar and the clipboard, native builds save it to open it with --link
+fn share_link(graph: &GraphSnapshot, targets: &AlgorithmTargets, info: &mut InfoPanel) {
+    let (start, end) = target_indices(graph, targets);
+    let fragment = write_link(graph, start, end);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    save_and_show("graph.link", fragment.as_bytes(), info);
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        crate::wasm_module::copy_link_js(&fragment);
+        info.text = format!("Copied a link to the graph with {} vertices", graph.len());
+    }
+}
+
+fn share_requested_link(graph: GraphData, targets: Res<AlgorithmTargets>, mut info: ResMut<InfoPanel>) {
+    if LINK_REQUESTED.swap(false, Ordering::Relaxed) {
+        share_link(&graph.snapshot(), &targets, &mut info);
+    }
+}
+
+fn attribute_map(q_attributes: &Query<(Entity, &FileAttributes)>) -> AttributeMap {
+    q_attributes.iter().map(|(entity, attributes)| (entity, attributes.clone())).collect()
+}
+
+fn export_graphml(graph: GraphData, q_attributes: Query<(Entity, &FileAttributes)>, mut info: ResMut<InfoPanel>) {
+    let contents = write_graphml(&graph.snapshot(), &attribute_map(&q_attributes));
+    save_and_show("graph.graphml", contents.as_bytes(), &mut info);
+}
+
+fn export_gexf(graph: GraphData, q_attributes: Query<(Entity, &FileAttributes)>, mut info: ResMut<InfoPanel>) {
+    let contents = write_gexf(&graph.snapshot(), &attribute_map(&q_attributes));
+    save_and_show("graph.gexf", contents.as_bytes(), &mut info);
+}
+
+// The whole run from its first step, whatever step the playback is at
+fn export_frames(frames: &RunFrameData, format: FrameFormat, info: &mut InfoPanel) {
+    let (name, canvases) = frames.frames(DEFAULT_WIDTH);
+    let files = format.encode(&name, &canvases, frames.settings.step_seconds);
+    for (file, contents) in files.iter() {
+        match save(file, contents) {
+            Ok(message) => info.text = message,
+            Err(error) => {
+                info.text = error;
+                return;
+            },
+        }
+    }
+    if files.len() > 1 {
+        info.text = format!("Saved {} frames of {name}", files.len());
+    }
+}
+
+#[cfg(test)]
+impl ImportedGraph {
+    pub fn names(&self) -> Vec<&str> {
+        self.vertices.iter().map(|vertex| vertex.name.as_str()).collect()
+    }
+
+    // The edges like the test graphs take them
+    pub fn edge_tuples(&self) -> Vec<(usize, usize, i32, bool)> {
+        self.edges.iter().map(|edge| (edge.from, edge.to, edge.weight, edge.directed)).collect()
+    }
+}
+
+// What the writers find hard: repeated names, quotes, backslashes, markup, negative weights and both kinds of edges
+#[cfg(test)]
+pub fn tricky_graph() -> GraphSnapshot {
+    let names = ["a", "a", "say \"hi\"", "ends in \\", "<b>&amp;", "Zoë 2"].map(str::to_string).to_vec();
+    let positions = (0..names.len()).map(|v| Vec2::new(v as f32 * 50., -(v as f32) * 25.)).collect();
+    let edges = [(0, 1, 5, false), (1, 2, -3, true), (2, 3, 0, false), (3, 0, 7, true), (4, 5, 1, false), (5, 0, -12, true)];
+    GraphSnapshot::detached(names, positions, &edges)
+}
+
+#[cfg(test)]
+pub fn snapshot_edges(graph: &GraphSnapshot) -> Vec<(usize, usize, i32, bool)> {
+    graph.edges.iter().map(|edge| (edge.from, edge.to, edge.weight, edge.directed)).collect()
+}
+
+// Attributes for the tricky graph: a color on a vertex and an edge and extra values of both
+#[cfg(test)]
+pub fn tricky_attributes(graph: &GraphSnapshot) -> AttributeMap {
+    let extra = |name: &str, kind: &str, value: &str| Attribute { name: name.to_string(), kind: kind.to_string(), value: value.to_string() };
+    let mut attributes = AttributeMap::default();
+    attributes.insert(graph.vertices[0], FileAttributes { color: Some(Color::srgb_u8(255, 0, 0)), extra: vec![extra("club", "string", "<A & B>")] });
+    attributes.insert(graph.vertices[3], FileAttributes { color: None, extra: vec![extra("score", "double", "2.5")] });
+    attributes.insert(graph.edge_entity(1), FileAttributes { color: Some(Color::srgb_u8(0, 128, 255)), extra: vec![extra("kind", "string", "road")] });
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{place_beside, Format};
+
+    #[test]
+    fn formats_by_extension() {
+        assert_eq!(Format::from_name("graph.GV"), Some(Format::Dot));
+        assert_eq!(Format::from_name("road.gr"), Some(Format::Dimacs));
+        assert_eq!(Format::from_name("archive.tar.gexf"), Some(Format::Gexf));
+        assert_eq!(Format::from_name("no_extension"), None);
+    }
+
+    #[test]
+    fn added_graph_goes_right_of_the_drawn_one() {
+        let drawn = [Vec2::new(0., 0.), Vec2::new(100., 200.)];
+        let mut positions = vec![Vec2::new(-50., 0.), Vec2::new(50., 20.)];
+        place_beside(&drawn, &mut positions);
+        assert!(positions.iter().all(|position| position.x > 100.));
+        assert_eq!(positions[0].y + positions[1].y, 200.);
+        assert_eq!(positions[1] - positions[0], Vec2::new(100., 20.));
+    }
+}
@@ -0,0 +1,212 @@
+use super::{deflate::{crc32, inflate, zlib}, raster::Canvas};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+fn header(png: &mut Vec<u8>, canvas: &Canvas) {
+    let mut data = vec![];
+    data.extend((canvas.width as u32).to_be_bytes());
+    data.extend((canvas.height as u32).to_be_bytes());
+    // 8 bits per channel, RGB, deflate, adaptive filtering, no interlacing
+    data.extend([8, 2, 0, 0, 0]);
+    chunk(png, b"IHDR", &data);
+}
+
+// Every row starts with its filter, Sub stores the difference to the pixel on the left which is mostly zero
+fn image_data(canvas: &Canvas) -> Vec<u8> {
+    let mut rows = Vec::with_capacity((canvas.width * 3 + 1) * canvas.height);
+    for row in canvas.pixels.chunks(canvas.width) {
+        rows.push(1);
+        let mut left = [0u8; 3];
+        for pixel in row {
+            rows.extend([0, 1, 2].map(|c| pixel[c].wrapping_sub(left[c])));
+            left = *pixel;
+        }
+    }
+    zlib(&rows)
+}
+
+pub fn encode_png(canvas: &Canvas) -> Vec<u8> {
+    let mut png = SIGNATURE.to_vec();
+    header(&mut png, canvas);
+    chunk(&mut png, b"IDAT", &image_data(canvas));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/**
+    # APNG
+    The first frame is the ordinary image so viewers without animation still show it, the others follow in fdAT chunks.
+    All frames have the size of the first, every frame stays for delay hundredths of a second and the animation loops.
+*/
+pub fn encode_apng(frames: &[Canvas], delay: u16) -> Vec<u8> {
+    let Some(first) = frames.first() else {return vec![]};
+    let mut png = SIGNATURE.to_vec();
+    header(&mut png, first);
+    let mut control = vec![];
+    control.extend((frames.len() as u32).to_be_bytes());
+    control.extend(0u32.to_be_bytes());
+    chunk(&mut png, b"acTL", &control);
+
+    let mut sequence = 0u32;
+    for (i, frame) in frames.iter().enumerate() {
+        let mut control = vec![];
+        control.extend(sequence.to_be_bytes());
+        control.extend((first.width as u32).to_be_bytes());
+        control.extend((first.height as u32).to_be_bytes());
+        control.extend(0u32.to_be_bytes());
+        control.extend(0u32.to_be_bytes());
+        control.extend(delay.to_be_bytes());
+        control.extend(100u16.to_be_bytes());
+        // Nothing is disposed and the frame replaces the canvas
+        control.extend([0, 0]);
+        chunk(&mut png, b"fcTL", &control);
+        sequence += 1;
+
+        if i == 0 {
+            chunk(&mut png, b"IDAT", &image_data(frame));
+        } else {
+            let mut data = sequence.to_be_bytes().to_vec();
+            data.extend(image_data(frame));
+            chunk(&mut png, b"fdAT", &data);
+            sequence += 1;
+        }
+    }
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+// Decoded images larger than this are not a thumbnail
+const MAX_PIXELS: usize = 1 << 22;
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = left as i16 + up as i16 - up_left as i16;
+    let distance = |value: u8| (estimate - value as i16).abs();
+    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+        left
+    } else if distance(up) <= distance(up_left) {
+        up
+    } else {
+        up_left
+    }
+}
+
+/**
+    # Decode
+    Reads the PNGs this app writes and the other 8 bit RGB or RGBA images without interlacing, which is what
+    thumbnails are saved as. Transparency is dropped.
+*/
+pub fn decode_png(png: &[u8]) -> Result<Canvas, String> {
+    let chunks = png.strip_prefix(&SIGNATURE[..]).ok_or("The file is not a PNG")?;
+    let (mut width, mut height, mut channels) = (0, 0, 0);
+    let mut data = vec![];
+    let mut position = 0;
+    while let Some(header) = chunks.get(position..position + 8) {
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let body = chunks.get(position + 8..).and_then(|rest| rest.get(..length)).ok_or("The PNG ends early")?;
+        match &header[4..8] {
+            b"IHDR" => {
+                let [w0, w1, w2, w3, h0, h1, h2, h3, depth, color, _, _, interlace] = body[..] else {
+                    return Err("The PNG has a broken header".to_string());
+                };
+                width = u32::from_be_bytes([w0, w1, w2, w3]) as usize;
+                height = u32::from_be_bytes([h0, h1, h2, h3]) as usize;
+                channels = match (depth, color, interlace) {
+                    (8, 2, 0) => 3,
+                    (8, 6, 0) => 4,
+                    _ => return Err("Only 8 bit RGB and RGBA PNGs without interlacing are read".to_string()),
+                };
+            },
+            b"IDAT" => data.extend(body),
+            b"IEND" => break,
+            _ => {},
+        }
+        position += length + 12;
+    }
+    if width == 0 || height == 0 || width.saturating_mul(height) > MAX_PIXELS {
+        return Err(format!("The PNG of {width} x {height} pixels can't be read"));
+    }
+
+    // The zlib header is two bytes before the deflated rows
+    let stride = width * channels;
+    let rows = inflate(data.get(2..).unwrap_or_default(), (stride + 1) * height)?;
+    let mut pixels = Vec::with_capacity(width * height);
+    let (mut previous, mut current) = (vec![0u8; stride], vec![0u8; stride]);
+    for row in rows.chunks(stride + 1).take(height) {
+        let [filter, row @ ..] = row else {break};
+        if row.len() < stride {
+            break;
+        }
+        for i in 0..stride {
+            let left = if i >= channels { current[i - channels] } else { 0 };
+            let up_left = if i >= channels { previous[i - channels] } else { 0 };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => previous[i],
+                3 => ((left as u16 + previous[i] as u16) / 2) as u8,
+                4 => paeth(left, previous[i], up_left),
+                _ => return Err(format!("The PNG has the unknown filter {filter}")),
+            };
+            current[i] = row[i].wrapping_add(predicted);
+        }
+        pixels.extend(current.chunks(channels).map(|pixel| [pixel[0], pixel[1], pixel[2]]));
+        std::mem::swap(&mut previous, &mut current);
+    }
+    if pixels.len() < width * height {
+        return Err("The PNG ends early".to_string());
+    }
+    Ok(Canvas::from_pixels(width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_png, encode_apng, encode_png};
+    use crate::app::files::raster::Canvas;
+
+    // Gradients that wrap around so the Sub filter stores large differences too
+    fn gradient(width: usize, height: usize, seed: usize) -> Canvas {
+        let pixels = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                [(x * 37 + seed) as u8, (y * 11 + x * seed) as u8, ((x ^ y) * 5) as u8]
+            })
+            .collect();
+        Canvas::from_pixels(width, height, pixels)
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let canvas = gradient(37, 23, 3);
+        let decoded = decode_png(&encode_png(&canvas)).unwrap();
+        assert_eq!((decoded.width, decoded.height), (37, 23));
+        assert_eq!(decoded.pixels, canvas.pixels);
+    }
+
+    #[test]
+    fn apng_shows_the_first_frame() {
+        let frames = [gradient(16, 9, 1), gradient(16, 9, 2), gradient(16, 9, 3)];
+        let apng = encode_apng(&frames, 50);
+        let decoded = decode_png(&apng).unwrap();
+        assert_eq!(decoded.pixels, frames[0].pixels);
+
+        let count = |kind: &[u8]| apng.windows(4).filter(|window| *window == kind).count();
+        assert_eq!((count(b"acTL"), count(b"fcTL"), count(b"fdAT")), (1, 3, 2));
+        assert!(encode_apng(&[], 50).is_empty());
+    }
+
+    #[test]
+    fn broken_pngs_are_errors() {
+        let png = encode_png(&gradient(8, 8, 0));
+        assert!(decode_png(&png[1..]).is_err());
+        assert!(decode_png(&png[..png.len() / 2]).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use bevy::{color::{Color, Srgba}, math::Vec2};
+
+use super::font::{ascii, glyph, GLYPH_HEIGHT, GLYPH_ROWS, GLYPH_SPACING, GLYPH_WIDTH};
+
+// Scanlines sampled per row of pixels, the coverage along a scanline is exact
+const SUBSAMPLES: usize = 4;
+// The distance between the tops of two lines of text in glyph heights
+pub const LINE_SPACING: f32 = 1.6;
+
+/**
+    # Canvas
+    RGB pixels row by row from the top left. Shapes are filled with anti-aliased edges by sampling a few
+    scanlines per row, which is all a drawing of discs, convex polygons and pixel text needs.
+    Coordinates are in pixels with y pointing down.
+*/
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+    // The coverage of the current row, kept to not allocate per shape
+    row: Vec<f32>,
+}
+
+fn channels(color: Color) -> ([f32; 3], f32) {
+    let color = Srgba::from(color);
+    ([color.red, color.green, color.blue].map(|c| c.clamp(0., 1.) * 255.), color.alpha.clamp(0., 1.))
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: Color) -> Self {
+        let (rgb, _) = channels(background);
+        Self {
+            width,
+            height,
+            pixels: vec![rgb.map(|c| c.round() as u8); width * height],
+            row: vec![0.; width + 1],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<[u8; 3]>) -> Self {
+        Self { width, height, pixels, row: vec![0.; width + 1] }
+    }
+
+    // Fills the rows between top and bottom, span gives the covered x range of a scanline
+    fn fill(&mut self, color: Color, top: f32, bottom: f32, span: impl Fn(f32) -> Option<(f32, f32)>) {
+        let (rgb, alpha) = channels(color);
+        let first = top.floor().max(0.) as usize;
+        let last = (bottom.ceil().max(0.) as usize).min(self.height);
+        let width = self.width as f32;
+        for y in first..last {
+            let (mut min, mut max) = (usize::MAX, 0);
+            for sample in 0..SUBSAMPLES {
+                let Some((start, end)) = span(y as f32 + (sample as f32 + 0.5) / SUBSAMPLES as f32) else {continue};
+                let (start, end) = (start.clamp(0., width), end.clamp(0., width));
+                if end <= start {
+                    continue;
+                }
+                let (from, to) = (start.floor() as usize, (end.ceil() as usize).min(self.width));
+                for x in from..to {
+                    let covered = end.min(x as f32 + 1.) - start.max(x as f32);
+                    self.row[x] += covered / SUBSAMPLES as f32;
+                }
+                min = min.min(from);
+                max = max.max(to);
+            }
+            for x in min..max {
+                let coverage = std::mem::take(&mut self.row[x]).min(1.) * alpha;
+                if coverage <= 0. {
+                    continue;
+                }
+                let pixel = &mut self.pixels[y * self.width + x];
+                for c in 0..3 {
+                    pixel[c] = (pixel[c] as f32 * (1. - coverage) + rgb[c] * coverage).round() as u8;
+                }
+            }
+        }
+    }
+
+    // A convex polygon, every scanline crosses it in one span
+    pub fn fill_polygon(&mut self, points: &[Vec2], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        let top = points.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+        let bottom = points.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+        self.fill(color, top, bottom, |y| {
+            let mut span: Option<(f32, f32)> = None;
+            for i in 0..points.len() {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                if (a.y <= y) == (b.y <= y) {
+                    continue;
+                }
+                let x = a.x + (y - a.y) / (b.y - a.y) * (b.x - a.x);
+                span = Some(span.map_or((x, x), |(start, end)| (start.min(x), end.max(x))));
+            }
+            span
+        });
+    }
+
+    pub fn fill_circle(&mut self, center: Vec2, radius: f32, color: Color) {
+        self.fill(color, center.y - radius, center.y + radius, |y| {
+            let dy = y - center.y;
+            let half = (radius * radius - dy * dy).sqrt();
+            (half > 0.).then_some((center.x - half, center.x + half))
+        });
+    }
+
+    pub fn fill_rect(&mut self, min: Vec2, max: Vec2, color: Color) {
+        self.fill(color, min.y, max.y, |_| Some((min.x, max.x)));
+    }
+
+    // The width of a line of text whose glyphs are size pixels high
+    pub fn text_width(text: &str, size: f32) -> f32 {
+        let scale = size / GLYPH_HEIGHT as f32;
+        let count = ascii(text).chars().count();
+        (count * (GLYPH_WIDTH + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING) as f32 * scale
+    }
+
+    // Text centered on a point with size the height of a capital, every line is centered on its own and the columns of every glyph are filled as bars
+    pub fn draw_text(&mut self, center: Vec2, text: &str, size: f32, color: Color) {
+        let scale = size / GLYPH_HEIGHT as f32;
+        let lines: Vec<&str> = text.lines().collect();
+        let top = center.y - (lines.len() as f32 * LINE_SPACING - (LINE_SPACING - 1.)) * size / 2.;
+        for (line_index, line) in lines.iter().enumerate() {
+            let origin = Vec2::new(center.x - Self::text_width(line, size) / 2., top + line_index as f32 * LINE_SPACING * size);
+            for (i, c) in ascii(line).chars().enumerate() {
+                for (column, bits) in glyph(c).iter().enumerate() {
+                    let x = origin.x + (i * (GLYPH_WIDTH + GLYPH_SPACING) + column) as f32 * scale;
+                    let mut row = 0;
+                    while row < GLYPH_ROWS {
+                        if bits >> row & 1 == 0 {
+                            row += 1;
+                            continue;
+                        }
+                        let start = row;
+                        while row < GLYPH_ROWS && bits >> row & 1 == 1 {
+                            row += 1;
+                        }
+                        let min = Vec2::new(x, origin.y + start as f32 * scale);
+                        self.fill_rect(min, Vec2::new(x + scale, origin.y + row as f32 * scale), color);
+                    }
+                }
+            }
+        }
+    }
+}
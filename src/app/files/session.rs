@@ -0,0 +1,109 @@
+use bevy::{ecs::system::SystemParam, math::Vec2, prelude::{Image, OrthographicProjection, Query, Res, State, Transform, With}, render::{render_asset::RenderAssetUsages, render_resource::{Extent3d, TextureDimension, TextureFormat}}};
+
+use crate::app::{algorithms::{graph::GraphSnapshot, targets::AlgorithmTargets}, build_graph::components::EditorState, camera::MainCamera};
+
+use super::{frames::RunFrameData, json::{graph_from_json, parse_value, quote, write_json, Json}, png::{decode_png, encode_png}, target_indices, ImportedGraph};
+
+// The size of the pictures of the save slots
+pub const THUMBNAIL_WIDTH: usize = 160;
+pub const THUMBNAIL_HEIGHT: usize = 100;
+
+// Where the camera looks and the mode of the editor when the session was saved
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub position: Vec2,
+    pub scale: f32,
+    pub mode: EditorState,
+}
+
+pub struct Session {
+    pub name: String,
+    pub view: View,
+    pub graph: ImportedGraph,
+}
+
+/**
+    # Session
+    The graph in the JSON of the File menu, wrapped in an object with the name of the session, the camera and
+    the editor mode. Loading a session goes the way of any other file for the graph.
+*/
+pub fn write_session(name: &str, graph: &GraphSnapshot, start: Option<usize>, end: Option<usize>, view: &View) -> String {
+    format!(
+        "{{\n\"name\": {},\n\"mode\": {},\n\"camera\": {{\"x\": {:.2}, \"y\": {:.2}, \"scale\": {:.3}}},\n\"graph\": {}\n}}\n",
+        quote(name),
+        quote(view.mode.name()),
+        view.position.x,
+        view.position.y,
+        view.scale,
+        write_json(graph, start, end).trim_end(),
+    )
+}
+
+pub fn parse_session(text: &str) -> Result<Session, String> {
+    let root = parse_value(text)?;
+    let graph = graph_from_json(root.get("graph").ok_or("The session has no graph")?)?;
+    let name = match root.get("name") {
+        Some(Json::String(name)) => name.clone(),
+        _ => String::new(),
+    };
+    let mode = match root.get("mode") {
+        Some(Json::String(mode)) => EditorState::from_name(mode).unwrap_or_default(),
+        _ => EditorState::default(),
+    };
+    let camera = root.get("camera");
+    let number = |key: &str| camera.and_then(|camera| camera.get(key)).and_then(Json::number).filter(|number| number.is_finite());
+    let view = View {
+        position: Vec2::new(number("x").unwrap_or(0.) as f32, number("y").unwrap_or(0.) as f32),
+        scale: number("scale").filter(|scale| *scale > 0.).unwrap_or(1.) as f32,
+        mode,
+    };
+    Ok(Session { name, view, graph })
+}
+
+// The name of a saved session without reading its graph
+pub fn session_name(text: &str) -> Option<String> {
+    match parse_value(text).ok()?.get("name") {
+        Some(Json::String(name)) => Some(name.clone()),
+        _ => None,
+    }
+}
+
+// The PNG of a thumbnail as a texture for the UI
+pub fn thumbnail_image(png: &[u8]) -> Result<Image, String> {
+    let canvas = decode_png(png)?;
+    let rgba = canvas.pixels.iter().flat_map(|[r, g, b]| [*r, *g, *b, 255]).collect();
+    Ok(Image::new(
+        Extent3d { width: canvas.width as u32, height: canvas.height as u32, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        rgba,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    ))
+}
+
+// Everything a session is written from
+#[derive(SystemParam)]
+pub struct SessionData<'w, 's> {
+    frames: RunFrameData<'w, 's>,
+    targets: Res<'w, AlgorithmTargets>,
+    q_camera: Query<'w, 's, (&'static Transform, &'static OrthographicProjection), With<MainCamera>>,
+    state: Res<'w, State<EditorState>>,
+}
+
+impl<'w, 's> SessionData<'w, 's> {
+    pub fn snapshot(&self) -> GraphSnapshot {
+        self.frames.graph.snapshot()
+    }
+
+    pub fn write(&self, name: &str, graph: &GraphSnapshot) -> String {
+        let (start, end) = target_indices(graph, &self.targets);
+        let (position, scale) = self.q_camera.get_single()
+            .map_or((Vec2::ZERO, 1.), |(transform, projection)| (transform.translation.truncate(), projection.scale));
+        write_session(name, graph, start, end, &View { position, scale, mode: *self.state.get() })
+    }
+
+    // The graph in its colors as a PNG
+    pub fn thumbnail(&self, graph: &GraphSnapshot) -> Vec<u8> {
+        encode_png(&self.frames.thumbnail(graph, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT))
+    }
+}
@@ -0,0 +1,235 @@
+use bevy::math::Vec2;
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::{deflate::{deflate, inflate}, ImportedGraph};
+
+// The first byte of the packed graph, a new layout gets a new version so old links keep opening
+const VERSION: u8 = 1;
+// The fragment of a link is graph= followed by the packed graph
+pub const LINK_PREFIX: &str = "graph=";
+// A link of a few kilobytes can't unpack to more than this
+const MAX_UNPACKED: usize = 1 << 26;
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// Base64 with the URL safe alphabet and no padding
+pub fn base64url(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| group | (*byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            text.push(BASE64URL[(group >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    text
+}
+
+// Also takes the standard alphabet and padding, links pass through chats that rewrite them
+pub fn parse_base64url(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut group, mut count) = (0u32, 0);
+    for c in text.trim_end_matches('=').chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            '-' | '+' => 62,
+            '_' | '/' => 63,
+            _ => return Err(format!("The link has the character {c} that is not base64")),
+        };
+        group = group << 6 | value;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            bytes.push((group >> count) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+// Unsigned LEB128, small numbers take one byte
+fn push_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+// Zigzag maps small negative numbers to small varints
+fn push_signed(bytes: &mut Vec<u8>, value: i64) {
+    push_varint(bytes, ((value << 1) ^ (value >> 63)) as u64);
+}
+
+struct Unpacker<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Unpacker<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self.bytes.get(self.position).ok_or("The link ends early")?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("The link has a number that is too long".to_string())
+    }
+
+    fn signed(&mut self) -> Result<i64, String> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    // An index below count
+    fn index(&mut self, count: usize) -> Result<usize, String> {
+        let index = self.varint()? as usize;
+        if index >= count {
+            return Err(format!("The link refers to vertex {index} of {count}"));
+        }
+        Ok(index)
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        let length = self.varint()? as usize;
+        let end = self.position.checked_add(length).filter(|end| *end <= self.bytes.len()).ok_or("The link ends early")?;
+        let text = String::from_utf8_lossy(&self.bytes[self.position..end]).to_string();
+        self.position = end;
+        Ok(text)
+    }
+}
+
+/**
+    # Share Link
+    Packs the graph into the fragment of a link: the names and the positions rounded to whole units of the
+    vertices, the edges with their weights and directions and the algorithm targets, as varints that are
+    deflated and written in base64url. Other attributes like colors are left out to keep links short.
+*/
+pub fn write_link(graph: &GraphSnapshot, start: Option<usize>, end: Option<usize>) -> String {
+    let mut bytes = vec![VERSION];
+    push_varint(&mut bytes, graph.len() as u64);
+    for v in 0..graph.len() {
+        push_varint(&mut bytes, graph.name(v).len() as u64);
+        bytes.extend(graph.name(v).as_bytes());
+        push_signed(&mut bytes, graph.positions[v].x.round() as i64);
+        push_signed(&mut bytes, graph.positions[v].y.round() as i64);
+    }
+    push_varint(&mut bytes, graph.edges.len() as u64);
+    for edge in graph.edges.iter() {
+        push_varint(&mut bytes, edge.from as u64);
+        push_varint(&mut bytes, edge.to as u64);
+        push_signed(&mut bytes, edge.weight as i64);
+        bytes.push(edge.directed as u8);
+    }
+    // 0 is no target, the others are one more than the vertex
+    for target in [start, end] {
+        push_varint(&mut bytes, target.map_or(0, |v| v as u64 + 1));
+    }
+    format!("{LINK_PREFIX}{}", base64url(&deflate(&bytes)))
+}
+
+// Takes a whole link, its fragment or only the packed graph
+pub fn parse_link(text: &str) -> Result<ImportedGraph, String> {
+    let text = text.trim();
+    let fragment = text.rsplit_once('#').map_or(text, |(_, fragment)| fragment);
+    let packed = fragment.strip_prefix(LINK_PREFIX).unwrap_or(fragment);
+    let bytes = inflate(&parse_base64url(packed)?, MAX_UNPACKED)?;
+    let mut unpacker = Unpacker { bytes: &bytes, position: 0 };
+    let version = unpacker.byte()?;
+    if version != VERSION {
+        return Err(format!("The link is of version {version}, this build opens version {VERSION}"));
+    }
+
+    let mut graph = ImportedGraph::default();
+    let vertices = unpacker.varint()? as usize;
+    for v in 0..vertices {
+        let name = unpacker.text()?;
+        let position = Vec2::new(unpacker.signed()? as f32, unpacker.signed()? as f32);
+        // Names can repeat in a graph, the file IDs of a link are the indices
+        graph.vertex(&v.to_string());
+        graph.vertices[v].name = name;
+        graph.vertices[v].position = Some(position);
+    }
+    let edges = unpacker.varint()? as usize;
+    for _ in 0..edges {
+        let from = unpacker.index(vertices)?;
+        let to = unpacker.index(vertices)?;
+        let weight = unpacker.signed()?.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        let directed = unpacker.byte()? != 0;
+        graph.add_edge(from, to, weight, directed);
+    }
+    graph.start = unpacker.index(vertices + 1)?.checked_sub(1);
+    graph.end = unpacker.index(vertices + 1)?.checked_sub(1);
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::files::{snapshot_edges, tricky_graph};
+
+    use super::{base64url, deflate, parse_base64url, parse_link, write_link, LINK_PREFIX};
+
+    #[test]
+    fn base64url_lengths() {
+        assert_eq!(base64url(b""), "");
+        assert_eq!(base64url(b"M"), "TQ");
+        assert_eq!(base64url(b"Ma"), "TWE");
+        assert_eq!(base64url(b"Man"), "TWFu");
+        assert_eq!(base64url(&[0xFB, 0xFF]), "-_8");
+        for length in 0..=7 {
+            let bytes: Vec<u8> = (0..length).map(|i| (i * 73 + 200) as u8).collect();
+            assert_eq!(parse_base64url(&base64url(&bytes)).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn standard_base64_and_padding() {
+        assert_eq!(parse_base64url("+/8=").unwrap(), vec![0xFB, 0xFF]);
+        assert_eq!(parse_base64url("TQ==").unwrap(), b"M");
+        assert!(parse_base64url("TW Fu").is_err());
+    }
+
+    #[test]
+    fn link_round_trip() {
+        let graph = tricky_graph();
+        let link = write_link(&graph, Some(4), Some(0));
+        let imported = parse_link(&format!("https://example.org/app#{link}")).unwrap();
+        assert_eq!(imported.names(), graph.names.iter().map(String::as_str).collect::<Vec<_>>());
+        assert_eq!(imported.edge_tuples(), snapshot_edges(&graph));
+        for (vertex, position) in imported.vertices.iter().zip(graph.positions.iter()) {
+            assert_eq!(vertex.position, Some(*position));
+        }
+        assert_eq!((imported.start, imported.end), (Some(4), Some(0)));
+
+        let without_targets = parse_link(&write_link(&graph, None, None)).unwrap();
+        assert_eq!((without_targets.start, without_targets.end), (None, None));
+    }
+
+    #[test]
+    fn broken_links_are_errors() {
+        let link = write_link(&tricky_graph(), None, None);
+        assert!(parse_link(&link[..link.len() / 2]).is_err());
+        // A newer version, and an edge to a vertex the graph doesn't have
+        assert!(parse_link(&base64url(&deflate(&[2, 0, 0, 0, 0]))).is_err());
+        assert!(parse_link(&base64url(&deflate(&[1, 1, 0, 0, 0, 1, 0, 3, 0, 0, 0, 0, 0]))).is_err());
+    }
+
+    #[test]
+    fn bombs_are_rejected() {
+        // Deflates to a few hundred kilobytes that would unpack past the limit
+        let bomb = deflate(&vec![0; (1 << 26) + 1]);
+        let link = format!("{LINK_PREFIX}{}", base64url(&bomb));
+        let Err(error) = parse_link(&link) else {panic!("the link unpacks past the limit")};
+        assert!(error.contains("bigger than"), "{error}");
+    }
+}
@@ -0,0 +1,371 @@
+use bevy::{asset::Assets, color::{Color, Srgba}, ecs::system::SystemParam, math::{Rect, Vec2}, prelude::{ClearColor, GlobalTransform, InheritedVisibility, OrthographicProjection, Query, Res, Transform, With}, sprite::{Anchor, ColorMaterial}, text::Text};
+
+use crate::app::{build_graph::{components::{edge_outline, BaseMaterial, Edge, EdgeWidth, Vertex}, RADIUS}, camera::MainCamera};
+
+use super::xml::escape;
+
+pub struct Polygon {
+    pub points: Vec<Vec2>,
+    pub color: Color,
+}
+
+pub struct Disc {
+    pub center: Vec2,
+    pub radius: f32,
+    pub color: Color,
+}
+
+pub struct Label {
+    pub position: Vec2,
+    pub text: String,
+    pub size: f32,
+    pub color: Color,
+}
+
+/**
+    # Drawing
+    What the graph looks like on screen in world coordinates: the edges with their arrowheads, the vertices
+    and every label, drawn in that order. bounds is the drawn area, clipped marks it as the camera viewport.
+*/
+pub struct Drawing {
+    pub bounds: Rect,
+    pub clipped: bool,
+    pub background: Color,
+    pub edges: Vec<Polygon>,
+    pub vertices: Vec<Disc>,
+    pub labels: Vec<Label>,
+}
+
+// Labels have no size before they are rendered, this is about the width of a character of the default font
+const CHARACTER_WIDTH: f32 = 0.6;
+
+#[derive(SystemParam)]
+pub struct DrawingData<'w, 's> {
+    q_vertex: Query<'w, 's, (&'static Transform, &'static BaseMaterial, &'static InheritedVisibility), With<Vertex>>,
+    q_edge: Query<'w, 's, (&'static Edge, &'static EdgeWidth, &'static BaseMaterial, &'static InheritedVisibility)>,
+    // Only world text has an anchor, the text of the UI doesn't
+    q_label: Query<'w, 's, (&'static Text, &'static GlobalTransform, &'static InheritedVisibility), With<Anchor>>,
+    q_camera: Query<'w, 's, (&'static GlobalTransform, &'static OrthographicProjection), With<MainCamera>>,
+    materials: Res<'w, Assets<ColorMaterial>>,
+    clear_color: Res<'w, ClearColor>,
+}
+
+impl<'w, 's> DrawingData<'w, 's> {
+    fn color(&self, material: &BaseMaterial) -> Color {
+        self.materials.get(&material.0).map_or(Color::WHITE, |material| material.color)
+    }
+
+    // The colors are the base materials, so an algorithm run shows and hovering doesn't
+    pub fn drawing(&self, clip: bool) -> Drawing {
+        let mut edges = vec![];
+        for (edge, width, material, visibility) in self.q_edge.iter() {
+            let (Ok((start, ..)), Ok((end, ..))) = (self.q_vertex.get(edge.start), self.q_vertex.get(edge.end)) else {continue};
+            if !visibility.get() {
+                continue;
+            }
+            let (start, end) = (start.translation.truncate(), end.translation.truncate());
+            let mid = (start + end) / 2.;
+            let (quad, arrow) = edge_outline(start, end, edge.directed, width.0);
+            let color = self.color(material);
+            edges.push(Polygon { points: quad.iter().map(|p| *p + mid).collect(), color });
+            if let Some(arrow) = arrow {
+                edges.push(Polygon { points: arrow.iter().map(|p| *p + mid).collect(), color });
+            }
+        }
+        let vertices: Vec<Disc> = self.q_vertex.iter()
+            .filter(|(.., visibility)| visibility.get())
+            .map(|(transform, material, _)| Disc {
+                center: transform.translation.truncate(),
+                radius: RADIUS * transform.scale.x,
+                color: self.color(material),
+            })
+            .collect();
+        let labels: Vec<Label> = self.q_label.iter()
+            .filter(|(.., visibility)| visibility.get())
+            .filter_map(|(text, transform, _)| {
+                let style = &text.sections.first()?.style;
+                Some(Label {
+                    position: transform.translation().truncate(),
+                    text: text.sections.iter().map(|section| section.value.as_str()).collect(),
+                    size: style.font_size,
+                    color: style.color,
+                })
+            })
+            .filter(|label| !label.text.trim().is_empty())
+            .collect();
+
+        let view = self.q_camera.get_single().ok().map(|(transform, projection)| {
+            let center = transform.translation().truncate();
+            Rect::from_corners(center + projection.area.min, center + projection.area.max)
+        });
+        let (bounds, clipped) = match view {
+            Some(view) if clip => (view, true),
+            _ => {
+                let mut bounds = Rect::EMPTY;
+                for polygon in edges.iter() {
+                    polygon.points.iter().for_each(|point| bounds = bounds.union_point(*point));
+                }
+                for disc in vertices.iter() {
+                    bounds = bounds.union(Rect::from_center_half_size(disc.center, Vec2::splat(disc.radius)));
+                }
+                for label in labels.iter() {
+                    let half_size = Vec2::new(label.text.chars().count() as f32 * CHARACTER_WIDTH, 1.) * label.size / 2.;
+                    bounds = bounds.union(Rect::from_center_half_size(label.position, half_size));
+                }
+                if bounds.is_empty() {
+                    bounds = view.unwrap_or(Rect::from_center_half_size(Vec2::ZERO, Vec2::splat(RADIUS)));
+                }
+                (bounds.inflate(RADIUS), false)
+            },
+        };
+        let mut drawing = Drawing {
+            bounds,
+            clipped,
+            background: self.clear_color.0,
+            edges,
+            vertices,
+            labels,
+        };
+        if clipped {
+            // Whatever is outside the viewport is left out of the file, what crosses its border is clipped by the viewer
+            drawing.edges.retain(|polygon| {
+                let outline = polygon.points.iter().fold(Rect::EMPTY, |outline, point| outline.union_point(*point));
+                !outline.intersect(bounds).is_empty()
+            });
+            drawing.vertices.retain(|disc| bounds.inflate(disc.radius).contains(disc.center));
+            drawing.labels.retain(|label| bounds.inflate(label.size * 4.).contains(label.position));
+        }
+        drawing
+    }
+}
+
+// #rrggbb and the opacity if the color isn't opaque
+fn svg_paint(color: Color) -> String {
+    let color = Srgba::from(color);
+    let hex = Srgba { alpha: 1., ..color }.to_hex();
+    if color.alpha < 1. {
+        format!("fill=\"{hex}\" fill-opacity=\"{:.3}\"", color.alpha)
+    } else {
+        format!("fill=\"{hex}\"")
+    }
+}
+
+/**
+    # SVG
+    One unit is one pixel of the world at zoom 1. The y axis of SVG points down so every y is negated.
+*/
+pub fn write_svg(drawing: &Drawing) -> String {
+    let b = drawing.bounds;
+    let point = |p: Vec2| format!("{:.2},{:.2}", p.x, -p.y);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\">\n",
+        b.width(),
+        b.height(),
+        b.min.x,
+        -b.max.y,
+        b.width(),
+        b.height(),
+    );
+    let background = format!(
+        "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" {}/>",
+        b.min.x,
+        -b.max.y,
+        b.width(),
+        b.height(),
+        svg_paint(drawing.background),
+    );
+    if drawing.clipped {
+        svg.push_str(&format!("  <defs><clipPath id=\"view\">{background}</clipPath></defs>\n"));
+        svg.push_str("  <g clip-path=\"url(#view)\">\n");
+    } else {
+        svg.push_str("  <g>\n");
+    }
+    svg.push_str(&format!("  {background}\n"));
+    svg.push_str("  <g id=\"edges\">\n");
+    for polygon in drawing.edges.iter() {
+        let points: Vec<String> = polygon.points.iter().map(|p| point(*p)).collect();
+        svg.push_str(&format!("    <polygon points=\"{}\" {}/>\n", points.join(" "), svg_paint(polygon.color)));
+    }
+    svg.push_str("  </g>\n  <g id=\"vertices\">\n");
+    for disc in drawing.vertices.iter() {
+        svg.push_str(&format!(
+            "    <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" {}/>\n",
+            disc.center.x,
+            -disc.center.y,
+            disc.radius,
+            svg_paint(disc.color),
+        ));
+    }
+    svg.push_str("  </g>\n  <g id=\"labels\" font-family=\"sans-serif\" text-anchor=\"middle\" dominant-baseline=\"central\">\n");
+    for label in drawing.labels.iter() {
+        svg.push_str(&format!(
+            "    <text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.1}\" {}>{}</text>\n",
+            label.position.x,
+            -label.position.y,
+            label.size,
+            svg_paint(label.color),
+            escape(&label.text),
+        ));
+    }
+    svg.push_str("  </g>\n  </g>\n</svg>\n");
+    svg
+}
+
+// Centimetres per world unit, a vertex is a centimetre wide
+const TIKZ_SCALE: f32 = 0.01;
+const POINTS_PER_CM: f32 = 28.45;
+
+fn latex_escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' => "\\textbackslash{}".to_string(),
+            '~' => "\\textasciitilde{}".to_string(),
+            '^' => "\\textasciicircum{}".to_string(),
+            '<' => "\\textless{}".to_string(),
+            '>' => "\\textgreater{}".to_string(),
+            '|' => "\\textbar{}".to_string(),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => format!("\\{c}"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/**
+    # TikZ
+    A tikzpicture in centimetres, every color is defined once at the top. Edges and arrowheads are filled
+    polygons like on screen so the picture looks the same, the labels use the font of the document.
+*/
+pub fn write_tikz(drawing: &Drawing) -> String {
+    let point = |p: Vec2| format!("({:.3},{:.3})", p.x * TIKZ_SCALE, p.y * TIKZ_SCALE);
+    let mut colors: Vec<Srgba> = vec![];
+    let mut color_name = |color: Color| {
+        let color = Srgba::from(color);
+        let i = colors.iter().position(|known| *known == color).unwrap_or_else(|| {
+            colors.push(color);
+            colors.len() - 1
+        });
+        let opacity = if color.alpha < 1. { format!(", opacity={:.3}", color.alpha) } else { String::new() };
+        format!("graphcolor{i}{opacity}")
+    };
+
+    let mut body = vec![];
+    let b = drawing.bounds;
+    if drawing.clipped {
+        body.push(format!("  \\clip {} rectangle {};", point(b.min), point(b.max)));
+    }
+    body.push(format!("  \\fill[{}] {} rectangle {};", color_name(drawing.background), point(b.min), point(b.max)));
+    for polygon in drawing.edges.iter() {
+        let points: Vec<String> = polygon.points.iter().map(|p| point(*p)).collect();
+        body.push(format!("  \\fill[{}] {} -- cycle;", color_name(polygon.color), points.join(" -- ")));
+    }
+    for disc in drawing.vertices.iter() {
+        body.push(format!(
+            "  \\fill[{}] {} circle[radius={:.3}];",
+            color_name(disc.color),
+            point(disc.center),
+            disc.radius * TIKZ_SCALE,
+        ));
+    }
+    for label in drawing.labels.iter() {
+        let size = label.size * TIKZ_SCALE * POINTS_PER_CM;
+        let color = color_name(label.color).replacen(", opacity", ", text opacity", 1);
+        body.push(format!(
+            "  \\node[text={color}, inner sep=0, font=\\fontsize{{{:.1}}}{{{:.1}}}\\selectfont] at {} {{{}}};",
+            size,
+            size * 1.2,
+            point(label.position),
+            latex_escape(&label.text),
+        ));
+    }
+
+    let mut tikz = String::from("% \\usepackage{tikz}\n\\begin{tikzpicture}\n");
+    for (i, color) in colors.iter().enumerate() {
+        let channel = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+        tikz.push_str(&format!(
+            "  \\definecolor{{graphcolor{i}}}{{RGB}}{{{},{},{}}}\n",
+            channel(color.red),
+            channel(color.green),
+            channel(color.blue),
+        ));
+    }
+    tikz.push_str(&body.join("\n"));
+    tikz.push_str("\n\\end{tikzpicture}\n");
+    tikz
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::Assets, color::Color, ecs::system::RunSystemOnce, math::{Rect, Vec2, Vec3}, prelude::{ClearColor, GlobalTransform, InheritedVisibility, OrthographicProjection, Transform, World}, sprite::ColorMaterial};
+
+    use crate::app::{build_graph::{components::{BaseMaterial, Edge, EdgeWidth, Vertex}, RADIUS}, camera::MainCamera};
+
+    use super::{write_svg, write_tikz, Disc, Drawing, DrawingData, Label};
+
+    fn drawing(text: &str) -> Drawing {
+        Drawing {
+            bounds: Rect::new(-10., 20., 30., 60.),
+            clipped: false,
+            background: Color::BLACK,
+            edges: vec![],
+            vertices: vec![Disc { center: Vec2::new(5., 40.), radius: 8., color: Color::WHITE }],
+            labels: vec![Label { position: Vec2::new(5., 40.), text: text.to_string(), size: 12., color: Color::WHITE }],
+        }
+    }
+
+    #[test]
+    fn names_are_escaped() {
+        let svg = write_svg(&drawing("<b>&amp; \"x\" 'y'"));
+        assert!(svg.contains(">&lt;b&gt;&amp;amp; &quot;x&quot; &apos;y&apos;</text>"));
+        let tikz = write_tikz(&drawing("50% of $x_1$ {a} \\ ~^ #&"));
+        assert!(tikz.contains("{50\\% of \\$x\\_1\\$ \\{a\\} \\textbackslash{} \\textasciitilde{}\\textasciicircum{} \\#\\&};"));
+    }
+
+    #[test]
+    fn svg_y_points_down() {
+        let mut drawing = drawing("a");
+        let svg = write_svg(&drawing);
+        assert!(svg.contains("width=\"40\" height=\"40\" viewBox=\"-10.00 -60.00 40.00 40.00\""));
+        assert!(svg.contains("<circle cx=\"5.00\" cy=\"-40.00\" r=\"8.00\" fill=\"#FFFFFF\"/>"));
+        assert!(svg.contains("<text x=\"5.00\" y=\"-40.00\""));
+        assert!(!svg.contains("clipPath"));
+        // TikZ keeps the y axis of the world
+        assert!(write_tikz(&drawing).contains("(0.050,0.400) circle[radius=0.080]"));
+
+        drawing.clipped = true;
+        assert!(write_svg(&drawing).contains("<clipPath id=\"view\"><rect x=\"-10.00\" y=\"-60.00\" width=\"40.00\" height=\"40.00\""));
+        assert!(write_tikz(&drawing).contains("\\clip (-0.100,0.200) rectangle (0.300,0.600);"));
+    }
+
+    #[test]
+    fn drawing_takes_base_materials_and_the_viewport() {
+        let mut world = World::new();
+        world.init_resource::<Assets<ColorMaterial>>();
+        world.insert_resource(ClearColor(Color::BLACK));
+        let colors = [Color::srgb(1., 0., 0.), Color::srgb(0., 1., 0.), Color::srgb(0., 0., 1.), Color::srgba(1., 1., 0., 0.5)];
+        let materials: Vec<BaseMaterial> = colors.iter()
+            .map(|color| BaseMaterial(world.resource_mut::<Assets<ColorMaterial>>().add(ColorMaterial::from(*color))))
+            .collect();
+        // The viewport is 200 wide around the origin, the last two vertices are far outside of it
+        let vertices: Vec<_> = [0., 30., 500., 600.].iter().zip(materials.iter())
+            .map(|(x, material)| world.spawn((Vertex, Transform::from_xyz(*x, 0., 0.), material.clone(), InheritedVisibility::VISIBLE)).id())
+            .collect();
+        for (start, end, material) in [(0, 1, 2), (1, 2, 3), (2, 3, 0)] {
+            world.spawn((Edge { start: vertices[start], end: vertices[end], directed: false }, EdgeWidth(4.), materials[material].clone(), InheritedVisibility::VISIBLE));
+        }
+        world.spawn((MainCamera, GlobalTransform::from_translation(Vec3::ZERO), OrthographicProjection { area: Rect::new(-100., -100., 100., 100.), ..Default::default() }));
+
+        let clipped = world.run_system_once(|data: DrawingData| data.drawing(true));
+        assert!(clipped.clipped);
+        assert_eq!(clipped.bounds, Rect::new(-100., -100., 100., 100.));
+        assert_eq!(clipped.vertices.iter().map(|disc| disc.color).collect::<Vec<_>>(), colors[..2].to_vec());
+        // The edge across the border stays for the viewer to clip
+        assert_eq!(clipped.edges.len(), 2);
+        assert!(colors[2..].iter().all(|color| clipped.edges.iter().any(|polygon| polygon.color == *color)));
+
+        let whole = world.run_system_once(|data: DrawingData| data.drawing(false));
+        assert!(!whole.clipped);
+        assert_eq!((whole.vertices.len(), whole.edges.len()), (4, 3));
+        assert!(whole.bounds.contains(Vec2::new(600. + RADIUS, 0.)));
+        assert!(write_svg(&whole).contains("fill=\"#FFFF00\" fill-opacity=\"0.500\""));
+    }
+}
@@ -0,0 +1,202 @@
+// An element with its attributes, child elements and the text directly inside it
+#[derive(Default, Debug)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+// Names without their namespace prefix, viz:color and color are the same
+fn local(name: &str) -> &str {
+    name.rsplit_once(':').map_or(name, |(_, local)| local)
+}
+
+impl Element {
+    pub fn is(&self, name: &str) -> bool {
+        local(&self.name).eq_ignore_ascii_case(name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(key, _)| local(key).eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.is(name))
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.is(name))
+    }
+
+    // Every element with the name below this one, depth first in document order
+    pub fn descendants<'a>(&'a self, name: &str) -> Vec<&'a Element> {
+        let mut found = vec![];
+        let mut stack: Vec<&Element> = self.children.iter().rev().collect();
+        while let Some(element) = stack.pop() {
+            if element.is(name) {
+                found.push(element);
+            }
+            stack.extend(element.children.iter().rev());
+        }
+        found
+    }
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {break};
+        let entity = &rest[1..end];
+        let character = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16).ok().and_then(char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match character {
+            Some(character) => {
+                result.push(character);
+                rest = &rest[end + 1..];
+            },
+            // Unknown entities stay as they are
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/**
+    # XML
+    Just enough XML for graph files: elements, attributes, text, CDATA and the five entities plus character
+    references. The prolog, comments, processing instructions and the DOCTYPE are skipped. Returns the root element.
+*/
+pub fn parse_xml(text: &str) -> Result<Element, String> {
+    // The root is a placeholder holding the document element
+    let mut stack: Vec<Element> = vec![Element::default()];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let Some(start) = rest.find('<') else {
+            stack.last_mut().expect("the root is never popped").text.push_str(&unescape(rest));
+            break;
+        };
+        stack.last_mut().expect("the root is never popped").text.push_str(&unescape(&rest[..start]));
+        rest = &rest[start..];
+
+        let skip_to = |rest: &str, end: &str| rest.find(end).map(|i| i + end.len()).ok_or(format!("{end} is missing"));
+        if rest.starts_with("<?") {
+            rest = &rest[skip_to(rest, "?>")?..];
+        } else if rest.starts_with("<!--") {
+            rest = &rest[skip_to(rest, "-->")?..];
+        } else if rest.starts_with("<![CDATA[") {
+            let end = rest.find("]]>").ok_or("]]> is missing")?;
+            stack.last_mut().expect("the root is never popped").text.push_str(&rest[9..end]);
+            rest = &rest[end + 3..];
+        } else if rest.starts_with("<!") {
+            // A DOCTYPE can have an internal subset in brackets
+            let mut depth = 0;
+            let end = rest.char_indices()
+                .find(|(_, c)| {
+                    match c {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        '>' if depth == 0 => return true,
+                        _ => {},
+                    }
+                    false
+                })
+                .map(|(i, _)| i)
+                .ok_or("A declaration is never closed")?;
+            rest = &rest[end + 1..];
+        } else if let Some(closing) = rest.strip_prefix("</") {
+            let end = closing.find('>').ok_or("A closing tag is never closed")?;
+            let name = closing[..end].trim();
+            let element = stack.pop().filter(|_| !stack.is_empty()).ok_or(format!("</{name}> closes nothing"))?;
+            if element.name != name {
+                return Err(format!("<{}> is closed by </{name}>", element.name));
+            }
+            stack.last_mut().expect("the root is never popped").children.push(element);
+            rest = &closing[end + 1..];
+        } else {
+            let (element, empty, length) = parse_tag(rest)?;
+            rest = &rest[length..];
+            if empty {
+                stack.last_mut().expect("the root is never popped").children.push(element);
+            } else {
+                stack.push(element);
+            }
+        }
+    }
+    if stack.len() > 1 {
+        return Err(format!("<{}> is never closed", stack[stack.len() - 1].name));
+    }
+    stack.pop()
+        .and_then(|root| root.children.into_iter().next())
+        .ok_or("The file has no elements".to_string())
+}
+
+// An opening tag, whether it closes itself and its length. The delimiters are ASCII so stepping through bytes is safe
+fn parse_tag(text: &str) -> Result<(Element, bool, usize), String> {
+    let bytes = text.as_bytes();
+    let mut i = 1;
+    while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' && bytes[i] != b'/' {
+        i += 1;
+    }
+    let mut element = Element {
+        name: text[1..i].to_string(),
+        ..Default::default()
+    };
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        match bytes.get(i) {
+            None => return Err(format!("<{}> is never closed", element.name)),
+            Some(b'>') => return Ok((element, false, i + 1)),
+            Some(b'/') if bytes.get(i + 1) == Some(&b'>') => return Ok((element, true, i + 2)),
+            _ => {},
+        }
+        let key_start = i;
+        while i < bytes.len() && bytes[i] != b'=' && !bytes[i].is_ascii_whitespace() && bytes[i] != b'>' {
+            i += 1;
+        }
+        let key = text[key_start..i].to_string();
+        while i < bytes.len() && (bytes[i].is_ascii_whitespace() || bytes[i] == b'=') {
+            i += 1;
+        }
+        let Some(quote) = bytes.get(i).copied().filter(|c| *c == b'"' || *c == b'\'') else {
+            return Err(format!("The attribute {key} of <{}> has no quoted value", element.name));
+        };
+        let value_start = i + 1;
+        i += 1;
+        while i < bytes.len() && bytes[i] != quote {
+            i += 1;
+        }
+        if i >= bytes.len() {
+            return Err(format!("The attribute {key} of <{}> is never closed", element.name));
+        }
+        element.attributes.push((key, unescape(&text[value_start..i])));
+        i += 1;
+    }
+}
@@ -0,0 +1,246 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::math::Vec2;
+
+use crate::app::layout::{LAYER_DISTANCE, SIBLING_DISTANCE};
+
+use super::random::Generated;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Classic {
+    Complete,
+    CompleteBipartite,
+    Cycle,
+    Path,
+    Star,
+    Wheel,
+    Hypercube,
+    Petersen,
+    Grid,
+    Torus,
+    BinaryTree,
+    MobiusKantor,
+    Heawood,
+}
+
+impl Classic {
+    pub const ALL: [Classic; 13] = [
+        Classic::Complete,
+        Classic::CompleteBipartite,
+        Classic::Cycle,
+        Classic::Path,
+        Classic::Star,
+        Classic::Wheel,
+        Classic::Hypercube,
+        Classic::Petersen,
+        Classic::Grid,
+        Classic::Torus,
+        Classic::BinaryTree,
+        Classic::MobiusKantor,
+        Classic::Heawood,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Classic::Complete => "Complete K_n",
+            Classic::CompleteBipartite => "Complete bipartite K_m,n",
+            Classic::Cycle => "Cycle C_n",
+            Classic::Path => "Path P_n",
+            Classic::Star => "Star with n leaves",
+            Classic::Wheel => "Wheel with n spokes",
+            Classic::Hypercube => "Hypercube Q_n",
+            Classic::Petersen => "Petersen",
+            Classic::Grid => "Grid m x n",
+            Classic::Torus => "Torus m x n",
+            Classic::BinaryTree => "Binary tree of depth n",
+            Classic::MobiusKantor => "Möbius-Kantor",
+            Classic::Heawood => "Heawood",
+        }
+    }
+}
+
+// Biggest hypercube dimension and binary tree depth, both double the vertices with every step
+const MAX_DIMENSION: usize = 8;
+const MAX_DEPTH: usize = 7;
+
+/**
+    # Classic Graphs
+    Builds a named graph around the origin, n and m are the sizes of the parameterised ones.
+    The sizes are raised to the smallest that still gives the graph, a cycle needs three vertices for example.
+*/
+pub fn classic(graph: Classic, n: usize, m: usize) -> Generated {
+    let (positions, edges) = match graph {
+        Classic::Complete => (ring(n, 0.), (0..n).flat_map(|a| (a + 1..n).map(move |b| (a, b))).collect()),
+        Classic::CompleteBipartite => {
+            // m on the top row, n on the bottom row
+            let positions = row(m, 0.).into_iter().chain(row(n, -LAYER_DISTANCE)).collect();
+            (positions, (0..m).flat_map(|a| (m..m + n).map(move |b| (a, b))).collect())
+        },
+        Classic::Cycle => {
+            let n = n.max(3);
+            (ring(n, 0.), (0..n).map(|v| (v, (v + 1) % n)).collect())
+        },
+        Classic::Path => (row(n, 0.), (1..n).map(|v| (v - 1, v)).collect()),
+        Classic::Star => {
+            let positions = std::iter::once(Vec2::ZERO).chain(ring(n, 0.)).collect();
+            (positions, (1..=n).map(|v| (0, v)).collect())
+        },
+        Classic::Wheel => {
+            let n = n.max(3);
+            let positions = std::iter::once(Vec2::ZERO).chain(ring(n, 0.)).collect();
+            let spokes = (1..=n).map(|v| (0, v));
+            let rim = (0..n).map(|v| (v + 1, (v + 1) % n + 1));
+            (positions, spokes.chain(rim).collect())
+        },
+        Classic::Hypercube => hypercube(n.clamp(1, MAX_DIMENSION)),
+        Classic::Petersen => generalized_petersen(5, 2),
+        Classic::Grid => lattice(m.max(1), n.max(1), false),
+        Classic::Torus => lattice(m.max(3), n.max(3), true),
+        Classic::BinaryTree => binary_tree(n.min(MAX_DEPTH)),
+        Classic::MobiusKantor => generalized_petersen(8, 3),
+        Classic::Heawood => {
+            // LCF notation [5, -5]^7, the even vertices reach 5 ahead on the 14-cycle
+            let cycle = (0..14).map(|v| (v, (v + 1) % 14));
+            let chords = (0..14).step_by(2).map(|v| (v, (v + 5) % 14));
+            (ring(14, 0.), cycle.chain(chords).collect())
+        },
+    };
+    Generated {
+        positions,
+        edges,
+        directed: false,
+    }
+}
+
+// n vertices on a circle starting at the top, the circle grows so neighbours keep their distance
+fn ring(n: usize, radius: f32) -> Vec<Vec2> {
+    let radius = radius.max((n as f32 * SIBLING_DISTANCE / TAU).max(SIBLING_DISTANCE));
+    (0..n).map(|v| Vec2::from_angle(FRAC_PI_2 - v as f32 * TAU / n as f32) * radius).collect()
+}
+
+// n vertices in a centered row at height y
+fn row(n: usize, y: f32) -> Vec<Vec2> {
+    (0..n).map(|v| Vec2::new((v as f32 - (n as f32 - 1.) / 2.) * SIBLING_DISTANCE, y)).collect()
+}
+
+// Every pair of bits spans a square, later squares are bigger and turned a little so no edge runs through a vertex.
+// The drawing is scaled until the closest vertices are a sibling distance apart
+fn hypercube(dimension: usize) -> (Vec<Vec2>, Vec<(usize, usize)>) {
+    let directions: Vec<Vec2> = (0..dimension)
+        .map(|bit| Vec2::from_angle((bit % 2) as f32 * FRAC_PI_2 + (bit / 2) as f32 * 0.3) * 2.2f32.powi((bit / 2) as i32))
+        .collect();
+    let mut positions: Vec<Vec2> = (0..1usize << dimension)
+        .map(|v| (0..dimension).filter(|bit| v >> bit & 1 == 1).map(|bit| directions[bit]).sum())
+        .collect();
+    let closest = (0..positions.len())
+        .flat_map(|a| (a + 1..positions.len()).map(move |b| (a, b)))
+        .map(|(a, b)| positions[a].distance(positions[b]))
+        .fold(f32::MAX, f32::min);
+    if closest < f32::MAX {
+        positions.iter_mut().for_each(|position| *position *= SIBLING_DISTANCE / closest);
+    }
+    let edges = (0..1usize << dimension)
+        .flat_map(|v| (0..dimension).map(move |bit| (v, v ^ 1 << bit)).filter(|(a, b)| a < b))
+        .collect();
+    (positions, edges)
+}
+
+// An outer n-cycle, every outer vertex has an inner twin and the twins are joined k steps apart
+fn generalized_petersen(n: usize, k: usize) -> (Vec<Vec2>, Vec<(usize, usize)>) {
+    let outer = ring(n, SIBLING_DISTANCE * 2.5);
+    let inner: Vec<Vec2> = outer.iter().map(|position| *position / 2.).collect();
+    let positions = outer.into_iter().chain(inner).collect();
+    let edges = (0..n)
+        .flat_map(|v| [(v, (v + 1) % n), (v, v + n), (v + n, (v + k) % n + n)])
+        .collect();
+    (positions, edges)
+}
+
+// Rows of n, the torus puts the rows on circles around each other so the wrapping edges stay short
+fn lattice(m: usize, n: usize, wrap: bool) -> (Vec<Vec2>, Vec<(usize, usize)>) {
+    let index = |r: usize, c: usize| r * n + c;
+    let positions = if wrap {
+        let inner = (n as f32 * SIBLING_DISTANCE / TAU).max(SIBLING_DISTANCE);
+        (0..m).flat_map(|r| ring(n, inner + r as f32 * SIBLING_DISTANCE)).collect()
+    } else {
+        (0..m).flat_map(|r| (0..n).map(move |c| Vec2::new(c as f32, -(r as f32)) * SIBLING_DISTANCE)).collect()
+    };
+    let mut edges = vec![];
+    for r in 0..m {
+        for c in 0..n {
+            if c + 1 < n || wrap {
+                edges.push((index(r, c), index(r, (c + 1) % n)));
+            }
+            if r + 1 < m || wrap {
+                edges.push((index(r, c), index((r + 1) % m, c)));
+            }
+        }
+    }
+    (positions, edges)
+}
+
+// Heap order, the children of v are 2v + 1 and 2v + 2. The leaves are a sibling distance apart
+fn binary_tree(depth: usize) -> (Vec<Vec2>, Vec<(usize, usize)>) {
+    let count = (1 << (depth + 1)) - 1;
+    let width = (1 << depth) as f32 * SIBLING_DISTANCE;
+    let positions = (0..count)
+        .map(|v: usize| {
+            let level = (v + 1).ilog2() as usize;
+            let slot = v + 1 - (1 << level);
+            let spacing = width / (1 << level) as f32;
+            Vec2::new((slot as f32 + 0.5) * spacing - width / 2., -(level as f32) * LAYER_DISTANCE)
+        })
+        .collect();
+    (positions, (1..count).map(|v| ((v - 1) / 2, v)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{classic, Classic};
+
+    // Vertices, simple edges and the degree of every vertex, repeated pairs and loops are dropped by the snapshot
+    fn counts(graph: Classic, n: usize, m: usize) -> (usize, usize, Vec<usize>) {
+        let generated = classic(graph, n, m);
+        let edges: Vec<(usize, usize, i32, bool)> = generated.edges.iter().map(|(a, b)| (*a, *b, 1, false)).collect();
+        let snapshot = GraphSnapshot::test_graph(generated.positions.len(), &edges);
+        assert_eq!(snapshot.edges.len(), generated.edges.len(), "{} has a repeated edge or a loop", graph.label());
+        let degrees = snapshot.simple_adjacency().iter().map(|adjacent| adjacent.len()).collect();
+        (snapshot.len(), snapshot.edges.len(), degrees)
+    }
+
+    fn regular(degrees: &[usize], degree: usize) -> bool {
+        degrees.iter().all(|d| *d == degree)
+    }
+
+    #[test]
+    fn cubic_graphs() {
+        for (graph, vertices, edges) in [(Classic::Petersen, 10, 15), (Classic::Heawood, 14, 21), (Classic::MobiusKantor, 16, 24)] {
+            let (n, m, degrees) = counts(graph, 0, 0);
+            assert_eq!((n, m), (vertices, edges), "{}", graph.label());
+            assert!(regular(&degrees, 3), "{}", graph.label());
+        }
+    }
+
+    #[test]
+    fn hypercubes() {
+        for d in 1..=6 {
+            let (n, m, degrees) = counts(Classic::Hypercube, d, 0);
+            assert_eq!((n, m), (1 << d, d << (d - 1)));
+            assert!(regular(&degrees, d));
+        }
+    }
+
+    #[test]
+    fn torus_is_four_regular() {
+        for (m, n) in [(3, 3), (4, 6), (5, 3)] {
+            let (vertices, edges, degrees) = counts(Classic::Torus, n, m);
+            assert_eq!((vertices, edges), (m * n, 2 * m * n));
+            assert!(regular(&degrees, 4));
+        }
+        // The grid keeps its border
+        let (vertices, edges, _) = counts(Classic::Grid, 4, 3);
+        assert_eq!((vertices, edges), (12, 17));
+    }
+}
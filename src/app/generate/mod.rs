@@ -0,0 +1,348 @@
+mod classic;
+mod random;
+mod rng;
+
+use bevy::{app::{Plugin, PostStartup, Update}, math::Vec2, prelude::{default, BuildChildren, Changed, ChildBuilder, Commands, Component, DetectChanges, NodeBundle, Query, Res, ResMut, Resource, TextBundle, Transform, With, Without}, text::Text, ui::{BackgroundColor, Display, FlexDirection, Interaction, PositionType, Style, UiRect, Val}};
+
+use crate::app::{algorithms::{playback::AlgorithmRun, steps::Trace, targets::AlgorithmTargets}, build_graph::builder::GraphBuilder, camera::MainCamera, ui::{spawn_button, text_style, InfoPanel, MenuAppExt, BUTTON_COLOR, BUTTON_HOVERED_COLOR, HEADER_COLOR, PANEL_COLOR}};
+
+use classic::{classic, Classic};
+use random::{generate, Generated, Model, Parameters};
+use rng::SeededRng;
+
+pub struct GeneratePlugin;
+impl Plugin for GeneratePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<GeneratorSettings>()
+        .add_systems(PostStartup, spawn_panel)
+        .add_systems(Update, (
+            press_model_buttons,
+            press_parameter_buttons,
+            press_action_buttons,
+            update_panel,
+        ))
+        .add_menu_button("Generate", "Toggle generator panel", toggle_panel)
+        ;
+        for graph in Classic::ALL {
+            app.add_menu_button("Library", graph.label(), move |
+                builder: GraphBuilder,
+                settings: Res<GeneratorSettings>,
+                q_camera: Query<&Transform, With<MainCamera>>,
+                info: ResMut<InfoPanel>,
+            | insert_classic(graph, builder, settings, q_camera, info));
+        }
+    }
+}
+
+/**
+    # Generator Settings
+    The chosen model and its parameters. The same settings and seed always give the same graph.
+*/
+#[derive(Resource)]
+pub struct GeneratorSettings {
+    visible: bool,
+    model: Model,
+    parameters: Parameters,
+    // Draws the seeds of the "New seed" button
+    seeds: SeededRng,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            model: Model::Gnp,
+            parameters: Parameters::default(),
+            seeds: SeededRng::new(0x5eed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Parameter {
+    Vertices,
+    Probability,
+    Edges,
+    Attach,
+    Neighbours,
+    Rewiring,
+    Radius,
+    Seed,
+    LibraryN,
+    LibraryM,
+}
+
+impl Parameter {
+    // The parameters of the random models, the library sizes are shown apart
+    const RANDOM: [Parameter; 8] = [
+        Parameter::Vertices,
+        Parameter::Probability,
+        Parameter::Edges,
+        Parameter::Attach,
+        Parameter::Neighbours,
+        Parameter::Rewiring,
+        Parameter::Radius,
+        Parameter::Seed,
+    ];
+
+    fn used_by(&self, model: Model) -> bool {
+        match self {
+            Parameter::Vertices | Parameter::Seed | Parameter::LibraryN | Parameter::LibraryM => true,
+            Parameter::Probability => matches!(model, Model::Gnp | Model::Dag),
+            Parameter::Edges => model == Model::Gnm,
+            Parameter::Attach => model == Model::BarabasiAlbert,
+            Parameter::Neighbours | Parameter::Rewiring => model == Model::WattsStrogatz,
+            Parameter::Radius => model == Model::Geometric,
+        }
+    }
+
+    fn label(&self, parameters: &Parameters) -> String {
+        match self {
+            Parameter::Vertices => format!("Vertices {}", parameters.n),
+            Parameter::Probability => format!("Edge probability {:.2}", parameters.p),
+            Parameter::Edges => format!("Edges {}", parameters.m),
+            Parameter::Attach => format!("Edges per new vertex {}", parameters.attach),
+            Parameter::Neighbours => format!("Ring neighbours {}", parameters.k),
+            Parameter::Rewiring => format!("Rewiring probability {:.2}", parameters.beta),
+            Parameter::Radius => format!("Radius {:.2}", parameters.radius),
+            Parameter::Seed => format!("Seed {}", parameters.seed),
+            Parameter::LibraryN => format!("n {}", parameters.library_n),
+            Parameter::LibraryM => format!("m {}", parameters.library_m),
+        }
+    }
+
+    // One press of - or +, the sign is -1 or 1
+    fn step(&self, parameters: &mut Parameters, sign: i32) {
+        let add = |value: usize, step: usize, min: usize, max: usize| {
+            if sign < 0 { value.saturating_sub(step).max(min) } else { (value + step).min(max) }
+        };
+        let add_f32 = |value: f32, step: f32| ((value + sign as f32 * step).clamp(0., 1.) * 100.).round() / 100.;
+        match self {
+            Parameter::Vertices => parameters.n = add(parameters.n, if parameters.n >= 50 { 10 } else { 1 }, 1, MAX_VERTICES),
+            Parameter::Probability => parameters.p = add_f32(parameters.p, 0.05),
+            Parameter::Edges => parameters.m = add(parameters.m, if parameters.m >= 50 { 10 } else { 1 }, 0, MAX_VERTICES * 4),
+            Parameter::Attach => parameters.attach = add(parameters.attach, 1, 1, 10),
+            Parameter::Neighbours => parameters.k = add(parameters.k, 2, 2, 20),
+            Parameter::Rewiring => parameters.beta = add_f32(parameters.beta, 0.05),
+            Parameter::Radius => parameters.radius = add_f32(parameters.radius, 0.05),
+            Parameter::Seed => parameters.seed = if sign < 0 { parameters.seed.saturating_sub(1) } else { parameters.seed.saturating_add(1) },
+            Parameter::LibraryN => parameters.library_n = add(parameters.library_n, 1, 1, MAX_LIBRARY_SIZE),
+            Parameter::LibraryM => parameters.library_m = add(parameters.library_m, 1, 1, MAX_LIBRARY_SIZE),
+        }
+    }
+}
+
+#[derive(Component)]
+struct GeneratorPanel;
+
+#[derive(Component)]
+struct ModelButton(Model);
+
+#[derive(Component)]
+struct ParameterRow(Parameter);
+
+#[derive(Component)]
+struct ParameterText(Parameter);
+
+// The parameter and -1 or 1
+#[derive(Component)]
+struct ParameterButton(Parameter, i32);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum ActionButton {
+    NewSeed,
+    Generate,
+    Clear,
+}
+
+const MAX_VERTICES: usize = 500;
+const MAX_LIBRARY_SIZE: usize = 30;
+
+fn toggle_panel(mut settings: ResMut<GeneratorSettings>) {
+    settings.visible = !settings.visible;
+}
+
+fn spawn_panel(
+    mut commands: Commands,
+    settings: Res<GeneratorSettings>,
+) {
+    let row_style = || Style {
+        flex_direction: FlexDirection::Row,
+        ..default()
+    };
+    commands.spawn((NodeBundle {
+        style: Style {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            right: Val::Px(0.),
+            bottom: Val::Px(0.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        background_color: PANEL_COLOR.into(),
+        ..default()
+    }, Interaction::default(), GeneratorPanel))
+    .with_children(|panel| {
+        panel.spawn(TextBundle::from_section("Random graph", text_style()));
+        // Two rows of models so the panel stays narrow
+        for models in Model::ALL.chunks(4) {
+            panel.spawn(NodeBundle { style: row_style(), ..default() })
+            .with_children(|row| {
+                for model in models {
+                    spawn_button(row, model.name(), BUTTON_COLOR, ModelButton(*model));
+                }
+            });
+        }
+        for parameter in Parameter::RANDOM {
+            spawn_parameter_row(panel, parameter, &settings.parameters);
+        }
+        panel.spawn(NodeBundle { style: row_style(), ..default() })
+        .with_children(|row| {
+            spawn_button(row, "New seed", BUTTON_COLOR, ActionButton::NewSeed);
+            spawn_button(row, "Generate", HEADER_COLOR, ActionButton::Generate);
+            spawn_button(row, "Clear graph", BUTTON_COLOR, ActionButton::Clear);
+        });
+        panel.spawn(TextBundle::from_section("Sizes of the Library graphs", text_style()));
+        for parameter in [Parameter::LibraryN, Parameter::LibraryM] {
+            spawn_parameter_row(panel, parameter, &settings.parameters);
+        }
+    });
+}
+
+fn spawn_parameter_row(panel: &mut ChildBuilder, parameter: Parameter, parameters: &Parameters) {
+    panel.spawn((NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            ..default()
+        },
+        ..default()
+    }, ParameterRow(parameter)))
+    .with_children(|row| {
+        spawn_button(row, "-", BUTTON_COLOR, ParameterButton(parameter, -1));
+        spawn_button(row, "+", BUTTON_COLOR, ParameterButton(parameter, 1));
+        row.spawn((TextBundle::from_section(parameter.label(parameters), text_style()), ParameterText(parameter)));
+    });
+}
+
+fn press_model_buttons(
+    mut settings: ResMut<GeneratorSettings>,
+    q_button: Query<(&Interaction, &ModelButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in q_button.iter() {
+        if *interaction == Interaction::Pressed {
+            settings.model = button.0;
+        }
+    }
+}
+
+fn press_parameter_buttons(
+    mut settings: ResMut<GeneratorSettings>,
+    mut q_button: Query<(&Interaction, &ParameterButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in q_button.iter_mut() {
+        match interaction {
+            Interaction::Pressed => button.0.step(&mut settings.parameters, button.1),
+            Interaction::Hovered => *color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *color = BUTTON_COLOR.into(),
+        }
+    }
+}
+
+fn press_action_buttons(
+    mut settings: ResMut<GeneratorSettings>,
+    mut q_button: Query<(&Interaction, &ActionButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut builder: GraphBuilder,
+    q_camera: Query<&Transform, With<MainCamera>>,
+    mut run: ResMut<AlgorithmRun>,
+    mut targets: ResMut<AlgorithmTargets>,
+    mut info: ResMut<InfoPanel>,
+) {
+    for (interaction, button, mut color) in q_button.iter_mut() {
+        let idle = if *button == ActionButton::Generate { HEADER_COLOR } else { BUTTON_COLOR };
+        match interaction {
+            Interaction::Pressed => {},
+            Interaction::Hovered => {
+                *color = BUTTON_HOVERED_COLOR.into();
+                continue;
+            },
+            Interaction::None => {
+                *color = idle.into();
+                continue;
+            },
+        }
+        match button {
+            ActionButton::NewSeed => settings.parameters.seed = settings.seeds.next_u64() % 1_000_000,
+            // Like the library graphs the generated graph is added to the existing one, clearing is a separate button
+            ActionButton::Generate => {
+                let center = q_camera.get_single().map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+                let generated = generate(settings.model, &settings.parameters);
+                let (vertices, edges) = insert(&mut builder, &generated, center);
+                info.text = format!(
+                    "{} with {} vertices and {} edges, seed {}",
+                    settings.model.name(),
+                    vertices,
+                    edges,
+                    settings.parameters.seed,
+                );
+            },
+            // The old graph goes together with everything that pointed at it
+            ActionButton::Clear => {
+                builder.clear();
+                run.start("", Trace::new());
+                targets.start = None;
+                targets.end = None;
+                info.text = "The graph was cleared".to_string();
+            },
+        }
+    }
+}
+
+// Adds the graph around the center with weight 1 on every edge, returns how many vertices and edges were added
+fn insert(builder: &mut GraphBuilder, generated: &Generated, center: Vec2) -> (usize, usize) {
+    let vertices: Vec<_> = generated.positions.iter().map(|position| builder.add_vertex(center + *position)).collect();
+    let edges = generated.edges.iter()
+        .filter(|(a, b)| builder.add_edge(vertices[*a], vertices[*b], 1, generated.directed).is_some())
+        .count();
+    (vertices.len(), edges)
+}
+
+// Library graphs go next to the existing graph instead of replacing it
+fn insert_classic(
+    graph: Classic,
+    mut builder: GraphBuilder,
+    settings: Res<GeneratorSettings>,
+    q_camera: Query<&Transform, With<MainCamera>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let center = q_camera.get_single().map_or(Vec2::ZERO, |transform| transform.translation.truncate());
+    let generated = classic(graph, settings.parameters.library_n, settings.parameters.library_m);
+    let (vertices, edges) = insert(&mut builder, &generated, center);
+    info.text = format!("{} with {} vertices and {} edges", graph.label(), vertices, edges);
+}
+
+// Shows the rows of the parameters the model reads and highlights the model
+fn update_panel(
+    settings: Res<GeneratorSettings>,
+    mut q_panel: Query<&mut Style, With<GeneratorPanel>>,
+    mut q_row: Query<(&mut Style, &ParameterRow), Without<GeneratorPanel>>,
+    mut q_text: Query<(&mut Text, &ParameterText)>,
+    mut q_model: Query<(&mut BackgroundColor, &ModelButton)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Ok(mut style) = q_panel.get_single_mut() {
+        style.display = if settings.visible { Display::Flex } else { Display::None };
+    }
+    for (mut style, row) in q_row.iter_mut() {
+        style.display = if row.0.used_by(settings.model) { Display::Flex } else { Display::None };
+    }
+    for (mut text, parameter) in q_text.iter_mut() {
+        text.sections[0].value = parameter.0.label(&settings.parameters);
+    }
+    for (mut color, button) in q_model.iter_mut() {
+        *color = if button.0 == settings.model { BUTTON_HOVERED_COLOR.into() } else { BUTTON_COLOR.into() };
+    }
+}
@@ -0,0 +1,426 @@
+use std::collections::VecDeque;
+
+use bevy::{math::Vec2, prelude::Entity, utils::HashSet};
+
+use crate::app::{build_graph::kdtree::TwoDTree, layout::{LAYER_DISTANCE, SIBLING_DISTANCE}};
+
+use super::rng::SeededRng;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Model {
+    // Every pair with probability p
+    Gnp,
+    // m distinct pairs
+    Gnm,
+    BarabasiAlbert,
+    WattsStrogatz,
+    Geometric,
+    Tree,
+    Dag,
+}
+
+impl Model {
+    pub const ALL: [Model; 7] = [Model::Gnp, Model::Gnm, Model::BarabasiAlbert, Model::WattsStrogatz, Model::Geometric, Model::Tree, Model::Dag];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::Gnp => "G(n, p)",
+            Model::Gnm => "G(n, m)",
+            Model::BarabasiAlbert => "Barabási-Albert",
+            Model::WattsStrogatz => "Watts-Strogatz",
+            Model::Geometric => "Random geometric",
+            Model::Tree => "Random tree",
+            Model::Dag => "Random DAG",
+        }
+    }
+}
+
+// The parameters of every model, each model reads the ones it needs
+#[derive(Clone, Copy, Debug)]
+pub struct Parameters {
+    pub n: usize,
+    // Edge probability of G(n, p) and the random DAG
+    pub p: f32,
+    // Edges of G(n, m)
+    pub m: usize,
+    // Edges of every new vertex in Barabási-Albert
+    pub attach: usize,
+    // Neighbours of every vertex on the Watts-Strogatz ring, even
+    pub k: usize,
+    // Rewiring probability of Watts-Strogatz
+    pub beta: f32,
+    // Connection radius of random geometric graphs as a share of the side of the square
+    pub radius: f32,
+    pub seed: u64,
+    // n and m of the library graphs
+    pub library_n: usize,
+    pub library_m: usize,
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
+        Self {
+            n: 20,
+            p: 0.15,
+            m: 30,
+            attach: 2,
+            k: 4,
+            beta: 0.1,
+            radius: 0.25,
+            seed: 1,
+            library_n: 5,
+            library_m: 3,
+        }
+    }
+}
+
+// A graph with positions around the origin, not yet in the world
+pub struct Generated {
+    pub positions: Vec<Vec2>,
+    pub edges: Vec<(usize, usize)>,
+    pub directed: bool,
+}
+
+// Side of the square the vertices are scattered in, grows with the vertices so the density stays the same
+fn side(n: usize) -> f32 {
+    (n as f32).sqrt() * SIBLING_DISTANCE * 1.5
+}
+
+fn scatter(n: usize, rng: &mut SeededRng) -> Vec<Vec2> {
+    let side = side(n);
+    (0..n).map(|_| (Vec2::new(rng.next_f32(), rng.next_f32()) - 0.5) * side).collect()
+}
+
+fn circle(n: usize) -> Vec<Vec2> {
+    let radius = (n as f32 * SIBLING_DISTANCE / std::f32::consts::TAU).max(SIBLING_DISTANCE);
+    (0..n).map(|v| Vec2::from_angle(std::f32::consts::FRAC_PI_2 - v as f32 * std::f32::consts::TAU / n as f32) * radius).collect()
+}
+
+// Rows by layer, every row centered
+fn layered(layer: &[usize]) -> Vec<Vec2> {
+    let count = layer.iter().max().map_or(0, |l| l + 1);
+    let mut width = vec![0; count];
+    for l in layer.iter() {
+        width[*l] += 1;
+    }
+    let mut next = vec![0; count];
+    layer.iter()
+        .map(|l| {
+            let x = next[*l] as f32 - (width[*l] as f32 - 1.) / 2.;
+            next[*l] += 1;
+            Vec2::new(x * SIBLING_DISTANCE, -(*l as f32) * LAYER_DISTANCE)
+        })
+        .collect()
+}
+
+pub fn generate(model: Model, parameters: &Parameters) -> Generated {
+    let mut rng = SeededRng::new(parameters.seed);
+    let n = parameters.n;
+    match model {
+        Model::Gnp => Generated {
+            edges: (0..n).flat_map(|a| (a + 1..n).map(move |b| (a, b))).filter(|_| rng.chance(parameters.p)).collect(),
+            positions: scatter(n, &mut SeededRng::new(parameters.seed ^ 1)),
+            directed: false,
+        },
+        Model::Gnm => Generated {
+            edges: gnm(n, parameters.m, &mut rng),
+            positions: scatter(n, &mut rng),
+            directed: false,
+        },
+        Model::BarabasiAlbert => Generated {
+            edges: barabasi_albert(n, parameters.attach, &mut rng),
+            positions: scatter(n, &mut rng),
+            directed: false,
+        },
+        Model::WattsStrogatz => Generated {
+            edges: watts_strogatz(n, parameters.k, parameters.beta, &mut rng),
+            positions: circle(n),
+            directed: false,
+        },
+        Model::Geometric => {
+            let positions = scatter(n, &mut rng);
+            Generated {
+                edges: geometric(&positions, parameters.radius * side(n)),
+                positions,
+                directed: false,
+            }
+        },
+        Model::Tree => {
+            let edges = prufer_tree(n, &mut rng);
+            Generated {
+                positions: layered(&bfs_depth(n, &edges)),
+                edges,
+                directed: false,
+            }
+        },
+        Model::Dag => {
+            let (edges, layer) = dag(n, parameters.p, &mut rng);
+            Generated {
+                positions: layered(&layer),
+                edges,
+                directed: true,
+            }
+        },
+    }
+}
+
+// Draws pairs until m different ones came up, capped at the number of pairs
+fn gnm(n: usize, m: usize, rng: &mut SeededRng) -> Vec<(usize, usize)> {
+    let m = m.min(n * n.saturating_sub(1) / 2);
+    let mut chosen: HashSet<(usize, usize)> = HashSet::new();
+    let mut edges = vec![];
+    while edges.len() < m {
+        let (a, b) = (rng.below(n), rng.below(n));
+        if a == b || !chosen.insert((a.min(b), a.max(b))) {
+            continue;
+        }
+        edges.push((a.min(b), a.max(b)));
+    }
+    edges
+}
+
+/**
+    # Barabási-Albert
+    Starts with a clique of attach + 1 vertices, every later vertex connects to attach different vertices picked by degree.
+    Every edge puts both of its ends on a list, so drawing from the list is drawing proportional to the degree.
+*/
+fn barabasi_albert(n: usize, attach: usize, rng: &mut SeededRng) -> Vec<(usize, usize)> {
+    let attach = attach.max(1);
+    let core = (attach + 1).min(n);
+    let mut edges: Vec<(usize, usize)> = (0..core).flat_map(|a| (a + 1..core).map(move |b| (a, b))).collect();
+    let mut ends: Vec<usize> = edges.iter().flat_map(|(a, b)| [*a, *b]).collect();
+    for v in core..n {
+        let mut targets: Vec<usize> = vec![];
+        while targets.len() < attach.min(v) {
+            let target = if ends.is_empty() { rng.below(v) } else { ends[rng.below(ends.len())] };
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        for target in targets {
+            edges.push((target, v));
+            ends.extend([target, v]);
+        }
+    }
+    edges
+}
+
+// A ring where every vertex knows its k nearest, every edge is rewired to a random vertex with probability beta
+fn watts_strogatz(n: usize, k: usize, beta: f32, rng: &mut SeededRng) -> Vec<(usize, usize)> {
+    let half = (k / 2).min(n.saturating_sub(1) / 2);
+    let key = |a: usize, b: usize| (a.min(b), a.max(b));
+    let mut present: HashSet<(usize, usize)> = HashSet::new();
+    let mut edges = vec![];
+    for step in 1..=half {
+        for a in 0..n {
+            let b = (a + step) % n;
+            present.insert(key(a, b));
+            edges.push((a, b));
+        }
+    }
+    for edge in edges.iter_mut() {
+        if !rng.chance(beta) {
+            continue;
+        }
+        let a = edge.0;
+        // Vertices connected to everything keep their edge
+        if (0..n).all(|c| c == a || present.contains(&key(a, c))) {
+            continue;
+        }
+        let mut c = rng.below(n);
+        while c == a || present.contains(&key(a, c)) {
+            c = rng.below(n);
+        }
+        present.remove(&key(edge.0, edge.1));
+        present.insert(key(a, c));
+        *edge = (a, c);
+    }
+    edges
+}
+
+// Connects every pair closer than the radius, the candidates come from a radius search in a kd-tree of the points
+fn geometric(positions: &[Vec2], radius: f32) -> Vec<(usize, usize)> {
+    let mut tree = TwoDTree::new();
+    tree.insert_list(positions.iter().enumerate().map(|(v, position)| (Entity::from_raw(v as u32), *position)).collect());
+    let mut edges = vec![];
+    for (a, position) in positions.iter().enumerate() {
+        let mut near: Vec<usize> = tree.radius_search(*position, radius)
+            .into_iter()
+            .map(|item| item.0.index() as usize)
+            .filter(|b| *b > a)
+            .collect();
+        near.sort();
+        edges.extend(near.into_iter().map(|b| (a, b)));
+    }
+    edges
+}
+
+/**
+    # Prüfer Tree
+    A random Prüfer sequence of length n - 2 decodes to a tree drawn uniformly from all n^(n-2) labelled trees.
+    The smallest leaf is joined to the next number of the sequence until two vertices are left.
+*/
+fn prufer_tree(n: usize, rng: &mut SeededRng) -> Vec<(usize, usize)> {
+    if n < 2 {
+        return vec![];
+    }
+    let sequence: Vec<usize> = (0..n - 2).map(|_| rng.below(n)).collect();
+    let mut degree = vec![1; n];
+    for v in sequence.iter() {
+        degree[*v] += 1;
+    }
+    let mut leaves: std::collections::BinaryHeap<std::cmp::Reverse<usize>> = (0..n).filter(|v| degree[*v] == 1).map(std::cmp::Reverse).collect();
+    let mut edges = vec![];
+    for v in sequence {
+        let std::cmp::Reverse(leaf) = leaves.pop().expect("a tree always has a leaf");
+        edges.push((v.min(leaf), v.max(leaf)));
+        degree[v] -= 1;
+        if degree[v] == 1 {
+            leaves.push(std::cmp::Reverse(v));
+        }
+    }
+    let std::cmp::Reverse(a) = leaves.pop().expect("two vertices are left");
+    let std::cmp::Reverse(b) = leaves.pop().expect("two vertices are left");
+    edges.push((a, b));
+    edges
+}
+
+fn bfs_depth(n: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut adjacency: Vec<Vec<usize>> = vec![vec![]; n];
+    for (a, b) in edges.iter() {
+        adjacency[*a].push(*b);
+        adjacency[*b].push(*a);
+    }
+    let mut depth: Vec<Option<usize>> = vec![None; n];
+    for root in 0..n {
+        if depth[root].is_some() {
+            continue;
+        }
+        depth[root] = Some(0);
+        let mut queue = VecDeque::from([root]);
+        while let Some(v) = queue.pop_front() {
+            for w in adjacency[v].iter().copied() {
+                if depth[w].is_none() {
+                    depth[w] = depth[v].map(|d| d + 1);
+                    queue.push_back(w);
+                }
+            }
+        }
+    }
+    depth.into_iter().map(|d| d.unwrap_or(0)).collect()
+}
+
+// Edges only go forward in a shuffled order of the vertices, so there can't be a cycle. Returns the longest path layers too
+fn dag(n: usize, p: f32, rng: &mut SeededRng) -> (Vec<(usize, usize)>, Vec<usize>) {
+    let mut order: Vec<usize> = (0..n).collect();
+    rng.shuffle(&mut order);
+    let mut edges = vec![];
+    let mut layer = vec![0; n];
+    for i in 0..n {
+        for j in i + 1..n {
+            if rng.chance(p) {
+                edges.push((order[i], order[j]));
+                layer[order[j]] = layer[order[j]].max(layer[order[i]] + 1);
+            }
+        }
+    }
+    (edges, layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{generate, Generated, Model, Parameters};
+
+    // Loops and repeated pairs are dropped by the snapshot, so they show up as missing edges
+    fn snapshot(generated: &Generated) -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = generated.edges.iter().map(|(a, b)| (*a, *b, 1, generated.directed)).collect();
+        GraphSnapshot::test_graph(generated.positions.len(), &edges)
+    }
+
+    fn connected(graph: &GraphSnapshot) -> bool {
+        let adjacency = graph.simple_adjacency();
+        let mut seen = vec![false; graph.len()];
+        seen[0] = true;
+        let mut queue = VecDeque::from([0]);
+        while let Some(v) = queue.pop_front() {
+            for w in adjacency[v].iter() {
+                if !seen[*w] {
+                    seen[*w] = true;
+                    queue.push_back(*w);
+                }
+            }
+        }
+        seen.into_iter().all(|s| s)
+    }
+
+    #[test]
+    fn same_seed_same_graph() {
+        for model in Model::ALL {
+            let parameters = Parameters { seed: 42, ..Default::default() };
+            let (a, b) = (generate(model, &parameters), generate(model, &parameters));
+            assert_eq!((a.edges, a.positions), (b.edges, b.positions), "{}", model.name());
+        }
+        let other = Parameters { seed: 43, ..Default::default() };
+        assert_ne!(generate(Model::Gnm, &other).edges, generate(Model::Gnm, &Parameters { seed: 42, ..Default::default() }).edges);
+    }
+
+    #[test]
+    fn gnm_draws_m_distinct_edges() {
+        for (n, m) in [(20, 30), (10, 45), (6, 100)] {
+            let generated = generate(Model::Gnm, &Parameters { n, m, ..Default::default() });
+            let expected = m.min(n * (n - 1) / 2);
+            assert_eq!(generated.edges.len(), expected);
+            assert_eq!(snapshot(&generated).edges.len(), expected);
+        }
+    }
+
+    #[test]
+    fn prufer_tree_is_a_spanning_tree() {
+        for seed in 0..20 {
+            let generated = generate(Model::Tree, &Parameters { n: 30, seed, ..Default::default() });
+            let graph = snapshot(&generated);
+            assert_eq!(graph.edges.len(), 29);
+            assert!(connected(&graph));
+        }
+    }
+
+    #[test]
+    fn dag_has_no_cycle() {
+        for seed in 0..20 {
+            let generated = generate(Model::Dag, &Parameters { n: 25, p: 0.3, seed, ..Default::default() });
+            let graph = snapshot(&generated);
+            assert!(generated.directed);
+            // Kahn's algorithm takes every vertex only without a cycle
+            let mut incoming: Vec<usize> = graph.inc.iter().map(|inc| inc.len()).collect();
+            let mut queue: VecDeque<usize> = (0..graph.len()).filter(|v| incoming[*v] == 0).collect();
+            let mut taken = 0;
+            while let Some(v) = queue.pop_front() {
+                taken += 1;
+                for (w, _) in graph.out[v].iter() {
+                    incoming[*w] -= 1;
+                    if incoming[*w] == 0 {
+                        queue.push_back(*w);
+                    }
+                }
+            }
+            assert_eq!(taken, graph.len());
+        }
+    }
+
+    #[test]
+    fn barabasi_albert_edge_count() {
+        for (n, attach) in [(20, 2), (50, 3), (10, 1)] {
+            let generated = generate(Model::BarabasiAlbert, &Parameters { n, attach, ..Default::default() });
+            // A clique of attach + 1 vertices and attach edges for every later one
+            let expected = attach * (attach + 1) / 2 + (n - attach - 1) * attach;
+            assert_eq!(generated.edges.len(), expected);
+            let graph = snapshot(&generated);
+            assert_eq!(graph.edges.len(), expected);
+            assert!(connected(&graph));
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use bevy::{app::{PreStartup, Update}, input::ButtonInput, math::Vec2, prelude::{App, Camera, Commands, DetectChanges, Entity, EventReader, GlobalTransform, IntoSystemConfigs, Local, MouseButton, Plugin, Query, Res, ResMut, Resource, Startup, TouchInput, With}, reflect::Reflect, time::Time, utils::HashMap, window::{PrimaryWindow, Window}};
 use leafwing_input_manager::{plugin::InputManagerPlugin, prelude::{serde_typetag, ActionState, DualAxislike, InputMap, MouseMove, MouseScrollAxis, UserInput}, Actionlike, InputManagerBundle};
 
-use super::{build_graph::res::InputCoords, camera::{spawn_camera, MainCamera}};
+use super::{build_graph::res::InputCoords, camera::{spawn_camera, MainCamera}, ui::PointerOverUi};
 
 #[cfg(target_arch = "wasm32")]
 use crate::wasm_module::log_js;
@@ -80,6 +80,7 @@ fn handle_selection(
     mut q_myaction: Query<&mut ActionState<NormalInput>>,
     mut pressed_duration: Local<Option<f32>>,
     time: Res<Time>,
+    over_ui: Res<PointerOverUi>,
 ) {
     const TRIGGERMAXTIME: f32 = 0.5;
     let mut state = q_myaction.single_mut();
@@ -97,7 +98,7 @@ fn handle_selection(
             }
         }
     } else if state.just_released(&NormalInput::Pressed) {
-        if pressed_duration.is_some() && pressed_duration.unwrap() <= TRIGGERMAXTIME {
+        if pressed_duration.is_some() && pressed_duration.unwrap() <= TRIGGERMAXTIME && !over_ui.0 {
             println!("Press Select");
             #[cfg(target_arch = "wasm32")]
             {
@@ -0,0 +1,237 @@
+use bevy::{app::Plugin, math::Vec2, prelude::{Commands, Entity, Query, Res, ResMut, Resource, Transform, With}};
+
+use crate::app::{algorithms::{graph::{GraphData, GraphSnapshot}, spectral::spectral_layout, targets::AlgorithmTargets}, build_graph::{components::Vertex, movement::VertexTween}, ui::{InfoPanel, MenuAppExt}};
+
+use super::{force::ForceLayout, sugiyama::layered_layout, tree::tree_layout, SIBLING_DISTANCE};
+
+pub struct ArrangePlugin;
+impl Plugin for ArrangePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<LayoutHistory>()
+        .add_menu_button("Layout", "Circular", arrange_circular)
+        .add_menu_button("Layout", "Grid", arrange_grid)
+        .add_menu_button("Layout", "Tree from start vertex", arrange_tree)
+        .add_menu_button("Layout", "Layered (Sugiyama)", arrange_layered)
+        .add_menu_button("Layout", "Spectral", arrange_spectral)
+        .add_menu_button("Layout", "Undo layout", undo_layout)
+        ;
+    }
+}
+
+// The positions before every one-shot layout, the newest last
+#[derive(Resource, Default)]
+pub struct LayoutHistory {
+    stack: Vec<Vec<(Entity, Vec2)>>,
+}
+
+const HISTORY_LENGTH: usize = 20;
+
+// Vertices around a circle in insertion order, the circle is big enough to keep them apart
+pub fn circular_layout(graph: &GraphSnapshot) -> Vec<Vec2> {
+    let n = graph.len();
+    let radius = if n < 2 { 0. } else { (n as f32 * SIBLING_DISTANCE / std::f32::consts::TAU).max(SIBLING_DISTANCE) };
+    (0..n)
+        .map(|v| Vec2::from_angle(std::f32::consts::FRAC_PI_2 - v as f32 * std::f32::consts::TAU / n as f32) * radius)
+        .collect()
+}
+
+// Rows of a square grid in insertion order
+pub fn grid_layout(graph: &GraphSnapshot) -> Vec<Vec2> {
+    let columns = (graph.len() as f32).sqrt().ceil().max(1.) as usize;
+    (0..graph.len())
+        .map(|v| Vec2::new((v % columns) as f32, -((v / columns) as f32)) * SIBLING_DISTANCE)
+        .collect()
+}
+
+/**
+    # Apply Layout
+    Moves the layout over the old centroid of the graph and tweens every vertex there. The old positions go on the history
+    and the force layout pauses so it doesn't pull the result apart. The kd-tree follows the tween every frame.
+*/
+fn apply_layout(
+    commands: &mut Commands,
+    graph: &GraphSnapshot,
+    mut positions: Vec<Vec2>,
+    history: &mut LayoutHistory,
+    force: &mut ForceLayout,
+) {
+    if graph.is_empty() {
+        return;
+    }
+    let n = graph.len() as f32;
+    let old_center = graph.positions.iter().copied().sum::<Vec2>() / n;
+    let min = positions.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min);
+    let max = positions.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max);
+    let shift = old_center - (min + max) / 2.;
+    positions.iter_mut().for_each(|position| *position += shift);
+
+    history.stack.push(graph.vertices.iter().copied().zip(graph.positions.iter().copied()).collect());
+    if history.stack.len() > HISTORY_LENGTH {
+        history.stack.remove(0);
+    }
+    force.running = false;
+    for (v, position) in positions.into_iter().enumerate() {
+        commands.entity(graph.vertices[v]).insert(VertexTween::new(graph.positions[v], position));
+    }
+}
+
+fn arrange_circular(
+    mut commands: Commands,
+    graph: GraphData,
+    mut history: ResMut<LayoutHistory>,
+    mut force: ResMut<ForceLayout>,
+) {
+    let graph = graph.snapshot();
+    apply_layout(&mut commands, &graph, circular_layout(&graph), &mut history, &mut force);
+}
+
+fn arrange_grid(
+    mut commands: Commands,
+    graph: GraphData,
+    mut history: ResMut<LayoutHistory>,
+    mut force: ResMut<ForceLayout>,
+) {
+    let graph = graph.snapshot();
+    apply_layout(&mut commands, &graph, grid_layout(&graph), &mut history, &mut force);
+}
+
+// Without a start vertex the tree grows from the first vertex
+fn arrange_tree(
+    mut commands: Commands,
+    graph: GraphData,
+    targets: Res<AlgorithmTargets>,
+    mut history: ResMut<LayoutHistory>,
+    mut force: ResMut<ForceLayout>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let graph = graph.snapshot();
+    if graph.is_empty() {
+        return;
+    }
+    let root = targets.start.and_then(|start| graph.index.get(&start).copied()).unwrap_or(0);
+    info.text = format!("Tree layout rooted at {}", graph.name(root));
+    apply_layout(&mut commands, &graph, tree_layout(&graph, root), &mut history, &mut force);
+}
+
+fn arrange_layered(
+    mut commands: Commands,
+    graph: GraphData,
+    mut history: ResMut<LayoutHistory>,
+    mut force: ResMut<ForceLayout>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let graph = graph.snapshot();
+    if graph.is_empty() {
+        return;
+    }
+    let layered = layered_layout(&graph);
+    info.text = format!(
+        "Layered layout with {} layers and {} crossings{}",
+        layered.layers,
+        layered.crossings,
+        if layered.reversed > 0 { format!(", {} edges were turned around to break cycles", layered.reversed) } else { String::new() },
+    );
+    apply_layout(&mut commands, &graph, layered.positions, &mut history, &mut force);
+}
+
+// The coordinates of the spectral layout are tiny, the layout is scaled to the size a grid of the vertices would have
+fn arrange_spectral(
+    mut commands: Commands,
+    graph: GraphData,
+    mut history: ResMut<LayoutHistory>,
+    mut force: ResMut<ForceLayout>,
+) {
+    let graph = graph.snapshot();
+    let size = (graph.len() as f32).sqrt() * SIBLING_DISTANCE;
+    apply_layout(&mut commands, &graph, spectral_layout(&graph, size), &mut history, &mut force);
+}
+
+fn undo_layout(
+    mut commands: Commands,
+    mut history: ResMut<LayoutHistory>,
+    q_vertex: Query<&Transform, With<Vertex>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let Some(positions) = history.stack.pop() else {
+        info.text = "There is no layout to undo".to_string();
+        return;
+    };
+    // Vertices deleted since then stay deleted
+    for (entity, position) in positions {
+        let Ok(transform) = q_vertex.get(entity) else {continue};
+        commands.entity(entity).insert(VertexTween::new(transform.translation.truncate(), position));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, math::Vec2, prelude::{Commands, Entity, ResMut, Transform, World}};
+
+    use crate::app::{algorithms::graph::GraphSnapshot, build_graph::{components::Vertex, movement::VertexTween}, layout::force::ForceLayout, ui::InfoPanel};
+
+    use super::{apply_layout, grid_layout, undo_layout, LayoutHistory, HISTORY_LENGTH};
+
+    fn tweens(world: &mut World, vertices: &[Entity]) -> Vec<(Vec2, Vec2)> {
+        vertices.iter().map(|v| {
+            let tween = world.get::<VertexTween>(*v).unwrap();
+            (tween.from, tween.to)
+        }).collect()
+    }
+
+    // Snapshot of the vertices in the world, the edges don't matter to a layout move
+    fn snapshot(world: &mut World, vertices: &[Entity]) -> GraphSnapshot {
+        let mut graph = GraphSnapshot::test_graph(vertices.len(), &[]);
+        graph.positions = vertices.iter().map(|v| world.get::<Transform>(*v).unwrap().translation.truncate()).collect();
+        graph.vertices = vertices.to_vec();
+        graph.index = vertices.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+        graph
+    }
+
+    #[test]
+    fn undo_goes_back_to_the_old_positions() {
+        let mut world = World::new();
+        world.init_resource::<LayoutHistory>();
+        world.init_resource::<InfoPanel>();
+        let old = [Vec2::new(0., 0.), Vec2::new(300., 40.), Vec2::new(-120., 500.), Vec2::new(60., -80.)];
+        let vertices: Vec<Entity> = old.iter().map(|p| world.spawn((Vertex, Transform::from_translation(p.extend(0.)))).id()).collect();
+
+        let graph = snapshot(&mut world, &vertices);
+        let mut force = ForceLayout::default();
+        force.running = true;
+        let moved = world.run_system_once(move |mut commands: Commands, mut history: ResMut<LayoutHistory>| {
+            apply_layout(&mut commands, &graph, grid_layout(&graph), &mut history, &mut force);
+            force.running
+        });
+        assert!(!moved);
+        let (from, new): (Vec<Vec2>, Vec<Vec2>) = tweens(&mut world, &vertices).into_iter().unzip();
+        assert_eq!(from, old);
+        assert_ne!(new, old);
+
+        // The tween is done, undo starts from the layout
+        for (v, position) in vertices.iter().zip(&new) {
+            world.get_mut::<Transform>(*v).unwrap().translation = position.extend(0.);
+        }
+        world.run_system_once(undo_layout);
+        assert_eq!(tweens(&mut world, &vertices), new.into_iter().zip(old).collect::<Vec<_>>());
+        assert!(world.resource::<InfoPanel>().text.is_empty());
+
+        world.run_system_once(undo_layout);
+        assert_eq!(world.resource::<InfoPanel>().text, "There is no layout to undo");
+    }
+
+    #[test]
+    fn history_forgets_the_oldest_layouts() {
+        let mut world = World::new();
+        world.init_resource::<LayoutHistory>();
+        let vertex = world.spawn((Vertex, Transform::default())).id();
+        let graph = snapshot(&mut world, &[vertex]);
+        world.run_system_once(move |mut commands: Commands, mut history: ResMut<LayoutHistory>| {
+            let mut force = ForceLayout::default();
+            for _ in 0..HISTORY_LENGTH + 5 {
+                apply_layout(&mut commands, &graph, vec![Vec2::ZERO], &mut history, &mut force);
+            }
+        });
+        assert_eq!(world.resource::<LayoutHistory>().stack.len(), HISTORY_LENGTH);
+    }
+}
@@ -0,0 +1,218 @@
+use bevy::math::Vec2;
+
+/**
+    # Barnes-Hut Quadtree
+    Every cell stores the total mass and the center of mass of the bodies inside of it. A cell that is small compared to its
+    distance from a body acts on it as one heavy body, which brings the repulsion of n bodies down to O(n log n).
+*/
+pub struct QuadTree {
+    nodes: Vec<QuadNode>,
+    // Body -> (Leaf, Position, Mass)
+    bodies: Vec<(usize, Vec2, f32)>,
+}
+
+struct QuadNode {
+    center: Vec2,
+    half: f32,
+    mass: f32,
+    count: usize,
+    // Weighted sum of the positions while building, the center of mass afterwards
+    mass_center: Vec2,
+    children: Option<[usize; 4]>,
+    // The bodies of a leaf, more than one when they are on the same spot
+    residents: Vec<usize>,
+}
+
+impl QuadNode {
+    fn new(center: Vec2, half: f32) -> Self {
+        Self {
+            center,
+            half,
+            mass: 0.,
+            count: 0,
+            mass_center: Vec2::ZERO,
+            children: None,
+            residents: vec![],
+        }
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        (point - self.center).abs().max_element() <= self.half
+    }
+}
+
+// Cells smaller than this stop splitting
+const MIN_HALF: f32 = 1e-2;
+const GOLDEN_ANGLE: f32 = 2.399_963;
+
+impl QuadTree {
+    pub fn new(positions: &[Vec2], masses: &[f32]) -> Self {
+        let min = positions.iter().copied().fold(Vec2::splat(f32::MAX), Vec2::min);
+        let max = positions.iter().copied().fold(Vec2::splat(f32::MIN), Vec2::max);
+        let center = if positions.is_empty() { Vec2::ZERO } else { (min + max) / 2. };
+        let half = ((max - min).max_element() / 2.).max(1.);
+        let bodies = positions.iter().zip(masses.iter()).map(|(position, mass)| (0, *position, *mass)).collect();
+        let mut tree = Self { nodes: vec![QuadNode::new(center, half)], bodies };
+        for (body, position) in positions.iter().enumerate() {
+            tree.insert(0, body, *position, positions, masses[body]);
+        }
+        for node in tree.nodes.iter_mut().filter(|node| node.mass > 0.) {
+            node.mass_center /= node.mass;
+        }
+        tree
+    }
+
+    fn quadrant(&self, node: usize, point: Vec2) -> usize {
+        let center = self.nodes[node].center;
+        (point.x > center.x) as usize + 2 * (point.y > center.y) as usize
+    }
+
+    fn split(&mut self, node: usize) -> [usize; 4] {
+        let QuadNode { center, half, .. } = self.nodes[node];
+        let quarter = half / 2.;
+        let first = self.nodes.len();
+        for quadrant in 0..4 {
+            let offset = Vec2::new(
+                if quadrant & 1 == 1 { quarter } else { -quarter },
+                if quadrant & 2 == 2 { quarter } else { -quarter },
+            );
+            self.nodes.push(QuadNode::new(center + offset, quarter));
+        }
+        let children = [first, first + 1, first + 2, first + 3];
+        self.nodes[node].children = Some(children);
+        children
+    }
+
+    fn insert(&mut self, mut node: usize, body: usize, position: Vec2, positions: &[Vec2], mass: f32) {
+        loop {
+            self.nodes[node].mass += mass;
+            self.nodes[node].count += 1;
+            self.nodes[node].mass_center += position * mass;
+            if let Some(children) = self.nodes[node].children {
+                node = children[self.quadrant(node, position)];
+                continue;
+            }
+            let resident = self.nodes[node].residents.first().copied();
+            if resident.is_none_or(|other| self.nodes[node].half < MIN_HALF || positions[other] == position) {
+                self.nodes[node].residents.push(body);
+                self.bodies[body].0 = node;
+                return;
+            }
+            // Push the resident bodies, which share one spot, one level down and try again
+            let other = resident.expect("the leaf has residents");
+            let other_mass = self.nodes[node].mass - mass;
+            let other_count = self.nodes[node].count - 1;
+            let children = self.split(node);
+            let residents = std::mem::take(&mut self.nodes[node].residents);
+            let child = children[self.quadrant(node, positions[other])];
+            for resident in residents.iter() {
+                self.bodies[*resident].0 = child;
+            }
+            self.nodes[child].mass = other_mass;
+            self.nodes[child].count = other_count;
+            self.nodes[child].mass_center = positions[other] * other_mass;
+            self.nodes[child].residents = residents;
+            node = children[self.quadrant(node, position)];
+        }
+    }
+
+    /**
+        # Repulsion
+        The sum of strength * mass / distance pushing the body away from every other body, theta is the largest ratio of
+        cell size to distance at which a cell still counts as one body. The leaf of the body itself only counts with
+        the other bodies on the same spot, the body is taken out of its mass and center of mass.
+    */
+    pub fn repulsion(&self, body: usize, position: Vec2, strength: f32, theta: f32) -> Vec2 {
+        let (leaf, own_position, own_mass) = self.bodies[body];
+        let mut force = Vec2::ZERO;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let (mut mass, mut mass_center) = (node.mass, node.mass_center);
+            if index == leaf {
+                if node.count <= 1 {
+                    continue;
+                }
+                mass -= own_mass;
+                mass_center = (node.mass_center * node.mass - own_position * own_mass) / mass;
+            }
+            if mass <= 0. {
+                continue;
+            }
+            let mut delta = position - mass_center;
+            // Bodies on the same spot push apart in a direction that depends on the body
+            if delta.length_squared() < 1e-6 {
+                delta = Vec2::from_angle(body as f32 * GOLDEN_ANGLE);
+            }
+            let distance = delta.length().max(1.);
+            let far = !node.contains(position) && 2. * node.half / distance < theta;
+            match node.children {
+                Some(children) if !far => stack.extend(children),
+                _ => force += delta / distance * strength * mass / distance,
+            }
+        }
+        force
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::QuadTree;
+
+    // The sum over every other body, what the tree approximates
+    fn brute_force(positions: &[Vec2], masses: &[f32], body: usize) -> Vec2 {
+        (0..positions.len())
+            .filter(|other| *other != body)
+            .map(|other| {
+                let delta = positions[body] - positions[other];
+                let distance = delta.length().max(1.);
+                delta / distance * masses[other] / distance
+            })
+            .sum()
+    }
+
+    #[test]
+    fn lone_body_feels_nothing() {
+        let tree = QuadTree::new(&[Vec2::new(3., 4.)], &[2.]);
+        assert_eq!(tree.repulsion(0, Vec2::new(3., 4.), 1., 0.5), Vec2::ZERO);
+    }
+
+    #[test]
+    fn exact_without_approximation() {
+        let positions: Vec<Vec2> = (0..40).map(|i| Vec2::new((i * 37 % 101) as f32 * 7., (i * 53 % 97) as f32 * 5.)).collect();
+        let masses: Vec<f32> = (0..40).map(|i| 1. + (i % 3) as f32).collect();
+        let tree = QuadTree::new(&positions, &masses);
+        for body in 0..positions.len() {
+            let force = tree.repulsion(body, positions[body], 1., 0.);
+            assert!((force - brute_force(&positions, &masses, body)).length() < 1e-4, "body {body}");
+        }
+    }
+
+    #[test]
+    fn coincident_bodies_push_each_other_but_not_themselves() {
+        let positions = [Vec2::new(10., 10.), Vec2::new(10., 10.), Vec2::new(200., 10.)];
+        let masses = [1., 1., 1.];
+        let tree = QuadTree::new(&positions, &masses);
+        for (body, position) in positions.iter().enumerate().take(2) {
+            let force = tree.repulsion(body, *position, 1., 0.);
+            // One unit from the other body on the same spot, a little from the far one pointing left
+            assert!((force.length() - 1.).abs() < 0.1, "{force}");
+        }
+        assert_ne!(tree.repulsion(0, positions[0], 1., 0.), tree.repulsion(1, positions[1], 1., 0.));
+        let far = tree.repulsion(2, positions[2], 1., 0.);
+        assert!((far - brute_force(&positions, &masses, 2)).length() < 1e-5);
+    }
+
+    #[test]
+    fn bodies_closer_than_the_smallest_cell_share_a_leaf() {
+        let positions = [Vec2::new(0., 0.), Vec2::new(0.002, 0.), Vec2::new(100., 100.)];
+        let masses = [1., 1., 1.];
+        let tree = QuadTree::new(&positions, &masses);
+        for body in 0..3 {
+            let force = tree.repulsion(body, positions[body], 1., 0.);
+            assert!((force - brute_force(&positions, &masses, body)).length() < 1e-5, "body {body}: {force}");
+        }
+    }
+}
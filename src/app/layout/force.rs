@@ -0,0 +1,349 @@
+use bevy::{app::{Plugin, Update}, color::Color, math::{Vec2, Vec3}, prelude::{Added, BuildChildren, Changed, Children, Commands, Component, DespawnRecursiveExt, Entity, Has, IntoSystemConfigs, NextState, Query, RemovedComponents, Res, ResMut, Resource, State, Transform, With}, utils::HashMap};
+use leafwing_input_manager::prelude::ActionState;
+
+use crate::app::{build_graph::{components::{label_text, Edge, EditorState, Vertex}, movement::{sync_kd_tree, VertexTween}, res::NearestPoints, RADIUS}, input::NormalInput, ui::{InfoPanel, MenuAppExt}};
+
+use super::barnes_hut::QuadTree;
+
+pub struct ForceLayoutPlugin;
+impl Plugin for ForceLayoutPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<ForceLayout>()
+        .add_systems(Update, (
+            (reheat_on_edit, step_force_layout).chain().before(sync_kd_tree),
+            pick_pinned,
+            show_pins,
+        ))
+        .add_menu_button("Layout", "Force-directed: run / pause", toggle_force_layout)
+        .add_menu_button("Layout", "Switch Fruchterman-Reingold / ForceAtlas2", switch_model)
+        .add_menu_button("Layout", "Pin / unpin vertex", pick_pin)
+        .add_menu_button("Layout", "Unpin all", unpin_all)
+        ;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ForceModel {
+    // Repulsion k² / 4d and attraction d² / k
+    FruchtermanReingold,
+    // Repulsion by degree k² (deg + 1)(deg + 1) / 40d and linear attraction d
+    ForceAtlas2,
+}
+
+impl ForceModel {
+    fn name(&self) -> &'static str {
+        match self {
+            ForceModel::FruchtermanReingold => "Fruchterman-Reingold",
+            ForceModel::ForceAtlas2 => "ForceAtlas2",
+        }
+    }
+}
+
+/**
+    # Force Layout
+    Moves the vertices a little every frame while running. The temperature caps how far a vertex moves in one iteration
+    and cools down over time until the layout settles, editing the graph or moving a vertex heats it up again.
+*/
+#[derive(Resource)]
+pub struct ForceLayout {
+    pub running: bool,
+    pub model: ForceModel,
+    temperature: f32,
+    // Set once no vertex moves noticeably anymore, the transforms are left alone until the layout is heated again
+    settled: bool,
+    // While picking a vertex to pin the editor is in edit mode, this is the state to go back to
+    picking: Option<EditorState>,
+}
+
+impl Default for ForceLayout {
+    fn default() -> Self {
+        Self {
+            running: false,
+            model: ForceModel::FruchtermanReingold,
+            temperature: START_TEMPERATURE,
+            settled: false,
+            picking: None,
+        }
+    }
+}
+
+impl ForceLayout {
+    fn heat(&mut self, temperature: f32) {
+        self.temperature = temperature;
+        self.settled = false;
+    }
+}
+
+// A vertex the force layout doesn't move
+#[derive(Component)]
+pub struct Pinned;
+
+// Text child of a pinned vertex
+#[derive(Component)]
+struct PinLabel;
+
+// Preferred distance between two adjacent vertices
+pub const IDEAL_DISTANCE: f32 = 5. * RADIUS;
+const START_TEMPERATURE: f32 = IDEAL_DISTANCE / 2.;
+// Settling a layout at once doesn't cool below this, the running layout cools until it settles
+const MIN_TEMPERATURE: f32 = 1.;
+// Moving a vertex by hand wakes a settled layout up with this temperature
+const NUDGE_TEMPERATURE: f32 = IDEAL_DISTANCE / 10.;
+// The running layout settles once no vertex moves farther than this in an iteration
+const SETTLE_DISTANCE: f32 = 0.2;
+const COOLING: f32 = 0.985;
+// Pull towards the center of mass per unit of distance, keeps disconnected parts together
+const GRAVITY: f32 = 0.02;
+const THETA: f32 = 0.9;
+// Moves shorter than this aren't written back so a settled layout doesn't touch the transforms
+const MIN_MOVE: f32 = 0.05;
+// Below the target labels
+const PIN_LABEL_OFFSET: Vec3 = Vec3::new(0., -RADIUS - 70., 2.);
+const PIN_LABEL_COLOR: Color = Color::srgb(1., 0.6, 0.6);
+
+/**
+    # Force Iteration
+    Returns how far every vertex moves in one iteration. Repulsion goes through the Barnes-Hut quadtree,
+    attraction acts along the edges regardless of their direction and pinned vertices don't move.
+*/
+pub fn force_iteration(positions: &[Vec2], edges: &[(usize, usize)], pinned: &[bool], model: ForceModel, temperature: f32) -> Vec<Vec2> {
+    let n = positions.len();
+    if n == 0 {
+        return vec![];
+    }
+    let mut degree = vec![0f32; n];
+    for (a, b) in edges.iter().filter(|(a, b)| a != b) {
+        degree[*a] += 1.;
+        degree[*b] += 1.;
+    }
+    let k = IDEAL_DISTANCE;
+    let (masses, strength): (Vec<f32>, f32) = match model {
+        ForceModel::FruchtermanReingold => (vec![1.; n], k * k / 4.),
+        ForceModel::ForceAtlas2 => (degree.iter().map(|d| d + 1.).collect(), k * k / 40.),
+    };
+    let tree = QuadTree::new(positions, &masses);
+    let center = positions.iter().copied().sum::<Vec2>() / n as f32;
+
+    let mut forces: Vec<Vec2> = (0..n)
+        .map(|v| tree.repulsion(v, positions[v], strength * masses[v], THETA) + (center - positions[v]) * GRAVITY * masses[v])
+        .collect();
+    for (a, b) in edges.iter().copied().filter(|(a, b)| a != b) {
+        let delta = positions[b] - positions[a];
+        let distance = delta.length();
+        let pull = match model {
+            ForceModel::FruchtermanReingold => delta * distance / k,
+            ForceModel::ForceAtlas2 => delta,
+        };
+        forces[a] += pull;
+        forces[b] -= pull;
+    }
+    forces
+        .into_iter()
+        .enumerate()
+        .map(|(v, force)| if pinned[v] { Vec2::ZERO } else { force.clamp_length_max(temperature) })
+        .collect()
+}
+
+/**
+    # Settle Layout
+    Runs a whole cooling schedule at once, for vertices that are placed before they are ever drawn.
+    Pinned vertices stay where they are.
+*/
+pub fn settle_layout(mut positions: Vec<Vec2>, edges: &[(usize, usize)], pinned: &[bool], iterations: usize) -> Vec<Vec2> {
+    let mut temperature = START_TEMPERATURE;
+    for _ in 0..iterations {
+        let moves = force_iteration(&positions, edges, pinned, ForceModel::FruchtermanReingold, temperature);
+        positions.iter_mut().zip(moves).for_each(|(position, step)| *position += step);
+        temperature = (temperature * COOLING).max(MIN_TEMPERATURE);
+    }
+    positions
+}
+
+type LaidOutVertex<'a> = (Entity, &'a mut Transform, Has<Pinned>, Has<VertexTween>);
+
+fn step_force_layout(
+    mut layout: ResMut<ForceLayout>,
+    mut q_vertex: Query<LaidOutVertex, With<Vertex>>,
+    q_edge: Query<&Edge>,
+) {
+    if !layout.running || layout.settled {
+        return;
+    }
+    let mut index: HashMap<Entity, usize> = HashMap::new();
+    let mut positions = vec![];
+    // Tweened vertices are on their way somewhere else and count as pinned
+    let mut pinned = vec![];
+    for (entity, transform, is_pinned, is_tweened) in q_vertex.iter() {
+        index.insert(entity, positions.len());
+        positions.push(transform.translation.truncate());
+        pinned.push(is_pinned || is_tweened);
+    }
+    let edges: Vec<(usize, usize)> = q_edge.iter()
+        .filter_map(|edge| Some((*index.get(&edge.start)?, *index.get(&edge.end)?)))
+        .collect();
+
+    let moves = force_iteration(&positions, &edges, &pinned, layout.model, layout.temperature);
+    // The last moves aren't written either, so the transforms stop changing and the kd-tree stays as it is
+    if moves.iter().all(|movement| movement.length() < SETTLE_DISTANCE) {
+        layout.settled = true;
+        return;
+    }
+    for (entity, mut transform, _, _) in q_vertex.iter_mut() {
+        let Some(movement) = index.get(&entity).map(|v| moves[*v]) else {continue};
+        if movement.length() < MIN_MOVE {
+            continue;
+        }
+        transform.translation.x += movement.x;
+        transform.translation.y += movement.y;
+    }
+    layout.temperature *= COOLING;
+}
+
+// While settled the layout doesn't write any transform, so a changed one was moved by hand or by a tween
+fn reheat_on_edit(
+    mut layout: ResMut<ForceLayout>,
+    q_added: Query<(), Added<Vertex>>,
+    q_added_edge: Query<(), Added<Edge>>,
+    q_moved: Query<(), (Changed<Transform>, With<Vertex>)>,
+    mut removed_vertices: RemovedComponents<Vertex>,
+    mut removed_edges: RemovedComponents<Edge>,
+) {
+    let removed = removed_vertices.read().count() + removed_edges.read().count() > 0;
+    if !layout.running {
+        return;
+    }
+    if removed || !q_added.is_empty() || !q_added_edge.is_empty() {
+        layout.heat(START_TEMPERATURE);
+    } else if layout.settled && !q_moved.is_empty() {
+        layout.heat(NUDGE_TEMPERATURE);
+    }
+}
+
+fn toggle_force_layout(
+    mut layout: ResMut<ForceLayout>,
+    mut info: ResMut<InfoPanel>,
+) {
+    layout.running = !layout.running;
+    if layout.running {
+        layout.heat(START_TEMPERATURE);
+    }
+    info.text = format!("{} layout {}", layout.model.name(), if layout.running { "running" } else { "paused" });
+}
+
+fn switch_model(
+    mut layout: ResMut<ForceLayout>,
+    mut info: ResMut<InfoPanel>,
+) {
+    layout.model = match layout.model {
+        ForceModel::FruchtermanReingold => ForceModel::ForceAtlas2,
+        ForceModel::ForceAtlas2 => ForceModel::FruchtermanReingold,
+    };
+    layout.heat(START_TEMPERATURE);
+    info.text = format!("Force layout uses {}", layout.model.name());
+}
+
+fn pick_pin(
+    mut layout: ResMut<ForceLayout>,
+    state: Res<State<EditorState>>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let previous = layout.picking.unwrap_or(*state.get());
+    layout.picking = Some(previous);
+    next_state.set(EditorState::Edit);
+    info.text = "Select the vertex to pin or unpin".to_string();
+}
+
+fn unpin_all(
+    mut commands: Commands,
+    q_pinned: Query<Entity, With<Pinned>>,
+) {
+    for entity in q_pinned.iter() {
+        commands.entity(entity).remove::<Pinned>();
+    }
+}
+
+fn pick_pinned(
+    mut commands: Commands,
+    mut layout: ResMut<ForceLayout>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+    nearest_points: Res<NearestPoints>,
+    q_my_action: Query<&ActionState<NormalInput>>,
+    q_pinned: Query<(), With<Pinned>>,
+) {
+    let Some(previous) = layout.picking else {return};
+    let my_action = q_my_action.single();
+    if !my_action.just_pressed(&NormalInput::Select) {
+        return;
+    }
+    let Some(vertex) = nearest_points.hovered() else {return};
+
+    if q_pinned.contains(vertex) {
+        commands.entity(vertex).remove::<Pinned>();
+    } else {
+        commands.entity(vertex).insert(Pinned);
+    }
+    layout.picking = None;
+    next_state.set(previous);
+    info.text.clear();
+}
+
+fn show_pins(
+    mut commands: Commands,
+    q_added: Query<Entity, Added<Pinned>>,
+    mut removed: RemovedComponents<Pinned>,
+    q_children: Query<&Children>,
+    q_label: Query<(), With<PinLabel>>,
+) {
+    for entity in q_added.iter() {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((label_text("pinned", PIN_LABEL_COLOR, PIN_LABEL_OFFSET), PinLabel));
+        });
+    }
+    for entity in removed.read() {
+        let Ok(children) = q_children.get(entity) else {continue};
+        for child in children.iter().filter(|child| q_label.contains(**child)) {
+            commands.entity(*child).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{force_iteration, settle_layout, ForceModel, COOLING, IDEAL_DISTANCE, SETTLE_DISTANCE, START_TEMPERATURE};
+
+    const EDGES: [(usize, usize); 4] = [(0, 1), (1, 2), (2, 0), (2, 3)];
+
+    fn start() -> Vec<Vec2> {
+        vec![Vec2::new(0., 0.), Vec2::new(10., 0.), Vec2::new(0., 10.), Vec2::new(5., 5.)]
+    }
+
+    #[test]
+    fn running_layout_settles() {
+        for model in [ForceModel::FruchtermanReingold, ForceModel::ForceAtlas2] {
+            let mut positions = start();
+            let mut temperature = START_TEMPERATURE;
+            let iterations = (0..2000).position(|_| {
+                let moves = force_iteration(&positions, &EDGES, &[false; 4], model, temperature);
+                positions.iter_mut().zip(moves.iter()).for_each(|(position, step)| *position += *step);
+                temperature *= COOLING;
+                moves.iter().all(|movement| movement.length() < SETTLE_DISTANCE)
+            });
+            assert!(iterations.is_some(), "{model:?} never settled");
+        }
+    }
+
+    #[test]
+    fn settled_layout_spreads_the_vertices() {
+        let positions = settle_layout(start(), &EDGES, &[true, false, false, false], 300);
+        assert_eq!(positions[0], Vec2::ZERO);
+        for (a, b) in EDGES {
+            let distance = positions[a].distance(positions[b]);
+            assert!(distance > IDEAL_DISTANCE / 2. && distance < IDEAL_DISTANCE * 2., "{a} {b} are {distance} apart");
+        }
+    }
+}
@@ -0,0 +1,28 @@
+mod arrange;
+mod barnes_hut;
+pub mod force;
+mod sugiyama;
+mod tree;
+
+use arrange::ArrangePlugin;
+use bevy::prelude::{App, Plugin};
+use force::ForceLayoutPlugin;
+
+use super::build_graph::RADIUS;
+
+pub struct LayoutPlugin;
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .add_plugins((
+            ForceLayoutPlugin,
+            ArrangePlugin,
+        ))
+        ;
+    }
+}
+
+// Horizontal distance between neighbouring vertices of the one-shot layouts
+pub const SIBLING_DISTANCE: f32 = 4. * RADIUS;
+// Vertical distance between the levels of a tree or the layers of a layered layout
+pub const LAYER_DISTANCE: f32 = 5. * RADIUS;
@@ -0,0 +1,383 @@
+use std::collections::VecDeque;
+
+use bevy::math::Vec2;
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::{LAYER_DISTANCE, SIBLING_DISTANCE};
+
+// Rounds of barycenter sweeps down and up
+const ORDER_ROUNDS: usize = 12;
+// Rounds that pull every node towards its neighbours after the order is fixed
+const ALIGN_ROUNDS: usize = 8;
+
+pub struct Layered {
+    pub positions: Vec<Vec2>,
+    pub layers: usize,
+    pub crossings: usize,
+    // Edges turned around to break the cycles
+    pub reversed: usize,
+}
+
+/**
+    # Sugiyama
+    1. Breaks cycles by turning around the edges a DFS finds going back, undirected edges point to the later vertex
+    2. Puts every vertex one layer below its lowest predecessor (longest path layering)
+    3. Splits edges spanning several layers with dummy nodes
+    4. Orders the layers with barycenter sweeps and swaps of neighbours, keeping the order with the fewest crossings
+    5. Places the nodes of a layer in order and pulls them towards their neighbours without overlapping
+*/
+pub fn layered_layout(graph: &GraphSnapshot) -> Layered {
+    let n = graph.len();
+    let mut edges: Vec<(usize, usize)> = graph.edges.iter()
+        .filter(|edge| edge.from != edge.to)
+        .map(|edge| if edge.directed { (edge.from, edge.to) } else { (edge.from.min(edge.to), edge.from.max(edge.to)) })
+        .collect();
+    edges.sort();
+    edges.dedup();
+    let reversed = break_cycles(n, &mut edges);
+    edges.sort();
+    edges.dedup();
+
+    let layer = longest_path_layers(n, &edges);
+    let layer_count = layer.iter().max().map_or(0, |l| l + 1);
+
+    let (node_layer, down) = split_long_edges(&layer, &edges);
+    let mut up: Vec<Vec<usize>> = vec![vec![]; node_layer.len()];
+    for (node, below) in down.iter().enumerate() {
+        for child in below {
+            up[*child].push(node);
+        }
+    }
+
+    let mut layers: Vec<Vec<usize>> = vec![vec![]; layer_count];
+    for (node, l) in node_layer.iter().enumerate() {
+        layers[*l].push(node);
+    }
+    let mut best = layers.clone();
+    let mut best_crossings = count_crossings(&layers, &down, node_layer.len());
+    for round in 0..ORDER_ROUNDS {
+        if round % 2 == 0 {
+            for l in 1..layer_count {
+                barycenter_sort(&mut layers, l, l - 1, &up, node_layer.len());
+            }
+        } else {
+            for l in (0..layer_count.saturating_sub(1)).rev() {
+                barycenter_sort(&mut layers, l, l + 1, &down, node_layer.len());
+            }
+        }
+        transpose(&mut layers, &up, &down, node_layer.len());
+        let crossings = count_crossings(&layers, &down, node_layer.len());
+        if crossings < best_crossings {
+            best_crossings = crossings;
+            best = layers.clone();
+        }
+    }
+
+    let x = place(&best, &up, &down, node_layer.len());
+    Layered {
+        positions: (0..n).map(|v| Vec2::new(x[v], -(layer[v] as f32) * LAYER_DISTANCE)).collect(),
+        layers: layer_count,
+        crossings: best_crossings,
+        reversed,
+    }
+}
+
+// Iterative DFS, an edge to a vertex on the stack closes a cycle and is turned around
+fn break_cycles(n: usize, edges: &mut [(usize, usize)]) -> usize {
+    let mut out: Vec<Vec<usize>> = vec![vec![]; n];
+    for (e, (from, _)) in edges.iter().enumerate() {
+        out[*from].push(e);
+    }
+    // 0 unvisited, 1 on the stack, 2 done
+    let mut state = vec![0u8; n];
+    let mut back = vec![];
+    for start in 0..n {
+        if state[start] != 0 {
+            continue;
+        }
+        state[start] = 1;
+        let mut stack = vec![(start, 0)];
+        while let Some((v, next)) = stack.last_mut() {
+            let v = *v;
+            let Some(e) = out[v].get(*next).copied() else {
+                state[v] = 2;
+                stack.pop();
+                continue;
+            };
+            *next += 1;
+            let w = edges[e].1;
+            match state[w] {
+                0 => {
+                    state[w] = 1;
+                    stack.push((w, 0));
+                },
+                1 => back.push(e),
+                _ => {},
+            }
+        }
+    }
+    for e in back.iter() {
+        let (from, to) = edges[*e];
+        edges[*e] = (to, from);
+    }
+    back.len()
+}
+
+// Edges spanning several layers go through a dummy node on every layer in between. Real vertices are the nodes 0..n,
+// dummies follow. Returns the layer of every node and the nodes one layer below it
+fn split_long_edges(layer: &[usize], edges: &[(usize, usize)]) -> (Vec<usize>, Vec<Vec<usize>>) {
+    let mut node_layer = layer.to_vec();
+    let mut down: Vec<Vec<usize>> = vec![vec![]; layer.len()];
+    for (from, to) in edges.iter().copied() {
+        let mut previous = from;
+        for dummy_layer in layer[from] + 1..layer[to] {
+            let dummy = node_layer.len();
+            node_layer.push(dummy_layer);
+            down.push(vec![]);
+            down[previous].push(dummy);
+            previous = dummy;
+        }
+        down[previous].push(to);
+    }
+    (node_layer, down)
+}
+
+// Kahn's algorithm, every vertex goes one layer below its lowest predecessor
+fn longest_path_layers(n: usize, edges: &[(usize, usize)]) -> Vec<usize> {
+    let mut out: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut indegree = vec![0; n];
+    for (from, to) in edges.iter() {
+        out[*from].push(*to);
+        indegree[*to] += 1;
+    }
+    let mut layer = vec![0; n];
+    let mut queue: VecDeque<usize> = (0..n).filter(|v| indegree[*v] == 0).collect();
+    while let Some(v) = queue.pop_front() {
+        for w in out[v].iter().copied() {
+            layer[w] = layer[w].max(layer[v] + 1);
+            indegree[w] -= 1;
+            if indegree[w] == 0 {
+                queue.push_back(w);
+            }
+        }
+    }
+    layer
+}
+
+// Sorts a layer by the mean position of the neighbours in the fixed layer, nodes without any keep their position
+fn barycenter_sort(layers: &mut [Vec<usize>], layer: usize, fixed: usize, neighbours: &[Vec<usize>], nodes: usize) {
+    let mut position = vec![0f32; nodes];
+    for (i, node) in layers[fixed].iter().enumerate() {
+        position[*node] = i as f32;
+    }
+    let keys: Vec<(usize, f32)> = layers[layer].iter().enumerate()
+        .map(|(i, node)| {
+            let adjacent = &neighbours[*node];
+            let key = if adjacent.is_empty() {
+                i as f32
+            } else {
+                adjacent.iter().map(|w| position[*w]).sum::<f32>() / adjacent.len() as f32
+            };
+            (*node, key)
+        })
+        .collect();
+    let mut sorted = keys;
+    sorted.sort_by(|a, b| a.1.total_cmp(&b.1));
+    layers[layer] = sorted.into_iter().map(|(node, _)| node).collect();
+}
+
+// Swaps neighbouring nodes of a layer as long as that removes crossings
+fn transpose(layers: &mut [Vec<usize>], up: &[Vec<usize>], down: &[Vec<usize>], nodes: usize) {
+    let mut position = vec![0; nodes];
+    for layer in layers.iter() {
+        for (i, node) in layer.iter().enumerate() {
+            position[*node] = i;
+        }
+    }
+    // Crossings between the edges of u and the edges of v while u is left of v
+    let crossings = |u: usize, v: usize, position: &[usize]| -> usize {
+        [up, down].iter()
+            .map(|neighbours| neighbours[u].iter()
+                .map(|a| neighbours[v].iter().filter(|b| position[*a] > position[**b]).count())
+                .sum::<usize>())
+            .sum()
+    };
+    let mut improved = true;
+    while improved {
+        improved = false;
+        for layer in layers.iter_mut() {
+            for i in 1..layer.len() {
+                let (u, v) = (layer[i - 1], layer[i]);
+                if crossings(v, u, &position) < crossings(u, v, &position) {
+                    layer.swap(i - 1, i);
+                    position[u] = i;
+                    position[v] = i - 1;
+                    improved = true;
+                }
+            }
+        }
+    }
+}
+
+// Two edges between the same pair of layers cross when their ends are in opposite order
+fn count_crossings(layers: &[Vec<usize>], down: &[Vec<usize>], nodes: usize) -> usize {
+    let mut position = vec![0; nodes];
+    for layer in layers.iter() {
+        for (i, node) in layer.iter().enumerate() {
+            position[*node] = i;
+        }
+    }
+    let mut crossings = 0;
+    for layer in layers.iter() {
+        let mut ends: Vec<(usize, usize)> = layer.iter()
+            .flat_map(|node| down[*node].iter().map(|w| (position[*node], position[*w])))
+            .collect();
+        ends.sort();
+        for i in 0..ends.len() {
+            for j in i + 1..ends.len() {
+                if ends[i].0 < ends[j].0 && ends[i].1 > ends[j].1 {
+                    crossings += 1;
+                }
+            }
+        }
+    }
+    crossings
+}
+
+// Every layer starts centered, then nodes move towards the mean of their neighbours while keeping the order and the distance
+fn place(layers: &[Vec<usize>], up: &[Vec<usize>], down: &[Vec<usize>], nodes: usize) -> Vec<f32> {
+    let mut x = vec![0f32; nodes];
+    for layer in layers.iter() {
+        let middle = (layer.len() as f32 - 1.) / 2.;
+        for (i, node) in layer.iter().enumerate() {
+            x[*node] = (i as f32 - middle) * SIBLING_DISTANCE;
+        }
+    }
+    for round in 0..ALIGN_ROUNDS {
+        let neighbours = if round % 2 == 0 { up } else { down };
+        for layer in layers.iter() {
+            let wanted: Vec<f32> = layer.iter()
+                .map(|node| {
+                    let adjacent = &neighbours[*node];
+                    if adjacent.is_empty() {
+                        x[*node]
+                    } else {
+                        adjacent.iter().map(|w| x[*w]).sum::<f32>() / adjacent.len() as f32
+                    }
+                })
+                .collect();
+            // Push overlapping nodes apart to the right, then shift the layer back to the wanted mean
+            let mut placed = wanted.clone();
+            for i in 1..placed.len() {
+                placed[i] = placed[i].max(placed[i - 1] + SIBLING_DISTANCE);
+            }
+            let shift = (wanted.iter().sum::<f32>() - placed.iter().sum::<f32>()) / placed.len().max(1) as f32;
+            for (node, position) in layer.iter().zip(placed.iter()) {
+                x[*node] = position + shift;
+            }
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::algorithms::graph::GraphSnapshot;
+
+    use super::{barycenter_sort, count_crossings, layered_layout, longest_path_layers, split_long_edges, transpose};
+
+    // The nodes one layer above every node
+    fn up(down: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut up = vec![vec![]; down.len()];
+        for (node, below) in down.iter().enumerate() {
+            for child in below {
+                up[*child].push(node);
+            }
+        }
+        up
+    }
+
+    #[test]
+    fn edges_point_down() {
+        let edges = [(0, 1, 1, true), (0, 2, 1, true), (1, 3, 1, true), (2, 3, 1, true), (3, 4, 1, true), (0, 4, 1, true)];
+        let graph = GraphSnapshot::test_graph(5, &edges);
+        let layered = layered_layout(&graph);
+        assert_eq!((layered.layers, layered.reversed), (4, 0));
+        for edge in graph.edges.iter() {
+            assert!(layered.positions[edge.to].y < layered.positions[edge.from].y);
+        }
+    }
+
+    #[test]
+    fn cycles_are_broken() {
+        // 0 → 1 → 2 → 0 and 2 → 3, one edge of the cycle is turned around
+        let graph = GraphSnapshot::test_graph(4, &[(0, 1, 1, true), (1, 2, 1, true), (2, 0, 1, true), (2, 3, 1, true)]);
+        let layered = layered_layout(&graph);
+        assert_eq!((layered.layers, layered.reversed), (4, 1));
+        let pointing_down = graph.edges.iter().filter(|edge| layered.positions[edge.to].y < layered.positions[edge.from].y).count();
+        assert_eq!(pointing_down, 3);
+    }
+
+    #[test]
+    fn dummies_split_long_edges() {
+        let layer = [0, 1, 2, 3];
+        let edges = [(0, 1), (1, 2), (2, 3), (0, 3), (1, 3)];
+        let (node_layer, down) = split_long_edges(&layer, &edges);
+        // 0 → 3 needs two dummies and 1 → 3 one
+        assert_eq!(node_layer, vec![0, 1, 2, 3, 1, 2, 2]);
+        for (node, below) in down.iter().enumerate() {
+            assert!(below.iter().all(|child| node_layer[*child] == node_layer[node] + 1));
+        }
+        assert_eq!(down[0], vec![1, 4]);
+        assert_eq!(down[4], vec![5]);
+        assert_eq!(down[5], vec![3]);
+    }
+
+    #[test]
+    fn ordering_removes_crossings() {
+        // 0 → 3 and 1 → 2 cross in the first order
+        let down = vec![vec![3], vec![2], vec![], vec![]];
+        let mut layers = vec![vec![0, 1], vec![2, 3]];
+        assert_eq!(count_crossings(&layers, &down, 4), 1);
+        barycenter_sort(&mut layers, 1, 0, &up(&down), 4);
+        assert_eq!(count_crossings(&layers, &down, 4), 0);
+
+        let mut layers = vec![vec![0, 1], vec![2, 3]];
+        transpose(&mut layers, &up(&down), &down, 4);
+        assert_eq!(count_crossings(&layers, &down, 4), 0);
+    }
+
+    #[test]
+    fn ordering_never_adds_crossings() {
+        // Pseudo random DAG, the crossings of the first order by index are the ones to beat
+        let mut state = 7u32;
+        let mut edges = vec![];
+        for _ in 0..30 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let from = (state >> 16) as usize % 15;
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let to = (state >> 16) as usize % 15;
+            if from < to {
+                edges.push((from, to, 1, true));
+            }
+        }
+        let graph = GraphSnapshot::test_graph(15, &edges);
+
+        let mut pairs: Vec<(usize, usize)> = edges.iter().map(|(from, to, _, _)| (*from, *to)).collect();
+        pairs.sort();
+        pairs.dedup();
+        let (node_layer, down) = split_long_edges(&longest_path_layers(15, &pairs), &pairs);
+        let mut layers = vec![vec![]; node_layer.iter().max().unwrap() + 1];
+        for (node, l) in node_layer.iter().enumerate() {
+            layers[*l].push(node);
+        }
+        let unordered = count_crossings(&layers, &down, node_layer.len());
+        assert!(unordered > 0);
+        assert!(layered_layout(&graph).crossings <= unordered);
+
+        // Transposing alone only swaps when that removes crossings
+        transpose(&mut layers, &up(&down), &down, node_layer.len());
+        assert!(count_crossings(&layers, &down, node_layer.len()) <= unordered);
+    }
+}
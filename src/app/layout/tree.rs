@@ -0,0 +1,158 @@
+use std::collections::VecDeque;
+
+use bevy::math::Vec2;
+
+use crate::app::algorithms::graph::GraphSnapshot;
+
+use super::{LAYER_DISTANCE, SIBLING_DISTANCE};
+
+// The leftmost and rightmost x of a subtree on every depth below its root, relative to the root
+type Contour = Vec<(f32, f32)>;
+
+/**
+    # Reingold-Tilford
+    Lays out a BFS tree of the root's component, directions ignored. Subtrees are built bottom up and every subtree
+    is pushed as close to its left siblings as their contours allow, then the parent is centered over its first and last
+    child. Other components follow to the right, each rooted at its first vertex.
+*/
+pub fn tree_layout(graph: &GraphSnapshot, root: usize) -> Vec<Vec2> {
+    let n = graph.len();
+    let adjacency = graph.simple_adjacency();
+    let mut positions = vec![Vec2::ZERO; n];
+    let mut seen = vec![false; n];
+    let mut left_edge = 0.;
+    let mut children: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut parent = vec![root; n];
+    let mut depth = vec![0; n];
+    // Offset of every vertex from its parent
+    let mut offset = vec![0f32; n];
+    let mut contours: Vec<Contour> = vec![vec![]; n];
+
+    for root in std::iter::once(root).chain(0..n) {
+        if seen[root] {
+            continue;
+        }
+        // BFS order, parents come before their children
+        let mut order = vec![root];
+        seen[root] = true;
+        let mut queue = VecDeque::from([root]);
+        while let Some(v) = queue.pop_front() {
+            for w in adjacency[v].iter().copied() {
+                if !seen[w] {
+                    seen[w] = true;
+                    parent[w] = v;
+                    depth[w] = depth[v] + 1;
+                    children[v].push(w);
+                    order.push(w);
+                    queue.push_back(w);
+                }
+            }
+        }
+
+        for v in order.iter().rev().copied() {
+            let mut merged: Contour = vec![];
+            let mut placed: Vec<f32> = vec![];
+            for child in children[v].iter().copied() {
+                let contour = std::mem::take(&mut contours[child]);
+                let shift = if placed.is_empty() {
+                    0.
+                } else {
+                    merged.iter().zip(contour.iter())
+                        .map(|((_, right), (left, _))| right - left + SIBLING_DISTANCE)
+                        .fold(f32::MIN, f32::max)
+                };
+                for (level, (left, right)) in contour.into_iter().enumerate() {
+                    match merged.get_mut(level) {
+                        Some(extent) => *extent = (extent.0.min(left + shift), extent.1.max(right + shift)),
+                        None => merged.push((left + shift, right + shift)),
+                    }
+                }
+                placed.push(shift);
+            }
+            let middle = match (placed.first(), placed.last()) {
+                (Some(first), Some(last)) => (first + last) / 2.,
+                _ => 0.,
+            };
+            for (child, x) in children[v].iter().zip(placed.iter()) {
+                offset[*child] = x - middle;
+            }
+            contours[v] = std::iter::once((0., 0.))
+                .chain(merged.into_iter().map(|(left, right)| (left - middle, right - middle)))
+                .collect();
+        }
+
+        // The contour of the root tells how wide the component is
+        let leftmost = contours[root].iter().map(|(left, _)| *left).fold(0., f32::min);
+        let rightmost = contours[root].iter().map(|(_, right)| *right).fold(0., f32::max);
+        let root_x = left_edge - leftmost;
+        left_edge = root_x + rightmost + SIBLING_DISTANCE;
+        for v in order {
+            let x = if v == root { root_x } else { positions[parent[v]].x + offset[v] };
+            positions[v] = Vec2::new(x, -(depth[v] as f32) * LAYER_DISTANCE);
+        }
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::app::{algorithms::graph::GraphSnapshot, layout::{LAYER_DISTANCE, SIBLING_DISTANCE}};
+
+    use super::tree_layout;
+
+    fn undirected(n: usize, edges: &[(usize, usize)]) -> GraphSnapshot {
+        let edges: Vec<(usize, usize, i32, bool)> = edges.iter().map(|(a, b)| (*a, *b, 1, false)).collect();
+        GraphSnapshot::test_graph(n, &edges)
+    }
+
+    // Vertices on the same layer are at least the sibling distance apart
+    fn assert_no_overlap(positions: &[bevy::math::Vec2]) {
+        for (a, p) in positions.iter().enumerate() {
+            for q in positions[a + 1..].iter().filter(|q| q.y == p.y) {
+                assert!((p.x - q.x).abs() >= SIBLING_DISTANCE - 1e-3, "{p} and {q} overlap");
+            }
+        }
+    }
+
+    #[test]
+    fn path_rooted_in_the_middle() {
+        let positions = tree_layout(&undirected(4, &[(0, 1), (1, 2), (2, 3)]), 1);
+        assert_eq!(positions[1].y, 0.);
+        assert_eq!(positions[0].y, -LAYER_DISTANCE);
+        assert_eq!(positions[3].y, -2. * LAYER_DISTANCE);
+        // 0 and 2 are the children of the root, 3 sits below 2
+        assert_eq!(positions[1].x, (positions[0].x + positions[2].x) / 2.);
+        assert_eq!(positions[3].x, positions[2].x);
+    }
+
+    #[test]
+    fn parents_are_centered_over_their_children() {
+        // A binary tree of depth 2 where the left subtree is missing a leaf
+        let edges = [(0, 1), (0, 2), (1, 3), (2, 4), (2, 5)];
+        let positions = tree_layout(&undirected(6, &edges), 0);
+        assert_no_overlap(&positions);
+        assert_eq!(positions[0].x, (positions[1].x + positions[2].x) / 2.);
+        assert_eq!(positions[2].x, (positions[4].x + positions[5].x) / 2.);
+        assert!(positions[1].x < positions[2].x && positions[3].x < positions[4].x);
+        assert_eq!(positions[5].y, -2. * LAYER_DISTANCE);
+    }
+
+    #[test]
+    fn subtrees_are_pushed_apart_by_their_contours() {
+        // The two children of the root have wide subtrees that would overlap below if the children were packed
+        let edges = [(0, 1), (0, 2), (1, 3), (1, 4), (1, 5), (2, 6), (2, 7), (2, 8)];
+        let positions = tree_layout(&undirected(9, &edges), 0);
+        assert_no_overlap(&positions);
+        assert!(positions[2].x - positions[1].x >= 3. * SIBLING_DISTANCE - 1e-3);
+    }
+
+    #[test]
+    fn components_follow_to_the_right() {
+        let positions = tree_layout(&undirected(5, &[(0, 1), (0, 2), (3, 4)]), 3);
+        let first = positions[3].x.max(positions[4].x);
+        let second = positions[..3].iter().map(|p| p.x).fold(f32::MAX, f32::min);
+        assert!(second >= first + SIBLING_DISTANCE - 1e-3);
+        assert_eq!(positions[0].y, 0.);
+        assert_eq!(positions[3].y, 0.);
+    }
+}
@@ -1,11 +1,29 @@
+mod algorithms;
+#[cfg(target_arch = "wasm32")]
+pub mod api;
 mod build_graph;
 mod camera;
+pub mod files;
+mod generate;
 mod input;
+mod layout;
+#[cfg(not(target_arch = "wasm32"))]
+mod platform;
+mod session;
+mod ui;
+#[cfg(not(target_arch = "wasm32"))]
+mod window_size;
 
-use bevy::{app::PluginGroup, math::Vec2, prelude::{default, App, DefaultPlugins}, window::{Window, WindowPlugin, WindowResolution}};
+use bevy::{app::PluginGroup, prelude::{default, App, DefaultPlugins}, window::{Window, WindowPlugin}};
+use algorithms::AlgorithmPlugin;
 use build_graph::BuildGraphPlugin;
 use camera::MyCameraPlugin;
+use files::FilesPlugin;
+use generate::GeneratePlugin;
 use input::MyInputPlugin;
+use layout::LayoutPlugin;
+use session::SessionPlugin;
+use ui::MenuPlugin;
 #[cfg(target_arch = "wasm32")]
 use crate::wasm_module::log_js;
 
@@ -18,7 +36,7 @@ pub fn run() {
         }
         
         #[cfg(not(target_arch = "wasm32"))]
-        {crate::not_wasm_module::get_app_window_size()}
+        {platform::get_app_window_size()}
     };
 
     
@@ -30,6 +48,8 @@ pub fn run() {
         resolution,
         title: "Bevy Graph Simulator".to_string(),
         canvas: Some("#bevy_graph_simulator_canvas".to_string()),
+        // The canvas follows the size of the element around it
+        fit_canvas_to_parent: true,
         ..default()
     };
     
@@ -39,13 +59,22 @@ pub fn run() {
         BuildGraphPlugin,
         MyCameraPlugin,
         MyInputPlugin,
+        MenuPlugin,
+        AlgorithmPlugin,
+        LayoutPlugin,
+        GeneratePlugin,
+        FilesPlugin,
+        SessionPlugin,
     ))
     ;
 
+    #[cfg(target_arch = "wasm32")]
+    app.add_plugins(api::ApiPlugin);
+
     #[cfg(not(target_arch = "wasm32"))] 
     {
         use bevy_inspector_egui::quick::WorldInspectorPlugin;
-        app.add_plugins(WorldInspectorPlugin::default());
+        app.add_plugins((WorldInspectorPlugin::default(), window_size::WindowSizePlugin));
     }
  
     app.run();
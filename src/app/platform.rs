@@ -0,0 +1,100 @@
+// Files of the native build, the browser build keeps the same things in the page
+use std::path::PathBuf;
+
+use bevy::window::WindowResolution;
+
+// Exported graphs go to the exports folder of the working directory
+pub fn save_file(name: &str, contents: &[u8]) -> std::io::Result<PathBuf> {
+    let folder = PathBuf::from("exports");
+    std::fs::create_dir_all(&folder)?;
+    let path = folder.join(name);
+    std::fs::write(&path, contents)?;
+    Ok(path)
+}
+
+pub const WINDOW_SETTING: &str = "window.txt";
+// Saved sizes outside of this are from a broken file
+pub const MIN_WINDOW_SIZE: f32 = 200.;
+const MAX_WINDOW_SIZE: f32 = 16384.;
+
+// The size the window had when it was last closed
+pub fn get_app_window_size() -> Option<WindowResolution> {
+    let saved = load_setting(WINDOW_SETTING).and_then(|text| {
+        let size: Vec<f32> = text.split_whitespace().map(|part| part.parse().ok()).collect::<Option<_>>()?;
+        let [width, height] = size[..] else {return None};
+        Some((width, height))
+    });
+    let (width, height) = saved
+        .filter(|(width, height)| [width, height].iter().all(|side| (MIN_WINDOW_SIZE..=MAX_WINDOW_SIZE).contains(*side)))
+        .unwrap_or((1280., 720.));
+    Some(WindowResolution::new(width, height))
+}
+
+// Settings go to the config folder of the user, or to a settings folder in the working directory without one
+fn setting_path(name: &str) -> PathBuf {
+    let folder = std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map_or_else(|| PathBuf::from("settings"), |folder| folder.join("bevy_graph_simulator"));
+    folder.join(name)
+}
+
+pub fn load_setting(name: &str) -> Option<String> {
+    std::fs::read_to_string(setting_path(name)).ok()
+}
+
+pub fn save_setting(name: &str, contents: &str) -> std::io::Result<()> {
+    let path = setting_path(name);
+    if let Some(folder) = path.parent() {
+        std::fs::create_dir_all(folder)?;
+    }
+    std::fs::write(path, contents)
+}
+
+// Sessions go to the data folder of the user, or to a sessions folder in the working directory without one
+fn session_folder() -> PathBuf {
+    std::env::var_os("APPDATA")
+        .or_else(|| std::env::var_os("XDG_DATA_HOME"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+        .map_or_else(|| PathBuf::from("sessions"), |folder| folder.join("bevy_graph_simulator").join("sessions"))
+}
+
+// A session without a thumbnail removes the old one
+pub fn save_session(key: &str, text: &str, thumbnail: Option<&[u8]>) -> Result<(), String> {
+    let folder = session_folder();
+    std::fs::create_dir_all(&folder).map_err(|error| error.to_string())?;
+    std::fs::write(folder.join(format!("{key}.session")), text).map_err(|error| error.to_string())?;
+    let thumbnail_path = folder.join(format!("{key}.png"));
+    match thumbnail {
+        Some(thumbnail) => std::fs::write(thumbnail_path, thumbnail).map_err(|error| error.to_string()),
+        None => {
+            let _ = std::fs::remove_file(thumbnail_path);
+            Ok(())
+        },
+    }
+}
+
+pub fn load_session(key: &str) -> Option<String> {
+    std::fs::read_to_string(session_folder().join(format!("{key}.session"))).ok()
+}
+
+pub fn load_thumbnail(key: &str) -> Option<Vec<u8>> {
+    std::fs::read(session_folder().join(format!("{key}.png"))).ok()
+}
+
+pub fn delete_session(key: &str) {
+    for extension in ["session", "png"] {
+        let _ = std::fs::remove_file(session_folder().join(format!("{key}.{extension}")));
+    }
+}
+
+pub fn session_keys() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(session_folder()) else {return vec![]};
+    let mut keys: Vec<String> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.strip_suffix(".session").map(str::to_string))
+        .collect();
+    keys.sort();
+    keys
+}
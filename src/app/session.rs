@@ -0,0 +1,410 @@
+use bevy::{app::{AppExit, Last, Plugin, PostStartup, Update}, asset::{Assets, Handle}, input::{keyboard::{Key, KeyboardInput}, ButtonState}, prelude::{default, BuildChildren, Changed, Commands, Component, DespawnRecursiveExt, DetectChanges, Entity, EventReader, Image, ImageBundle, IntoSystemConfigs, Local, NextState, NodeBundle, OrthographicProjection, Query, Res, ResMut, Resource, TextBundle, Transform, With}, text::Text, time::Time, ui::{AlignItems, BackgroundColor, Display, FlexDirection, Interaction, PositionType, Style, UiImage, UiRect, Val}};
+
+use super::{build_graph::components::EditorState, camera::MainCamera, files::{file_stem, queue_file, Import, session::{parse_session, session_name, thumbnail_image, SessionData, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH}}, ui::{spawn_button, text_style, InfoPanel, MenuAppExt, BUTTON_COLOR, BUTTON_HOVERED_COLOR, HEADER_COLOR, PANEL_COLOR}};
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::platform::{delete_session, load_session, load_thumbnail, save_session, session_keys};
+#[cfg(target_arch = "wasm32")]
+use crate::wasm_module::{delete_session, load_session, load_thumbnail, save_session, session_keys};
+
+/**
+    # Session Plugin
+    Autosaves the graph, the camera and the editor mode every few seconds and when the app closes, and offers
+    to restore them on the next launch. Named save slots with a thumbnail of their graph keep sessions that
+    should last, like one per exercise.
+*/
+pub struct SessionPlugin;
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .init_resource::<Sessions>()
+        .add_systems(PostStartup, (spawn_slot_panel, offer_restore))
+        .add_systems(Update, (
+            (press_prompt_buttons, press_slot_buttons, type_slot_name, restore_session).chain(),
+            update_slot_panel,
+        ))
+        // Closing the window sends AppExit in PostUpdate, the autosave runs after it to save on the frame the app exits
+        .add_systems(Last, autosave)
+        .add_menu_button("Session", "Toggle save slots", toggle_slots)
+        ;
+    }
+}
+
+struct Slot {
+    key: String,
+    name: String,
+    thumbnail: Option<Handle<Image>>,
+}
+
+#[derive(Resource, Default)]
+pub struct Sessions {
+    visible: bool,
+    // The name the next slot is saved under
+    name: String,
+    slots: Vec<Slot>,
+    // Counts the changes of the slots so the list is only rebuilt for them
+    revision: usize,
+    // The autosave of the last launch while the prompt asks about it, nothing is autosaved until it is answered
+    offered: Option<String>,
+    // A session to restore on this frame
+    load: Option<String>,
+    // The text of the last autosave, an unchanged session is not written again
+    saved: String,
+}
+
+#[derive(Component)]
+struct SlotPanel;
+
+#[derive(Component)]
+struct SlotList;
+
+#[derive(Component)]
+struct SlotNameText;
+
+#[derive(Component)]
+struct RestorePrompt;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum PromptButton {
+    Restore,
+    Dismiss,
+}
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum SlotButton {
+    Save,
+    Load(usize),
+    Delete(usize),
+}
+
+const AUTOSAVE: &str = "autosave";
+const SLOT_PREFIX: &str = "slot_";
+const AUTOSAVE_SECONDS: f32 = 10.;
+const MAX_NAME_LENGTH: usize = 40;
+// The thumbnails of the list are half the saved size
+const THUMBNAIL_SCALE: f32 = 0.5;
+
+fn toggle_slots(mut sessions: ResMut<Sessions>, mut images: ResMut<Assets<Image>>) {
+    sessions.visible = !sessions.visible;
+    if sessions.visible {
+        refresh_slots(&mut sessions, &mut images);
+    }
+}
+
+// Reads the slots from storage, the handles of the old thumbnails are dropped with the list
+fn refresh_slots(sessions: &mut Sessions, images: &mut Assets<Image>) {
+    sessions.slots = session_keys().into_iter()
+        .filter(|key| key.starts_with(SLOT_PREFIX))
+        .map(|key| Slot {
+            name: load_session(&key).and_then(|text| session_name(&text)).unwrap_or_else(|| key[SLOT_PREFIX.len()..].to_string()),
+            thumbnail: load_thumbnail(&key).and_then(|png| thumbnail_image(&png).ok()).map(|image| images.add(image)),
+            key,
+        })
+        .collect();
+    sessions.revision += 1;
+}
+
+fn save_slot(sessions: &mut Sessions, data: &SessionData, images: &mut Assets<Image>, info: &mut InfoPanel) {
+    let name = sessions.name.trim().to_string();
+    if name.is_empty() {
+        info.text = "Type a name for the save slot".to_string();
+        return;
+    }
+    let graph = data.snapshot();
+    let key = format!("{SLOT_PREFIX}{}", file_stem(&name));
+    info.text = match save_session(&key, &data.write(&name, &graph), Some(&data.thumbnail(&graph))) {
+        Ok(()) => format!("Saved {name} with {} vertices", graph.len()),
+        Err(error) => format!("Could not save {name}: {error}"),
+    };
+    sessions.name.clear();
+    refresh_slots(sessions, images);
+}
+
+fn autosave(
+    mut sessions: ResMut<Sessions>,
+    data: SessionData,
+    mut exit: EventReader<AppExit>,
+    mut info: ResMut<InfoPanel>,
+    time: Res<Time>,
+    mut since_save: Local<f32>,
+) {
+    *since_save += time.delta_seconds();
+    let exiting = exit.read().count() > 0;
+    if sessions.offered.is_some() || (*since_save < AUTOSAVE_SECONDS && !exiting) {
+        return;
+    }
+    *since_save = 0.;
+    let text = data.write("Autosave", &data.snapshot());
+    if text == sessions.saved {
+        return;
+    }
+    // A failed autosave is reported once and tried again after the next change
+    if let Err(error) = save_session(AUTOSAVE, &text, None) {
+        info.text = format!("Could not autosave: {error}");
+    }
+    sessions.saved = text;
+}
+
+// Asks before the autosave of the last launch replaces whatever this one opened with
+fn offer_restore(
+    mut commands: Commands,
+    mut sessions: ResMut<Sessions>,
+) {
+    let Some(text) = load_session(AUTOSAVE) else {return};
+    let Ok(session) = parse_session(&text) else {return};
+    if session.graph.vertices.is_empty() {
+        return;
+    }
+    let question = format!(
+        "Restore the session from last time with {} vertices and {} edges?",
+        session.graph.vertices.len(),
+        session.graph.edges.len(),
+    );
+    sessions.offered = Some(text);
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(30.),
+            top: Val::Percent(40.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Px(10.)),
+            ..default()
+        },
+        background_color: PANEL_COLOR.into(),
+        ..default()
+    }, Interaction::default(), RestorePrompt))
+    .with_children(|panel| {
+        panel.spawn(TextBundle::from_section(question, text_style()));
+        panel.spawn(NodeBundle { style: Style { margin: UiRect::top(Val::Px(6.)), ..default() }, ..default() })
+        .with_children(|row| {
+            spawn_button(row, "Restore", HEADER_COLOR, PromptButton::Restore);
+            spawn_button(row, "Not now", BUTTON_COLOR, PromptButton::Dismiss);
+        });
+    });
+}
+
+fn press_prompt_buttons(
+    mut commands: Commands,
+    mut sessions: ResMut<Sessions>,
+    mut q_button: Query<(&Interaction, &PromptButton, &mut BackgroundColor), Changed<Interaction>>,
+    q_prompt: Query<Entity, With<RestorePrompt>>,
+) {
+    for (interaction, button, mut color) in q_button.iter_mut() {
+        let idle = if *button == PromptButton::Restore { HEADER_COLOR } else { BUTTON_COLOR };
+        match interaction {
+            Interaction::Pressed => {},
+            Interaction::Hovered => {
+                *color = BUTTON_HOVERED_COLOR.into();
+                continue;
+            },
+            Interaction::None => {
+                *color = idle.into();
+                continue;
+            },
+        }
+        let offered = sessions.offered.take();
+        if *button == PromptButton::Restore {
+            sessions.load = offered;
+        }
+        for prompt in q_prompt.iter() {
+            commands.entity(prompt).despawn_recursive();
+        }
+    }
+}
+
+// The camera and the mode are set right away, the graph is imported like a file
+fn restore_session(
+    mut sessions: ResMut<Sessions>,
+    mut q_camera: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+    mut next_state: ResMut<NextState<EditorState>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    let Some(text) = sessions.load.take() else {return};
+    let session = match parse_session(&text) {
+        Ok(session) => session,
+        Err(error) => {
+            info.text = format!("Could not restore the session: {error}");
+            return;
+        },
+    };
+    if let Ok((mut transform, mut projection)) = q_camera.get_single_mut() {
+        transform.translation.x = session.view.position.x;
+        transform.translation.y = session.view.position.y;
+        projection.scale = session.view.scale;
+    }
+    next_state.set(session.view.mode);
+    let name = if session.name.is_empty() { AUTOSAVE.to_string() } else { file_stem(&session.name) };
+    queue_file(format!("{name}.session"), text.into_bytes(), Import::Replace);
+}
+
+fn press_slot_buttons(
+    mut sessions: ResMut<Sessions>,
+    mut q_button: Query<(&Interaction, &SlotButton, &mut BackgroundColor), Changed<Interaction>>,
+    data: SessionData,
+    mut images: ResMut<Assets<Image>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    for (interaction, button, mut color) in q_button.iter_mut() {
+        let idle = if *button == SlotButton::Save { HEADER_COLOR } else { BUTTON_COLOR };
+        match interaction {
+            Interaction::Pressed => {},
+            Interaction::Hovered => {
+                *color = BUTTON_HOVERED_COLOR.into();
+                continue;
+            },
+            Interaction::None => {
+                *color = idle.into();
+                continue;
+            },
+        }
+        match *button {
+            SlotButton::Save => save_slot(&mut sessions, &data, &mut images, &mut info),
+            SlotButton::Load(i) => {
+                let Some(slot) = sessions.slots.get(i) else {continue};
+                match load_session(&slot.key) {
+                    Some(text) => sessions.load = Some(text),
+                    None => info.text = format!("Could not read {}", slot.name),
+                }
+            },
+            SlotButton::Delete(i) => {
+                let Some(slot) = sessions.slots.get(i) else {continue};
+                delete_session(&slot.key);
+                info.text = format!("Deleted {}", slot.name);
+                refresh_slots(&mut sessions, &mut images);
+            },
+        }
+        // The list was rebuilt or the graph is replaced, the other buttons of this frame are stale
+        return;
+    }
+}
+
+// The name field takes the keyboard while the panel is open, Enter saves
+fn type_slot_name(
+    mut sessions: ResMut<Sessions>,
+    mut events: EventReader<KeyboardInput>,
+    data: SessionData,
+    mut images: ResMut<Assets<Image>>,
+    mut info: ResMut<InfoPanel>,
+) {
+    if !sessions.visible || sessions.offered.is_some() {
+        events.clear();
+        return;
+    }
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(characters) => {
+                for c in characters.chars().filter(|c| !c.is_control()) {
+                    if sessions.name.chars().count() < MAX_NAME_LENGTH {
+                        sessions.name.push(c);
+                    }
+                }
+            },
+            Key::Space if sessions.name.chars().count() < MAX_NAME_LENGTH => sessions.name.push(' '),
+            Key::Backspace => {
+                sessions.name.pop();
+            },
+            Key::Enter => save_slot(&mut sessions, &data, &mut images, &mut info),
+            _ => {},
+        }
+    }
+}
+
+fn spawn_slot_panel(
+    mut commands: Commands,
+) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.),
+            top: Val::Px(0.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        background_color: PANEL_COLOR.into(),
+        ..default()
+    }, Interaction::default(), SlotPanel))
+    .with_children(|panel| {
+        panel.spawn(TextBundle::from_section("Save slots", text_style()));
+        panel.spawn(NodeBundle { style: Style { align_items: AlignItems::Center, ..default() }, ..default() })
+        .with_children(|row| {
+            row.spawn((TextBundle::from_section("", text_style()).with_style(Style {
+                min_width: Val::Px(160.),
+                margin: UiRect::right(Val::Px(4.)),
+                ..default()
+            }), SlotNameText));
+            spawn_button(row, "Save", HEADER_COLOR, SlotButton::Save);
+        });
+        panel.spawn((NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        }, SlotList));
+    });
+}
+
+fn update_slot_panel(
+    mut commands: Commands,
+    sessions: Res<Sessions>,
+    mut q_panel: Query<&mut Style, With<SlotPanel>>,
+    mut q_text: Query<&mut Text, With<SlotNameText>>,
+    q_list: Query<Entity, With<SlotList>>,
+    mut revision: Local<usize>,
+) {
+    if !sessions.is_changed() {
+        return;
+    }
+    if let Ok(mut style) = q_panel.get_single_mut() {
+        style.display = if sessions.visible { Display::Flex } else { Display::None };
+    }
+    if let Ok(mut text) = q_text.get_single_mut() {
+        text.sections[0].value = format!("Name: {}_", sessions.name);
+    }
+    let Ok(list) = q_list.get_single() else {return};
+    if *revision == sessions.revision {
+        return;
+    }
+    *revision = sessions.revision;
+    commands.entity(list).despawn_descendants();
+    commands.entity(list).with_children(|list| {
+        if sessions.slots.is_empty() {
+            list.spawn(TextBundle::from_section("No saved sessions yet", text_style()));
+        }
+        for (i, slot) in sessions.slots.iter().enumerate() {
+            list.spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    margin: UiRect::top(Val::Px(4.)),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                let size = Style {
+                    width: Val::Px(THUMBNAIL_WIDTH as f32 * THUMBNAIL_SCALE),
+                    height: Val::Px(THUMBNAIL_HEIGHT as f32 * THUMBNAIL_SCALE),
+                    margin: UiRect::right(Val::Px(4.)),
+                    ..default()
+                };
+                match &slot.thumbnail {
+                    Some(thumbnail) => row.spawn(ImageBundle { style: size, image: UiImage::new(thumbnail.clone()), ..default() }),
+                    None => row.spawn(NodeBundle { style: size, background_color: BUTTON_COLOR.into(), ..default() }),
+                };
+                row.spawn(TextBundle::from_section(slot.name.clone(), text_style()).with_style(Style {
+                    min_width: Val::Px(100.),
+                    margin: UiRect::right(Val::Px(4.)),
+                    ..default()
+                }));
+                spawn_button(row, "Load", BUTTON_COLOR, SlotButton::Load(i));
+                spawn_button(row, "Delete", BUTTON_COLOR, SlotButton::Delete(i));
+            });
+        }
+    });
+}
@@ -0,0 +1,255 @@
+use bevy::{app::{PostStartup, Update}, color::Color, ecs::system::SystemId, prelude::{default, App, BuildChildren, Bundle, ButtonBundle, Changed, ChildBuilder, Children, Commands, Component, DetectChanges, IntoSystem, NodeBundle, Parent, Plugin, Query, Res, ResMut, Resource, TextBundle, With}, text::{Text, TextStyle}, ui::{AlignItems, BackgroundColor, Display, FlexDirection, Interaction, Overflow, PositionType, Style, UiRect, Val}};
+
+pub struct MenuPlugin;
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app
+        .init_resource::<MenuRegistry>()
+        .init_resource::<InfoPanel>()
+        .init_resource::<PointerOverUi>()
+        .add_systems(PostStartup, spawn_ui)
+        .add_systems(Update, (
+            press_menu_buttons,
+            toggle_menu_sections,
+            update_pointer_over_ui,
+            update_info_text,
+        ))
+        ;
+    }
+}
+
+struct MenuEntry {
+    section: &'static str,
+    label: &'static str,
+    system: SystemId,
+}
+
+// Buttons of the side menu, every button runs a one-shot system when pressed
+#[derive(Resource, Default)]
+pub struct MenuRegistry {
+    entries: Vec<MenuEntry>,
+}
+
+// Text shown in the bottom left corner, the status line describes the editor and the text the running algorithm
+#[derive(Resource, Default)]
+pub struct InfoPanel {
+    pub status: String,
+    pub text: String,
+}
+
+// True while the pointer is over a menu so clicks don't place vertices underneath
+#[derive(Resource, Default)]
+pub struct PointerOverUi(pub bool);
+
+pub trait MenuAppExt {
+    fn add_menu_button<M>(&mut self, section: &'static str, label: &'static str, system: impl IntoSystem<(), (), M> + 'static) -> &mut Self;
+}
+
+impl MenuAppExt for App {
+    fn add_menu_button<M>(&mut self, section: &'static str, label: &'static str, system: impl IntoSystem<(), (), M> + 'static) -> &mut Self {
+        let system = self.world_mut().register_system(system);
+        self.world_mut()
+            .get_resource_or_insert_with(MenuRegistry::default)
+            .entries
+            .push(MenuEntry { section, label, system });
+        self
+    }
+}
+
+#[derive(Component)]
+struct MenuButton(SystemId);
+
+#[derive(Component)]
+struct SectionHeader;
+
+#[derive(Component)]
+struct SectionBody;
+
+#[derive(Component)]
+struct InfoText;
+
+const FONT_SIZE: f32 = 16.0;
+pub const PANEL_COLOR: Color = Color::srgba(0.08, 0.08, 0.1, 0.85);
+pub const HEADER_COLOR: Color = Color::srgb(0.2, 0.2, 0.28);
+pub const BUTTON_COLOR: Color = Color::srgb(0.14, 0.14, 0.18);
+pub const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.3, 0.3, 0.4);
+
+pub fn text_style() -> TextStyle {
+    TextStyle {
+        font_size: FONT_SIZE,
+        color: Color::WHITE,
+        ..default()
+    }
+}
+
+pub fn button_style() -> Style {
+    Style {
+        padding: UiRect::axes(Val::Px(8.), Val::Px(4.)),
+        margin: UiRect::bottom(Val::Px(2.)),
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+// A button with a label in a row of buttons, the marker tells the presses apart
+pub fn spawn_button(parent: &mut ChildBuilder, label: &str, color: Color, marker: impl Bundle) {
+    parent.spawn((ButtonBundle {
+        style: Style {
+            margin: UiRect::right(Val::Px(2.)),
+            ..button_style()
+        },
+        background_color: color.into(),
+        ..default()
+    }, marker))
+    .with_children(|button| {
+        button.spawn(TextBundle::from_section(label, text_style()));
+    });
+}
+
+fn spawn_ui(
+    mut commands: Commands,
+    registry: Res<MenuRegistry>,
+) {
+    let mut sections: Vec<&'static str> = vec![];
+    for entry in registry.entries.iter() {
+        if !sections.contains(&entry.section) {
+            sections.push(entry.section);
+        }
+    }
+
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(0.),
+            top: Val::Px(0.),
+            max_height: Val::Percent(100.),
+            min_width: Val::Px(180.),
+            flex_direction: FlexDirection::Column,
+            overflow: Overflow::clip_y(),
+            padding: UiRect::all(Val::Px(4.)),
+            ..default()
+        },
+        background_color: PANEL_COLOR.into(),
+        ..default()
+    }, Interaction::default()))
+    .with_children(|panel| {
+        for section in sections {
+            panel.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|section_node| {
+                section_node.spawn((ButtonBundle {
+                    style: button_style(),
+                    background_color: HEADER_COLOR.into(),
+                    ..default()
+                }, SectionHeader))
+                .with_children(|header| {
+                    header.spawn(TextBundle::from_section(section, text_style()));
+                });
+
+                section_node.spawn((NodeBundle {
+                    style: Style {
+                        display: Display::None,
+                        flex_direction: FlexDirection::Column,
+                        margin: UiRect::left(Val::Px(8.)),
+                        ..default()
+                    },
+                    ..default()
+                }, SectionBody))
+                .with_children(|body| {
+                    for entry in registry.entries.iter().filter(|entry| entry.section == section) {
+                        body.spawn((ButtonBundle {
+                            style: button_style(),
+                            background_color: BUTTON_COLOR.into(),
+                            ..default()
+                        }, MenuButton(entry.system)))
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(entry.label, text_style()));
+                        });
+                    }
+                });
+            });
+        }
+    });
+
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(0.),
+            bottom: Val::Px(0.),
+            max_width: Val::Percent(45.),
+            padding: UiRect::all(Val::Px(6.)),
+            ..default()
+        },
+        background_color: PANEL_COLOR.into(),
+        ..default()
+    }, Interaction::default()))
+    .with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", text_style()), InfoText));
+    });
+}
+
+fn press_menu_buttons(
+    mut commands: Commands,
+    mut q_button: Query<(&Interaction, &MenuButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in q_button.iter_mut() {
+        match interaction {
+            Interaction::Pressed => commands.run_system(button.0),
+            Interaction::Hovered => *color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *color = BUTTON_COLOR.into(),
+        }
+    }
+}
+
+type PressedHeader = (Changed<Interaction>, With<SectionHeader>);
+
+// Every section node holds its header and its body
+fn toggle_menu_sections(
+    q_header: Query<(&Interaction, &Parent), PressedHeader>,
+    q_children: Query<&Children>,
+    mut q_body: Query<&mut Style, With<SectionBody>>,
+) {
+    for (interaction, parent) in q_header.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Ok(children) = q_children.get(parent.get()) else {continue};
+        for child in children.iter() {
+            let Ok(mut style) = q_body.get_mut(*child) else {continue};
+            style.display = match style.display {
+                Display::None => Display::Flex,
+                _ => Display::None,
+            };
+        }
+    }
+}
+
+fn update_pointer_over_ui(
+    q_interaction: Query<&Interaction>,
+    mut over_ui: ResMut<PointerOverUi>,
+) {
+    let over = q_interaction.iter().any(|interaction| *interaction != Interaction::None);
+    if over_ui.0 != over {
+        over_ui.0 = over;
+    }
+}
+
+fn update_info_text(
+    info: Res<InfoPanel>,
+    mut q_text: Query<&mut Text, With<InfoText>>,
+) {
+    if !info.is_changed() {
+        return;
+    }
+    let Ok(mut text) = q_text.get_single_mut() else {return};
+    text.sections[0].value = if info.text.is_empty() {
+        info.status.clone()
+    } else {
+        format!("{}\n\n{}", info.status, info.text)
+    };
+}
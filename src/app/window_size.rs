@@ -0,0 +1,39 @@
+use bevy::{app::{AppExit, Last, Plugin}, log::warn, math::Vec2, prelude::{EventReader, Local, Res}, time::Time, window::WindowResized};
+
+use super::platform::{save_setting, MIN_WINDOW_SIZE, WINDOW_SETTING};
+
+pub struct WindowSizePlugin;
+impl Plugin for WindowSizePlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app
+        .add_systems(Last, remember_window_size)
+        ;
+    }
+}
+
+// Dragging the border of the window resizes it every frame, the size is saved once it stays for this long
+const SAVE_DELAY: f32 = 0.5;
+
+// The next window opens with the size this one had. Closing the window sends AppExit in PostUpdate,
+// so this runs in Last to still save a size on the frame the app exits
+fn remember_window_size(
+    mut resized: EventReader<WindowResized>,
+    mut exit: EventReader<AppExit>,
+    mut pending: Local<Option<(Vec2, f32)>>,
+    time: Res<Time>,
+) {
+    // A minimized window has no size
+    if let Some(event) = resized.read().filter(|event| event.width.min(event.height) >= MIN_WINDOW_SIZE).last() {
+        *pending = Some((Vec2::new(event.width, event.height), 0.));
+    }
+    let Some((size, waited)) = pending.as_mut() else {return};
+    *waited += time.delta_seconds();
+    if *waited < SAVE_DELAY && exit.is_empty() {
+        return;
+    }
+    exit.clear();
+    if let Err(error) = save_setting(WINDOW_SETTING, &format!("{} {}", size.x.round(), size.y.round())) {
+        warn!("Could not save the window size: {error}");
+    }
+    *pending = None;
+}
@@ -7,7 +7,7 @@ pub mod wasm_module {
     use bevy::{window::WindowResolution};
     use wasm_bindgen::prelude::{wasm_bindgen, JsValue}; 
     use serde_wasm_bindgen::{from_value, Error};
-    use crate::app::run;
+    use crate::app::{api::{self, ApiCommand}, run};
 
     #[derive(Serialize, Deserialize)]
     pub struct WindowSize {
@@ -33,8 +33,108 @@ pub mod wasm_module {
         
         #[wasm_bindgen(js_namespace = window)]
         fn get_window_size() -> JsValue;
+
+        #[wasm_bindgen(js_namespace = window)]
+        fn download_file(name: &str, contents: &[u8]);
+    }
+
+    // The link lives in the fragment so it never reaches the server
+    #[wasm_bindgen(inline_js = "
+        export function url_fragment() { return window.location.hash.slice(1); }
+        export function copy_link(fragment) {
+            const link = window.location.href.split('#')[0] + '#' + fragment;
+            window.history.replaceState(null, '', link);
+            navigator.clipboard?.writeText(link);
+        }
+    ")]
+    extern "C" {
+        fn url_fragment() -> String;
+        fn copy_link(fragment: &str);
     }
     
+    // Callbacks the page registers for the events of the simulator, every callback gets the parsed detail
+    #[wasm_bindgen(inline_js = "
+        const listeners = new Map();
+        export function add_listener(event, callback) {
+            if (!listeners.has(event)) listeners.set(event, []);
+            listeners.get(event).push(callback);
+        }
+        export function emit(event, detail) {
+            // A throwing callback must not unwind through the app loop
+            for (const callback of listeners.get(event) ?? []) {
+                try { callback(JSON.parse(detail)); } catch (e) { console.error(e); }
+            }
+        }
+    ")]
+    extern "C" {
+        fn add_listener(event: &str, callback: JsValue);
+        fn emit(event: &str, detail: &str);
+    }
+
+    // Sessions live in the local storage of the page, a full storage throws and the message is returned
+    #[wasm_bindgen(inline_js = "
+        export function storage_get(key) {
+            try { return localStorage.getItem(key); } catch (e) { return null; }
+        }
+        export function storage_set(key, value) {
+            try { localStorage.setItem(key, value); return ''; } catch (e) { return String(e); }
+        }
+        export function storage_remove(key) {
+            try { localStorage.removeItem(key); } catch (e) {}
+        }
+        export function storage_keys(prefix) {
+            try {
+                return Object.keys(localStorage).filter(key => key.startsWith(prefix)).map(key => key.slice(prefix.length)).join('\\n');
+            } catch (e) { return ''; }
+        }
+    ")]
+    extern "C" {
+        fn storage_get(key: &str) -> Option<String>;
+        fn storage_set(key: &str, value: &str) -> String;
+        fn storage_remove(key: &str);
+        fn storage_keys(prefix: &str) -> String;
+    }
+
+    const SESSION_PREFIX: &str = "bevy_graph_simulator/session/";
+    // Thumbnails are stored as base64url text
+    const THUMBNAIL_PREFIX: &str = "bevy_graph_simulator/thumbnail/";
+
+    fn store(key: &str, value: &str) -> Result<(), String> {
+        let error = storage_set(key, value);
+        if error.is_empty() { Ok(()) } else { Err(error) }
+    }
+
+    // A session without a thumbnail removes the old one
+    pub fn save_session(key: &str, text: &str, thumbnail: Option<&[u8]>) -> Result<(), String> {
+        store(&format!("{SESSION_PREFIX}{key}"), text)?;
+        match thumbnail {
+            Some(thumbnail) => store(&format!("{THUMBNAIL_PREFIX}{key}"), &crate::app::files::base64url(thumbnail)),
+            None => {
+                storage_remove(&format!("{THUMBNAIL_PREFIX}{key}"));
+                Ok(())
+            },
+        }
+    }
+
+    pub fn load_session(key: &str) -> Option<String> {
+        storage_get(&format!("{SESSION_PREFIX}{key}"))
+    }
+
+    pub fn load_thumbnail(key: &str) -> Option<Vec<u8>> {
+        crate::app::files::parse_base64url(&storage_get(&format!("{THUMBNAIL_PREFIX}{key}"))?).ok()
+    }
+
+    pub fn delete_session(key: &str) {
+        storage_remove(&format!("{SESSION_PREFIX}{key}"));
+        storage_remove(&format!("{THUMBNAIL_PREFIX}{key}"));
+    }
+
+    pub fn session_keys() -> Vec<String> {
+        let mut keys: Vec<String> = storage_keys(SESSION_PREFIX).lines().filter(|key| !key.is_empty()).map(str::to_string).collect();
+        keys.sort();
+        keys
+    }
+
     #[wasm_bindgen]
     pub fn alert_js(message: &str) {
         alert(message);
@@ -45,10 +145,82 @@ pub mod wasm_module {
         log(message);
     }
     
+    // A graph shared in the link of the page is imported on the first frame
     #[wasm_bindgen]
     pub fn run_app() {
+        let fragment = url_fragment();
+        if fragment.starts_with(crate::app::files::LINK_PREFIX) {
+            crate::app::files::queue_file(crate::app::files::SHARED_LINK.to_string(), fragment.into_bytes(), crate::app::files::Import::Replace);
+        }
         run();
     }
+
+    // Called by the page to put a link to the graph in the address bar and the clipboard
+    #[wasm_bindgen]
+    pub fn copy_graph_link() {
+        crate::app::files::request_link();
+    }
+
+    pub fn copy_link_js(fragment: &str) {
+        copy_link(fragment);
+    }
+
+    pub fn emit_js(event: &str, detail: &str) {
+        emit(event, detail);
+    }
+
+    // Replaces the graph with one in the JSON of the File menu, it is imported on the next frame
+    #[wasm_bindgen]
+    pub fn load_graph(json: &str) {
+        api::load_graph(json);
+    }
+
+    // The graph as of the last frame in the same JSON
+    #[wasm_bindgen]
+    pub fn export_graph() -> String {
+        api::exported_graph()
+    }
+
+    // add, edit or delete
+    #[wasm_bindgen]
+    pub fn set_editor_mode(mode: &str) -> Result<(), JsValue> {
+        let state = api::editor_mode(mode).ok_or_else(|| JsValue::from_str(&format!("Unknown editor mode {mode}, the modes are add, edit and delete")))?;
+        api::send(ApiCommand::SetEditorMode(state));
+        Ok(())
+    }
+
+    // Starts an algorithm by its command line name, start and end are vertex names and default to the picked targets
+    #[wasm_bindgen]
+    pub fn run_algorithm(name: &str, start: Option<String>, end: Option<String>) -> Result<(), JsValue> {
+        if !api::ALGORITHMS.contains(&name.to_ascii_lowercase().as_str()) {
+            return Err(JsValue::from_str(&format!("Unknown algorithm {name}, the algorithms are {}", api::ALGORITHMS.join(", "))));
+        }
+        api::send(ApiCommand::RunAlgorithm { name: name.to_string(), start, end });
+        Ok(())
+    }
+
+    // Shows the next step of the run and pauses it
+    #[wasm_bindgen]
+    pub fn step() {
+        api::send(ApiCommand::Step);
+    }
+
+    // Registers a callback for vertex_added, edge_added, algorithm_finished or error
+    #[wasm_bindgen]
+    pub fn on(event: &str, callback: JsValue) {
+        add_listener(event, callback);
+    }
+
+    // The page offers the file as a download
+    pub fn download_js(name: &str, contents: &[u8]) {
+        download_file(name, contents);
+    }
+
+    // Called by the page with the name and the text of a file the user picked, it is imported on the next frame
+    #[wasm_bindgen]
+    pub fn import_file(name: &str, contents: &str) {
+        crate::app::files::queue_file(name.to_string(), contents.as_bytes().to_vec(), crate::app::files::Import::Add);
+    }
     
     pub fn get_app_window_size() -> Option<WindowResolution> {
         let js_val = get_window_size();
@@ -6,14 +6,27 @@ use app::run;
 
 pub fn main() {
     #[cfg(not(target_arch = "wasm32"))]
-    run();
-}
-
-#[cfg(not(target_arch = "wasm32"))]
-pub mod not_wasm_module {
-    use bevy::{window::WindowResolution};
-
-    pub fn get_app_window_size() -> Option<WindowResolution> {
-        Some( WindowResolution::new(1280f32, 720f32) )
+    {
+        // Renders the frames of an algorithm run to files without opening a window
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.first().map(String::as_str) == Some("--render") {
+            match app::files::render_headless(&args[1..]) {
+                Ok(message) => println!("{message}"),
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                },
+            }
+            return;
+        }
+        // Opens a graph shared as a link, the whole link or only the text after graph=
+        if args.first().map(String::as_str) == Some("--link") {
+            let Some(link) = args.get(1) else {
+                eprintln!("--link needs the link of a graph");
+                std::process::exit(1);
+            };
+            app::files::queue_file(app::files::SHARED_LINK.to_string(), link.as_bytes().to_vec(), app::files::Import::Replace);
+        }
+        run();
     }
-}
\ No newline at end of file
+}